@@ -580,3 +580,202 @@ impl AymBackend for AymPrecise {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::{vec, vec::Vec};
+
+    const CLOCK: usize = 1773400;
+    const SAMPLE_RATE: usize = 44100;
+
+    fn ay() -> AymPrecise {
+        AymBackend::new(SoundChip::AY, AyMode::Mono, CLOCK, SAMPLE_RATE)
+    }
+
+    /// Envelope levels (`0..32`) as described in the AY-3-8910 datasheet: the
+    /// first 32-step ramp direction is set by ATTACK bit, then ramp either
+    /// holds, repeats or alternates its direction
+    fn reference_envelope(shape: usize, steps: usize) -> Vec<usize> {
+        let cont = shape & 0x08 != 0;
+        let attack = shape & 0x04 != 0;
+        let alternate = shape & 0x02 != 0;
+        let hold = shape & 0x01 != 0;
+        let ramp = |up: bool, pos: usize| if up { pos } else { 31 - pos };
+        (0..steps)
+            .map(|step| {
+                let (cycle, pos) = (step / 32, step % 32);
+                if cycle == 0 {
+                    ramp(attack, pos)
+                } else if !cont {
+                    0
+                } else if hold {
+                    if attack ^ alternate {
+                        31
+                    } else {
+                        0
+                    }
+                } else {
+                    ramp(attack ^ (alternate && cycle % 2 == 1), pos)
+                }
+            })
+            .collect()
+    }
+
+    /// Replays register writes from the reference fixture (see
+    /// `test_data/ay_reference.py`) and returns output level of channel A
+    /// (`0..16`) for each chip tick, together with the expected levels.
+    /// Fixtures are generated by the datasheet model, not captured from the
+    /// real chip
+    fn replay_reference(fixture: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let writes_count = u16::from_le_bytes([fixture[0], fixture[1]]) as usize;
+        let (writes, rest) = fixture[2..].split_at(writes_count * 6);
+        let (ticks, expected) = rest.split_at(4);
+        let ticks = u32::from_le_bytes(ticks.try_into().unwrap()) as usize;
+        assert_eq!(expected.len(), ticks);
+
+        let mut ay = ay();
+        let mut writes = writes.chunks_exact(6).peekable();
+        let mut levels = Vec::with_capacity(ticks);
+        for tick in 0..ticks {
+            while let Some(write) =
+                writes.next_if(|w| u32::from_le_bytes(w[0..4].try_into().unwrap()) as usize == tick)
+            {
+                ay.write_register(write[4], write[5]);
+            }
+            ay.update_mixer();
+            // Other channels are silent, AY DAC uses each level twice
+            let pan = ay.channels[0].pan_left;
+            let level = (0..16)
+                .find(|&level| AY_DAC_TABLE[level * 2 + 1] * pan == ay.left)
+                .expect("unexpected output value");
+            levels.push(level as u8);
+        }
+        (levels, expected.to_vec())
+    }
+
+    #[test]
+    fn matches_reference_model() {
+        let fixtures: [(&str, &[u8]); 6] = [
+            ("tone", include_bytes!("../../test_data/tone.bin")),
+            (
+                "tone_period_zero",
+                include_bytes!("../../test_data/tone_period_zero.bin"),
+            ),
+            ("noise", include_bytes!("../../test_data/noise.bin")),
+            (
+                "tone_noise",
+                include_bytes!("../../test_data/tone_noise.bin"),
+            ),
+            ("envelopes", include_bytes!("../../test_data/envelopes.bin")),
+            ("tune", include_bytes!("../../test_data/tune.bin")),
+        ];
+        for (name, fixture) in fixtures {
+            let (levels, expected) = replay_reference(fixture);
+            let mismatch = levels.iter().zip(&expected).position(|(a, b)| a != b);
+            assert_eq!(mismatch, None, "{}: first mismatch at tick", name);
+        }
+    }
+
+    #[test]
+    fn envelope_shapes() {
+        for shape in 0..16 {
+            let mut ay = ay();
+            ay.write_register(11, 0);
+            ay.write_register(12, 0);
+            ay.write_register(13, shape as u8);
+            // With period 1 envelope changes on each chip tick, starting from
+            // the level set by shape write
+            let mut levels = vec![ay.envelope];
+            levels.extend((1..32 * 4).map(|_| ay.update_envelope()));
+            assert_eq!(levels, reference_envelope(shape, 32 * 4), "shape {}", shape);
+        }
+    }
+
+    #[test]
+    fn envelope_restarts_on_shape_write() {
+        let mut ay = ay();
+        ay.write_register(11, 3);
+        ay.write_register(13, 0x0C);
+        (0..3 * 20).for_each(|_| {
+            ay.update_envelope();
+        });
+        assert_eq!(ay.envelope, 20);
+        // Same shape write restarts the envelope
+        ay.write_register(13, 0x0C);
+        assert_eq!(ay.envelope, 0);
+        assert_eq!(ay.envelope_counter, 0);
+    }
+
+    #[test]
+    fn noise_lfsr_period() {
+        let mut ay = ay();
+        ay.write_register(6, 1);
+        let initial = ay.noise;
+        let mut period = 0;
+        loop {
+            // Noise generator is clocked at half of the tone generator rate
+            ay.update_noise();
+            ay.update_noise();
+            period += 1;
+            if ay.noise == initial {
+                break;
+            }
+        }
+        assert_eq!(period, (1 << 17) - 1);
+    }
+
+    #[test]
+    fn tone_frequency() {
+        let mut ay = ay();
+        ay.enable_dc_filter();
+        ay.write_register(0, 0x00);
+        ay.write_register(1, 0x01);
+        ay.write_register(7, 0x3E);
+        ay.write_register(8, 0x0F);
+        let samples = (0..SAMPLE_RATE)
+            .map(|_| ay.next_sample().left)
+            .collect::<Vec<_>>();
+        // Skip DC filter settling time
+        let rising_edges = samples[SAMPLE_RATE / 2..]
+            .windows(2)
+            .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+            .count();
+        // Tone period is 16 * P chip clocks, edges are counted for half a second
+        let expected = CLOCK as f64 / (16.0 * 256.0) / 2.0;
+        assert!(
+            (rising_edges as f64 - expected).abs() <= 1.0,
+            "{} rising edges, {} expected",
+            rising_edges,
+            expected
+        );
+    }
+
+    #[test]
+    fn volume_table() {
+        // AY has 16 volume levels, envelope uses each level for two steps
+        for pair in AY_DAC_TABLE.chunks_exact(2) {
+            assert_eq!(pair[0], pair[1]);
+        }
+        for table in [&AY_DAC_TABLE, &YM_DAC_TABLE] {
+            assert_eq!(table[0], 0.0);
+            assert_eq!(table[31], 1.0);
+            assert!(table.windows(2).all(|w| w[0] <= w[1]));
+        }
+        // Fixed volume matches envelope level with the same amplitude
+        let mut ay = ay();
+        ay.write_register(7, 0x3F);
+        for volume in 0..16 {
+            ay.write_register(8, volume);
+            ay.update_mixer();
+            let fixed = ay.left;
+            ay.write_register(8, 0x10);
+            ay.envelope = volume as usize * 2 + 1;
+            ay.envelope_period = u16::MAX;
+            ay.update_mixer();
+            assert_eq!(ay.left, fixed, "volume {}", volume);
+        }
+    }
+}
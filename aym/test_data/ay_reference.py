@@ -0,0 +1,172 @@
+#!/usr/bin/env python3
+"""Independent chip-level model of the AY-3-8910, written from the datasheet
+(GI AY-3-8910/8912 Programmable Sound Generator Data Manual). Generates
+reference output levels of channel A for register write sequences, which are
+compared with `AymPrecise` state in its unit tests.
+
+NOTE: fixtures are generated by this model, they are NOT captures of the real
+hardware. Tests only check that `AymPrecise` agrees with an independently
+written implementation of the datasheet, chip quirks which are not described
+in the datasheet are not covered.
+
+The model is clocked at chip clock / 8 ("tick"):
+- tone output toggles each `TP` ticks (period 0 acts as 1), which gives
+  the datasheet tone frequency of clock / (16 * TP);
+- noise 17-bit LFSR (bit 0 xor bit 3 fed to bit 16) shifts each `2 * NP`
+  ticks, giving clock / (16 * NP);
+- envelope has 16 steps, one step lasts `2 * EP` ticks, so the full cycle is
+  256 * EP clocks. Envelope restarts on shape register write;
+- channel output is `(tone | tone_off) & (noise | noise_off)` multiplied by
+  fixed volume or envelope level.
+
+Fixture format (little endian):
+    u16 writes count, then writes as (u32 tick, u8 register, u8 value)
+    u32 ticks count, then channel A output level (0..16) for each tick
+
+Usage: ay_reference.py <output_dir>
+"""
+import os
+import struct
+import sys
+
+
+def envelope_level(shape, step):
+    """Envelope level for `step` since the shape write, datasheet table"""
+    cont = shape & 0x08
+    attack = shape & 0x04
+    alternate = shape & 0x02
+    hold = shape & 0x01
+    cycle, pos = divmod(step, 16)
+    if cycle == 0:
+        return pos if attack else 15 - pos
+    if not cont:
+        return 0
+    if hold:
+        # Holds the last level of the first cycle, inverted by ALTERNATE
+        first_end = 15 if attack else 0
+        return 15 - first_end if alternate else first_end
+    up = bool(attack)
+    if alternate and cycle % 2 == 1:
+        up = not up
+    return pos if up else 15 - pos
+
+
+class Ay:
+    def __init__(self):
+        self.regs = [0] * 16
+        self.tone_counter = [0, 0, 0]
+        self.tone = [0, 0, 0]
+        self.noise_counter = 0
+        self.lfsr = 1
+        self.env_counter = 0
+        self.env_step = 0
+
+    def write(self, reg, value):
+        self.regs[reg] = value
+        if reg == 13:
+            self.env_counter = 0
+            self.env_step = 0
+
+    def tone_period(self, ch):
+        period = self.regs[ch * 2] | ((self.regs[ch * 2 + 1] & 0x0F) << 8)
+        return max(period, 1)
+
+    def tick(self):
+        """Advances the chip by one tick, returns channel A level"""
+        noise_period = max(self.regs[6] & 0x1F, 1)
+        self.noise_counter += 1
+        if self.noise_counter >= noise_period * 2:
+            self.noise_counter = 0
+            feedback = (self.lfsr ^ (self.lfsr >> 3)) & 1
+            self.lfsr = (self.lfsr >> 1) | (feedback << 16)
+
+        env_period = max(self.regs[11] | (self.regs[12] << 8), 1)
+        self.env_counter += 1
+        if self.env_counter >= env_period * 2:
+            self.env_counter = 0
+            self.env_step += 1
+
+        for ch in range(3):
+            self.tone_counter[ch] += 1
+            if self.tone_counter[ch] >= self.tone_period(ch):
+                self.tone_counter[ch] = 0
+                self.tone[ch] ^= 1
+
+        mixer = self.regs[7]
+        tone_off = mixer & 0x01
+        noise_off = (mixer >> 3) & 0x01
+        out = (self.tone[0] | tone_off) & ((self.lfsr & 1) | noise_off)
+        if self.regs[8] & 0x10:
+            level = envelope_level(self.regs[13] & 0x0F, self.env_step)
+        else:
+            level = self.regs[8] & 0x0F
+        return out * level
+
+
+def render(writes, ticks):
+    ay = Ay()
+    levels = bytearray()
+    pending = sorted(writes, key=lambda w: w[0])
+    for tick in range(ticks):
+        while pending and pending[0][0] == tick:
+            _, reg, value = pending.pop(0)
+            ay.write(reg, value)
+        levels.append(ay.tick())
+    out = struct.pack("<H", len(writes))
+    for tick, reg, value in writes:
+        out += struct.pack("<IBB", tick, reg, value)
+    return out + struct.pack("<I", ticks) + bytes(levels)
+
+
+def channel_a(mixer, volume, tone=0, noise=0):
+    return [
+        (0, 0, tone & 0xFF),
+        (0, 1, tone >> 8),
+        (0, 6, noise),
+        (0, 7, mixer),
+        (0, 8, volume),
+    ]
+
+
+def scenarios():
+    envelopes = [(0, 11, 2), (0, 12, 0), (0, 7, 0x3F), (0, 8, 0x10)]
+    for shape in range(16):
+        envelopes.append((shape * 256, 13, shape))
+
+    tune = channel_a(0x3E, 0x0F, tone=7)
+    tune += [
+        # Period change in the middle of the half-period
+        (100, 0, 3),
+        (160, 1, 0x01),
+        (170, 1, 0x00),
+        # Tone modulated by envelope, retriggered while running
+        (200, 11, 3),
+        (200, 13, 0x0E),
+        (200, 8, 0x10),
+        (350, 13, 0x0E),
+        # Noise mixed in, then volume back to fixed level
+        (500, 6, 2),
+        (500, 7, 0x36),
+        (700, 8, 0x08),
+        (800, 7, 0x37),
+    ]
+
+    return {
+        "tone.bin": (channel_a(0x3E, 0x0F, tone=5), 512),
+        "tone_period_zero.bin": (channel_a(0x3E, 0x0C, tone=0), 64),
+        "noise.bin": (channel_a(0x37, 0x0F, noise=3), 2048),
+        "tone_noise.bin": (channel_a(0x36, 0x0A, tone=9, noise=1), 2048),
+        "envelopes.bin": (envelopes, 16 * 256),
+        "tune.bin": (tune, 1024),
+    }
+
+
+def main():
+    out_dir = sys.argv[1]
+    for name, (writes, ticks) in scenarios().items():
+        with open(os.path.join(out_dir, name), "wb") as f:
+            f.write(render(writes, ticks))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,291 @@
+//! Minimal example of `rustzx-core` integration into Bevy: emulator frame is
+//! uploaded to the texture of a sprite, and sound is pulled from the emulator
+//! into the custom Bevy audio source.
+//!
+//! Usage: `cargo run --release -- [file.tap|file.sna]`
+use bevy::{
+    audio::{AddAudioSource, Source},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+use rustzx_core::{
+    host::{
+        BufferCursor, FrameBuffer, Host, HostContext, Snapshot, StubDebugInterface, StubIoExtender,
+        Tape,
+    },
+    zx::{
+        constants::{FPS, SCREEN_HEIGHT, SCREEN_WIDTH},
+        keys::ZXKey,
+        machine::ZXMachine,
+        sound::ay::ZXAYMode,
+    },
+    BorderMode, EmulationMode, Emulator, RamPattern, RustzxSettings,
+};
+use rustzx_utils::{
+    frame_buffer::{compose_rgba_frame, RgbaFrameBuffer, RgbaFrameBufferContext},
+    io::DynamicAsset,
+    stopwatch::InstantStopwatch,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const SAMPLE_RATE: usize = 44100;
+const AUDIO_CHANNELS: usize = 2;
+const AUDIO_BUFFER_LIMIT: usize = SAMPLE_RATE / 5 * AUDIO_CHANNELS;
+const SCREEN_SCALE: f32 = 3.0;
+
+struct BevyHost;
+
+impl Host for BevyHost {
+    type Context = BevyHostContext;
+    type DebugInterface = StubDebugInterface;
+    type EmulationStopwatch = InstantStopwatch;
+    type FrameBuffer = RgbaFrameBuffer;
+    type IoExtender = StubIoExtender;
+    type TapeAsset = DynamicAsset;
+}
+
+struct BevyHostContext;
+
+impl HostContext<BevyHost> for BevyHostContext {
+    fn frame_buffer_context(&self) -> <<BevyHost as Host>::FrameBuffer as FrameBuffer>::Context {
+        RgbaFrameBufferContext::default()
+    }
+}
+
+/// Interleaved stereo samples shared between emulator and audio thread
+type SampleQueue = Arc<Mutex<VecDeque<f32>>>;
+
+/// Emulator is not `Send`, therefore it is stored as non-send resource
+struct Spectrum {
+    emulator: Emulator<BevyHost>,
+    audio_buffer: Vec<f32>,
+    samples: SampleQueue,
+}
+
+#[derive(Resource)]
+struct ScreenTexture(Handle<Image>);
+
+#[derive(Asset, TypePath)]
+struct SpectrumAudio {
+    samples: SampleQueue,
+}
+
+struct SpectrumAudioDecoder {
+    samples: SampleQueue,
+}
+
+impl Iterator for SpectrumAudioDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        // Emit silence on buffer underrun to keep the stream alive
+        Some(self.samples.lock().unwrap().pop_front().unwrap_or(0.0))
+    }
+}
+
+impl Source for SpectrumAudioDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        AUDIO_CHANNELS as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE as u32
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Decodable for SpectrumAudio {
+    type DecoderItem = f32;
+    type Decoder = SpectrumAudioDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        SpectrumAudioDecoder {
+            samples: self.samples.clone(),
+        }
+    }
+}
+
+fn main() {
+    let settings = RustzxSettings {
+        machine: ZXMachine::Sinclair128K,
+        emulation_mode: EmulationMode::FrameCount(1),
+        tape_fastload_enabled: true,
+        kempston_enabled: false,
+        mouse_enabled: false,
+        ram_pattern: RamPattern::Zeros,
+        border_mode: BorderMode::Precise,
+        ay_mode: ZXAYMode::ABC,
+        ay_enabled: true,
+        beeper_enabled: true,
+        sound_enabled: true,
+        sound_volume: 100,
+        sound_sample_rate: SAMPLE_RATE,
+        load_default_rom: true,
+        autoload_enabled: true,
+    };
+
+    let mut emulator =
+        Emulator::new(settings, BevyHostContext).expect("Failed to initialize emulator");
+
+    if let Some(path) = std::env::args().nth(1) {
+        let data = std::fs::read(&path).expect("Failed to read file");
+        let asset: DynamicAsset = BufferCursor::new(data).into();
+        if path.to_lowercase().ends_with(".sna") {
+            emulator.load_snapshot(Snapshot::Sna(asset))
+        } else {
+            emulator.load_tape(Tape::Tap(asset))
+        }
+        .expect("Failed to load file");
+    }
+
+    App::new()
+        .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
+        .add_audio_source::<SpectrumAudio>()
+        .insert_resource(Time::<Fixed>::from_hz(FPS as f64))
+        .insert_non_send_resource(Spectrum {
+            emulator,
+            audio_buffer: vec![0.0; AUDIO_BUFFER_LIMIT],
+            samples: Default::default(),
+        })
+        .add_systems(Startup, setup)
+        .add_systems(FixedUpdate, emulate_frame)
+        .add_systems(Update, (send_keys, update_screen))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut audio: ResMut<Assets<SpectrumAudio>>,
+    spectrum: NonSend<Spectrum>,
+) {
+    let image = Image::new_fill(
+        Extent3d {
+            width: SCREEN_WIDTH as u32,
+            height: SCREEN_HEIGHT as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    let texture = images.add(image);
+
+    commands.spawn(Camera2dBundle::default());
+    commands.spawn(SpriteBundle {
+        texture: texture.clone(),
+        transform: Transform::from_scale(Vec3::splat(SCREEN_SCALE)),
+        ..default()
+    });
+    commands.spawn(AudioSourceBundle {
+        source: audio.add(SpectrumAudio {
+            samples: spectrum.samples.clone(),
+        }),
+        ..default()
+    });
+    commands.insert_resource(ScreenTexture(texture));
+}
+
+fn emulate_frame(mut spectrum: NonSendMut<Spectrum>) {
+    let spectrum = &mut *spectrum;
+    spectrum
+        .emulator
+        .emulate_frames(Duration::MAX)
+        .expect("Emulation failed");
+
+    let frames = spectrum.emulator.pull_audio(&mut spectrum.audio_buffer);
+    let mut samples = spectrum.samples.lock().unwrap();
+    samples.extend(&spectrum.audio_buffer[..frames * AUDIO_CHANNELS]);
+    // Drop the oldest samples if audio thread can't keep up
+    let overflow = samples.len().saturating_sub(AUDIO_BUFFER_LIMIT);
+    samples.drain(..overflow);
+}
+
+fn update_screen(
+    spectrum: NonSend<Spectrum>,
+    texture: Res<ScreenTexture>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if let Some(image) = images.get_mut(&texture.0) {
+        compose_rgba_frame(
+            spectrum.emulator.border_buffer(),
+            spectrum.emulator.screen_buffer(),
+            &mut image.data,
+        );
+    }
+}
+
+fn send_keys(mut spectrum: NonSendMut<Spectrum>, keyboard: Res<ButtonInput<KeyCode>>) {
+    for key in keyboard.get_just_pressed() {
+        if let Some(zx_key) = map_key(*key) {
+            spectrum.emulator.send_key(zx_key, true);
+        }
+    }
+    for key in keyboard.get_just_released() {
+        if let Some(zx_key) = map_key(*key) {
+            spectrum.emulator.send_key(zx_key, false);
+        }
+    }
+}
+
+fn map_key(key: KeyCode) -> Option<ZXKey> {
+    let zx_key = match key {
+        KeyCode::ShiftLeft => ZXKey::Shift,
+        KeyCode::ShiftRight | KeyCode::ControlLeft => ZXKey::SymShift,
+        KeyCode::Enter => ZXKey::Enter,
+        KeyCode::Space => ZXKey::Space,
+        KeyCode::Digit0 => ZXKey::N0,
+        KeyCode::Digit1 => ZXKey::N1,
+        KeyCode::Digit2 => ZXKey::N2,
+        KeyCode::Digit3 => ZXKey::N3,
+        KeyCode::Digit4 => ZXKey::N4,
+        KeyCode::Digit5 => ZXKey::N5,
+        KeyCode::Digit6 => ZXKey::N6,
+        KeyCode::Digit7 => ZXKey::N7,
+        KeyCode::Digit8 => ZXKey::N8,
+        KeyCode::Digit9 => ZXKey::N9,
+        KeyCode::KeyA => ZXKey::A,
+        KeyCode::KeyB => ZXKey::B,
+        KeyCode::KeyC => ZXKey::C,
+        KeyCode::KeyD => ZXKey::D,
+        KeyCode::KeyE => ZXKey::E,
+        KeyCode::KeyF => ZXKey::F,
+        KeyCode::KeyG => ZXKey::G,
+        KeyCode::KeyH => ZXKey::H,
+        KeyCode::KeyI => ZXKey::I,
+        KeyCode::KeyJ => ZXKey::J,
+        KeyCode::KeyK => ZXKey::K,
+        KeyCode::KeyL => ZXKey::L,
+        KeyCode::KeyM => ZXKey::M,
+        KeyCode::KeyN => ZXKey::N,
+        KeyCode::KeyO => ZXKey::O,
+        KeyCode::KeyP => ZXKey::P,
+        KeyCode::KeyQ => ZXKey::Q,
+        KeyCode::KeyR => ZXKey::R,
+        KeyCode::KeyS => ZXKey::S,
+        KeyCode::KeyT => ZXKey::T,
+        KeyCode::KeyU => ZXKey::U,
+        KeyCode::KeyV => ZXKey::V,
+        KeyCode::KeyW => ZXKey::W,
+        KeyCode::KeyX => ZXKey::X,
+        KeyCode::KeyY => ZXKey::Y,
+        KeyCode::KeyZ => ZXKey::Z,
+        _ => return None,
+    };
+    Some(zx_key)
+}
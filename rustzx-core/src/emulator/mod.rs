@@ -1,38 +1,50 @@
 //! Platform-independent high-level Emulator interaction module
 mod fastload;
 pub mod poke;
+mod screen_reader;
 mod screenshot;
 mod snapshot;
+pub mod state;
 
 use crate::{
-    error::RomLoadError,
+    error::{RomLoadError, SettingsError},
     host::{
-        DataRecorder, Host, LoadableAsset, RomFormat, RomSet, Screen, ScreenAsset, Snapshot,
-        SnapshotAsset, SnapshotRecorder, Stopwatch, Tape,
+        DataRecorder, FrameBuffer, FrameBuffers, Host, HostContext, LoadableAsset, RomFormat,
+        RomSet, Screen, ScreenAsset, Snapshot, SnapshotAsset, SnapshotRecorder, Stopwatch, Tape,
     },
     settings::RustzxSettings,
     utils::EmulationMode,
     zx::{
         controller::ZXController,
         events::EmulationEvents,
+        feedback::{FeedbackEvent, MemoryTrigger},
         joy::{
             kempston::KempstonKey,
             sinclair::{SinclairJoyNum, SinclairKey},
         },
         keys::{CompoundKey, ZXKey},
+        machine::ZXMachine,
         mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection},
-        tape::{Tap, TapeImpl},
-        video::colors::ZXColor,
+        peripherals::{self, Peripheral},
+        tape::{TapParser, TapeImpl, TapePlayer, TzxParser},
+        video::{colors::ZXColor, screen::ZXScreen},
     },
     Result,
 };
+use alloc::{string::String, vec::Vec};
 use core::time::Duration;
 use rustzx_z80::Z80;
+use snapshot::slt::SltLevel;
+use state::{CpuState, MachineState};
 
+#[cfg(feature = "autoload")]
+use crate::host::BufferCursor;
+#[cfg(feature = "ide")]
+use crate::zx::ide::SimpleIde;
 #[cfg(feature = "sound")]
 use crate::zx::sound::sample::SoundSample;
-#[cfg(feature = "autoload")]
-use crate::{host::BufferCursor, zx::machine::ZXMachine};
+#[cfg(feature = "precise-border")]
+use crate::zx::video::border::ZXBorder;
 
 /// Represents emulator stop reason
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -47,8 +59,10 @@ pub enum EmulationStopReason {
 
 /// Represents emulator emulation result
 pub struct EmulationInfo {
-    /// Emulation duration in emulated time (not real time)
+    /// Host time spent on emulation, measured by the host stopwatch
     pub duration: Duration,
+    /// Count of fully emulated frames
+    pub frames: usize,
     /// Emulation stop reason, see [EmulationStopReason]
     pub stop_reason: EmulationStopReason,
 }
@@ -62,6 +76,9 @@ pub struct Emulator<H: Host> {
     fast_load: bool,
     #[cfg(feature = "sound")]
     sound_enabled: bool,
+    slt_levels: Vec<SltLevel>,
+    last_basic_report: Option<String>,
+    frame_buffer_context: <H::FrameBuffer as FrameBuffer>::Context,
 }
 
 impl<H: Host> Emulator<H> {
@@ -69,12 +86,19 @@ impl<H: Host> Emulator<H> {
     /// # Arguments
     /// `settings` - emulator settings
     pub fn new(settings: RustzxSettings, context: H::Context) -> Result<Self> {
+        #[cfg(feature = "embedded-roms")]
+        if settings.load_default_rom && !settings.machine.has_embedded_rom() {
+            return Err(RomLoadError::EmbeddedRomNotAvailable.into());
+        }
+        settings.validate()?;
+
         let mode = settings.emulation_mode;
         let fast_load = settings.tape_fastload_enabled;
         #[cfg(feature = "sound")]
         let sound_enabled = settings.sound_enabled;
 
         let cpu = Z80::default();
+        let frame_buffer_context = context.frame_buffer_context();
         let controller = ZXController::<H>::new(&settings, context);
 
         let this = Self {
@@ -85,6 +109,9 @@ impl<H: Host> Emulator<H> {
             fast_load,
             #[cfg(feature = "sound")]
             sound_enabled,
+            slt_levels: Vec::new(),
+            last_basic_report: None,
+            frame_buffer_context,
         };
 
         Ok(this)
@@ -106,6 +133,49 @@ impl<H: Host> Emulator<H> {
         self.sound_enabled = value;
     }
 
+    /// Attaches or detaches Kempston joystick at runtime. Returns error if
+    /// joystick conflicts with the already attached peripherals
+    pub fn set_kempston_enabled(&mut self, value: bool) -> core::result::Result<(), SettingsError> {
+        if value {
+            self.check_peripheral(Peripheral::KempstonJoy)?;
+        }
+        self.settings.kempston_enabled = value;
+        self.controller.set_kempston_enabled(value);
+        Ok(())
+    }
+
+    pub fn kempston_enabled(&self) -> bool {
+        self.controller.kempston.is_some()
+    }
+
+    /// Attaches or detaches Kempston mouse at runtime. Returns error if mouse
+    /// conflicts with the already attached peripherals
+    pub fn set_mouse_enabled(&mut self, value: bool) -> core::result::Result<(), SettingsError> {
+        if value {
+            self.check_peripheral(Peripheral::KempstonMouse)?;
+        }
+        self.settings.mouse_enabled = value;
+        self.controller.set_mouse_enabled(value);
+        Ok(())
+    }
+
+    pub fn mouse_enabled(&self) -> bool {
+        self.settings.mouse_enabled
+    }
+
+    /// Enables or disables AY chip at runtime. On 48K AY is treated as an
+    /// external interface, which is removed from the bus when disabled
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fn set_ay_enabled(&mut self, value: bool) {
+        self.settings.ay_enabled = value;
+        self.controller.set_ay_enabled(value);
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fn ay_enabled(&self) -> bool {
+        self.settings.ay_enabled
+    }
+
     /// function for sound generation request check
     #[cfg(feature = "sound")]
     pub fn have_sound(&self) -> bool {
@@ -118,8 +188,14 @@ impl<H: Host> Emulator<H> {
     }
 
     pub fn load_snapshot(&mut self, snapshot: Snapshot<impl SnapshotAsset>) -> Result<()> {
+        // Level data of previously loaded SLT snapshot is not relevant anymore
+        self.slt_levels.clear();
         match snapshot {
             Snapshot::Sna(asset) => snapshot::sna::load(self, asset),
+            Snapshot::Z80(asset) => snapshot::z80::load(self, asset),
+            #[cfg(feature = "szx")]
+            Snapshot::Szx(asset) => snapshot::szx::load(self, asset),
+            Snapshot::Slt(asset) => snapshot::slt::load(self, asset),
         }
     }
 
@@ -129,24 +205,36 @@ impl<H: Host> Emulator<H> {
     {
         match recorder {
             SnapshotRecorder::Sna(recorder) => snapshot::sna::save(self, recorder),
+            SnapshotRecorder::Z80(recorder) => snapshot::z80::save(self, recorder),
         }
     }
 
     pub fn load_tape(&mut self, tape: Tape<H::TapeAsset>) -> Result<()> {
-        match tape {
-            Tape::Tap(asset) => {
-                self.controller.tape = Tap::from_asset(asset)?.into();
-            }
-        }
+        let stop_if_48k = matches!(
+            self.settings.machine,
+            ZXMachine::Sinclair48K | ZXMachine::TimexTC2048
+        );
+        self.controller.tape = match tape {
+            Tape::Tap(asset) => TapePlayer::new(TapParser::from_asset(asset), stop_if_48k).into(),
+            Tape::Tzx(asset) => TapePlayer::new(TzxParser::from_asset(asset), stop_if_48k).into(),
+            Tape::Blocks(source) => TapePlayer::new(source, stop_if_48k).into(),
+        };
 
         #[cfg(feature = "autoload")]
         if self.settings.autoload_enabled {
             let snapshot = match self.settings.machine {
-                ZXMachine::Sinclair48K => &snapshot::autoload::tape::SNAPSHOT_SNA_48K,
-                ZXMachine::Sinclair128K => &snapshot::autoload::tape::SNAPSHOT_SNA_128K,
+                ZXMachine::Sinclair48K => Some(&snapshot::autoload::tape::SNAPSHOT_SNA_48K),
+                ZXMachine::Sinclair128K => Some(&snapshot::autoload::tape::SNAPSHOT_SNA_128K),
+                // Autoload snapshot depends on the ROM, which is not embedded for +3
+                // and localized models
+                ZXMachine::SinclairPlus3
+                | ZXMachine::Sinclair128KSpanish
+                | ZXMachine::TimexTC2048 => None,
             };
 
-            self.load_snapshot(Snapshot::Sna(BufferCursor::new(snapshot)))?;
+            if let Some(snapshot) = snapshot {
+                self.load_snapshot(Snapshot::Sna(BufferCursor::new(snapshot)))?;
+            }
         }
 
         Ok(())
@@ -192,6 +280,19 @@ impl<H: Host> Emulator<H> {
         self.controller.tape.rewind()
     }
 
+    /// Returns index of the tape block which is currently loading (or was
+    /// loaded last), `None` if tape is not inserted or no blocks were read yet
+    pub fn tape_block(&self) -> Option<usize> {
+        self.controller.tape.current_block()
+    }
+
+    /// Returns part of the last emulated frame (0.0..=1.0) which CPU spent in
+    /// HALT state. Value close to 1.0 means that the machine is idle (e.g. ROM
+    /// waiting for a key press at the BASIC prompt)
+    pub fn last_frame_halt_ratio(&self) -> f32 {
+        self.controller.last_frame_halt_ratio()
+    }
+
     pub fn screen_buffer(&self) -> &H::FrameBuffer {
         self.controller.screen.frame_buffer()
     }
@@ -201,6 +302,40 @@ impl<H: Host> Emulator<H> {
         self.controller.border.frame_buffer()
     }
 
+    /// Creates frame buffers to be exchanged via [Emulator::swap_frame_buffers]
+    pub fn new_frame_buffers(&self) -> FrameBuffers<H::FrameBuffer> {
+        FrameBuffers {
+            screen: ZXScreen::<H::FrameBuffer>::new_frame_buffer(self.frame_buffer_context.clone()),
+            #[cfg(feature = "precise-border")]
+            border: ZXBorder::<H::FrameBuffer>::new_frame_buffer(
+                self.settings.border_mode,
+                self.frame_buffer_context.clone(),
+            ),
+        }
+    }
+
+    /// Exchanges buffers of the last completed frame with the provided ones,
+    /// which are reused by the emulator. This way host owns the completed
+    /// frame (e.g. can pass it to the render thread) without copying, while
+    /// emulation continues into the other buffers, so torn frames are not
+    /// possible. Returns false and leaves buffers untouched if no frame was
+    /// completed since the previous swap. After the swap [Emulator::screen_buffer]
+    /// and [Emulator::border_buffer] return stale contents until the next
+    /// frame is completed
+    pub fn swap_frame_buffers(&mut self, buffers: &mut FrameBuffers<H::FrameBuffer>) -> bool {
+        if !self.controller.take_frame_completed() {
+            return false;
+        }
+        self.controller
+            .screen
+            .swap_frame_buffer(&mut buffers.screen);
+        #[cfg(feature = "precise-border")]
+        self.controller
+            .border
+            .swap_frame_buffer(&mut buffers.border);
+        true
+    }
+
     pub fn set_io_extender(&mut self, extender: H::IoExtender) {
         self.controller.io_extender = Some(extender);
     }
@@ -209,6 +344,48 @@ impl<H: Host> Emulator<H> {
         self.controller.io_extender.as_mut()
     }
 
+    /// Attaches [Host::DiskImage] as master drive of the simple 8-bit IDE interface
+    /// (as supported by +3e ROMs). Previously attached disk is returned
+    #[cfg(feature = "ide")]
+    pub fn attach_ide_disk(&mut self, disk: H::DiskImage) -> Option<H::DiskImage> {
+        self.controller
+            .ide
+            .replace(SimpleIde::new(disk))
+            .map(SimpleIde::into_disk)
+    }
+
+    /// Detaches IDE interface with its disk image
+    #[cfg(feature = "ide")]
+    pub fn detach_ide_disk(&mut self) -> Option<H::DiskImage> {
+        self.controller.ide.take().map(SimpleIde::into_disk)
+    }
+
+    /// Returns peripherals which are currently attached to the machine
+    pub fn peripherals(&self) -> Vec<Peripheral> {
+        #[cfg(feature = "ide")]
+        let ide = self.controller.ide.as_ref().map(|_| Peripheral::Ide);
+        #[cfg(not(feature = "ide"))]
+        let ide = None;
+        self.settings.peripherals().into_iter().chain(ide).collect()
+    }
+
+    /// Checks that `peripheral` can be attached without conflicts with the
+    /// already attached ones
+    pub fn check_peripheral(
+        &self,
+        peripheral: Peripheral,
+    ) -> core::result::Result<(), SettingsError> {
+        let mut peripherals = self.peripherals();
+        peripherals.push(peripheral);
+        peripherals::check_conflicts(&peripherals)
+    }
+
+    /// Returns currently attached [Host::DiskImage]
+    #[cfg(feature = "ide")]
+    pub fn ide_disk(&mut self) -> Option<&mut H::DiskImage> {
+        self.controller.ide.as_mut().map(SimpleIde::disk)
+    }
+
     /// Sets [Host::DebugInterface] for the emulator instance
     pub fn set_debug_interface(&mut self, debug_interface: H::DebugInterface) {
         self.controller.debug_interface = Some(debug_interface);
@@ -224,10 +401,79 @@ impl<H: Host> Emulator<H> {
         self.controller.memory.read(addr)
     }
 
+    /// Returns text of the screen character row (`0..24`), recognized with the
+    /// ROM character set. Unrecognized characters are replaced with `?`
+    pub fn screen_text_row(&self, row: usize) -> String {
+        assert!(row < screen_reader::TEXT_ROWS);
+        screen_reader::read_text_row(&self.controller, row)
+    }
+
+    /// Checks the lower screen for the standard BASIC report (e.g. `0 OK, 0:1`)
+    /// and returns it if it has appeared since the previous call. Intended to
+    /// be called once per frame to provide spoken feedback for blind users
+    pub fn poll_basic_report(&mut self) -> Option<String> {
+        let report = (screen_reader::TEXT_ROWS - 2..screen_reader::TEXT_ROWS)
+            .map(|row| screen_reader::read_text_row(&self.controller, row))
+            .find(|text| screen_reader::is_basic_report(text));
+        if report == self.last_basic_report {
+            return None;
+        }
+        self.last_basic_report = report.clone();
+        report
+    }
+
     pub fn border_color(&self) -> ZXColor {
         self.controller.border_color
     }
 
+    /// Returns current CPU and peripherals state
+    pub fn machine_state(&self) -> MachineState {
+        let regs = &self.cpu.regs;
+        let cpu = CpuState {
+            af: regs.get_af(),
+            bc: regs.get_bc(),
+            de: regs.get_de(),
+            hl: regs.get_hl(),
+            af_alt: u16::from_be_bytes([regs.get_acc_alt(), regs.get_flags_alt()]),
+            bc_alt: u16::from_be_bytes([regs.get_b_alt(), regs.get_c_alt()]),
+            de_alt: u16::from_be_bytes([regs.get_d_alt(), regs.get_e_alt()]),
+            hl_alt: u16::from_be_bytes([regs.get_h_alt(), regs.get_l_alt()]),
+            ix: regs.get_ix(),
+            iy: regs.get_iy(),
+            sp: regs.get_sp(),
+            pc: regs.get_pc(),
+            mem_ptr: regs.get_mem_ptr(),
+            i: regs.get_i(),
+            r: regs.get_r(),
+            iff1: regs.get_iff1(),
+            iff2: regs.get_iff2(),
+            im: self.cpu.get_im().into(),
+            halted: self.cpu.is_halted(),
+        };
+        let machine = self.settings.machine;
+        MachineState {
+            machine,
+            cpu,
+            border: self.controller.border_color,
+            frame_clocks: self.controller.frame_clocks(),
+            port_7ffd: (machine.base_machine() != ZXMachine::Sinclair48K)
+                .then(|| self.controller.read_7ffd()),
+            port_1ffd: (machine == ZXMachine::SinclairPlus3).then(|| self.controller.read_1ffd()),
+            ay_registers: self.controller.ay_registers(),
+        }
+    }
+
+    /// Returns count of 16K RAM pages
+    pub fn ram_pages_count(&self) -> usize {
+        self.controller.memory.ram_pages_count()
+    }
+
+    /// Returns contents of 16K RAM page. For 48K machine pages are mapped to
+    /// 0x4000, 0x8000 and 0xC000 addresses consequently
+    pub fn ram_page(&self, page: u8) -> &[u8] {
+        self.controller.memory.ram_page_data(page)
+    }
+
     pub fn send_key(&mut self, key: ZXKey, pressed: bool) {
         self.controller.send_key(key, pressed);
     }
@@ -238,7 +484,11 @@ impl<H: Host> Emulator<H> {
 
     pub fn send_kempston_key(&mut self, key: KempstonKey, pressed: bool) {
         if let Some(joy) = &mut self.controller.kempston {
+            let fire_pressed = joy.read() & KempstonKey::Fire as u8 != 0;
             joy.key(key, pressed);
+            if matches!(key, KempstonKey::Fire) && pressed && !fire_pressed {
+                self.controller.feedback.push(FeedbackEvent::KempstonFire);
+            }
         }
     }
 
@@ -263,6 +513,48 @@ impl<H: Host> Emulator<H> {
         self.controller.mixer.pop()
     }
 
+    /// Fills `buffer` with interleaved stereo samples (left, right, left, ...) which
+    /// were generated by the emulator so far. Returns count of written stereo
+    /// frames, which can be less than `buffer.len() / 2` if not enough samples
+    /// are available yet.
+    #[cfg(feature = "sound")]
+    pub fn pull_audio(&mut self, buffer: &mut [f32]) -> usize {
+        let mut frames = 0;
+        for frame in buffer.chunks_exact_mut(2) {
+            match self.controller.mixer.pop() {
+                Some(sample) => {
+                    frame[0] = sample.left;
+                    frame[1] = sample.right;
+                    frames += 1;
+                }
+                None => break,
+            }
+        }
+        frames
+    }
+
+    /// Enables or disables collection of [FeedbackEvent]s. When enabled, host
+    /// should drain them via [Emulator::next_feedback_event]
+    pub fn set_feedback_enabled(&mut self, value: bool) {
+        self.controller.feedback.set_enabled(value);
+    }
+
+    /// Registers RAM write watch, returns trigger id which is reported in
+    /// [FeedbackEvent::MemoryTrigger]
+    pub fn add_memory_trigger(&mut self, trigger: MemoryTrigger) -> usize {
+        self.controller.feedback.add_trigger(trigger)
+    }
+
+    /// Removes all registered memory triggers
+    pub fn clear_memory_triggers(&mut self) {
+        self.controller.feedback.clear_triggers();
+    }
+
+    /// Returns next pending feedback event
+    pub fn next_feedback_event(&mut self) -> Option<FeedbackEvent> {
+        self.controller.feedback.pop()
+    }
+
     fn process_fast_load_event(&mut self) -> Result<()> {
         if self.controller.tape.can_fast_load() && self.fast_load {
             fastload::tap::fast_load_tap(self)?;
@@ -284,6 +576,7 @@ impl<H: Host> Emulator<H> {
     /// Perform emulatio up to `emulation_limit` duration, returns actual elapsed duration
     pub fn emulate_frames(&mut self, emulation_limit: Duration) -> Result<EmulationInfo> {
         let stopwatch = H::EmulationStopwatch::new();
+        let mut frames = 0;
         // frame loop
         loop {
             // reset controller internal frame counter
@@ -300,25 +593,31 @@ impl<H: Host> Emulator<H> {
                     if events.contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED) {
                         self.process_fast_load_event()?;
                     }
+                    if events.contains(EmulationEvents::SLT_TRAP) {
+                        snapshot::slt::trap(self);
+                    }
                     if events.contains(EmulationEvents::PC_BREAKPOINT) {
                         return Ok(EmulationInfo {
                             duration: stopwatch.measure(),
+                            frames: frames + self.controller.frames_count(),
                             stop_reason: EmulationStopReason::Breakpoint,
                         });
                     }
                 }
 
                 match self.mode {
-                    EmulationMode::FrameCount(frames) => {
-                        if self.controller.frames_count() >= frames {
+                    EmulationMode::FrameCount(count) => {
+                        if self.controller.frames_count() >= count {
                             return Ok(EmulationInfo {
                                 duration: stopwatch.measure(),
+                                frames: self.controller.frames_count(),
                                 stop_reason: EmulationStopReason::Completed,
                             });
                         };
                     }
                     EmulationMode::Max => {
                         if self.controller.frames_count() != 0 {
+                            frames += self.controller.frames_count();
                             break 'cpu;
                         }
                     }
@@ -328,6 +627,7 @@ impl<H: Host> Emulator<H> {
             if stopwatch.measure() > emulation_limit {
                 return Ok(EmulationInfo {
                     duration: stopwatch.measure(),
+                    frames,
                     stop_reason: EmulationStopReason::Timeout,
                 });
             }
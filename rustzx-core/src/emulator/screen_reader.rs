@@ -0,0 +1,75 @@
+//! Text recognition of the screen contents, used for accessibility features
+//! (e.g. reading of BASIC reports aloud)
+use crate::{
+    host::Host,
+    utils::screen::bitmap_line_addr,
+    zx::{
+        constants::{CANVAS_HEIGHT, CANVAS_WIDTH},
+        controller::ZXController,
+    },
+};
+use alloc::string::String;
+
+pub const TEXT_ROWS: usize = CANVAS_HEIGHT / 8;
+pub const TEXT_COLS: usize = CANVAS_WIDTH / 8;
+
+/// Character set location in the BASIC ROM, covers `0x20..=0x7F` codes
+const ROM_CHARSET_ADDR: usize = 0x3D00;
+const ROM_CHARSET_FIRST_CHAR: u8 = 0x20;
+const ROM_CHARSET_LEN: usize = 96;
+/// The last ROM charset character is the copyright sign
+const ROM_CHAR_COPYRIGHT: u8 = 0x7F;
+/// Character which replaces unrecognized screen cells
+const UNKNOWN_CHAR: char = '?';
+
+/// Reads text of the character row (`0..24`), recognized with the ROM
+/// character set. Inverse video characters (e.g. cursor) are recognized as
+/// well. Trailing spaces are trimmed
+pub(crate) fn read_text_row<H: Host>(controller: &ZXController<H>, row: usize) -> String {
+    let screen = controller.memory.ram_page_data(controller.screen_bank());
+    let charset = &controller.memory.rom_page_data(controller.basic_rom_page())
+        [ROM_CHARSET_ADDR..ROM_CHARSET_ADDR + ROM_CHARSET_LEN * 8];
+
+    let mut text = String::with_capacity(TEXT_COLS);
+    for col in 0..TEXT_COLS {
+        let mut cell = [0u8; 8];
+        for (line, byte) in cell.iter_mut().enumerate() {
+            let addr = bitmap_line_addr(row * 8 + line) as usize - 0x4000 + col;
+            *byte = screen[addr];
+        }
+        let inverse = cell.map(|byte| !byte);
+        let ch = charset
+            .chunks_exact(8)
+            .position(|glyph| glyph == cell || glyph == inverse)
+            .map(|index| match ROM_CHARSET_FIRST_CHAR + index as u8 {
+                ROM_CHAR_COPYRIGHT => '©',
+                code => code as char,
+            })
+            .unwrap_or(UNKNOWN_CHAR);
+        text.push(ch);
+    }
+    let len = text.trim_end().len();
+    text.truncate(len);
+    text
+}
+
+/// Returns true if text has format of the standard BASIC report,
+/// e.g. `0 OK, 0:1` or `C Nonsense in BASIC, 10:1`
+pub(crate) fn is_basic_report(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    if bytes.len() < 2 || !matches!(bytes[0], b'0'..=b'9' | b'A'..=b'R') || bytes[1] != b' ' {
+        return false;
+    }
+    let position = match text.rfind(", ") {
+        Some(pos) if pos > 2 => &text[pos + 2..],
+        _ => return false,
+    };
+    match position.split_once(':') {
+        Some((line, statement)) => is_number(line) && is_number(statement),
+        None => false,
+    }
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
@@ -1,3 +1,43 @@
 #[cfg(feature = "autoload")]
 pub mod autoload;
+pub mod slt;
 pub mod sna;
+#[cfg(feature = "szx")]
+pub mod szx;
+pub mod z80;
+
+use crate::{
+    host::{LoadableAsset, SeekFrom, SeekableAsset, Snapshot, SnapshotAsset},
+    zx::machine::ZXMachine,
+    Result,
+};
+use alloc::{vec, vec::Vec};
+
+impl<A: SnapshotAsset> Snapshot<A> {
+    /// Detects machine required by the snapshot from its header. Returns base
+    /// machine, localized models of the same machine can load the snapshot
+    /// too (see `ZXMachine::can_load_snapshot_of`). Snapshots which rely on
+    /// Timex TC2048 hardware return `ZXMachine::TimexTC2048`. Snapshot asset
+    /// is rewound to the start after detection
+    pub fn machine(&mut self) -> Result<ZXMachine> {
+        let (machine, asset) = match self {
+            Snapshot::Sna(asset) => (sna::machine(asset), asset),
+            Snapshot::Z80(asset) => (z80::machine(asset), asset),
+            #[cfg(feature = "szx")]
+            Snapshot::Szx(asset) => (szx::machine(asset), asset),
+            Snapshot::Slt(asset) => (slt::machine(asset), asset),
+        };
+        asset.seek(SeekFrom::Start(0))?;
+        machine
+    }
+}
+
+/// Reads whole snapshot asset into memory. Used for formats with
+/// variable-size blocks, which are easier to parse from a slice
+fn read_whole_asset(asset: &mut (impl LoadableAsset + SeekableAsset)) -> Result<Vec<u8>> {
+    let size = asset.seek(SeekFrom::End(0))?;
+    asset.seek(SeekFrom::Start(0))?;
+    let mut data = vec![0u8; size];
+    asset.read_exact(&mut data)?;
+    Ok(data)
+}
@@ -0,0 +1,106 @@
+//! SLT (super level loader) snapshot format loading. SLT file is Z80 snapshot
+//! followed by the table of level data blocks, which are loaded by the game
+//! via `ED FB` trap instruction instead of the tape.
+//! Format description: https://worldofspectrum.org/faq/reference/z80format.htm
+use crate::{
+    emulator::{
+        snapshot::{
+            read_whole_asset,
+            z80::{self, Z80_SLT_SIGNATURE},
+        },
+        Emulator,
+    },
+    error::SnapshotLoadError,
+    host::{Host, LoadableAsset, SeekableAsset},
+    zx::machine::ZXMachine,
+    Result,
+};
+use alloc::vec::Vec;
+use rustzx_z80::{RegName16, Z80Bus};
+
+const SLT_TABLE_ENTRY_SIZE: usize = 8;
+const SLT_DATA_TYPE_END: u16 = 0;
+const SLT_DATA_TYPE_LEVEL: u16 = 1;
+
+/// Level data block, loaded from SLT file
+pub(crate) struct SltLevel {
+    number: u16,
+    data: Vec<u8>,
+}
+
+/// Returns base machine required by the SLT snapshot
+pub fn machine(asset: &mut (impl LoadableAsset + SeekableAsset)) -> Result<ZXMachine> {
+    let data = read_whole_asset(asset)?;
+    z80::machine_from_data(&data)
+}
+
+/// SLT snapshot loading function. Only level data blocks are kept, other
+/// blocks (instructions, screens, pokes) are skipped
+pub fn load<H, A>(emulator: &mut Emulator<H>, mut asset: A) -> Result<()>
+where
+    H: Host,
+    A: LoadableAsset + SeekableAsset,
+{
+    let data = read_whole_asset(&mut asset)?;
+    let snapshot_end = z80::load_data(emulator, &data)?;
+
+    let mut table_pos = data
+        .get(snapshot_end..)
+        .filter(|extension| extension.starts_with(Z80_SLT_SIGNATURE))
+        .map(|_| snapshot_end + Z80_SLT_SIGNATURE.len())
+        .ok_or(SnapshotLoadError::InvalidSltFile)?;
+
+    let mut entries = Vec::new();
+    loop {
+        let entry = data
+            .get(table_pos..table_pos + SLT_TABLE_ENTRY_SIZE)
+            .ok_or(SnapshotLoadError::InvalidSltFile)?;
+        table_pos += SLT_TABLE_ENTRY_SIZE;
+
+        let data_type = u16::from_le_bytes([entry[0], entry[1]]);
+        if data_type == SLT_DATA_TYPE_END {
+            break;
+        }
+        let number = u16::from_le_bytes([entry[2], entry[3]]);
+        let length = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]) as usize;
+        entries.push((data_type, number, length));
+    }
+
+    // Data blocks follow the table in the same order as table entries
+    let mut data_pos = table_pos;
+    let mut levels = Vec::new();
+    for (data_type, number, length) in entries {
+        let block = data
+            .get(data_pos..data_pos + length)
+            .ok_or(SnapshotLoadError::InvalidSltFile)?;
+        data_pos += length;
+
+        if data_type == SLT_DATA_TYPE_LEVEL {
+            levels.push(SltLevel {
+                number,
+                data: z80::decompress_to_vec(block)?,
+            });
+        }
+    }
+
+    emulator.slt_levels = levels;
+
+    Ok(())
+}
+
+/// Handles `ED FB` trap: level data with the number from A register is
+/// written to memory at address from HL register. Trap is ignored if
+/// requested level is not present
+pub(crate) fn trap<H: Host>(emulator: &mut Emulator<H>) {
+    let number = emulator.cpu.regs.get_acc() as u16;
+    let mut addr = emulator.cpu.regs.get_reg_16(RegName16::HL);
+
+    let level = match emulator.slt_levels.iter().find(|l| l.number == number) {
+        Some(level) => level,
+        None => return,
+    };
+    for byte in level.data.iter().copied() {
+        emulator.controller.write_internal(addr, byte);
+        addr = addr.wrapping_add(1);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     emulator::Emulator,
-    error::IoError,
+    error::{IoError, SnapshotLoadError},
     host::{DataRecorder, Host, LoadableAsset, SeekFrom, SeekableAsset},
     zx::{machine::ZXMachine, video::colors::ZXColor},
     Result,
@@ -20,6 +20,18 @@ const SNA_128K_TAIL_BANKS: &[u8] = &[0, 1, 3, 4, 6, 7];
 const SNA_PAGINATED_PAGED_BANK_ADDRESS: u16 = 0xFFFF;
 const SNA_48K_RAM_PAGES_COUNT: u8 = 3;
 
+/// Returns base machine required by the SNA snapshot, which is defined by
+/// the snapshot size
+pub fn machine(asset: &mut (impl LoadableAsset + SeekableAsset)) -> Result<ZXMachine> {
+    let size = asset.seek(SeekFrom::End(0))?;
+    asset.seek(SeekFrom::Start(0))?;
+    match size {
+        SNA_48K_SIZE => Ok(ZXMachine::Sinclair48K),
+        size if size > SNA_48K_SIZE => Ok(ZXMachine::Sinclair128K),
+        _ => Err(IoError::UnexpectedEof.into()),
+    }
+}
+
 /// SNA snapshot loading function
 pub fn load<H, A>(emulator: &mut Emulator<H>, mut asset: A) -> Result<()>
 where
@@ -34,6 +46,15 @@ where
     if !is_128k && size < SNA_48K_SIZE {
         return Err(IoError::UnexpectedEof.into());
     }
+    let machine = if is_128k {
+        ZXMachine::Sinclair128K
+    } else {
+        ZXMachine::Sinclair48K
+    };
+    // SNA has no +2A/+3 paging state (1FFD port)
+    if !emulator.settings.machine.can_load_snapshot_of(machine) {
+        return Err(SnapshotLoadError::MachineNotSupported.into());
+    }
 
     let mut header = [0u8; SNA_HEADER_SIZE];
     asset.read_exact(&mut header)?;
@@ -173,7 +194,7 @@ struct ScopedSnapshotState<'a, H: Host> {
 
 impl<'a, H: Host> ScopedSnapshotState<'a, H> {
     fn enter(emulator: &'a mut Emulator<H>) -> Self {
-        let is_48k = emulator.settings.machine == ZXMachine::Sinclair48K;
+        let is_48k = emulator.settings.machine.base_machine() == ZXMachine::Sinclair48K;
         if is_48k {
             emulator.cpu.push_pc_to_stack(&mut emulator.controller);
         }
@@ -197,6 +218,9 @@ where
     H: Host,
     R: DataRecorder,
 {
+    if emulator.settings.machine.base_machine() == ZXMachine::SinclairPlus3 {
+        return Err(SnapshotLoadError::MachineNotSupported.into());
+    }
     let state = ScopedSnapshotState::enter(emulator);
     let ScopedSnapshotState { emulator, is_48k } = &state;
 
@@ -0,0 +1,359 @@
+//! SZX (zx-state) snapshot format loading, used by Spectaculator, Fuse and ZX Spin.
+//! Format description: https://www.spectaculator.com/docs/zx-state/intro.shtml
+use crate::{
+    emulator::{snapshot::read_whole_asset, Emulator},
+    error::SnapshotLoadError,
+    host::{Host, LoadableAsset, SeekFrom, SeekableAsset},
+    zx::{machine::ZXMachine, memory::PAGE_SIZE, video::colors::ZXColor},
+    Result,
+};
+
+const SZX_MAGIC: &[u8; 4] = b"ZXST";
+const SZX_HEADER_SIZE: usize = 8;
+const SZX_CHUNK_HEADER_SIZE: usize = 8;
+const SZX_MACHINE_ID_OFFSET: usize = 6;
+
+const SZX_MACHINE_ID_48K: u8 = 1;
+const SZX_MACHINE_ID_128K: u8 = 2;
+const SZX_MACHINE_ID_PLUS2: u8 = 3;
+const SZX_MACHINE_ID_PLUS2A: u8 = 4;
+const SZX_MACHINE_ID_PLUS3: u8 = 5;
+const SZX_MACHINE_ID_PLUS3E: u8 = 6;
+const SZX_MACHINE_ID_TC2048: u8 = 8;
+
+const SZX_CHUNK_Z80_REGS: &[u8; 4] = b"Z80R";
+const SZX_CHUNK_SPECTRUM_REGS: &[u8; 4] = b"SPCR";
+const SZX_CHUNK_RAM_PAGE: &[u8; 4] = b"RAMP";
+#[cfg(all(feature = "sound", feature = "ay"))]
+const SZX_CHUNK_AY: &[u8; 4] = b"AY\0\0";
+const SZX_CHUNK_JOYSTICK: &[u8; 4] = b"JOY\0";
+const SZX_CHUNK_KEYBOARD: &[u8; 4] = b"KEYB";
+const SZX_CHUNK_MOUSE: &[u8; 4] = b"AMXM";
+const SZX_CHUNK_TIMEX_REGS: &[u8; 4] = b"SCLD";
+const SZX_CHUNK_PLUS3_DISK: &[u8; 4] = b"+3\0\0";
+/// Chunks with state of the peripherals which are not emulated. Snapshots
+/// with them are rejected instead of silently running without the peripheral
+const SZX_UNSUPPORTED_CHUNKS: &[&[u8; 4]] = &[
+    b"ATRP", b"B128", b"BDSK", b"CFRP", b"COVX", b"DIDE", b"DOCK", b"DRUM", b"DSK\0", b"GS\0\0",
+    b"GSRP", b"IF1\0", b"IF2R", b"MFCE", b"ODSK", b"OPUS", b"PDSK", b"PLSD", b"PLTT", b"ROM\0",
+    b"SIDE", b"SPXR", b"USPE", b"ZMMC", b"ZXAT", b"ZXPR",
+];
+
+const SZX_Z80_REGS_SIZE: usize = 37;
+const SZX_SPECTRUM_REGS_SIZE: usize = 8;
+const SZX_RAM_PAGE_HEADER_SIZE: usize = 3;
+#[cfg(all(feature = "sound", feature = "ay"))]
+const SZX_AY_SIZE: usize = 18;
+const SZX_JOYSTICK_SIZE: usize = 6;
+const SZX_KEYBOARD_SIZE: usize = 5;
+const SZX_MOUSE_SIZE: usize = 7;
+const SZX_TIMEX_REGS_SIZE: usize = 2;
+const SZX_PLUS3_DISK_SIZE: usize = 2;
+const SZX_Z80_FLAG_EI_LAST: u8 = 0x01;
+const SZX_Z80_FLAG_HALTED: u8 = 0x02;
+const SZX_JOYSTICK_FLAG_ALWAYS_PORT_31: u32 = 0x01;
+const SZX_JOYSTICK_KEMPSTON: u8 = 0;
+const SZX_JOYSTICK_CURSOR: u8 = 2;
+const SZX_JOYSTICK_SINCLAIR1: u8 = 3;
+const SZX_JOYSTICK_SINCLAIR2: u8 = 4;
+const SZX_JOYSTICK_NONE: u8 = 8;
+const SZX_KEYBOARD_FLAG_ISSUE2: u32 = 0x01;
+const SZX_MOUSE_NONE: u8 = 0;
+const SZX_MOUSE_KEMPSTON: u8 = 2;
+const OPCODE_HALT: u8 = 0x76;
+const SZX_RAM_PAGE_COMPRESSED_FLAG: u16 = 0x0001;
+const SZX_BORDER_COLOR_MASK: u8 = 0x07;
+const SZX_INTERRUPT_MODE_MASK: u8 = 0x03;
+
+/// Returns ram bank index for the given szx page number
+fn ram_bank_from_page(machine: ZXMachine, page: u8) -> Option<u8> {
+    match machine {
+        ZXMachine::Sinclair48K | ZXMachine::TimexTC2048 => match page {
+            5 => Some(0),
+            2 => Some(1),
+            0 => Some(2),
+            _ => None,
+        },
+        ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish | ZXMachine::SinclairPlus3 => {
+            (page < 8).then_some(page)
+        }
+    }
+}
+
+/// Returns machine required by the SZX snapshot
+pub fn machine(asset: &mut (impl LoadableAsset + SeekableAsset)) -> Result<ZXMachine> {
+    let mut header = [0u8; SZX_HEADER_SIZE];
+    asset.seek(SeekFrom::Start(0))?;
+    asset
+        .read_exact(&mut header)
+        .map_err(|_| SnapshotLoadError::InvalidSzxFile)?;
+    machine_from_header(&header)
+}
+
+fn machine_from_header(data: &[u8]) -> Result<ZXMachine> {
+    let header = data
+        .get(..SZX_HEADER_SIZE)
+        .ok_or(SnapshotLoadError::InvalidSzxFile)?;
+    if &header[..SZX_MAGIC.len()] != SZX_MAGIC {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+
+    match header[SZX_MACHINE_ID_OFFSET] {
+        SZX_MACHINE_ID_48K => Ok(ZXMachine::Sinclair48K),
+        SZX_MACHINE_ID_128K | SZX_MACHINE_ID_PLUS2 => Ok(ZXMachine::Sinclair128K),
+        SZX_MACHINE_ID_PLUS2A | SZX_MACHINE_ID_PLUS3 | SZX_MACHINE_ID_PLUS3E => {
+            Ok(ZXMachine::SinclairPlus3)
+        }
+        SZX_MACHINE_ID_TC2048 => Ok(ZXMachine::TimexTC2048),
+        _ => Err(SnapshotLoadError::MachineNotSupported.into()),
+    }
+}
+
+/// SZX snapshot loading function. Chunks with state of the peripherals which
+/// are not emulated (e.g. Interface 1 or disk interfaces) result in error,
+/// other unknown chunks (e.g. creator info) are skipped
+pub fn load<H, A>(emulator: &mut Emulator<H>, mut asset: A) -> Result<()>
+where
+    H: Host,
+    A: LoadableAsset + SeekableAsset,
+{
+    let data = read_whole_asset(&mut asset)?;
+
+    let machine = machine_from_header(&data)?;
+    if !emulator.settings.machine.can_load_snapshot_of(machine) {
+        return Err(SnapshotLoadError::MachineNotSupported.into());
+    }
+
+    let mut halted = false;
+    let mut pos = SZX_HEADER_SIZE;
+    while pos < data.len() {
+        let chunk_header = data
+            .get(pos..pos + SZX_CHUNK_HEADER_SIZE)
+            .ok_or(SnapshotLoadError::InvalidSzxFile)?;
+        let id = &[
+            chunk_header[0],
+            chunk_header[1],
+            chunk_header[2],
+            chunk_header[3],
+        ];
+        let size = u32::from_le_bytes([
+            chunk_header[4],
+            chunk_header[5],
+            chunk_header[6],
+            chunk_header[7],
+        ]) as usize;
+        pos += SZX_CHUNK_HEADER_SIZE;
+        let chunk = data
+            .get(pos..pos + size)
+            .ok_or(SnapshotLoadError::InvalidSzxFile)?;
+        pos += size;
+
+        match id {
+            SZX_CHUNK_Z80_REGS => halted = load_z80_regs(emulator, chunk)?,
+            SZX_CHUNK_SPECTRUM_REGS => load_spectrum_regs(emulator, chunk)?,
+            SZX_CHUNK_RAM_PAGE => load_ram_page(emulator, chunk)?,
+            #[cfg(all(feature = "sound", feature = "ay"))]
+            SZX_CHUNK_AY => load_ay(emulator, chunk)?,
+            SZX_CHUNK_JOYSTICK => load_joystick(emulator, chunk)?,
+            SZX_CHUNK_KEYBOARD => check_keyboard(chunk)?,
+            SZX_CHUNK_MOUSE => load_mouse(emulator, chunk)?,
+            SZX_CHUNK_TIMEX_REGS => load_timex_regs(emulator, chunk)?,
+            SZX_CHUNK_PLUS3_DISK => check_plus3_disk(chunk)?,
+            id if SZX_UNSUPPORTED_CHUNKS.contains(&id) => {
+                return Err(SnapshotLoadError::PeripheralNotSupported.into());
+            }
+            _ => {}
+        }
+    }
+
+    // Halted CPU repeats `HALT` instruction, but some emulators save PC
+    // pointing after it. Memory is checked after all RAM pages are loaded
+    if halted {
+        let pc = emulator.cpu.regs.get_pc();
+        if emulator.controller.memory.read(pc) != OPCODE_HALT
+            && emulator.controller.memory.read(pc.wrapping_sub(1)) == OPCODE_HALT
+        {
+            emulator.cpu.regs.set_pc(pc.wrapping_sub(1));
+        }
+        emulator.cpu.set_halted(true);
+    }
+
+    // Refresh screen and other memory-dependent peripheral
+    emulator.controller.refresh_memory_dependent_devices();
+
+    Ok(())
+}
+
+/// Loads CPU registers and frame position, returns CPU halted state
+fn load_z80_regs<H: Host>(emulator: &mut Emulator<H>, chunk: &[u8]) -> Result<bool> {
+    if chunk.len() < SZX_Z80_REGS_SIZE {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+    let word = |offset: usize| u16::from_le_bytes([chunk[offset], chunk[offset + 1]]);
+
+    let regs = &mut emulator.cpu.regs;
+    // alt regs
+    regs.set_af(word(8));
+    regs.set_bc(word(10));
+    regs.set_de(word(12));
+    regs.set_hl(word(14));
+    regs.exx();
+    regs.swap_af_alt();
+    // regs
+    regs.set_af(word(0));
+    regs.set_bc(word(2));
+    regs.set_de(word(4));
+    regs.set_hl(word(6));
+    regs.set_ix(word(16));
+    regs.set_iy(word(18));
+    regs.set_sp(word(20));
+    regs.set_pc(word(22));
+    regs.set_i(chunk[24]);
+    regs.set_r(chunk[25]);
+    regs.set_iff1(chunk[26] != 0);
+    regs.set_iff2(chunk[27] != 0);
+    regs.set_mem_ptr(word(35));
+    emulator.cpu.set_im(chunk[28] & SZX_INTERRUPT_MODE_MASK);
+    let flags = chunk[34];
+    emulator
+        .cpu
+        .set_skip_interrupt(flags & SZX_Z80_FLAG_EI_LAST != 0);
+
+    let cycles_start = u32::from_le_bytes([chunk[29], chunk[30], chunk[31], chunk[32]]);
+    emulator.controller.set_frame_clocks(cycles_start as usize);
+
+    Ok(flags & SZX_Z80_FLAG_HALTED != 0)
+}
+
+fn load_spectrum_regs<H: Host>(emulator: &mut Emulator<H>, chunk: &[u8]) -> Result<()> {
+    if chunk.len() < SZX_SPECTRUM_REGS_SIZE {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+
+    emulator
+        .controller
+        .set_border_color(0, ZXColor::from_bits(chunk[0] & SZX_BORDER_COLOR_MASK));
+    if emulator.settings.machine == ZXMachine::SinclairPlus3 {
+        emulator.controller.write_1ffd(chunk[2]);
+    }
+    if emulator.settings.machine.base_machine() != ZXMachine::Sinclair48K {
+        emulator.controller.write_7ffd(chunk[1]);
+    }
+
+    Ok(())
+}
+
+fn load_ram_page<H: Host>(emulator: &mut Emulator<H>, chunk: &[u8]) -> Result<()> {
+    if chunk.len() < SZX_RAM_PAGE_HEADER_SIZE {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+    let flags = u16::from_le_bytes([chunk[0], chunk[1]]);
+    let bank = match ram_bank_from_page(emulator.settings.machine, chunk[2]) {
+        Some(bank) => bank,
+        None => return Ok(()),
+    };
+
+    let page_data = &chunk[SZX_RAM_PAGE_HEADER_SIZE..];
+    let dest = emulator.controller.memory.ram_page_data_mut(bank);
+    if flags & SZX_RAM_PAGE_COMPRESSED_FLAG != 0 {
+        let decompressed = miniz_oxide::inflate::decompress_to_vec_zlib(page_data)
+            .map_err(|_| SnapshotLoadError::InvalidSzxFile)?;
+        if decompressed.len() != PAGE_SIZE {
+            return Err(SnapshotLoadError::InvalidSzxFile.into());
+        }
+        dest.copy_from_slice(&decompressed);
+    } else {
+        let page_data = page_data
+            .get(..PAGE_SIZE)
+            .ok_or(SnapshotLoadError::InvalidSzxFile)?;
+        dest.copy_from_slice(page_data);
+    }
+
+    Ok(())
+}
+
+#[cfg(all(feature = "sound", feature = "ay"))]
+fn load_ay<H: Host>(emulator: &mut Emulator<H>, chunk: &[u8]) -> Result<()> {
+    if chunk.len() < SZX_AY_SIZE {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+    emulator.controller.mixer.ay.restore(&chunk[2..], chunk[1]);
+
+    Ok(())
+}
+
+fn load_joystick<H: Host>(emulator: &mut Emulator<H>, chunk: &[u8]) -> Result<()> {
+    if chunk.len() < SZX_JOYSTICK_SIZE {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+    let flags = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    let mut kempston = flags & SZX_JOYSTICK_FLAG_ALWAYS_PORT_31 != 0;
+    for joystick in [chunk[4], chunk[5]] {
+        match joystick {
+            SZX_JOYSTICK_KEMPSTON => kempston = true,
+            // Keyboard-mapped joysticks don't need any hardware
+            SZX_JOYSTICK_CURSOR
+            | SZX_JOYSTICK_SINCLAIR1
+            | SZX_JOYSTICK_SINCLAIR2
+            | SZX_JOYSTICK_NONE => {}
+            _ => return Err(SnapshotLoadError::PeripheralNotSupported.into()),
+        }
+    }
+    if kempston {
+        emulator.set_kempston_enabled(true)?;
+    }
+
+    Ok(())
+}
+
+/// Keyboard chunk holds only host joystick mapping besides the keyboard issue
+fn check_keyboard(chunk: &[u8]) -> Result<()> {
+    if chunk.len() < SZX_KEYBOARD_SIZE {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+    let flags = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    if flags & SZX_KEYBOARD_FLAG_ISSUE2 != 0 {
+        return Err(SnapshotLoadError::PeripheralNotSupported.into());
+    }
+
+    Ok(())
+}
+
+fn load_mouse<H: Host>(emulator: &mut Emulator<H>, chunk: &[u8]) -> Result<()> {
+    if chunk.len() < SZX_MOUSE_SIZE {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+    match chunk[0] {
+        SZX_MOUSE_NONE => {}
+        SZX_MOUSE_KEMPSTON => emulator.set_mouse_enabled(true)?,
+        _ => return Err(SnapshotLoadError::PeripheralNotSupported.into()),
+    }
+
+    Ok(())
+}
+
+fn load_timex_regs<H: Host>(emulator: &mut Emulator<H>, chunk: &[u8]) -> Result<()> {
+    if chunk.len() < SZX_TIMEX_REGS_SIZE {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+    // Memory paging via port 0xF4 requires dock or extended ROM
+    if emulator.settings.machine != ZXMachine::TimexTC2048 || chunk[0] != 0 {
+        return Err(SnapshotLoadError::PeripheralNotSupported.into());
+    }
+    emulator.controller.set_timex_video_mode(chunk[1]);
+
+    Ok(())
+}
+
+/// +3 disk drive is not emulated, so only snapshots with stopped motor can be
+/// loaded
+fn check_plus3_disk(chunk: &[u8]) -> Result<()> {
+    if chunk.len() < SZX_PLUS3_DISK_SIZE {
+        return Err(SnapshotLoadError::InvalidSzxFile.into());
+    }
+    if chunk[1] != 0 {
+        return Err(SnapshotLoadError::PeripheralNotSupported.into());
+    }
+
+    Ok(())
+}
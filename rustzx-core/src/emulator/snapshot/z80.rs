@@ -0,0 +1,454 @@
+//! Z80 snapshot format loading (versions 1, 2 and 3)
+//! Format description: https://worldofspectrum.org/faq/reference/z80format.htm
+use crate::{
+    emulator::{snapshot::read_whole_asset, Emulator},
+    error::{IoError, SnapshotLoadError},
+    host::{DataRecorder, Host, LoadableAsset, SeekableAsset},
+    zx::{
+        machine::ZXMachine,
+        memory::{PAGE_SIZE, SIZE_48K},
+        video::colors::ZXColor,
+    },
+    Result,
+};
+use alloc::{vec, vec::Vec};
+
+const Z80_V1_HEADER_SIZE: usize = 30;
+const Z80_V2_EXTRA_HEADER_SIZE: usize = 23;
+/// Size of v3 additional header with the last write to 1FFD port
+const Z80_V3_EXTRA_HEADER_SIZE: usize = 55;
+const Z80_EXTRA_HEADER_LENGTH_SIZE: usize = 2;
+const Z80_BLOCK_HEADER_SIZE: usize = 3;
+const Z80_UNCOMPRESSED_BLOCK_LENGTH: usize = 0xFFFF;
+const Z80_COMPRESSED_FLAG_MASK: u8 = 0x20;
+const Z80_BORDER_COLOR_MASK: u8 = 0x07;
+const Z80_INTERRUPT_MODE_MASK: u8 = 0x03;
+#[cfg(all(feature = "sound", feature = "ay"))]
+const Z80_AY_ENABLED_FLAG_MASK: u8 = 0x04;
+const Z80_MODIFIED_HARDWARE_FLAG_MASK: u8 = 0x80;
+const Z80_RLE_MARKER: u8 = 0xED;
+const Z80_V1_END_MARKER_SIZE: usize = 4;
+/// Signature of SLT extension, which may follow memory blocks of v2/v3 snapshot
+pub(super) const Z80_SLT_SIGNATURE: &[u8; 6] = b"\0\0\0SLT";
+#[cfg(all(feature = "sound", feature = "ay"))]
+const Z80_AY_REGS_COUNT: usize = 16;
+
+/// Offsets in the additional header of v2/v3 snapshots
+mod extra {
+    pub const PC: usize = 0;
+    pub const HARDWARE_MODE: usize = 2;
+    pub const PORT_7FFD: usize = 3;
+    /// 0xFF if Interface 1 ROM is paged in
+    pub const IF1_PAGED: usize = 4;
+    /// Last write to port 0xFF in Timex hardware modes, shares the byte
+    /// with `IF1_PAGED`
+    pub const PORT_TIMEX_FF: usize = 4;
+    pub const FLAGS: usize = 5;
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub const AY_SELECTED_REG: usize = 6;
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub const AY_REGS: usize = 7;
+    /// 0xFF if MGT ROM is paged in, only present in v3 snapshots
+    pub const MGT_PAGED: usize = 51;
+    /// Only present in v3 snapshots with 55-byte additional header
+    pub const PORT_1FFD: usize = 54;
+}
+
+/// Value of the paged ROM flags
+const Z80_ROM_PAGED: u8 = 0xFF;
+
+/// Returns machine, required for the given snapshot hardware mode
+fn machine_from_hardware_mode(is_v2: bool, mode: u8) -> Option<ZXMachine> {
+    match (is_v2, mode) {
+        // 48K, 48K + IF1
+        (_, 0 | 1) => Some(ZXMachine::Sinclair48K),
+        // 48K + MGT (v3)
+        (false, 3) => Some(ZXMachine::Sinclair48K),
+        // 128K, 128K + IF1 (v2)
+        (true, 3 | 4) => Some(ZXMachine::Sinclair128K),
+        // 128K, 128K + IF1, 128K + MGT, +2 (v3)
+        (false, 4 | 5 | 6 | 12) => Some(ZXMachine::Sinclair128K),
+        // +3, +2A (v3)
+        (false, 7 | 8 | 13) => Some(ZXMachine::SinclairPlus3),
+        (_, 14) => Some(ZXMachine::TimexTC2048),
+        // SamRam and various clones are not supported
+        _ => None,
+    }
+}
+
+/// Checks that snapshot doesn't depend on the state of Interface 1 or MGT,
+/// which are not emulated. Snapshots with attached, but not paged interface
+/// are loaded as for the machine without it
+fn check_interfaces(is_v2: bool, extra: &[u8]) -> Result<()> {
+    let (if1, mgt) = match (is_v2, extra[extra::HARDWARE_MODE]) {
+        (_, 1) | (true, 4) | (false, 5) => (true, false),
+        (false, 3 | 6) => (false, true),
+        _ => (false, false),
+    };
+    let if1_paged = if1 && extra[extra::IF1_PAGED] == Z80_ROM_PAGED;
+    let mgt_paged = mgt && extra.get(extra::MGT_PAGED) == Some(&Z80_ROM_PAGED);
+    if if1_paged || mgt_paged {
+        return Err(SnapshotLoadError::PeripheralNotSupported.into());
+    }
+    Ok(())
+}
+
+/// Decompresses `ED ED nn bb` run-length encoded data. Returns count of
+/// consumed source bytes when `dest` has been filled.
+fn decompress(source: &[u8], dest: &mut [u8]) -> Result<usize> {
+    let mut src_pos = 0;
+    let mut dest_pos = 0;
+
+    while dest_pos < dest.len() {
+        let byte = *source
+            .get(src_pos)
+            .ok_or(SnapshotLoadError::InvalidZ80File)?;
+        if byte == Z80_RLE_MARKER && source.get(src_pos + 1) == Some(&Z80_RLE_MARKER) {
+            let run = source
+                .get(src_pos + 2..src_pos + 4)
+                .ok_or(SnapshotLoadError::InvalidZ80File)?;
+            let (count, value) = (run[0] as usize, run[1]);
+            dest.get_mut(dest_pos..dest_pos + count)
+                .ok_or(SnapshotLoadError::InvalidZ80File)?
+                .fill(value);
+            dest_pos += count;
+            src_pos += 4;
+        } else {
+            dest[dest_pos] = byte;
+            dest_pos += 1;
+            src_pos += 1;
+        }
+    }
+
+    Ok(src_pos)
+}
+
+/// Decompresses whole `ED ED nn bb` run-length encoded block of unknown
+/// decompressed size
+pub(super) fn decompress_to_vec(source: &[u8]) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut pos = 0;
+
+    while pos < source.len() {
+        if source[pos] == Z80_RLE_MARKER && source.get(pos + 1) == Some(&Z80_RLE_MARKER) {
+            let run = source
+                .get(pos + 2..pos + 4)
+                .ok_or(SnapshotLoadError::InvalidZ80File)?;
+            data.resize(data.len() + run[0] as usize, run[1]);
+            pos += 4;
+        } else {
+            data.push(source[pos]);
+            pos += 1;
+        }
+    }
+
+    Ok(data)
+}
+
+/// Returns ram bank index for the given z80 memory block page number
+fn ram_bank_from_page(machine: ZXMachine, page: u8) -> Option<u8> {
+    match machine {
+        ZXMachine::Sinclair48K | ZXMachine::TimexTC2048 => match page {
+            8 => Some(0),
+            4 => Some(1),
+            5 => Some(2),
+            _ => None,
+        },
+        ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish | ZXMachine::SinclairPlus3 => {
+            match page {
+                3..=10 => Some(page - 3),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Returns machine required by the Z80 snapshot
+pub fn machine(asset: &mut (impl LoadableAsset + SeekableAsset)) -> Result<ZXMachine> {
+    let data = read_whole_asset(asset)?;
+    machine_from_data(&data)
+}
+
+/// Returns machine required by the Z80 snapshot from the given buffer
+pub(super) fn machine_from_data(data: &[u8]) -> Result<ZXMachine> {
+    let header = data
+        .get(..Z80_V1_HEADER_SIZE)
+        .ok_or(IoError::UnexpectedEof)?;
+    // Version 1 snapshots (non-zero PC) are always 48K
+    if u16::from_le_bytes([header[6], header[7]]) != 0 {
+        return Ok(ZXMachine::Sinclair48K);
+    }
+    let (extra, _) = extra_header(data)?;
+    let is_v2 = extra.len() == Z80_V2_EXTRA_HEADER_SIZE;
+    machine_from_hardware_mode(is_v2, extra[extra::HARDWARE_MODE])
+        .ok_or_else(|| SnapshotLoadError::MachineNotSupported.into())
+}
+
+/// Returns additional header of v2/v3 snapshot and offset of the first memory
+/// block
+fn extra_header(data: &[u8]) -> Result<(&[u8], usize)> {
+    let extra_header_start = Z80_V1_HEADER_SIZE + Z80_EXTRA_HEADER_LENGTH_SIZE;
+    let extra_header_length = data
+        .get(Z80_V1_HEADER_SIZE..extra_header_start)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+        .ok_or(IoError::UnexpectedEof)?;
+
+    let blocks_start = extra_header_start + extra_header_length;
+    let extra = data
+        .get(extra_header_start..blocks_start)
+        .ok_or(IoError::UnexpectedEof)?;
+    if extra.len() < Z80_V2_EXTRA_HEADER_SIZE {
+        return Err(SnapshotLoadError::InvalidZ80File.into());
+    }
+    Ok((extra, blocks_start))
+}
+
+/// Z80 snapshot loading function
+pub fn load<H, A>(emulator: &mut Emulator<H>, mut asset: A) -> Result<()>
+where
+    H: Host,
+    A: LoadableAsset + SeekableAsset,
+{
+    let data = read_whole_asset(&mut asset)?;
+    load_data(emulator, &data)?;
+    Ok(())
+}
+
+/// Loads Z80 snapshot from the given buffer. Returns offset of the first byte
+/// after the snapshot data (e.g. start of SLT extension)
+pub(super) fn load_data<H: Host>(emulator: &mut Emulator<H>, data: &[u8]) -> Result<usize> {
+    let header = data
+        .get(..Z80_V1_HEADER_SIZE)
+        .ok_or(IoError::UnexpectedEof)?;
+    let word = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+
+    let regs = &mut emulator.cpu.regs;
+    // alt regs
+    regs.set_bc(word(15));
+    regs.set_de(word(17));
+    regs.set_hl(word(19));
+    regs.exx();
+    regs.set_af(u16::from_le_bytes([header[22], header[21]]));
+    regs.swap_af_alt();
+    // regs
+    regs.set_acc(header[0]);
+    regs.set_flags(header[1]);
+    regs.set_bc(word(2));
+    regs.set_hl(word(4));
+    regs.set_de(word(13));
+    regs.set_iy(word(23));
+    regs.set_ix(word(25));
+    regs.set_sp(word(8));
+    regs.set_i(header[10]);
+    // Byte 12 is treated as 1 if it is equal to 255 for compatibility reasons
+    let flags = if header[12] == 0xFF { 0x01 } else { header[12] };
+    // 7th bit of R is stored separately
+    regs.set_r((header[11] & 0x7F) | ((flags & 0x01) << 7));
+    regs.set_iff1(header[27] != 0);
+    regs.set_iff2(header[28] != 0);
+    emulator.cpu.set_im(header[29] & Z80_INTERRUPT_MODE_MASK);
+    emulator
+        .controller
+        .set_border_color(0, ZXColor::from_bits((flags >> 1) & Z80_BORDER_COLOR_MASK));
+
+    let end;
+    let pc = word(6);
+    if pc != 0 {
+        // Version 1, always 48K
+        if emulator.settings.machine.base_machine() != ZXMachine::Sinclair48K {
+            return Err(SnapshotLoadError::MachineNotSupported.into());
+        }
+        emulator.cpu.regs.set_pc(pc);
+
+        let body = &data[Z80_V1_HEADER_SIZE..];
+        let mut ram = vec![0u8; SIZE_48K];
+        let body_size = if flags & Z80_COMPRESSED_FLAG_MASK != 0 {
+            decompress(body, &mut ram)? + Z80_V1_END_MARKER_SIZE
+        } else {
+            let body = body.get(..SIZE_48K).ok_or(IoError::UnexpectedEof)?;
+            ram.copy_from_slice(body);
+            SIZE_48K
+        };
+        end = Z80_V1_HEADER_SIZE + body_size;
+
+        for (page, data) in ram.chunks_exact(PAGE_SIZE).enumerate() {
+            let page = emulator.controller.memory.ram_page_data_mut(page as u8);
+            page.copy_from_slice(data);
+        }
+    } else {
+        end = load_v2_v3(emulator, data)?;
+    }
+
+    // Refresh screen and other memory-dependent peripheral
+    emulator.controller.refresh_memory_dependent_devices();
+
+    Ok(end)
+}
+
+fn load_v2_v3<H: Host>(emulator: &mut Emulator<H>, data: &[u8]) -> Result<usize> {
+    let (extra, blocks_start) = extra_header(data)?;
+    let is_v2 = extra.len() == Z80_V2_EXTRA_HEADER_SIZE;
+    let machine = machine_from_hardware_mode(is_v2, extra[extra::HARDWARE_MODE])
+        .ok_or(SnapshotLoadError::MachineNotSupported)?;
+    // Modified hardware flag means 16K machine instead of 48K (+2 instead of 128K
+    // is fine, as they are compatible)
+    let is_16k = machine == ZXMachine::Sinclair48K
+        && extra[extra::FLAGS] & Z80_MODIFIED_HARDWARE_FLAG_MASK != 0;
+    if !emulator.settings.machine.can_load_snapshot_of(machine) || is_16k {
+        return Err(SnapshotLoadError::MachineNotSupported.into());
+    }
+    check_interfaces(is_v2, extra)?;
+
+    emulator
+        .cpu
+        .regs
+        .set_pc(u16::from_le_bytes([extra[extra::PC], extra[extra::PC + 1]]));
+
+    if machine == ZXMachine::SinclairPlus3 {
+        if let Some(port_1ffd) = extra.get(extra::PORT_1FFD) {
+            emulator.controller.write_1ffd(*port_1ffd);
+        }
+    }
+    if machine.base_machine() != ZXMachine::Sinclair48K {
+        emulator.controller.write_7ffd(extra[extra::PORT_7FFD]);
+    }
+    if machine == ZXMachine::TimexTC2048 {
+        emulator
+            .controller
+            .set_timex_video_mode(extra[extra::PORT_TIMEX_FF]);
+    }
+
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    if machine.base_machine() != ZXMachine::Sinclair48K
+        || extra[extra::FLAGS] & Z80_AY_ENABLED_FLAG_MASK != 0
+    {
+        emulator.controller.mixer.ay.restore(
+            &extra[extra::AY_REGS..extra::AY_REGS + Z80_AY_REGS_COUNT],
+            extra[extra::AY_SELECTED_REG],
+        );
+    }
+
+    let mut pos = blocks_start;
+    while pos < data.len() {
+        if data[pos..].starts_with(Z80_SLT_SIGNATURE) {
+            break;
+        }
+        let block_header = data
+            .get(pos..pos + Z80_BLOCK_HEADER_SIZE)
+            .ok_or(IoError::UnexpectedEof)?;
+        let length = u16::from_le_bytes([block_header[0], block_header[1]]) as usize;
+        let page = block_header[2];
+        pos += Z80_BLOCK_HEADER_SIZE;
+
+        let block = &data[pos..];
+        let bank = match ram_bank_from_page(machine, page) {
+            Some(bank) => bank,
+            None => {
+                // ROM or interface memory pages are skipped
+                let skip = if length == Z80_UNCOMPRESSED_BLOCK_LENGTH {
+                    PAGE_SIZE
+                } else {
+                    length
+                };
+                pos += skip;
+                continue;
+            }
+        };
+
+        let dest = emulator.controller.memory.ram_page_data_mut(bank);
+        if length == Z80_UNCOMPRESSED_BLOCK_LENGTH {
+            let block = block.get(..PAGE_SIZE).ok_or(IoError::UnexpectedEof)?;
+            dest.copy_from_slice(block);
+            pos += PAGE_SIZE;
+        } else {
+            let block = block.get(..length).ok_or(IoError::UnexpectedEof)?;
+            decompress(block, dest)?;
+            pos += length;
+        }
+    }
+
+    Ok(pos)
+}
+
+/// Returns hardware mode of v3 snapshot for the given machine
+fn hardware_mode_from_machine(machine: ZXMachine) -> u8 {
+    match machine {
+        ZXMachine::Sinclair48K => 0,
+        ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish => 4,
+        ZXMachine::SinclairPlus3 => 7,
+        ZXMachine::TimexTC2048 => 14,
+    }
+}
+
+/// Returns z80 memory block page number for the given ram bank
+fn page_from_ram_bank(machine: ZXMachine, bank: u8) -> u8 {
+    match machine {
+        ZXMachine::Sinclair48K => [8, 4, 5][bank as usize],
+        _ => bank + 3,
+    }
+}
+
+/// Saves v3 Z80 snapshot with uncompressed memory blocks. Unlike SNA, it
+/// keeps +2A/+3 paging state and AY registers
+pub fn save<H, R>(emulator: &mut Emulator<H>, mut recorder: R) -> Result<()>
+where
+    H: Host,
+    R: DataRecorder,
+{
+    let machine = emulator.settings.machine;
+    let regs = &emulator.cpu.regs;
+
+    let mut header = [0u8; Z80_V1_HEADER_SIZE];
+    header[0] = regs.get_acc();
+    header[1] = regs.get_flags();
+    header[2..4].copy_from_slice(&regs.get_bc().to_le_bytes());
+    header[4..6].copy_from_slice(&regs.get_hl().to_le_bytes());
+    // Zero PC marks v2/v3 snapshot, real PC is stored in additional header
+    header[8..10].copy_from_slice(&regs.get_sp().to_le_bytes());
+    header[10] = regs.get_i();
+    header[11] = regs.get_r() & 0x7F;
+    let border: u8 = emulator.controller.border_color.into();
+    header[12] = (regs.get_r() >> 7) | (border << 1);
+    header[13..15].copy_from_slice(&regs.get_de().to_le_bytes());
+    header[15] = regs.get_c_alt();
+    header[16] = regs.get_b_alt();
+    header[17] = regs.get_e_alt();
+    header[18] = regs.get_d_alt();
+    header[19] = regs.get_l_alt();
+    header[20] = regs.get_h_alt();
+    header[21] = regs.get_acc_alt();
+    header[22] = regs.get_flags_alt();
+    header[23..25].copy_from_slice(&regs.get_iy().to_le_bytes());
+    header[25..27].copy_from_slice(&regs.get_ix().to_le_bytes());
+    header[27] = regs.get_iff1() as u8;
+    header[28] = regs.get_iff2() as u8;
+    header[29] = emulator.cpu.get_im().into();
+    recorder.write_all(&header)?;
+
+    let mut extra = [0u8; Z80_V3_EXTRA_HEADER_SIZE];
+    extra[extra::PC..extra::PC + 2].copy_from_slice(&regs.get_pc().to_le_bytes());
+    extra[extra::HARDWARE_MODE] = hardware_mode_from_machine(machine);
+    extra[extra::PORT_7FFD] = emulator.controller.read_7ffd();
+    extra[extra::PORT_1FFD] = emulator.controller.read_1ffd();
+    if machine == ZXMachine::TimexTC2048 {
+        extra[extra::PORT_TIMEX_FF] = emulator.controller.timex_video_mode();
+    }
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    if let Some(ay_regs) = emulator.controller.ay_registers() {
+        extra[extra::FLAGS] |= Z80_AY_ENABLED_FLAG_MASK;
+        extra[extra::AY_SELECTED_REG] = emulator.controller.mixer.ay.selected_reg();
+        extra[extra::AY_REGS..extra::AY_REGS + Z80_AY_REGS_COUNT].copy_from_slice(&ay_regs);
+    }
+    recorder.write_all(&(Z80_V3_EXTRA_HEADER_SIZE as u16).to_le_bytes())?;
+    recorder.write_all(&extra)?;
+
+    let memory = &emulator.controller.memory;
+    for bank in 0..memory.ram_pages_count() as u8 {
+        let [length_lo, length_hi] = (Z80_UNCOMPRESSED_BLOCK_LENGTH as u16).to_le_bytes();
+        let page = page_from_ram_bank(machine.base_machine(), bank);
+        recorder.write_all(&[length_lo, length_hi, page])?;
+        recorder.write_all(memory.ram_page_data(bank))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! Read-only view of the emulator state, intended for debugging tools (e.g.
+//! comparing two snapshots after the core upgrade)
+use crate::zx::{machine::ZXMachine, video::colors::ZXColor};
+
+/// CPU registers state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuState {
+    pub af: u16,
+    pub bc: u16,
+    pub de: u16,
+    pub hl: u16,
+    pub af_alt: u16,
+    pub bc_alt: u16,
+    pub de_alt: u16,
+    pub hl_alt: u16,
+    pub ix: u16,
+    pub iy: u16,
+    pub sp: u16,
+    pub pc: u16,
+    pub mem_ptr: u16,
+    pub i: u8,
+    pub r: u8,
+    pub iff1: bool,
+    pub iff2: bool,
+    pub im: u8,
+    pub halted: bool,
+}
+
+/// Machine state except memory contents, which is available separately via
+/// [crate::Emulator::ram_page]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MachineState {
+    pub machine: ZXMachine,
+    pub cpu: CpuState,
+    pub border: ZXColor,
+    /// T-state position within the current frame
+    pub frame_clocks: usize,
+    /// Last value written to 128K paging port, `None` for 48K machine
+    pub port_7ffd: Option<u8>,
+    /// Last value written to +2A/+3 paging port, `None` for other machines
+    pub port_1ffd: Option<u8>,
+    /// AY registers, `None` when AY chip is not attached
+    pub ay_registers: Option<[u8; 16]>,
+}
@@ -1,3 +1,4 @@
+use crate::zx::peripherals::Peripheral;
 use displaydoc::Display;
 use from_variants::FromVariants;
 
@@ -11,6 +12,10 @@ pub enum Error {
     TapeLoad(TapeLoadError),
     /// Failed to load screen
     ScreenLoad(ScreenLoadError),
+    /// Failed to load snapshot
+    SnapshotLoad(SnapshotLoadError),
+    /// Invalid emulator settings: {0}
+    Settings(SettingsError),
 }
 
 #[derive(Debug, Display)]
@@ -29,12 +34,18 @@ pub enum IoError {
 pub enum RomLoadError {
     /// More assets required to load rom
     MoreAssetsRequired,
+    /// Embedded rom is not available for the selected machine
+    EmbeddedRomNotAvailable,
 }
 
 #[derive(Debug, Display)]
 pub enum TapeLoadError {
     /// Provided tap file is invalid
     InvalidTapFile,
+    /// Provided tzx file is invalid
+    InvalidTzxFile,
+    /// Tzx block with id {0:#04X} is not supported
+    UnsupportedTzxBlock(u8),
 }
 
 #[derive(Debug, Display)]
@@ -44,3 +55,23 @@ pub enum ScreenLoadError {
     /// Selected machine can't be used to load given screen file
     MachineNotSupported,
 }
+
+#[derive(Debug, Display)]
+pub enum SnapshotLoadError {
+    /// Provided z80 file is invalid
+    InvalidZ80File,
+    /// Provided szx file is invalid
+    InvalidSzxFile,
+    /// Provided slt file is invalid
+    InvalidSltFile,
+    /// Selected machine can't be used to load given snapshot file
+    MachineNotSupported,
+    /// Snapshot contains state of the peripheral which is not emulated
+    PeripheralNotSupported,
+}
+
+#[derive(Debug, Display)]
+pub enum SettingsError {
+    /// {0} responds on port {2:#06X} of {1}, they can't be enabled together
+    PeripheralConflict(Peripheral, Peripheral, u16),
+}
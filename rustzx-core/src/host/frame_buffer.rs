@@ -11,4 +11,18 @@ pub trait FrameBuffer {
     fn new(width: usize, height: usize, source: FrameBufferSource, context: Self::Context) -> Self;
     /// Set `color` with `brightness` for pixel on canvas at (`x`, `y`)
     fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness);
+    /// Called on the border buffer in `BorderMode::SolidColor` when the border
+    /// color changes. Hosts which fill the border themselves can override it,
+    /// default implementation sets the single pixel of the buffer
+    fn set_border_color(&mut self, color: ZXColor) {
+        self.set_color(0, 0, color, ZXBrightness::Normal);
+    }
+}
+
+/// Frame buffers of the completed frame, which are exchanged with the emulator
+/// via [crate::Emulator::swap_frame_buffers]
+pub struct FrameBuffers<FB: FrameBuffer> {
+    pub screen: FB,
+    #[cfg(feature = "precise-border")]
+    pub border: FB,
 }
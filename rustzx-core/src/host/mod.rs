@@ -1,10 +1,15 @@
 mod frame_buffer;
 mod io;
 
+use crate::error::IoError;
+use alloc::boxed::Box;
+
 pub use core::time::Duration;
-pub use frame_buffer::{FrameBuffer, FrameBufferSource};
+pub use frame_buffer::{FrameBuffer, FrameBufferSource, FrameBuffers};
 pub use io::{BufferCursor, DataRecorder, LoadableAsset, SeekFrom, SeekableAsset};
 
+pub use crate::zx::tape::{DataBlockTimings, TapParser, TapeBlock, TapeBlockSource, TzxParser};
+
 pub trait Stopwatch {
     fn new() -> Self;
     fn measure(&self) -> Duration;
@@ -12,16 +17,22 @@ pub trait Stopwatch {
 
 pub enum Snapshot<LoadableAssetImpl: LoadableAsset> {
     Sna(LoadableAssetImpl),
-    // TODO(#55): Implement SLT snapshot format support
+    Z80(LoadableAssetImpl),
+    #[cfg(feature = "szx")]
+    Szx(LoadableAssetImpl),
+    Slt(LoadableAssetImpl),
 }
 
 pub enum SnapshotRecorder<DataRecorderImpl: DataRecorder> {
     Sna(DataRecorderImpl),
+    Z80(DataRecorderImpl),
 }
 
 pub enum Tape<LoadableAssetImpl: LoadableAsset> {
     Tap(LoadableAssetImpl),
-    // TODO(#56): Implement TZX tape format support
+    Tzx(LoadableAssetImpl),
+    /// Tape blocks parsed by the host, e.g. on a separate thread
+    Blocks(Box<dyn TapeBlockSource + Send>),
 }
 
 pub enum Screen<LoadableAssetImpl: LoadableAsset> {
@@ -92,6 +103,37 @@ impl DebugInterface for StubDebugInterface {
     }
 }
 
+/// Size of the [DiskImage] sector in bytes
+pub const DISK_SECTOR_SIZE: usize = 512;
+
+/// Host-backed hard disk image, attached to the emulated IDE interface
+pub trait DiskImage {
+    /// Returns count of sectors available on the disk
+    fn sector_count(&self) -> u32;
+    /// Reads sector with given LBA address to the buffer
+    fn read_sector(&mut self, lba: u32, buffer: &mut [u8; DISK_SECTOR_SIZE])
+        -> Result<(), IoError>;
+    /// Writes buffer to the sector with given LBA address
+    fn write_sector(&mut self, lba: u32, buffer: &[u8; DISK_SECTOR_SIZE]) -> Result<(), IoError>;
+}
+
+/// Disk image which has no sectors, used when IDE is not required by the host
+pub struct StubDiskImage;
+
+impl DiskImage for StubDiskImage {
+    fn sector_count(&self) -> u32 {
+        0
+    }
+
+    fn read_sector(&mut self, _: u32, _: &mut [u8; DISK_SECTOR_SIZE]) -> Result<(), IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+
+    fn write_sector(&mut self, _: u32, _: &[u8; DISK_SECTOR_SIZE]) -> Result<(), IoError> {
+        Err(IoError::HostAssetImplFailed)
+    }
+}
+
 /// Represents set of required types for emulator implementation
 /// based on `rustzx-core`.
 pub trait Host {
@@ -108,4 +150,7 @@ pub trait Host {
     type IoExtender: IoExtender;
     /// Debug interface logic (e.g. breakpoints)
     type DebugInterface: DebugInterface;
+    /// Hard disk image, used by IDE interface
+    #[cfg(feature = "ide")]
+    type DiskImage: DiskImage;
 }
@@ -8,9 +8,12 @@ pub mod error;
 pub mod host;
 pub mod zx;
 
-pub use emulator::{poke, EmulationInfo, EmulationStopReason, Emulator};
+pub use emulator::{poke, state, EmulationInfo, EmulationStopReason, Emulator};
 pub use settings::RustzxSettings;
 pub use utils::EmulationMode;
+pub use zx::memory::RamPattern;
+#[cfg(feature = "precise-border")]
+pub use zx::video::border::BorderMode;
 
 #[cfg(feature = "strum")]
 pub use strum::IntoEnumIterator as IterableEnum;
@@ -1,7 +1,18 @@
-use crate::{utils::EmulationMode, zx::machine::ZXMachine};
+use crate::{
+    error::SettingsError,
+    utils::EmulationMode,
+    zx::{
+        machine::ZXMachine,
+        memory::RamPattern,
+        peripherals::{self, Peripheral},
+    },
+};
+use alloc::vec::Vec;
 
 #[cfg(all(feature = "sound", feature = "ay"))]
 use crate::zx::sound::ay::ZXAYMode;
+#[cfg(feature = "precise-border")]
+use crate::zx::video::border::BorderMode;
 
 pub struct RustzxSettings {
     pub machine: ZXMachine,
@@ -9,6 +20,9 @@ pub struct RustzxSettings {
     pub tape_fastload_enabled: bool,
     pub kempston_enabled: bool,
     pub mouse_enabled: bool,
+    pub ram_pattern: RamPattern,
+    #[cfg(feature = "precise-border")]
+    pub border_mode: BorderMode,
     #[cfg(all(feature = "sound", feature = "ay"))]
     pub ay_mode: ZXAYMode,
     #[cfg(all(feature = "sound", feature = "ay"))]
@@ -26,3 +40,27 @@ pub struct RustzxSettings {
     #[cfg(feature = "autoload")]
     pub autoload_enabled: bool,
 }
+
+impl RustzxSettings {
+    /// Returns peripherals attached to the machine with these settings
+    pub fn peripherals(&self) -> Vec<Peripheral> {
+        let mut peripherals = Vec::new();
+        // Timex has built-in joystick interface
+        if self.kempston_enabled || self.machine == ZXMachine::TimexTC2048 {
+            peripherals.push(Peripheral::KempstonJoy);
+        }
+        if self.mouse_enabled {
+            peripherals.push(Peripheral::KempstonMouse);
+        }
+        #[cfg(all(feature = "sound", feature = "ay"))]
+        if self.ay_enabled && !self.machine.has_ay() {
+            peripherals.push(Peripheral::Ay);
+        }
+        peripherals
+    }
+
+    /// Checks that enabled peripherals don't conflict with each other
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        peripherals::check_conflicts(&self.peripherals())
+    }
+}
@@ -2,7 +2,7 @@
 
 pub mod screen;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum EmulationMode {
     FrameCount(usize),
     Max,
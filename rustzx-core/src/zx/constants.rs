@@ -26,3 +26,5 @@ pub(crate) const BORDER_COLS: usize = 4;
 pub(crate) const BORDER_ROWS: usize = 3;
 /// Tape loading trap at LD-BREAK routine in ROM
 pub(crate) const ADDR_LD_BREAK: u16 = 0x056B;
+/// Level data loading trap instruction (ED FB) of SLT snapshots
+pub(crate) const OPCODE_SLT_TRAP: u8 = 0xFB;
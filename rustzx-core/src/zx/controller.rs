@@ -5,8 +5,9 @@ use crate::{
     settings::RustzxSettings,
     utils::screen::bitmap_line_addr,
     zx::{
-        constants::{ADDR_LD_BREAK, CANVAS_HEIGHT, CLOCKS_PER_COL},
+        constants::{ADDR_LD_BREAK, CANVAS_HEIGHT, CLOCKS_PER_COL, OPCODE_SLT_TRAP},
         events::EmulationEvents,
+        feedback::Feedback,
         joy::{
             kempston::KempstonJoy,
             sinclair::{self, SinclairJoyNum, SinclairKey},
@@ -19,8 +20,10 @@ use crate::{
         video::{colors::ZXColor, screen::ZXScreen},
     },
 };
-use rustzx_z80::Z80Bus;
+use rustzx_z80::{Opcode, Prefix, Z80Bus};
 
+#[cfg(feature = "ide")]
+use crate::zx::ide::SimpleIde;
 #[cfg(feature = "embedded-roms")]
 use crate::zx::roms;
 #[cfg(feature = "sound")]
@@ -41,6 +44,9 @@ pub(crate) struct ZXController<H: Host> {
     pub mouse: Option<KempstonMouse>,
     pub io_extender: Option<H::IoExtender>,
     pub debug_interface: Option<H::DebugInterface>,
+    #[cfg(feature = "ide")]
+    pub ide: Option<SimpleIde<H::DiskImage>>,
+    pub feedback: Feedback,
     #[cfg(feature = "sound")]
     pub mixer: ZXMixer,
     pub keyboard: [u8; 8],
@@ -53,10 +59,21 @@ pub(crate) struct ZXController<H: Host> {
     frame_clocks: usize,
     // frames count, which passed during emulation invocation
     passed_frames: usize,
+    // frame was completed since the last frame buffers swap
+    frame_completed: bool,
+    // Timex video mode register (port 0xFF)
+    timex_video_mode: u8,
+    // frame clocks at which CPU was halted, set while CPU is in HALT state
+    halted_at: Option<usize>,
+    // clocks which CPU spent in HALT state during current frame
+    frame_halt_clocks: usize,
+    // part of the last completed frame which CPU spent in HALT state
+    last_frame_halt_ratio: f32,
     events: EmulationEvents,
     paging_enabled: bool,
     screen_bank: u8,
     current_port_7ffd: u8,
+    current_port_1ffd: u8,
     // Z80 module expected controller implementation without errors,
     // so we need to store the internal errors manually. For sake of simplicity,
     // Only last error is saved
@@ -69,19 +86,25 @@ impl<H: Host> ZXController<H> {
     pub fn new(settings: &RustzxSettings, host_context: H::Context) -> Self {
         let (memory, paging, screen_bank);
         match settings.machine {
-            ZXMachine::Sinclair48K => {
-                memory = ZXMemory::new(RomType::K16, RamType::K48);
+            ZXMachine::Sinclair48K | ZXMachine::TimexTC2048 => {
+                memory = ZXMemory::new(RomType::K16, RamType::K48, settings.ram_pattern);
                 paging = false;
                 screen_bank = 0;
             }
-            ZXMachine::Sinclair128K => {
-                memory = ZXMemory::new(RomType::K32, RamType::K128);
+            ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish => {
+                memory = ZXMemory::new(RomType::K32, RamType::K128, settings.ram_pattern);
+                paging = true;
+                screen_bank = 5;
+            }
+            ZXMachine::SinclairPlus3 => {
+                memory = ZXMemory::new(RomType::K64, RamType::K128, settings.ram_pattern);
                 paging = true;
                 screen_bank = 5;
             }
         };
 
-        let kempston = if settings.kempston_enabled {
+        // Timex has joystick interface built in
+        let kempston = if settings.kempston_enabled || settings.machine == ZXMachine::TimexTC2048 {
             Some(KempstonJoy::default())
         } else {
             None
@@ -95,7 +118,11 @@ impl<H: Host> ZXController<H> {
 
         let screen = ZXScreen::new(settings.machine, host_context.frame_buffer_context());
         #[cfg(feature = "precise-border")]
-        let border = ZXBorder::new(settings.machine, host_context.frame_buffer_context());
+        let border = ZXBorder::new(
+            settings.machine,
+            settings.border_mode,
+            host_context.frame_buffer_context(),
+        );
 
         #[cfg(feature = "sound")]
         let mixer = Self::create_mixer(settings);
@@ -110,6 +137,9 @@ impl<H: Host> ZXController<H> {
             mouse,
             io_extender: None,
             debug_interface: None,
+            #[cfg(feature = "ide")]
+            ide: None,
+            feedback: Default::default(),
             #[cfg(feature = "sound")]
             mixer,
             keyboard: [0xFF; 8],
@@ -119,11 +149,17 @@ impl<H: Host> ZXController<H> {
             border_color: ZXColor::Black,
             frame_clocks: 0,
             passed_frames: 0,
+            frame_completed: false,
+            timex_video_mode: 0,
+            halted_at: None,
+            frame_halt_clocks: 0,
+            last_frame_halt_ratio: 0.0,
             tape: Default::default(),
             events: Default::default(),
             paging_enabled: paging,
             screen_bank,
             current_port_7ffd: 0,
+            current_port_1ffd: 0,
             last_emulation_error: None,
         };
 
@@ -176,6 +212,9 @@ impl<H: Host> ZXController<H> {
                 let page = self.memory.rom_page_data_mut(1);
                 page.copy_from_slice(roms::ROM_128K_1);
             }
+            // ROMs of +3 and localized models are not embedded, see
+            // `ZXMachine::has_embedded_rom`
+            ZXMachine::SinclairPlus3 | ZXMachine::Sinclair128KSpanish | ZXMachine::TimexTC2048 => {}
         }
     }
 
@@ -283,19 +322,32 @@ impl<H: Host> ZXController<H> {
         }
     }
 
-    /// Returns early IO contention clocks
+    /// Applies contention for the first IO cycle clock. Together with
+    /// `io_contention_last` it produces the following IO timings, where `C:n`
+    /// is a contended and `N:n` is an uncontended wait of `n` clocks:
+    ///
+    /// | High byte in contended bank | ULA port (A0 = 0) | Timings             |
+    /// |-----------------------------|-------------------|---------------------|
+    /// | No                          | No                | N:4                 |
+    /// | No                          | Yes               | N:1, C:3            |
+    /// | Yes                         | No                | C:1, C:1, C:1, C:1  |
+    /// | Yes                         | Yes               | C:1, C:3            |
+    ///
+    /// The last clock of each pattern is performed by the caller after port
+    /// value is sampled or written. Machines without IO contention always use N:4,
+    /// see `ZXMachine::no_mreq_is_contended`
     fn io_contention_first(&mut self, port: u16) {
-        if self.addr_is_contended(port) {
+        if self.machine.no_mreq_is_contended() && self.addr_is_contended(port) {
             self.do_contention();
         };
         self.wait_internal(1);
     }
 
-    /// Returns late IO contention clocks
+    /// Applies contention for the rest of IO cycle, excluding its last clock
     fn io_contention_last(&mut self, port: u16) {
         if self.machine.port_is_contended(port) {
             self.do_contention_and_wait(2);
-        } else if self.addr_is_contended(port) {
+        } else if self.machine.no_mreq_is_contended() && self.addr_is_contended(port) {
             self.do_contention_and_wait(1);
             self.do_contention_and_wait(1);
             self.do_contention();
@@ -306,12 +358,77 @@ impl<H: Host> ZXController<H> {
 
     /// Starts a new frame
     fn new_frame(&mut self) {
-        self.frame_clocks -= self.machine.specs().clocks_frame;
+        let clocks_frame = self.machine.specs().clocks_frame;
+        if let Some(halted_at) = self.halted_at {
+            // HALT state continues in the new frame
+            self.frame_halt_clocks += clocks_frame.saturating_sub(halted_at);
+            self.halted_at = Some(0);
+        }
+        self.last_frame_halt_ratio = (self.frame_halt_clocks as f32 / clocks_frame as f32).min(1.0);
+        self.frame_halt_clocks = 0;
+        self.frame_clocks -= clocks_frame;
         self.screen.new_frame();
         #[cfg(feature = "precise-border")]
         self.border.new_frame();
         #[cfg(feature = "sound")]
         self.mixer.new_frame();
+        self.feedback.new_frame();
+        self.frame_completed = true;
+    }
+
+    /// Returns T-state position within the current frame
+    pub fn frame_clocks(&self) -> usize {
+        self.frame_clocks
+    }
+
+    /// Moves emulation forward to the given T-state position within the frame,
+    /// starting a new frame if the position was already passed. Used by
+    /// snapshot loaders to restore beam position
+    #[cfg(feature = "szx")]
+    pub(crate) fn set_frame_clocks(&mut self, clocks: usize) {
+        let clocks_frame = self.machine.specs().clocks_frame;
+        let clocks = clocks % clocks_frame;
+        if clocks < self.frame_clocks {
+            self.wait_internal(clocks_frame - self.frame_clocks);
+        }
+        self.wait_internal(clocks - self.frame_clocks);
+    }
+
+    /// Returns Timex video mode register (port 0xFF)
+    pub(crate) fn timex_video_mode(&self) -> u8 {
+        self.timex_video_mode
+    }
+
+    /// Restores Timex video mode register (port 0xFF)
+    pub(crate) fn set_timex_video_mode(&mut self, value: u8) {
+        self.timex_video_mode = value;
+        self.screen.set_timex_video_mode(value);
+    }
+
+    /// Returns true if frame was completed since the last call
+    pub fn take_frame_completed(&mut self) -> bool {
+        core::mem::take(&mut self.frame_completed)
+    }
+
+    /// Returns part of the last completed frame (0.0..=1.0) which CPU spent
+    /// in HALT state, waiting for the interrupt
+    pub fn last_frame_halt_ratio(&self) -> f32 {
+        self.last_frame_halt_ratio
+    }
+
+    /// Returns RAM bank which is currently displayed
+    pub fn screen_bank(&self) -> u8 {
+        self.screen_bank
+    }
+
+    /// Returns ROM page with 48K BASIC
+    pub fn basic_rom_page(&self) -> u8 {
+        match self.machine {
+            ZXMachine::Sinclair48K | ZXMachine::TimexTC2048 => 0,
+            ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish => 1,
+            // 48K BASIC ROM of +2A/+3
+            ZXMachine::SinclairPlus3 => 3,
+        }
     }
 
     /// Collects all events from the last emulation step
@@ -333,15 +450,7 @@ impl<H: Host> ZXController<H> {
             return;
         }
         self.current_port_7ffd = val;
-        // remap top 16K of the ram
-        self.memory.remap(3, Page::Ram(val & 0x07));
-        // third block is not pageable
-        // second block is screen buffer, not pageable. but we need to change active buffer
-        let new_screen_bank = if val & 0x08 == 0 { 5 } else { 7 };
-        self.screen.switch_bank(new_screen_bank as usize);
-        self.screen_bank = new_screen_bank;
-        // remap ROM
-        self.memory.remap(0, Page::Rom((val >> 4) & 0x01));
+        self.update_memory_map();
         // check paging allow bit
         if val & 0x20 != 0 {
             self.paging_enabled = false;
@@ -352,8 +461,89 @@ impl<H: Host> ZXController<H> {
         self.current_port_7ffd
     }
 
+    pub fn read_1ffd(&self) -> u8 {
+        self.current_port_1ffd
+    }
+
+    /// Writes +2A/+3 additional memory paging port. Paging lock bit of 7FFD port
+    /// also locks this port
+    pub fn write_1ffd(&mut self, val: u8) {
+        if !self.paging_enabled {
+            return;
+        }
+        self.current_port_1ffd = val;
+        self.update_memory_map();
+    }
+
+    /// Rebuilds memory map from the current state of paging ports
+    fn update_memory_map(&mut self) {
+        let val = self.current_port_7ffd;
+        // second block is screen buffer, not pageable. but we need to change active buffer
+        let new_screen_bank = if val & 0x08 == 0 { 5 } else { 7 };
+        self.screen.switch_bank(new_screen_bank as usize);
+        self.screen_bank = new_screen_bank;
+
+        // +2A/+3 special paging mode, all blocks are mapped to RAM
+        if self.current_port_1ffd & 0x01 != 0 {
+            let banks = match (self.current_port_1ffd >> 1) & 0x03 {
+                0 => [0, 1, 2, 3],
+                1 => [4, 5, 6, 7],
+                2 => [4, 5, 6, 3],
+                _ => [4, 7, 6, 3],
+            };
+            for (block, bank) in banks.iter().copied().enumerate() {
+                self.memory.remap(block, Page::Ram(bank));
+            }
+            return;
+        }
+
+        // second and third blocks are not pageable in normal mode
+        self.memory.remap(1, Page::Ram(5));
+        self.memory.remap(2, Page::Ram(2));
+        // remap top 16K of the ram
+        self.memory.remap(3, Page::Ram(val & 0x07));
+        // remap ROM, high bit of ROM index is taken from 1FFD on +2A/+3
+        let rom = ((val >> 4) & 0x01) | ((self.current_port_1ffd >> 1) & 0x02);
+        self.memory.remap(0, Page::Rom(rom));
+    }
+
+    /// Attaches or detaches Kempston joystick. Attached joystick always
+    /// starts with all buttons released
+    pub fn set_kempston_enabled(&mut self, value: bool) {
+        // Built-in interface of Timex can't be detached
+        let value = value || self.machine == ZXMachine::TimexTC2048;
+        if value != self.kempston.is_some() {
+            self.kempston = value.then(KempstonJoy::default);
+        }
+    }
+
+    /// Attaches or detaches Kempston mouse. Attached mouse always starts
+    /// from its default state
+    pub fn set_mouse_enabled(&mut self, value: bool) {
+        if value != self.mouse.is_some() {
+            self.mouse = value.then(KempstonMouse::default);
+        }
+    }
+
+    /// Enables or disables AY chip. AY is a built-in device on 128K and +3,
+    /// so it stays on the bus and is only muted, while on 48K it is treated as
+    /// external interface which is removed from the bus when disabled
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fn set_ay_enabled(&mut self, value: bool) {
+        self.mixer.set_ay_enabled(value);
+    }
+
+    /// Returns true if AY ports are decoded by the machine
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    fn ay_attached(&self) -> bool {
+        self.machine.has_ay() || self.mixer.ay_enabled()
+    }
+
     #[cfg(all(feature = "sound", feature = "ay"))]
     fn read_ay_port(&mut self) -> u8 {
+        if !self.ay_attached() {
+            return self.floating_bus_value();
+        }
         self.mixer.ay.read()
     }
 
@@ -364,15 +554,66 @@ impl<H: Host> ZXController<H> {
 
     #[cfg(all(feature = "sound", feature = "ay"))]
     fn write_ay_port(&mut self, value: u8) {
-        self.mixer.ay.write(value);
+        if self.ay_attached() {
+            self.mixer.ay.write(value);
+        }
     }
 
     #[cfg(not(all(feature = "sound", feature = "ay")))]
     fn write_ay_port(&mut self, _: u8) {}
 
+    /// Reads IDE register if the interface is attached and decodes the port
+    #[cfg(feature = "ide")]
+    fn read_ide(&mut self, port: u16) -> Option<u8> {
+        self.ide
+            .as_mut()
+            .filter(|ide| ide.port_matches(port))
+            .map(|ide| ide.read(port))
+    }
+
+    #[cfg(not(feature = "ide"))]
+    fn read_ide(&mut self, _: u16) -> Option<u8> {
+        None
+    }
+
+    /// Returns true if IDE interface is attached and decodes the port
+    #[cfg(feature = "ide")]
+    fn ide_decodes(&self, port: u16) -> bool {
+        self.ide.as_ref().is_some_and(|ide| ide.port_matches(port))
+    }
+
+    #[cfg(not(feature = "ide"))]
+    fn ide_decodes(&self, _: u16) -> bool {
+        false
+    }
+
+    /// Writes IDE register, port should be checked with `ide_decodes` first
+    #[cfg(feature = "ide")]
+    fn write_ide(&mut self, port: u16, data: u8) {
+        if let Some(ide) = self.ide.as_mut() {
+            ide.write(port, data);
+        }
+    }
+
+    #[cfg(not(feature = "ide"))]
+    fn write_ide(&mut self, _: u16, _: u8) {}
+
+    /// Returns AY registers when AY chip is attached to the machine
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fn ay_registers(&self) -> Option<[u8; 16]> {
+        self.ay_attached().then(|| self.mixer.ay.registers())
+    }
+
+    #[cfg(not(all(feature = "sound", feature = "ay")))]
+    pub fn ay_registers(&self) -> Option<[u8; 16]> {
+        None
+    }
+
     #[cfg(all(feature = "sound", feature = "ay"))]
     fn select_ay_reg(&mut self, value: u8) {
-        self.mixer.ay.select_reg(value)
+        if self.ay_attached() {
+            self.mixer.ay.select_reg(value)
+        }
     }
 
     #[cfg(not(all(feature = "sound", feature = "ay")))]
@@ -395,12 +636,12 @@ impl<H: Host> ZXController<H> {
 
     pub(crate) fn refresh_memory_dependent_devices(&mut self) {
         match self.machine {
-            ZXMachine::Sinclair48K => {
+            ZXMachine::Sinclair48K | ZXMachine::TimexTC2048 => {
                 for (idx, data) in self.memory.ram_page_data(0).iter().enumerate() {
                     self.screen.update(idx as u16, 0, *data);
                 }
             }
-            ZXMachine::Sinclair128K => {
+            ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish | ZXMachine::SinclairPlus3 => {
                 for (idx, data) in self.memory.ram_page_data(5).iter().enumerate() {
                     self.screen.update(idx as u16, 5, *data);
                 }
@@ -417,12 +658,7 @@ impl<H: Host> Z80Bus for ZXController<H> {
     /// loading detection breakpoint
     fn pc_callback(&mut self, addr: u16) {
         // check mapped memory page at 0x0000 .. 0x3FFF
-        let check_fast_load = match self.machine {
-            ZXMachine::Sinclair48K if self.memory.get_bank_type(0) == Page::Rom(0) => true,
-            ZXMachine::Sinclair128K if self.memory.get_bank_type(0) == Page::Rom(1) => true,
-            _ => false,
-        };
-        if check_fast_load {
+        if self.memory.get_bank_type(0) == Page::Rom(self.basic_rom_page()) {
             // Tape LOAD/VERIFY
             if addr == ADDR_LD_BREAK {
                 // Add event (Fast tape loading request) it must be executed
@@ -449,6 +685,9 @@ impl<H: Host> Z80Bus for ZXController<H> {
         if let Page::Ram(bank) = self.memory.get_page(addr) {
             self.screen
                 .update(addr % PAGE_SIZE as u16, bank as usize, data);
+            if self.feedback.enabled() {
+                self.feedback.process_memory_write(addr, data);
+            }
         }
     }
 
@@ -458,8 +697,12 @@ impl<H: Host> Z80Bus for ZXController<H> {
         if let Err(e) = self.tape.process_clocks(clk) {
             self.last_emulation_error = Some(e);
         }
+        if self.feedback.enabled() {
+            self.feedback.process_tape_bit(self.tape.current_bit());
+        }
         #[cfg(feature = "sound")]
         {
+            self.mixer.beeper.set_tape_input(self.tape.current_bit());
             let pos = self.frame_pos();
             self.mixer.process(pos);
         }
@@ -472,21 +715,20 @@ impl<H: Host> Z80Bus for ZXController<H> {
 
     // wait with memory request pin active
     fn wait_mreq(&mut self, addr: u16, clk: usize) {
-        match self.machine {
-            ZXMachine::Sinclair48K | ZXMachine::Sinclair128K => {
-                // contention in low 16k RAM
-                if self.addr_is_contended(addr) {
-                    self.do_contention();
-                }
-            }
+        // contention in low 16k RAM
+        if self.addr_is_contended(addr) {
+            self.do_contention();
         }
         self.wait_internal(clk);
     }
 
     /// wait without memory request pin active
     fn wait_no_mreq(&mut self, addr: u16, clk: usize) {
-        // only for 48 K!
-        self.wait_mreq(addr, clk);
+        if self.machine.no_mreq_is_contended() {
+            self.wait_mreq(addr, clk);
+        } else {
+            self.wait_internal(clk);
+        }
     }
 
     /// read io from hardware
@@ -530,14 +772,19 @@ impl<H: Host> Z80Bus for ZXController<H> {
             self.mouse.as_ref().unwrap().x_pos_port
         } else if self.mouse.is_some() && (port & 0x0521 == 0x0501) {
             self.mouse.as_ref().unwrap().y_pos_port
+        } else if let Some(value) = self.read_ide(port) {
+            value
         } else if port & 0xC002 == 0xC000 {
             self.read_ay_port()
         } else if self.kempston.is_some() && (port & 0x00E0 == 0) {
             self.kempston.as_ref().unwrap().read()
+        } else if self.machine == ZXMachine::TimexTC2048 && port & 0x00FF == 0x00FF {
+            self.timex_video_mode
         } else {
             self.floating_bus_value()
         };
-        // add one clock after operation
+        // Port value (including EAR bit, which is measured by tape loaders) is
+        // sampled before the last clock of IO cycle
         self.wait_internal(1);
         output
     }
@@ -554,6 +801,11 @@ impl<H: Host> Z80Bus for ZXController<H> {
             .map_or(false, |e| e.extends_port(port))
         {
             self.io_extender.as_mut().unwrap().write(port, data);
+        } else if self.ide_decodes(port) {
+            self.write_ide(port, data);
+        } else if self.machine == ZXMachine::TimexTC2048 && port & 0x00FF == 0x00FF {
+            self.timex_video_mode = data;
+            self.screen.set_timex_video_mode(data);
         } else if port & 0xC002 == 0xC000 {
             self.select_ay_reg(data);
         } else if port & 0xC002 == 0x8000 {
@@ -566,8 +818,12 @@ impl<H: Host> Z80Bus for ZXController<H> {
                 let ear = data & 0x10 != 0;
                 self.mixer.beeper.change_state(ear, mic);
             }
-        } else if (port & 0x8002 == 0) && (self.machine == ZXMachine::Sinclair128K) {
+        } else if ((port & 0x8002 == 0) && (self.machine.base_machine() == ZXMachine::Sinclair128K))
+            || ((port & 0xC002 == 0x4000) && (self.machine == ZXMachine::SinclairPlus3))
+        {
             self.write_7ffd(data);
+        } else if (port & 0xF002 == 0x1000) && (self.machine == ZXMachine::SinclairPlus3) {
+            self.write_1ffd(data);
         }
         // last contention after byte write
         self.io_contention_last(port);
@@ -595,5 +851,21 @@ impl<H: Host> Z80Bus for ZXController<H> {
     fn reti(&mut self) {}
 
     /// CPU calls when was being halted
-    fn halt(&mut self, _: bool) {}
+    fn halt(&mut self, halted: bool) {
+        if halted {
+            // HALT instruction is repeated until the interrupt, only the
+            // first one is counted
+            self.halted_at.get_or_insert(self.frame_clocks);
+        } else if let Some(halted_at) = self.halted_at.take() {
+            self.frame_halt_clocks += self.frame_clocks.saturating_sub(halted_at);
+        }
+    }
+
+    /// SLT snapshots use otherwise unused `ED FB` instruction as level data
+    /// loading trap, which should be processed by emulator immediately
+    fn process_unknown_opcode(&mut self, prefix: Prefix, opcode: Opcode) {
+        if prefix == Prefix::ED && opcode.byte == OPCODE_SLT_TRAP {
+            self.events |= EmulationEvents::SLT_TRAP;
+        }
+    }
 }
@@ -8,6 +8,8 @@ bitflags! {
         const TAPE_FAST_LOAD_TRIGGER_DETECTED = 0b00000001;
         /// Set when PC breakpoint is reached
         const PC_BREAKPOINT = 0b00000010;
+        /// Set when SLT level data loading trap instruction is executed
+        const SLT_TRAP = 0b00000100;
     }
 }
 
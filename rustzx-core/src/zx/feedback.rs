@@ -0,0 +1,105 @@
+//! Emulation events which can be translated by the host into tactile
+//! feedback (e.g. gamepad rumble)
+use alloc::{collections::VecDeque, vec::Vec};
+
+/// Max count of events kept in the queue, oldest events are dropped if host
+/// doesn't drain the queue fast enough
+const MAX_QUEUED_EVENTS: usize = 64;
+
+/// Feedback event, produced during emulation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeedbackEvent {
+    /// Tape signal has changed its level given number of times during the
+    /// last frame (reported once per frame while tape is playing)
+    TapeEdges(u32),
+    /// Kempston joystick fire button was pressed
+    KempstonFire,
+    /// Memory trigger with given id was hit by `value` write
+    MemoryTrigger { id: usize, value: u8 },
+}
+
+/// Watch for RAM writes which produces [FeedbackEvent::MemoryTrigger]
+/// (e.g. on lives counter change)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryTrigger {
+    pub addr: u16,
+    /// If set, trigger is hit only when the given value is written
+    pub value: Option<u8>,
+}
+
+impl MemoryTrigger {
+    fn matches(&self, addr: u16, value: u8) -> bool {
+        self.addr == addr && self.value.map(|v| v == value).unwrap_or(true)
+    }
+}
+
+/// Collects feedback events for the host
+#[derive(Default)]
+pub(crate) struct Feedback {
+    enabled: bool,
+    triggers: Vec<MemoryTrigger>,
+    events: VecDeque<FeedbackEvent>,
+    tape_bit: bool,
+    tape_edges: u32,
+}
+
+impl Feedback {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, value: bool) {
+        self.enabled = value;
+        if !value {
+            self.events.clear();
+            self.tape_edges = 0;
+        }
+    }
+
+    pub fn add_trigger(&mut self, trigger: MemoryTrigger) -> usize {
+        self.triggers.push(trigger);
+        self.triggers.len() - 1
+    }
+
+    pub fn clear_triggers(&mut self) {
+        self.triggers.clear();
+    }
+
+    pub fn push(&mut self, event: FeedbackEvent) {
+        if !self.enabled {
+            return;
+        }
+        if self.events.len() == MAX_QUEUED_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    pub fn pop(&mut self) -> Option<FeedbackEvent> {
+        self.events.pop_front()
+    }
+
+    /// Tracks tape signal level, should be called on each clocks step
+    pub fn process_tape_bit(&mut self, bit: bool) {
+        if bit != self.tape_bit {
+            self.tape_bit = bit;
+            self.tape_edges += 1;
+        }
+    }
+
+    /// Checks RAM write against registered memory triggers
+    pub fn process_memory_write(&mut self, addr: u16, value: u8) {
+        for id in 0..self.triggers.len() {
+            if self.triggers[id].matches(addr, value) {
+                self.push(FeedbackEvent::MemoryTrigger { id, value });
+            }
+        }
+    }
+
+    pub fn new_frame(&mut self) {
+        if self.tape_edges != 0 {
+            let edges = core::mem::take(&mut self.tape_edges);
+            self.push(FeedbackEvent::TapeEdges(edges));
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! Minimal ATA drive emulation: PIO sector read/write, IDENTIFY and
+//! geometry-related commands in both CHS and LBA addressing modes
+use crate::host::{DiskImage, DISK_SECTOR_SIZE};
+
+mod register {
+    pub const DATA: u8 = 0;
+    pub const ERROR_FEATURES: u8 = 1;
+    pub const SECTOR_COUNT: u8 = 2;
+    pub const SECTOR_NUMBER: u8 = 3;
+    pub const CYLINDER_LOW: u8 = 4;
+    pub const CYLINDER_HIGH: u8 = 5;
+    pub const DRIVE_HEAD: u8 = 6;
+    pub const STATUS_COMMAND: u8 = 7;
+}
+
+mod status {
+    pub const ERR: u8 = 0x01;
+    pub const DRQ: u8 = 0x08;
+    pub const DSC: u8 = 0x10;
+    pub const DRDY: u8 = 0x40;
+}
+
+mod error {
+    pub const ABRT: u8 = 0x04;
+    pub const IDNF: u8 = 0x10;
+    pub const UNC: u8 = 0x40;
+}
+
+mod command {
+    pub const RECALIBRATE: u8 = 0x10;
+    pub const READ_SECTORS: u8 = 0x20;
+    pub const READ_SECTORS_NO_RETRY: u8 = 0x21;
+    pub const WRITE_SECTORS: u8 = 0x30;
+    pub const WRITE_SECTORS_NO_RETRY: u8 = 0x31;
+    pub const READ_VERIFY_SECTORS: u8 = 0x40;
+    pub const READ_VERIFY_SECTORS_NO_RETRY: u8 = 0x41;
+    pub const SEEK: u8 = 0x70;
+    pub const INITIALIZE_DEVICE_PARAMETERS: u8 = 0x91;
+    pub const IDENTIFY_DEVICE: u8 = 0xEC;
+    pub const SET_FEATURES: u8 = 0xEF;
+}
+
+const DRIVE_HEAD_SLAVE_MASK: u8 = 0x10;
+const DRIVE_HEAD_LBA_MASK: u8 = 0x40;
+const DRIVE_HEAD_HEAD_MASK: u8 = 0x0F;
+
+const DEFAULT_HEADS: u32 = 16;
+const DEFAULT_SECTORS_PER_TRACK: u32 = 63;
+const MAX_CYLINDERS: u32 = 16383;
+
+const IDENTIFY_SERIAL: &[u8] = b"RUSTZX0001";
+const IDENTIFY_FIRMWARE: &[u8] = b"1.0";
+const IDENTIFY_MODEL: &[u8] = b"RUSTZX HARD DISK";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    None,
+    Read,
+    Write,
+}
+
+pub(crate) struct AtaDrive<D: DiskImage> {
+    disk: D,
+    error: u8,
+    features: u8,
+    sector_count: u8,
+    sector_number: u8,
+    cylinder: u16,
+    drive_head: u8,
+    status: u8,
+    // Logical geometry, used for CHS to LBA translation
+    heads: u32,
+    sectors_per_track: u32,
+    buffer: [u8; DISK_SECTOR_SIZE],
+    buffer_pos: usize,
+    transfer: Transfer,
+    // Sectors left to transfer after the current one
+    sectors_left: u32,
+    // Current LBA address of multi-sector transfer
+    lba: u32,
+    // Identify data is transferred via buffer, but is not a disk sector
+    transfer_from_disk: bool,
+}
+
+impl<D: DiskImage> AtaDrive<D> {
+    pub fn new(disk: D) -> Self {
+        Self {
+            disk,
+            error: 0,
+            features: 0,
+            sector_count: 1,
+            sector_number: 1,
+            cylinder: 0,
+            drive_head: 0,
+            status: status::DRDY | status::DSC,
+            heads: DEFAULT_HEADS,
+            sectors_per_track: DEFAULT_SECTORS_PER_TRACK,
+            buffer: [0; DISK_SECTOR_SIZE],
+            buffer_pos: 0,
+            transfer: Transfer::None,
+            sectors_left: 0,
+            lba: 0,
+            transfer_from_disk: false,
+        }
+    }
+
+    pub fn disk(&mut self) -> &mut D {
+        &mut self.disk
+    }
+
+    pub fn into_disk(self) -> D {
+        self.disk
+    }
+
+    fn slave_selected(&self) -> bool {
+        self.drive_head & DRIVE_HEAD_SLAVE_MASK != 0
+    }
+
+    pub fn read(&mut self, reg: u8) -> u8 {
+        // Slave drive is not present: by ATA spec master drive responds
+        // with zeroes on behalf of it
+        if self.slave_selected() && reg != register::DRIVE_HEAD {
+            return 0x00;
+        }
+        match reg {
+            register::DATA => self.read_data(),
+            register::ERROR_FEATURES => self.error,
+            register::SECTOR_COUNT => self.sector_count,
+            register::SECTOR_NUMBER => self.sector_number,
+            register::CYLINDER_LOW => self.cylinder as u8,
+            register::CYLINDER_HIGH => (self.cylinder >> 8) as u8,
+            register::DRIVE_HEAD => self.drive_head,
+            register::STATUS_COMMAND => self.status,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, reg: u8, value: u8) {
+        match reg {
+            register::DATA => self.write_data(value),
+            register::ERROR_FEATURES => self.features = value,
+            register::SECTOR_COUNT => self.sector_count = value,
+            register::SECTOR_NUMBER => self.sector_number = value,
+            register::CYLINDER_LOW => self.cylinder = (self.cylinder & 0xFF00) | value as u16,
+            register::CYLINDER_HIGH => {
+                self.cylinder = (self.cylinder & 0x00FF) | ((value as u16) << 8)
+            }
+            register::DRIVE_HEAD => self.drive_head = value,
+            register::STATUS_COMMAND if !self.slave_selected() => self.execute(value),
+            _ => {}
+        }
+    }
+
+    /// Reads low byte of the next data word
+    fn read_data(&mut self) -> u8 {
+        if self.transfer != Transfer::Read {
+            return 0xFF;
+        }
+        let value = self.buffer[self.buffer_pos];
+        self.buffer_pos += 2;
+        if self.buffer_pos >= DISK_SECTOR_SIZE {
+            if self.transfer_from_disk && self.sectors_left != 0 {
+                self.sectors_left -= 1;
+                self.lba += 1;
+                self.read_sector();
+            } else {
+                self.finish_transfer();
+            }
+        }
+        value
+    }
+
+    /// Writes low byte of the next data word, high byte is set to zero
+    fn write_data(&mut self, value: u8) {
+        if self.transfer != Transfer::Write {
+            return;
+        }
+        self.buffer[self.buffer_pos] = value;
+        self.buffer[self.buffer_pos + 1] = 0;
+        self.buffer_pos += 2;
+        if self.buffer_pos >= DISK_SECTOR_SIZE {
+            if self.disk.write_sector(self.lba, &self.buffer).is_err() {
+                self.abort(error::UNC);
+                return;
+            }
+            if self.sectors_left != 0 {
+                self.sectors_left -= 1;
+                self.lba += 1;
+                self.buffer_pos = 0;
+                self.store_address(self.lba);
+            } else {
+                self.store_address(self.lba);
+                self.finish_transfer();
+            }
+        }
+    }
+
+    fn execute(&mut self, cmd: u8) {
+        self.error = 0;
+        self.status = status::DRDY | status::DSC;
+        self.transfer = Transfer::None;
+
+        match cmd {
+            command::IDENTIFY_DEVICE => {
+                self.fill_identify_data();
+                self.start_transfer(Transfer::Read, false);
+            }
+            command::READ_SECTORS | command::READ_SECTORS_NO_RETRY => {
+                if self.prepare_sectors_transfer() {
+                    self.read_sector();
+                }
+            }
+            command::WRITE_SECTORS | command::WRITE_SECTORS_NO_RETRY => {
+                if self.prepare_sectors_transfer() {
+                    self.start_transfer(Transfer::Write, true);
+                }
+            }
+            command::READ_VERIFY_SECTORS | command::READ_VERIFY_SECTORS_NO_RETRY => {
+                self.prepare_sectors_transfer();
+            }
+            command::INITIALIZE_DEVICE_PARAMETERS => {
+                let sectors_per_track = self.sector_count as u32;
+                if sectors_per_track == 0 {
+                    self.abort(error::ABRT);
+                    return;
+                }
+                self.sectors_per_track = sectors_per_track;
+                self.heads = (self.drive_head & DRIVE_HEAD_HEAD_MASK) as u32 + 1;
+            }
+            // Mechanical and feature commands have no effect on emulated drive
+            command::SET_FEATURES | command::SEEK => {}
+            cmd if cmd & 0xF0 == command::RECALIBRATE => {}
+            _ => self.abort(error::ABRT),
+        }
+    }
+
+    /// Validates address and count of sectors for read/write operation
+    fn prepare_sectors_transfer(&mut self) -> bool {
+        let count = match self.sector_count {
+            0 => 256,
+            count => count as u32,
+        };
+        let lba = match self.current_address() {
+            Some(lba) if lba + count <= self.disk.sector_count() => lba,
+            _ => {
+                self.abort(error::IDNF);
+                return false;
+            }
+        };
+        self.lba = lba;
+        self.sectors_left = count - 1;
+        true
+    }
+
+    fn read_sector(&mut self) {
+        if self.disk.read_sector(self.lba, &mut self.buffer).is_err() {
+            self.abort(error::UNC);
+            return;
+        }
+        self.store_address(self.lba);
+        self.start_transfer(Transfer::Read, true);
+    }
+
+    fn start_transfer(&mut self, transfer: Transfer, from_disk: bool) {
+        self.transfer = transfer;
+        self.transfer_from_disk = from_disk;
+        self.buffer_pos = 0;
+        self.status |= status::DRQ;
+    }
+
+    fn finish_transfer(&mut self) {
+        self.transfer = Transfer::None;
+        self.status &= !status::DRQ;
+    }
+
+    fn abort(&mut self, error: u8) {
+        self.error = error;
+        self.transfer = Transfer::None;
+        self.status = status::DRDY | status::DSC | status::ERR;
+    }
+
+    fn lba_mode(&self) -> bool {
+        self.drive_head & DRIVE_HEAD_LBA_MASK != 0
+    }
+
+    /// Returns LBA address from task file registers
+    fn current_address(&self) -> Option<u32> {
+        if self.lba_mode() {
+            let lba = ((self.drive_head & DRIVE_HEAD_HEAD_MASK) as u32) << 24
+                | (self.cylinder as u32) << 8
+                | self.sector_number as u32;
+            return Some(lba);
+        }
+        let head = (self.drive_head & DRIVE_HEAD_HEAD_MASK) as u32;
+        let sector = self.sector_number as u32;
+        if sector == 0 || sector > self.sectors_per_track || head >= self.heads {
+            return None;
+        }
+        Some((self.cylinder as u32 * self.heads + head) * self.sectors_per_track + sector - 1)
+    }
+
+    /// Updates task file registers to point to the given LBA address
+    fn store_address(&mut self, lba: u32) {
+        let head;
+        if self.lba_mode() {
+            self.sector_number = lba as u8;
+            self.cylinder = (lba >> 8) as u16;
+            head = (lba >> 24) as u8;
+        } else {
+            self.sector_number = (lba % self.sectors_per_track) as u8 + 1;
+            let track = lba / self.sectors_per_track;
+            self.cylinder = (track / self.heads) as u16;
+            head = (track % self.heads) as u8;
+        }
+        self.drive_head = (self.drive_head & !DRIVE_HEAD_HEAD_MASK) | (head & DRIVE_HEAD_HEAD_MASK);
+    }
+
+    fn fill_identify_data(&mut self) {
+        let total_sectors = self.disk.sector_count();
+        let default_cylinders =
+            (total_sectors / (DEFAULT_HEADS * DEFAULT_SECTORS_PER_TRACK)).min(MAX_CYLINDERS);
+        let current_cylinders =
+            (total_sectors / (self.heads * self.sectors_per_track)).min(MAX_CYLINDERS);
+        let current_capacity = current_cylinders * self.heads * self.sectors_per_track;
+
+        self.buffer = [0; DISK_SECTOR_SIZE];
+        let words: &[(usize, u16)] = &[
+            // Fixed device
+            (0, 0x0040),
+            (1, default_cylinders as u16),
+            (3, DEFAULT_HEADS as u16),
+            (6, DEFAULT_SECTORS_PER_TRACK as u16),
+            // LBA supported
+            (49, 0x0200),
+            // Words 54-58 are valid
+            (53, 0x0001),
+            (54, current_cylinders as u16),
+            (55, self.heads as u16),
+            (56, self.sectors_per_track as u16),
+            (57, current_capacity as u16),
+            (58, (current_capacity >> 16) as u16),
+            (60, total_sectors as u16),
+            (61, (total_sectors >> 16) as u16),
+        ];
+        for (index, value) in words.iter().copied() {
+            self.buffer[index * 2..index * 2 + 2].copy_from_slice(&value.to_le_bytes());
+        }
+        self.fill_identify_string(10, 20, IDENTIFY_SERIAL);
+        self.fill_identify_string(23, 8, IDENTIFY_FIRMWARE);
+        self.fill_identify_string(27, 40, IDENTIFY_MODEL);
+    }
+
+    /// ATA strings are padded with spaces and have swapped bytes in each word
+    fn fill_identify_string(&mut self, word: usize, length: usize, value: &[u8]) {
+        for idx in 0..length {
+            let char = value.get(idx).copied().unwrap_or(b' ');
+            self.buffer[word * 2 + (idx ^ 1)] = char;
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! IDE interface emulation. Only "simple 8-bit" interface (as supported by
+//! +3e ROMs) is emulated: only low byte of each 16-bit data word is
+//! transferred, therefore only half of each disk sector is used.
+mod ata;
+
+use crate::host::DiskImage;
+use ata::AtaDrive;
+
+/// Mask and value of the port address bits decoded by the interface
+pub(crate) const IDE_PORT_MASK: u16 = 0x003F;
+pub(crate) const IDE_PORT_VALUE: u16 = 0x002F;
+/// ATA register index is selected by A6..A8 lines (ports 0x002F, 0x006F,
+/// 0x00AF, 0x00EF, 0x012F, 0x016F, 0x01AF and 0x01EF)
+const IDE_REGISTER_SHIFT: u16 = 6;
+const IDE_REGISTER_MASK: u16 = 0x07;
+
+/// Simple 8-bit IDE interface with the single (master) drive
+pub(crate) struct SimpleIde<D: DiskImage> {
+    drive: AtaDrive<D>,
+}
+
+impl<D: DiskImage> SimpleIde<D> {
+    pub fn new(disk: D) -> Self {
+        Self {
+            drive: AtaDrive::new(disk),
+        }
+    }
+
+    /// Returns true if the port is handled by the interface
+    pub fn port_matches(&self, port: u16) -> bool {
+        port & IDE_PORT_MASK == IDE_PORT_VALUE
+    }
+
+    pub fn read(&mut self, port: u16) -> u8 {
+        self.drive.read(Self::register(port))
+    }
+
+    pub fn write(&mut self, port: u16, value: u8) {
+        self.drive.write(Self::register(port), value);
+    }
+
+    pub fn disk(&mut self) -> &mut D {
+        self.drive.disk()
+    }
+
+    pub fn into_disk(self) -> D {
+        self.drive.into_disk()
+    }
+
+    fn register(port: u16) -> u8 {
+        ((port >> IDE_REGISTER_SHIFT) & IDE_REGISTER_MASK) as u8
+    }
+}
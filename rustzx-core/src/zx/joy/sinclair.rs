@@ -1,7 +1,7 @@
 use crate::zx::keys::ZXKey;
 
 #[cfg_attr(feature = "strum", derive(strum::EnumIter))]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SinclairKey {
     Left,
     Right,
@@ -11,7 +11,7 @@ pub enum SinclairKey {
 }
 
 #[cfg_attr(feature = "strum", derive(strum::EnumIter))]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SinclairJoyNum {
     Fist,
     Second,
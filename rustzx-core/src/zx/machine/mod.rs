@@ -42,31 +42,93 @@ lazy_static! {
     };
 }
 
+lazy_static! {
+    /// ZX Spectrum +2A/+3 Specs
+    pub static ref SPECS_PLUS3: ZXSpecs = {
+        ZXSpecsBuilder::new()
+            .freq_cpu(3_546_900)
+            .clocks_first_pixel(14362)
+            .clocks_ula_read_shift(2)
+            .clocks_ula_beam_shift(1)
+            .clocks_row(24, 128, 24, 52)
+            .lines(48, 192, 48, 23)
+            .contention([1, 0, 7, 6, 5, 4, 3, 2], 1)
+            .interrupt_length(32)
+            .rom_pages(4)
+            .build()
+    };
+}
+
 /// Machine type
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ZXMachine {
     Sinclair48K,
     Sinclair128K,
+    /// ZX Spectrum +2A/+3 hardware, also used to run +3e ROMs. No ROM is
+    /// embedded for this machine, therefore it should be always provided by host
+    SinclairPlus3,
+    /// Spanish ZX Spectrum 128K (and Spanish grey +2) by Investronica. Hardware
+    /// is the same as in 128K, ROM with Spanish editor and messages should be
+    /// provided by host
+    Sinclair128KSpanish,
+    /// Timex Computer 2048, Portuguese 48K-compatible model. Has built-in
+    /// Kempston joystick interface and Timex video mode register on port `0xFF`,
+    /// which selects the second screen at `0x6000` (extended color and high
+    /// resolution modes are not emulated yet). ROM should be provided by host
+    TimexTC2048,
 }
 
 impl ZXMachine {
     /// Returns current machine specs as ref to static value
     pub fn specs(self) -> &'static ZXSpecs {
         match self {
-            ZXMachine::Sinclair48K => &SPECS_48K,
-            ZXMachine::Sinclair128K => &SPECS_128K,
+            ZXMachine::Sinclair48K | ZXMachine::TimexTC2048 => &SPECS_48K,
+            ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish => &SPECS_128K,
+            ZXMachine::SinclairPlus3 => &SPECS_PLUS3,
+        }
+    }
+
+    /// Returns Sinclair machine with the same memory layout and paging, which
+    /// is used to check compatibility of snapshots with localized models
+    pub fn base_machine(self) -> ZXMachine {
+        match self {
+            ZXMachine::Sinclair48K | ZXMachine::TimexTC2048 => ZXMachine::Sinclair48K,
+            ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish => ZXMachine::Sinclair128K,
+            ZXMachine::SinclairPlus3 => ZXMachine::SinclairPlus3,
+        }
+    }
+
+    /// Returns true if snapshot, which requires `snapshot` machine, can be
+    /// loaded. Snapshots of the base machine can be loaded on its localized
+    /// models, while snapshots which rely on the model-specific hardware
+    /// (e.g. Timex video modes) require the same model
+    pub fn can_load_snapshot_of(self, snapshot: ZXMachine) -> bool {
+        snapshot == self || snapshot == self.base_machine()
+    }
+
+    /// Returns true if AY chip is built into the machine
+    pub fn has_ay(self) -> bool {
+        self.base_machine() != ZXMachine::Sinclair48K
+    }
+
+    /// Returns true if ROM for the machine is available in `embedded-roms` feature
+    pub fn has_embedded_rom(self) -> bool {
+        match self {
+            ZXMachine::Sinclair48K | ZXMachine::Sinclair128K => true,
+            ZXMachine::SinclairPlus3 | ZXMachine::Sinclair128KSpanish | ZXMachine::TimexTC2048 => {
+                false
+            }
         }
     }
 
     /// Returns contention during specified time
     pub fn contention_clocks(self, clocks: usize) -> usize {
         let specs = self.specs();
-        if (clocks < (specs.clocks_first_pixel - 1))
-            || (clocks >= (specs.clocks_first_pixel - 1) + specs.lines_screen * specs.clocks_line)
-        {
+        let origin = specs.clocks_ula_contention_origin;
+        if (clocks < origin) || (clocks >= origin + specs.lines_screen * specs.clocks_line) {
             return 0;
         }
-        let clocks_trough_line = (clocks - (specs.clocks_first_pixel - 1)) % specs.clocks_line;
+        let clocks_trough_line = (clocks - origin) % specs.clocks_line;
         if clocks_trough_line >= specs.clocks_screen_row {
             return 0;
         }
@@ -76,21 +138,39 @@ impl ZXMachine {
     /// Checks port contention on machine
     pub fn port_is_contended(self, port: u16) -> bool {
         match self {
-            ZXMachine::Sinclair48K | ZXMachine::Sinclair128K => {
+            ZXMachine::Sinclair48K
+            | ZXMachine::Sinclair128K
+            | ZXMachine::Sinclair128KSpanish
+            | ZXMachine::TimexTC2048 => {
                 // every even port
                 (port & 0x0001) == 0
             }
+            ZXMachine::SinclairPlus3 => false,
+        }
+    }
+
+    /// Returns true if cycles without memory request (including IO operations)
+    /// are affected by memory contention. +2A/+3 gate array contends only
+    /// memory requests
+    pub fn no_mreq_is_contended(self) -> bool {
+        match self {
+            ZXMachine::Sinclair48K
+            | ZXMachine::Sinclair128K
+            | ZXMachine::Sinclair128KSpanish
+            | ZXMachine::TimexTC2048 => true,
+            ZXMachine::SinclairPlus3 => false,
         }
     }
 
     /// Returns contention status of bank
     pub fn bank_is_contended(self, page: usize) -> bool {
         match self {
-            ZXMachine::Sinclair48K => page == 0,
-            ZXMachine::Sinclair128K => {
+            ZXMachine::Sinclair48K | ZXMachine::TimexTC2048 => page == 0,
+            ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish => {
                 let contended_pages = [1, 3, 5, 7];
                 contended_pages.iter().any(|&x| x == page)
             }
+            ZXMachine::SinclairPlus3 => page >= 4,
         }
     }
 }
@@ -6,16 +6,21 @@ pub const PAGE_SIZE: usize = 16 * 1024;
 pub const SIZE_16K: usize = PAGE_SIZE;
 pub const SIZE_32K: usize = PAGE_SIZE * 2;
 pub const SIZE_48K: usize = PAGE_SIZE * 3;
+pub const SIZE_64K: usize = PAGE_SIZE * 4;
 pub const SIZE_128K: usize = PAGE_SIZE * 8;
 // count of all memory blocks
 pub const MEM_BLOCKS: usize = 4;
+// size of the single stripe in `RamPattern::Stripes`
+const RAM_STRIPE_SIZE: usize = 128;
 
 /// Rom can be:
 /// - 16K (Sinclair48K)
 /// - 32K (Sinclair128K, 2+)
+/// - 64K (Amstrad 2A+, Amstrad 3+)
 pub enum RomType {
     K16,
     K32,
+    K64,
 }
 
 /// Ram can be:
@@ -26,6 +31,51 @@ pub enum RamType {
     K128,
 }
 
+/// RAM contents on machine power-on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RamPattern {
+    /// All RAM is filled with zeros
+    #[default]
+    Zeros,
+    /// Alternating stripes of `0x00` and `0xFF` (128 bytes each), similar to
+    /// the state of real hardware RAM chips after power-on
+    Stripes,
+    /// Pseudo-random values, generated from the provided seed. The same seed
+    /// always produces the same RAM contents
+    Random(u64),
+}
+
+impl RamPattern {
+    /// Fills provided buffer with the pattern
+    pub fn fill(self, buffer: &mut [u8]) {
+        match self {
+            RamPattern::Zeros => buffer.fill(0),
+            RamPattern::Stripes => {
+                buffer
+                    .chunks_mut(RAM_STRIPE_SIZE)
+                    .enumerate()
+                    .for_each(|(idx, stripe)| stripe.fill(if idx % 2 == 0 { 0x00 } else { 0xFF }));
+            }
+            RamPattern::Random(seed) => {
+                let mut state = seed;
+                buffer.chunks_mut(8).for_each(|chunk| {
+                    let value = splitmix64(&mut state).to_le_bytes();
+                    chunk.copy_from_slice(&value[..chunk.len()]);
+                });
+            }
+        }
+    }
+}
+
+/// SplitMix64 PRNG step, works with any seed including zero
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 // Page info and type
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Page {
@@ -42,8 +92,9 @@ pub struct ZXMemory {
 }
 
 impl ZXMemory {
-    /// Returns new Memory with corresponding rom and ram types
-    pub fn new(rom_type: RomType, ram_type: RamType) -> ZXMemory {
+    /// Returns new Memory with corresponding rom and ram types. RAM is
+    /// initialized with the given pattern
+    pub fn new(rom_type: RomType, ram_type: RamType, ram_pattern: RamPattern) -> ZXMemory {
         let ram_size;
         let mem_map;
         // build memory map.
@@ -60,10 +111,13 @@ impl ZXMemory {
         let rom_size = match rom_type {
             RomType::K16 => SIZE_16K,
             RomType::K32 => SIZE_32K,
+            RomType::K64 => SIZE_64K,
         };
+        let mut ram = vec![0; ram_size];
+        ram_pattern.fill(&mut ram);
         ZXMemory {
             rom: vec![0; rom_size],
-            ram: vec![0; ram_size],
+            ram,
             map: mem_map,
         }
     }
@@ -132,6 +186,15 @@ impl ZXMemory {
         &mut self.rom[shift..shift + PAGE_SIZE]
     }
 
+    /// Returns slice to rom page
+    pub fn rom_page_data(&self, page: u8) -> &[u8] {
+        if (page as usize + 1) * PAGE_SIZE > self.rom.len() {
+            panic!("[ERROR] Rom page {} does not exists!", page);
+        }
+        let shift = page as usize * PAGE_SIZE;
+        &self.rom[shift..shift + PAGE_SIZE]
+    }
+
     /// Returns mutable slice to ram page
     pub fn ram_page_data_mut(&mut self, page: u8) -> &mut [u8] {
         if (page as usize + 1) * PAGE_SIZE > self.ram.len() {
@@ -150,6 +213,11 @@ impl ZXMemory {
         &self.ram[shift..shift + PAGE_SIZE]
     }
 
+    /// Returns count of ram pages
+    pub fn ram_pages_count(&self) -> usize {
+        self.ram.len() / PAGE_SIZE
+    }
+
     /// Calculates [Page] and local offset from memory address
     fn paged_address(&self, addr: u16) -> (Page, usize) {
         let page = self.map[(addr as usize) / PAGE_SIZE];
@@ -2,16 +2,20 @@
 //! One of core platform-independent modules
 pub(crate) mod controller;
 pub(crate) mod events;
+#[cfg(feature = "ide")]
+pub(crate) mod ide;
 pub(crate) mod memory;
 #[cfg(feature = "embedded-roms")]
 pub(crate) mod roms;
 pub(crate) mod tape;
 
 pub mod constants;
+pub mod feedback;
 pub mod joy;
 pub mod keys;
 pub mod machine;
 pub mod mouse;
+pub mod peripherals;
 
 #[cfg(feature = "sound")]
 pub mod sound;
@@ -0,0 +1,61 @@
+//! Detection of peripherals which can't be used together. Interfaces decode
+//! only some of the port address bits, so one interface may respond on the
+//! ports of another one, which leaves the latter unreachable for software
+use crate::error::SettingsError;
+#[cfg(feature = "ide")]
+use crate::zx::ide;
+use displaydoc::Display;
+
+/// Peripheral which can be attached to the emulated machine
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum Peripheral {
+    /// Kempston joystick
+    KempstonJoy,
+    /// Kempston mouse
+    KempstonMouse,
+    /// AY interface
+    Ay,
+    /// IDE interface
+    #[cfg(feature = "ide")]
+    Ide,
+}
+
+impl Peripheral {
+    /// Ports which are used by software to access the peripheral
+    fn ports(self) -> &'static [u16] {
+        match self {
+            Peripheral::KempstonJoy => &[0x001F],
+            Peripheral::KempstonMouse => &[0xFADF, 0xFBDF, 0xFFDF],
+            Peripheral::Ay => &[0xFFFD, 0xBFFD],
+            #[cfg(feature = "ide")]
+            Peripheral::Ide => &[
+                0x002F, 0x006F, 0x00AF, 0x00EF, 0x012F, 0x016F, 0x01AF, 0x01EF,
+            ],
+        }
+    }
+
+    /// Returns true if the port is decoded by the peripheral
+    fn decodes(self, port: u16) -> bool {
+        match self {
+            Peripheral::KempstonJoy => port & 0x00E0 == 0,
+            Peripheral::KempstonMouse => {
+                port & 0x0121 == 0x0001 || port & 0x0521 == 0x0101 || port & 0x0521 == 0x0501
+            }
+            Peripheral::Ay => port & 0xC002 == 0xC000 || port & 0xC002 == 0x8000,
+            #[cfg(feature = "ide")]
+            Peripheral::Ide => port & ide::IDE_PORT_MASK == ide::IDE_PORT_VALUE,
+        }
+    }
+}
+
+/// Checks that none of the given peripherals responds on the ports of another
+pub fn check_conflicts(peripherals: &[Peripheral]) -> Result<(), SettingsError> {
+    for &peripheral in peripherals {
+        for &other in peripherals.iter().filter(|&&other| other != peripheral) {
+            if let Some(&port) = peripheral.ports().iter().find(|&&p| other.decodes(p)) {
+                return Err(SettingsError::PeripheralConflict(other, peripheral, port));
+            }
+        }
+    }
+    Ok(())
+}
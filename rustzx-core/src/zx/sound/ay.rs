@@ -4,6 +4,12 @@ use aym::{AyMode, AymBackend, AymPrecise, SoundChip};
 /// AY chip runs on the same frequency on 128K, 2+, 3+
 const AY_FREQ: usize = 1773400;
 
+/// Bits which are implemented in each AY register. Unused bits of the
+/// tone/noise periods, volumes and envelope shape read back as zero
+const AY_REGISTER_MASKS: [u8; 16] = [
+    0xFF, 0x0F, 0xFF, 0x0F, 0xFF, 0x0F, 0x1F, 0xFF, 0x1F, 0x1F, 0x1F, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF,
+];
+
 /// AY output mode
 #[derive(Clone, Copy)]
 #[allow(clippy::upper_case_acronyms)]
@@ -44,13 +50,35 @@ impl ZXAyChip {
 
     pub fn write(&mut self, data: u8) {
         let reg = self.current_reg;
-        self.regs[reg] = data;
+        self.regs[reg] = data & AY_REGISTER_MASKS[reg];
         self.ay.write_register(reg as u8, data);
     }
 
     pub fn read(&self) -> u8 {
         self.regs[self.current_reg]
     }
+
+    pub fn registers(&self) -> [u8; 16] {
+        self.regs
+    }
+
+    pub fn selected_reg(&self) -> u8 {
+        self.current_reg as u8
+    }
+
+    /// Resets all registers to their power-on state
+    pub fn reset(&mut self) {
+        self.restore(&[0; 16], 0);
+    }
+
+    /// Restores registers state (e.g. from snapshot)
+    pub fn restore(&mut self, regs: &[u8], current_reg: u8) {
+        for (reg, value) in regs.iter().copied().enumerate().take(self.regs.len()) {
+            self.select_reg(reg as u8);
+            self.write(value);
+        }
+        self.select_reg(current_reg);
+    }
 }
 
 impl SampleGenerator<f64> for ZXAyChip {
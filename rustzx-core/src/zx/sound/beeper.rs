@@ -1,10 +1,26 @@
 use crate::zx::sound::sample::{SampleGenerator, SoundSample};
 
-/// Simple beeper implementation
+/// Speaker levels for `[ear][mic]` bits of port 0xFE, normalized to 0..1.
+/// ULA drives a single pin for both bits via different resistors, so the
+/// levels are not a sum of EAR and MIC levels. Measured on issue 3 board as
+/// 0.39V, 0.73V, 3.66V and 3.79V
+const ULA_OUTPUT_LEVELS: [[f64; 2]; 2] = [[0.0, 0.1], [0.9618, 1.0]];
+/// Tape signal on EAR socket shares the same pin, but is passed via input
+/// resistor, therefore it pulls the pin level only by a small fraction of the
+/// remaining range
+const TAPE_INPUT_FACTOR: f64 = 0.1;
+/// Beeper levels (0..1) are scaled to 0..0.5, which is a quarter of -1..1
+/// sample range: only positive half-wave is produced because of current
+/// emulator lack of dc filtering, and it is halved because relatively to AY
+/// chip, square wave of a beeper is too loud
+const BEEPER_SAMPLE_FACTOR: f64 = 0.5;
+
+/// Beeper implementation, which mixes EAR, MIC and tape input levels
 #[derive(Default)]
 pub(crate) struct ZXBeeper {
     mic: bool,
     ear: bool,
+    tape: bool,
 }
 
 impl ZXBeeper {
@@ -13,28 +29,52 @@ impl ZXBeeper {
         self.ear = ear;
         self.mic = mic;
     }
+
+    /// Changes tape signal level on EAR socket
+    pub fn set_tape_input(&mut self, tape: bool) {
+        self.tape = tape;
+    }
+
+    fn level(&self) -> f64 {
+        let level = ULA_OUTPUT_LEVELS[self.ear as usize][self.mic as usize];
+        if self.tape {
+            level + (1.0 - level) * TAPE_INPUT_FACTOR
+        } else {
+            level
+        }
+    }
 }
 
 impl SampleGenerator<f64> for ZXBeeper {
     fn gen_sample(&mut self) -> SoundSample<f64> {
-        // - Beeper intentionally made produce only positive half-wave 0..0.5
-        // range instead of -0.25..0.25) because of current emulator lack of
-        // dc filtering.
-        // - Beeper only produces a quarter of available sample
-        // range because relatively to AY chip, square wave of a beeper is
-        // too loud
-
-        const EAR_SAMPLE_FACTOR: f64 = 0.5;
-        const MIC_SAMPLE_FACTOR: f64 = EAR_SAMPLE_FACTOR / 5.0;
-
-        let mut sample = 0.0;
-        if self.ear {
-            sample += EAR_SAMPLE_FACTOR;
-        }
-        if self.mic {
-            sample += MIC_SAMPLE_FACTOR;
-        }
-
+        let sample = self.level() * BEEPER_SAMPLE_FACTOR;
         SoundSample::new(sample, sample)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(ear: bool, mic: bool, tape: bool) -> f64 {
+        let mut beeper = ZXBeeper::default();
+        beeper.change_state(ear, mic);
+        beeper.set_tape_input(tape);
+        beeper.level()
+    }
+
+    #[test]
+    fn beeper_levels_are_nonlinear() {
+        let silence = level(false, false, false);
+        let mic = level(false, true, false);
+        let ear = level(true, false, false);
+        let both = level(true, true, false);
+        assert!(silence < mic && mic < ear && ear < both);
+        assert!(both - silence < (ear - silence) + (mic - silence));
+        assert_eq!(both, 1.0);
+        // Tape input can't exceed the top level
+        assert_eq!(level(true, true, true), 1.0);
+        assert!(level(false, false, true) > silence);
+        assert!(level(true, false, true) - ear < level(false, false, true) - silence);
+    }
+}
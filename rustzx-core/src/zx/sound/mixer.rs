@@ -64,6 +64,22 @@ impl ZXMixer {
         self.master_volume = volume;
     }
 
+    /// Returns true if AY chip output is mixed
+    #[cfg(feature = "ay")]
+    pub fn ay_enabled(&self) -> bool {
+        self.use_ay
+    }
+
+    /// Enables or disables AY chip output. Chip is reset on disable, therefore
+    /// it starts from the power-on state when enabled again
+    #[cfg(feature = "ay")]
+    pub fn set_ay_enabled(&mut self, value: bool) {
+        if self.use_ay && !value {
+            self.ay.reset();
+        }
+        self.use_ay = value;
+    }
+
     /// Updates internal buffer of mixer and fills it with new samples
     pub fn process(&mut self, current_time: f64) {
         // buffer overflow
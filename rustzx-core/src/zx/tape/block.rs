@@ -0,0 +1,76 @@
+use crate::Result;
+use alloc::{boxed::Box, vec::Vec};
+
+/// Pulse timings of the data block, T-states are given for 3.5 MHz clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataBlockTimings {
+    pub pilot_pulse: u16,
+    /// Count of pilot tone pulses. Zero means that block has no pilot tone
+    /// and sync pulses (TZX "pure data" block)
+    pub pilot_pulses: u16,
+    pub sync1_pulse: u16,
+    pub sync2_pulse: u16,
+    pub bit_zero_pulse: u16,
+    pub bit_one_pulse: u16,
+    /// Count of bits used in the last byte of the block (1..=8)
+    pub last_byte_bits: u8,
+    /// Pause after the block in milliseconds
+    pub pause_ms: u16,
+}
+
+impl DataBlockTimings {
+    /// Timings used by the ROM saving routine
+    pub fn standard(flag_byte: u8, pause_ms: u16) -> Self {
+        Self {
+            pilot_pulse: 2168,
+            pilot_pulses: if flag_byte == 0x00 { 8063 } else { 3223 },
+            sync1_pulse: 667,
+            sync2_pulse: 735,
+            bit_zero_pulse: 855,
+            bit_one_pulse: 1710,
+            last_byte_bits: 8,
+            pause_ms,
+        }
+    }
+}
+
+/// Playable tape block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TapeBlock {
+    /// Block with pilot tone, sync pulses and data
+    Data {
+        timings: DataBlockTimings,
+        data: Vec<u8>,
+    },
+    /// Sequence of pulses with the same length
+    PureTone { pulse: u16, pulses: u16 },
+    /// Sequence of pulses with different lengths
+    Pulses(Vec<u16>),
+    /// Silence for the given time in milliseconds
+    Pause { ms: u16 },
+    /// Stops the tape, playback will continue from the next block
+    Stop,
+    /// Stops the tape only if emulated machine is in 48K mode
+    StopIf48K,
+}
+
+/// Source of the parsed tape blocks. Implemented for tape formats supported
+/// by the core, host may provide its own implementation to move parsing out
+/// of the emulation thread.
+pub trait TapeBlockSource {
+    /// Returns next block and its index in the tape image. Returns `None` if
+    /// end of the tape is reached
+    fn next_block(&mut self) -> Result<Option<(usize, TapeBlock)>>;
+    /// Rewinds source to the first block
+    fn rewind(&mut self) -> Result<()>;
+}
+
+impl<T: TapeBlockSource + ?Sized> TapeBlockSource for Box<T> {
+    fn next_block(&mut self) -> Result<Option<(usize, TapeBlock)>> {
+        (**self).next_block()
+    }
+
+    fn rewind(&mut self) -> Result<()> {
+        (**self).rewind()
+    }
+}
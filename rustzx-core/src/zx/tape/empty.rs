@@ -15,6 +15,10 @@ impl TapeImpl for Empty {
         Ok(false)
     }
 
+    fn current_block(&self) -> Option<usize> {
+        None
+    }
+
     fn current_bit(&self) -> bool {
         false
     }
@@ -1,20 +1,29 @@
+mod block;
 mod empty;
+mod player;
 mod tap;
+mod tzx;
 
+pub use block::{DataBlockTimings, TapeBlock, TapeBlockSource};
 pub use empty::Empty;
-pub use tap::Tap;
+pub use player::TapePlayer;
+pub use tap::TapParser;
+pub use tzx::TzxParser;
 
 use crate::{
     host::{LoadableAsset, SeekableAsset},
     Result,
 };
 
+use alloc::boxed::Box;
 use enum_dispatch::enum_dispatch;
 
 #[allow(clippy::large_enum_variant)]
 #[enum_dispatch(TapeImpl)]
 pub enum ZXTape<A: LoadableAsset + SeekableAsset> {
-    Tap(Tap<A>),
+    Tap(TapePlayer<TapParser<A>>),
+    Tzx(TapePlayer<TzxParser<A>>),
+    Blocks(TapePlayer<Box<dyn TapeBlockSource + Send>>),
     Empty(Empty),
 }
 
@@ -31,6 +40,9 @@ pub trait TapeImpl {
     fn next_block_byte(&mut self) -> Result<Option<u8>>;
     /// Loads next block. Returns false if end of the tape is reached
     fn next_block(&mut self) -> Result<bool>;
+    /// Returns index of the block which is currently read from the tape, `None`
+    /// if no blocks were read yet
+    fn current_block(&self) -> Option<usize>;
     /// Returns current tape (`ear`) bit
     fn current_bit(&self) -> bool;
     /// Perform tape processing emulation within `clocks` time limit
@@ -0,0 +1,323 @@
+use crate::{
+    error::TapeLoadError,
+    zx::tape::{DataBlockTimings, TapeBlock, TapeBlockSource, TapeImpl},
+    Result,
+};
+use alloc::vec::Vec;
+
+/// Tape block timings are defined for 3.5 MHz clock
+const CLOCKS_PER_MS: usize = 3500;
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum TapeState {
+    Stop,
+    Play,
+    Pilot { pulses_left: usize },
+    Sync,
+    NextByte,
+    NextBit { mask: u8 },
+    BitHalf { half_bit_delay: usize, mask: u8 },
+    Pause,
+    Tone { pulse: usize, pulses_left: usize },
+    Pulses { index: usize },
+}
+
+/// Plays blocks provided by the tape block source
+pub struct TapePlayer<S: TapeBlockSource> {
+    source: S,
+    stop_if_48k: bool,
+    state: TapeState,
+    prev_state: TapeState,
+    timings: DataBlockTimings,
+    data: Vec<u8>,
+    data_pos: usize,
+    pulses: Vec<u16>,
+    block_index: Option<usize>,
+    tape_ended: bool,
+    // Non-fastload related fields
+    curr_bit: bool,
+    curr_byte: u8,
+    delay: usize,
+}
+
+impl<S: TapeBlockSource> TapePlayer<S> {
+    /// Creates new player. `stop_if_48k` defines if tzx "stop the tape if in
+    /// 48K mode" blocks should stop the playback
+    pub fn new(source: S, stop_if_48k: bool) -> Self {
+        Self {
+            source,
+            stop_if_48k,
+            state: TapeState::Stop,
+            prev_state: TapeState::Stop,
+            timings: DataBlockTimings::standard(0x00, 0),
+            data: Vec::new(),
+            data_pos: 0,
+            pulses: Vec::new(),
+            block_index: None,
+            tape_ended: false,
+            curr_bit: false,
+            curr_byte: 0x00,
+            delay: 0,
+        }
+    }
+
+    fn fetch_block(&mut self) -> Result<Option<TapeBlock>> {
+        if self.tape_ended {
+            return Ok(None);
+        }
+
+        match self.source.next_block()? {
+            Some((index, block)) => {
+                self.block_index = Some(index);
+                Ok(Some(block))
+            }
+            None => {
+                self.tape_ended = true;
+                Ok(None)
+            }
+        }
+    }
+
+    fn stop_playback(&mut self) {
+        // Playback will be resumed from the next block
+        self.prev_state = TapeState::Play;
+        self.state = TapeState::Stop;
+    }
+}
+
+impl<S: TapeBlockSource> TapeImpl for TapePlayer<S> {
+    fn can_fast_load(&self) -> bool {
+        self.state == TapeState::Stop
+    }
+
+    fn next_block_byte(&mut self) -> Result<Option<u8>> {
+        if self.tape_ended {
+            return Ok(None);
+        }
+
+        let byte = self.data.get(self.data_pos).copied();
+        if byte.is_some() {
+            self.data_pos += 1;
+        }
+        Ok(byte)
+    }
+
+    fn next_block(&mut self) -> Result<bool> {
+        // Only data blocks can be loaded by the ROM routine, skip the rest
+        while let Some(block) = self.fetch_block()? {
+            if let TapeBlock::Data { timings, data } = block {
+                self.timings = timings;
+                self.data = data;
+                self.data_pos = 0;
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn current_block(&self) -> Option<usize> {
+        self.block_index
+    }
+
+    fn current_bit(&self) -> bool {
+        self.curr_bit
+    }
+
+    fn process_clocks(&mut self, clocks: usize) -> Result<()> {
+        if self.state == TapeState::Stop {
+            return Ok(());
+        }
+
+        if self.delay > 0 {
+            if clocks > self.delay {
+                self.delay = 0;
+            } else {
+                self.delay -= clocks;
+            }
+            return Ok(());
+        }
+
+        'state_machine: loop {
+            match self.state {
+                TapeState::Stop => {
+                    // Reset tape but leave in Stopped state
+                    self.rewind()?;
+                    self.state = TapeState::Stop;
+                    break 'state_machine;
+                }
+                TapeState::Play => match self.fetch_block()? {
+                    None => self.state = TapeState::Stop,
+                    Some(TapeBlock::Data { timings, data }) => {
+                        let first_byte = *data.first().ok_or(TapeLoadError::InvalidTapFile)?;
+                        self.timings = timings;
+                        self.data = data;
+                        self.data_pos = 1;
+                        self.curr_byte = first_byte;
+
+                        // Pure data blocks start right from the first bit
+                        if timings.pilot_pulses == 0 {
+                            self.state = TapeState::NextBit { mask: 0x80 };
+                            continue 'state_machine;
+                        }
+
+                        self.curr_bit = true;
+                        self.delay = timings.pilot_pulse as usize;
+                        self.state = TapeState::Pilot {
+                            pulses_left: timings.pilot_pulses as usize,
+                        };
+                        break 'state_machine;
+                    }
+                    Some(TapeBlock::PureTone { pulse, pulses }) => {
+                        self.state = TapeState::Tone {
+                            pulse: pulse as usize,
+                            pulses_left: pulses as usize,
+                        };
+                    }
+                    Some(TapeBlock::Pulses(pulses)) => {
+                        self.pulses = pulses;
+                        self.state = TapeState::Pulses { index: 0 };
+                    }
+                    Some(TapeBlock::Pause { ms }) => {
+                        self.delay = ms as usize * CLOCKS_PER_MS;
+                        break 'state_machine;
+                    }
+                    Some(TapeBlock::Stop) => {
+                        self.stop_playback();
+                        break 'state_machine;
+                    }
+                    Some(TapeBlock::StopIf48K) => {
+                        if self.stop_if_48k {
+                            self.stop_playback();
+                            break 'state_machine;
+                        }
+                    }
+                },
+                TapeState::Pilot { mut pulses_left } => {
+                    self.curr_bit = !self.curr_bit;
+                    pulses_left -= 1;
+                    if pulses_left == 0 {
+                        self.delay = self.timings.sync1_pulse as usize;
+                        self.state = TapeState::Sync;
+                    } else {
+                        self.delay = self.timings.pilot_pulse as usize;
+                        self.state = TapeState::Pilot { pulses_left };
+                    }
+                    break 'state_machine;
+                }
+                TapeState::Sync => {
+                    self.curr_bit = !self.curr_bit;
+                    self.delay = self.timings.sync2_pulse as usize;
+                    self.state = TapeState::NextBit { mask: 0x80 };
+                    break 'state_machine;
+                }
+                TapeState::NextByte => {
+                    self.state = if let Some(&byte) = self.data.get(self.data_pos) {
+                        self.data_pos += 1;
+                        self.curr_byte = byte;
+                        TapeState::NextBit { mask: 0x80 }
+                    } else {
+                        TapeState::Pause
+                    }
+                }
+                TapeState::NextBit { mask } => {
+                    self.curr_bit = !self.curr_bit;
+                    let half_bit_delay = if (self.curr_byte & mask) == 0 {
+                        self.timings.bit_zero_pulse
+                    } else {
+                        self.timings.bit_one_pulse
+                    } as usize;
+                    self.delay = half_bit_delay;
+                    self.state = TapeState::BitHalf {
+                        half_bit_delay,
+                        mask,
+                    };
+                    break 'state_machine;
+                }
+                TapeState::BitHalf {
+                    half_bit_delay,
+                    mut mask,
+                } => {
+                    self.curr_bit = !self.curr_bit;
+                    self.delay = half_bit_delay;
+                    mask >>= 1;
+                    let bits_in_byte = if self.data_pos == self.data.len() {
+                        self.timings.last_byte_bits as u32
+                    } else {
+                        8
+                    };
+                    // `mask` has as many leading zeros as bits were played
+                    self.state = if mask.leading_zeros() >= bits_in_byte {
+                        TapeState::NextByte
+                    } else {
+                        TapeState::NextBit { mask }
+                    };
+                    break 'state_machine;
+                }
+                TapeState::Pause => {
+                    // Next block or end of the tape
+                    self.state = TapeState::Play;
+                    if self.timings.pause_ms == 0 {
+                        continue 'state_machine;
+                    }
+                    self.curr_bit = !self.curr_bit;
+                    self.delay = self.timings.pause_ms as usize * CLOCKS_PER_MS;
+                    break 'state_machine;
+                }
+                TapeState::Tone { pulse, pulses_left } => {
+                    if pulses_left == 0 {
+                        self.state = TapeState::Play;
+                        continue 'state_machine;
+                    }
+                    self.curr_bit = !self.curr_bit;
+                    self.delay = pulse;
+                    self.state = TapeState::Tone {
+                        pulse,
+                        pulses_left: pulses_left - 1,
+                    };
+                    break 'state_machine;
+                }
+                TapeState::Pulses { index } => {
+                    if let Some(&pulse) = self.pulses.get(index) {
+                        self.curr_bit = !self.curr_bit;
+                        self.delay = pulse as usize;
+                        self.state = TapeState::Pulses { index: index + 1 };
+                        break 'state_machine;
+                    }
+                    self.state = TapeState::Play;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        let state = self.state;
+        self.prev_state = state;
+        self.state = TapeState::Stop;
+    }
+
+    fn play(&mut self) {
+        if self.state == TapeState::Stop {
+            if self.prev_state == TapeState::Stop {
+                self.state = TapeState::Play;
+            } else {
+                self.state = self.prev_state;
+            }
+        }
+    }
+
+    fn rewind(&mut self) -> Result<()> {
+        self.curr_bit = false;
+        self.curr_byte = 0x00;
+        self.data.clear();
+        self.data_pos = 0;
+        self.pulses.clear();
+        self.block_index = None;
+        self.delay = 0;
+        self.source.rewind()?;
+        self.tape_ended = false;
+        Ok(())
+    }
+}
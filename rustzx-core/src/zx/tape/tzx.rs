@@ -0,0 +1,397 @@
+use crate::{
+    error::TapeLoadError,
+    host::{LoadableAsset, SeekFrom, SeekableAsset},
+    zx::tape::{DataBlockTimings, TapeBlock, TapeBlockSource},
+    Result,
+};
+use alloc::{vec, vec::Vec};
+
+const TZX_SIGNATURE: &[u8; 8] = b"ZXTape!\x1A";
+const TZX_HEADER_SIZE: usize = 10;
+
+const BLOCK_STANDARD_SPEED: u8 = 0x10;
+const BLOCK_TURBO_SPEED: u8 = 0x11;
+const BLOCK_PURE_TONE: u8 = 0x12;
+const BLOCK_PULSES: u8 = 0x13;
+const BLOCK_PURE_DATA: u8 = 0x14;
+const BLOCK_DIRECT_RECORDING: u8 = 0x15;
+const BLOCK_CSW_RECORDING: u8 = 0x18;
+const BLOCK_GENERALIZED_DATA: u8 = 0x19;
+const BLOCK_PAUSE: u8 = 0x20;
+const BLOCK_GROUP_START: u8 = 0x21;
+const BLOCK_GROUP_END: u8 = 0x22;
+const BLOCK_JUMP: u8 = 0x23;
+const BLOCK_LOOP_START: u8 = 0x24;
+const BLOCK_LOOP_END: u8 = 0x25;
+const BLOCK_CALL_SEQUENCE: u8 = 0x26;
+const BLOCK_RETURN: u8 = 0x27;
+const BLOCK_SELECT: u8 = 0x28;
+const BLOCK_STOP_IF_48K: u8 = 0x2A;
+const BLOCK_TEXT: u8 = 0x30;
+const BLOCK_MESSAGE: u8 = 0x31;
+const BLOCK_ARCHIVE_INFO: u8 = 0x32;
+const BLOCK_HARDWARE_TYPE: u8 = 0x33;
+const BLOCK_EMULATION_INFO: u8 = 0x34;
+const BLOCK_CUSTOM_INFO: u8 = 0x35;
+const BLOCK_SNAPSHOT: u8 = 0x40;
+const BLOCK_GLUE: u8 = 0x5A;
+
+enum ParsedBlock {
+    Playable(TapeBlock),
+    Skipped,
+    LoopStart { repetitions: u16 },
+    LoopEnd,
+    Jump { offset: i16 },
+}
+
+struct Loop {
+    first_block: usize,
+    repetitions_left: u16,
+}
+
+/// Parser of the tzx files. Blocks which only carry information are skipped,
+/// loops and jumps are resolved by the parser. Blocks which require sample
+/// level playback (direct/CSW recordings, generalized data) and call
+/// sequences are not supported.
+pub struct TzxParser<A: LoadableAsset + SeekableAsset> {
+    asset: A,
+    header_checked: bool,
+    /// Index of the next block to parse
+    block_index: usize,
+    /// Offsets of already visited blocks, used to resolve loops and jumps
+    block_offsets: Vec<usize>,
+    active_loop: Option<Loop>,
+}
+
+impl<A: LoadableAsset + SeekableAsset> TzxParser<A> {
+    pub fn from_asset(asset: A) -> Self {
+        Self {
+            asset,
+            header_checked: false,
+            block_index: 0,
+            block_offsets: Vec::new(),
+            active_loop: None,
+        }
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let mut buffer = [0u8; 1];
+        self.asset.read_exact(&mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    fn peek_u8(&mut self) -> Result<u8> {
+        let value = self.read_u8()?;
+        self.asset.seek(SeekFrom::Current(-1))?;
+        Ok(value)
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        let mut buffer = [0u8; 2];
+        self.asset.read_exact(&mut buffer)?;
+        Ok(u16::from_le_bytes(buffer))
+    }
+
+    fn read_u24(&mut self) -> Result<usize> {
+        let mut buffer = [0u8; 4];
+        self.asset.read_exact(&mut buffer[0..3])?;
+        Ok(u32::from_le_bytes(buffer) as usize)
+    }
+
+    fn read_u32(&mut self) -> Result<usize> {
+        let mut buffer = [0u8; 4];
+        self.asset.read_exact(&mut buffer)?;
+        Ok(u32::from_le_bytes(buffer) as usize)
+    }
+
+    fn read_data(&mut self, len: usize) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; len];
+        self.asset.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.asset.seek(SeekFrom::Current(len as isize))?;
+        Ok(())
+    }
+
+    fn position(&mut self) -> Result<usize> {
+        Ok(self.asset.seek(SeekFrom::Current(0))?)
+    }
+
+    /// Checks tzx file signature. Signature is checked on the first
+    /// `next_block` call if it was not checked explicitly
+    pub fn check_header(&mut self) -> Result<()> {
+        let mut header = [0u8; TZX_HEADER_SIZE];
+        self.asset
+            .read_exact(&mut header)
+            .map_err(|_| TapeLoadError::InvalidTzxFile)?;
+        if &header[0..TZX_SIGNATURE.len()] != TZX_SIGNATURE {
+            return Err(TapeLoadError::InvalidTzxFile.into());
+        }
+        self.header_checked = true;
+        Ok(())
+    }
+
+    /// Reads block id, returns `None` if end of the tape is reached
+    fn next_block_id(&mut self) -> Result<Option<u8>> {
+        let offset = self.position()?;
+        let mut id = [0u8; 1];
+        if self.asset.read_exact(&mut id).is_err() {
+            return Ok(None);
+        }
+        if self.block_index == self.block_offsets.len() {
+            self.block_offsets.push(offset);
+        }
+        Ok(Some(id[0]))
+    }
+
+    fn parse_block(&mut self, id: u8) -> Result<ParsedBlock> {
+        let block = match id {
+            BLOCK_STANDARD_SPEED => {
+                let pause_ms = self.read_u16()?;
+                let len = self.read_u16()? as usize;
+                // Pilot tone length depends on the flag byte
+                let flag_byte = if len > 0 { self.peek_u8()? } else { 0x00 };
+                self.parse_data_block(DataBlockTimings::standard(flag_byte, pause_ms), len)?
+            }
+            BLOCK_TURBO_SPEED => {
+                let pilot_pulse = self.read_u16()?;
+                let sync1_pulse = self.read_u16()?;
+                let sync2_pulse = self.read_u16()?;
+                let bit_zero_pulse = self.read_u16()?;
+                let bit_one_pulse = self.read_u16()?;
+                let pilot_pulses = self.read_u16()?;
+                let last_byte_bits = self.read_u8()?;
+                let pause_ms = self.read_u16()?;
+                let len = self.read_u24()?;
+                let timings = DataBlockTimings {
+                    pilot_pulse,
+                    pilot_pulses,
+                    sync1_pulse,
+                    sync2_pulse,
+                    bit_zero_pulse,
+                    bit_one_pulse,
+                    last_byte_bits,
+                    pause_ms,
+                };
+                self.parse_data_block(timings, len)?
+            }
+            BLOCK_PURE_TONE => {
+                let pulse = self.read_u16()?;
+                let pulses = self.read_u16()?;
+                ParsedBlock::Playable(TapeBlock::PureTone { pulse, pulses })
+            }
+            BLOCK_PULSES => {
+                let count = self.read_u8()?;
+                let pulses = (0..count)
+                    .map(|_| self.read_u16())
+                    .collect::<Result<Vec<_>>>()?;
+                ParsedBlock::Playable(TapeBlock::Pulses(pulses))
+            }
+            BLOCK_PURE_DATA => {
+                let bit_zero_pulse = self.read_u16()?;
+                let bit_one_pulse = self.read_u16()?;
+                let last_byte_bits = self.read_u8()?;
+                let pause_ms = self.read_u16()?;
+                let len = self.read_u24()?;
+                let timings = DataBlockTimings {
+                    pilot_pulse: 0,
+                    pilot_pulses: 0,
+                    sync1_pulse: 0,
+                    sync2_pulse: 0,
+                    bit_zero_pulse,
+                    bit_one_pulse,
+                    last_byte_bits,
+                    pause_ms,
+                };
+                self.parse_data_block(timings, len)?
+            }
+            BLOCK_PAUSE => {
+                let ms = self.read_u16()?;
+                let block = if ms == 0 {
+                    TapeBlock::Stop
+                } else {
+                    TapeBlock::Pause { ms }
+                };
+                ParsedBlock::Playable(block)
+            }
+            BLOCK_GROUP_START | BLOCK_TEXT => {
+                let len = self.read_u8()? as usize;
+                self.skip(len)?;
+                ParsedBlock::Skipped
+            }
+            BLOCK_GROUP_END => ParsedBlock::Skipped,
+            BLOCK_JUMP => {
+                let offset = self.read_u16()? as i16;
+                ParsedBlock::Jump { offset }
+            }
+            BLOCK_LOOP_START => {
+                let repetitions = self.read_u16()?;
+                ParsedBlock::LoopStart { repetitions }
+            }
+            BLOCK_LOOP_END => ParsedBlock::LoopEnd,
+            BLOCK_SELECT | BLOCK_ARCHIVE_INFO => {
+                let len = self.read_u16()? as usize;
+                self.skip(len)?;
+                ParsedBlock::Skipped
+            }
+            BLOCK_STOP_IF_48K => {
+                let len = self.read_u32()?;
+                self.skip(len)?;
+                ParsedBlock::Playable(TapeBlock::StopIf48K)
+            }
+            BLOCK_MESSAGE => {
+                let _display_time = self.read_u8()?;
+                let len = self.read_u8()? as usize;
+                self.skip(len)?;
+                ParsedBlock::Skipped
+            }
+            BLOCK_HARDWARE_TYPE => {
+                let count = self.read_u8()? as usize;
+                self.skip(count * 3)?;
+                ParsedBlock::Skipped
+            }
+            BLOCK_EMULATION_INFO => {
+                self.skip(8)?;
+                ParsedBlock::Skipped
+            }
+            BLOCK_CUSTOM_INFO => {
+                self.skip(16)?;
+                let len = self.read_u32()?;
+                self.skip(len)?;
+                ParsedBlock::Skipped
+            }
+            BLOCK_SNAPSHOT => {
+                self.skip(1)?;
+                let len = self.read_u24()?;
+                self.skip(len)?;
+                ParsedBlock::Skipped
+            }
+            BLOCK_GLUE => {
+                self.skip(9)?;
+                ParsedBlock::Skipped
+            }
+            BLOCK_DIRECT_RECORDING
+            | BLOCK_CSW_RECORDING
+            | BLOCK_GENERALIZED_DATA
+            | BLOCK_CALL_SEQUENCE
+            | BLOCK_RETURN => return Err(TapeLoadError::UnsupportedTzxBlock(id).into()),
+            _ => {
+                // All blocks introduced after TZX 1.10 start with the block
+                // length, which allows to skip unknown blocks
+                let len = self.read_u32()?;
+                self.skip(len)?;
+                ParsedBlock::Skipped
+            }
+        };
+
+        Ok(block)
+    }
+
+    fn parse_data_block(&mut self, timings: DataBlockTimings, len: usize) -> Result<ParsedBlock> {
+        if !(1..=8).contains(&timings.last_byte_bits) {
+            return Err(TapeLoadError::InvalidTzxFile.into());
+        }
+        let data = self.read_data(len)?;
+        if data.is_empty() {
+            // Data block without data is only a pause
+            let block = if timings.pause_ms == 0 {
+                ParsedBlock::Skipped
+            } else {
+                ParsedBlock::Playable(TapeBlock::Pause {
+                    ms: timings.pause_ms,
+                })
+            };
+            return Ok(block);
+        }
+        Ok(ParsedBlock::Playable(TapeBlock::Data { timings, data }))
+    }
+
+    /// Moves parser to the block with the given index
+    fn seek_block(&mut self, index: usize) -> Result<()> {
+        if let Some(&offset) = self.block_offsets.get(index) {
+            self.asset.seek(SeekFrom::Start(offset))?;
+            self.block_index = index;
+            return Ok(());
+        }
+
+        // Target block was not visited yet, skip blocks up to it
+        let last_known = self.block_offsets.len() - 1;
+        self.asset
+            .seek(SeekFrom::Start(self.block_offsets[last_known]))?;
+        self.block_index = last_known;
+        while self.block_index < index {
+            let id = self.next_block_id()?.ok_or(TapeLoadError::InvalidTzxFile)?;
+            self.parse_block(id)?;
+            self.block_index += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<A: LoadableAsset + SeekableAsset> TapeBlockSource for TzxParser<A> {
+    fn next_block(&mut self) -> Result<Option<(usize, TapeBlock)>> {
+        if !self.header_checked {
+            self.check_header()?;
+        }
+
+        // Blocks visited while looking for the next playable block. Revisiting
+        // any of them means that jumps or loops never reach a playable block
+        let mut visited = Vec::new();
+        loop {
+            let index = self.block_index;
+            if visited.contains(&index) {
+                return Err(TapeLoadError::InvalidTzxFile.into());
+            }
+            visited.push(index);
+            let id = match self.next_block_id()? {
+                Some(id) => id,
+                None => return Ok(None),
+            };
+
+            match self.parse_block(id)? {
+                ParsedBlock::Playable(block) => {
+                    self.block_index += 1;
+                    return Ok(Some((index, block)));
+                }
+                ParsedBlock::Skipped => self.block_index += 1,
+                ParsedBlock::LoopStart { repetitions } => {
+                    self.block_index += 1;
+                    self.active_loop = Some(Loop {
+                        first_block: self.block_index,
+                        repetitions_left: repetitions,
+                    });
+                }
+                ParsedBlock::LoopEnd => {
+                    self.block_index += 1;
+                    if let Some(active_loop) = self.active_loop.as_mut() {
+                        active_loop.repetitions_left =
+                            active_loop.repetitions_left.saturating_sub(1);
+                        if active_loop.repetitions_left > 0 {
+                            let first_block = active_loop.first_block;
+                            self.seek_block(first_block)?;
+                        } else {
+                            self.active_loop = None;
+                        }
+                    }
+                }
+                ParsedBlock::Jump { offset } => {
+                    let target = index as isize + offset as isize;
+                    if offset == 0 || target < 0 {
+                        return Err(TapeLoadError::InvalidTzxFile.into());
+                    }
+                    self.seek_block(target as usize)?;
+                }
+            }
+        }
+    }
+
+    fn rewind(&mut self) -> Result<()> {
+        self.asset.seek(SeekFrom::Start(0))?;
+        self.header_checked = false;
+        self.block_index = 0;
+        self.active_loop = None;
+        Ok(())
+    }
+}
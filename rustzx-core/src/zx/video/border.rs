@@ -11,6 +11,22 @@ use crate::{
     },
 };
 
+/// Border rendering mode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BorderMode {
+    /// Border is rendered pixel by pixel with precise beam timings
+    #[default]
+    Precise,
+    /// Border pixels are not generated: border frame buffer has a single pixel,
+    /// which is set via [crate::host::FrameBuffer::set_border_color] only when
+    /// border color changes. Suitable for hosts which fill the border with a
+    /// solid color
+    SolidColor,
+    /// Only 256x192 canvas is rendered, border frame buffer has a single pixel
+    /// and is never updated
+    CanvasOnly,
+}
+
 /// Internal struct, which contains information about beam position and color
 #[derive(Clone, Copy)]
 struct BeamInfo {
@@ -39,28 +55,43 @@ impl BeamInfo {
 /// ZX Spectrum Border Device
 pub struct ZXBorder<FB: FrameBuffer> {
     machine: ZXMachine,
+    mode: BorderMode,
+    /// Buffer of the frame which is currently rendered
     buffer: FB,
+    /// Buffer of the last completed frame
+    front_buffer: FB,
     beam_last: BeamInfo,
     border_changed: bool,
     beam_block: bool,
 }
 impl<FB: FrameBuffer> ZXBorder<FB> {
     /// Returns new instance of border device
-    pub fn new(machine: ZXMachine, context: FB::Context) -> Self {
+    pub fn new(machine: ZXMachine, mode: BorderMode, context: FB::Context) -> Self {
+        let beam_last = BeamInfo::first_pixel(ZXColor::White);
+        let mut front_buffer = Self::new_frame_buffer(mode, context.clone());
+        if mode == BorderMode::SolidColor {
+            front_buffer.set_border_color(beam_last.color);
+        }
         ZXBorder {
             machine,
-            buffer: FB::new(
-                SCREEN_WIDTH,
-                SCREEN_HEIGHT,
-                FrameBufferSource::Border,
-                context,
-            ),
-            beam_last: BeamInfo::first_pixel(ZXColor::White),
+            mode,
+            buffer: Self::new_frame_buffer(mode, context),
+            front_buffer,
+            beam_last,
             border_changed: true,
             beam_block: false,
         }
     }
 
+    /// Creates frame buffer of the size required by border `mode`
+    pub fn new_frame_buffer(mode: BorderMode, context: FB::Context) -> FB {
+        let (width, height) = match mode {
+            BorderMode::Precise => (SCREEN_WIDTH, SCREEN_HEIGHT),
+            BorderMode::SolidColor | BorderMode::CanvasOnly => (1, 1),
+        };
+        FB::new(width, height, FrameBufferSource::Border, context)
+    }
+
     /// ULA draws 2 pixels per TState.
     /// This function helps to determine pixel, which will be rendered at specific time
     /// and bool value, which signals end of frame
@@ -111,6 +142,9 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
 
     /// starts new frame
     pub fn new_frame(&mut self) {
+        if self.mode != BorderMode::Precise {
+            return;
+        }
         // if border was not changed during prev frame then force change color of whole border
         if !self.border_changed {
             self.beam_last.reset();
@@ -119,6 +153,9 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         if !self.beam_block {
             self.fill_to(SCREEN_HEIGHT - 1, SCREEN_WIDTH);
         }
+        // all pixels are rendered on each frame, so previous front buffer
+        // contents can be safely overwritten
+        core::mem::swap(&mut self.buffer, &mut self.front_buffer);
         // move beam to begin and reset flags
         self.beam_last.reset();
         self.border_changed = false;
@@ -127,6 +164,17 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
 
     /// changes color of border
     pub fn set_border(&mut self, clocks: usize, color: ZXColor) {
+        match self.mode {
+            BorderMode::Precise => {}
+            BorderMode::SolidColor => {
+                if color != self.beam_last.color {
+                    self.front_buffer.set_border_color(color);
+                    self.beam_last.color = color;
+                }
+                return;
+            }
+            BorderMode::CanvasOnly => return,
+        }
         // border updated during frame
         self.border_changed = true;
         let (line, pixel, frame_end) = self.next_border_pixel(clocks);
@@ -141,8 +189,18 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         self.beam_last = BeamInfo::new(line, pixel, color);
     }
 
-    /// Returns reference to texture
+    /// Returns buffer of the last completed frame
     pub fn frame_buffer(&self) -> &FB {
-        &self.buffer
+        &self.front_buffer
+    }
+
+    /// Exchanges buffer of the last completed frame with the provided one
+    pub fn swap_frame_buffer(&mut self, buffer: &mut FB) {
+        core::mem::swap(&mut self.front_buffer, buffer);
+        // Solid color buffer is updated only on color change, so new buffer
+        // should get the current color
+        if self.mode == BorderMode::SolidColor {
+            self.front_buffer.set_border_color(self.beam_last.color);
+        }
     }
 }
@@ -7,7 +7,7 @@ pub enum ZXBrightness {
 
 /// ZX Spectrum color enum
 /// Constructs self from 3-bit value
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ZXColor {
     Black = 0,
     Blue = 1,
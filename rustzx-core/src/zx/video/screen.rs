@@ -14,6 +14,9 @@ use crate::{
 };
 use alloc::boxed::Box;
 
+/// Timex second screen is placed at 0x6000, in the same RAM page as the first
+const TIMEX_SECOND_SCREEN_REL: u16 = 0x2000;
+
 /// Represents how much 8x1 have been already **rendered**.
 #[derive(PartialEq, Eq, Debug)]
 pub struct BlocksCount {
@@ -100,18 +103,8 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
             last_blocks: BlocksCount::new(0, 0),
             flash: false,
             frame_counter: 0,
-            buffer: FB::new(
-                CANVAS_WIDTH,
-                CANVAS_HEIGHT,
-                FrameBufferSource::Screen,
-                context.clone(),
-            ),
-            back_buffer: FB::new(
-                CANVAS_WIDTH,
-                CANVAS_HEIGHT,
-                FrameBufferSource::Screen,
-                context,
-            ),
+            buffer: Self::new_frame_buffer(context.clone()),
+            back_buffer: Self::new_frame_buffer(context),
             banks: [
                 ScreenBank {
                     attributes: Box::new([ZXAttribute::from_byte(0); ATTR_COLS * ATTR_ROWS]),
@@ -131,23 +124,46 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
         self.flash = !self.flash;
     }
 
-    /// transforms zx spectrum bank to local index
-    fn local_bank(&self, bank: usize) -> Option<usize> {
+    /// transforms zx spectrum bank and address relative to it to local index
+    /// and address relative to the local bank
+    fn local_address(&self, bank: usize, rel_addr: u16) -> Option<(usize, u16)> {
         match self.machine {
-            ZXMachine::Sinclair48K if bank == 0 => Some(0),
-            ZXMachine::Sinclair128K if bank == 5 => Some(0),
-            ZXMachine::Sinclair128K if bank == 7 => Some(1),
+            ZXMachine::Sinclair48K if bank == 0 => Some((0, rel_addr)),
+            ZXMachine::TimexTC2048 if bank == 0 => {
+                if rel_addr < TIMEX_SECOND_SCREEN_REL {
+                    Some((0, rel_addr))
+                } else {
+                    Some((1, rel_addr - TIMEX_SECOND_SCREEN_REL))
+                }
+            }
+            ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish | ZXMachine::SinclairPlus3
+                if bank == 5 =>
+            {
+                Some((0, rel_addr))
+            }
+            ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish | ZXMachine::SinclairPlus3
+                if bank == 7 =>
+            {
+                Some((1, rel_addr))
+            }
             _ => None,
         }
     }
 
     /// selects bank of memory
     pub fn switch_bank(&mut self, bank: usize) {
-        if let Some(bank) = self.local_bank(bank) {
+        if let Some((bank, _)) = self.local_address(bank, 0) {
             self.active_bank = bank;
         }
     }
 
+    /// Applies Timex video mode (bits 0..=2 of port 0xFF register). Only
+    /// standard mode with the first (`000`) or the second (`001`) screen is
+    /// emulated, extended color and high resolution modes show the first one
+    pub fn set_timex_video_mode(&mut self, mode: u8) {
+        self.active_bank = usize::from(mode & 0x07 == 0x01);
+    }
+
     /// renders some  8x1 blocks
     /// `clocks` - current  clocks count form frame start.
     /// if clocks < previous call clocks then discard processing
@@ -203,7 +219,7 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
 
     /// Updates data if screen ram
     pub fn update(&mut self, rel_addr: u16, bank: usize, data: u8) {
-        if let Some(bank) = self.local_bank(bank) {
+        if let Some((bank, rel_addr)) = self.local_address(bank, rel_addr) {
             match rel_addr {
                 // change bitmap
                 0..=BITMAP_MAX_REL => {
@@ -227,4 +243,21 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
     pub fn frame_buffer(&self) -> &FB {
         &self.buffer
     }
+
+    /// Exchanges buffer of the last completed frame with the provided one.
+    /// Frames are rendered to the back buffer, so provided buffer contents
+    /// do not affect rendering
+    pub fn swap_frame_buffer(&mut self, buffer: &mut FB) {
+        core::mem::swap(&mut self.buffer, buffer);
+    }
+
+    /// Creates canvas-sized frame buffer
+    pub fn new_frame_buffer(context: FB::Context) -> FB {
+        FB::new(
+            CANVAS_WIDTH,
+            CANVAS_HEIGHT,
+            FrameBufferSource::Screen,
+            context,
+        )
+    }
 }
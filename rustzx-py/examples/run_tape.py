@@ -0,0 +1,24 @@
+"""Loads a tape, waits for it to start and saves the screen as PNG.
+
+Usage: python run_tape.py game.tap screen.png (requires Pillow)
+"""
+import sys
+
+import rustzx
+from PIL import Image
+
+emulator = rustzx.Emulator(machine="48k")
+emulator.load(sys.argv[1])
+emulator.run_frame(50 * 10)
+
+# Press "Space" for a single frame
+emulator.send_key("Space", True)
+emulator.run_frame()
+emulator.send_key("Space", False)
+emulator.run_frame(50)
+
+width, height, rgba = emulator.screenshot()
+Image.frombytes("RGBA", (width, height), rgba).save(sys.argv[2])
+
+# Read system variable LAST_K (23560)
+print("Last key:", emulator.read_memory(23560)[0])
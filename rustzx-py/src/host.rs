@@ -0,0 +1,28 @@
+use rustzx_core::host::{
+    FrameBuffer, Host, HostContext, StubDebugInterface, StubDiskImage, StubIoExtender,
+};
+use rustzx_utils::{
+    frame_buffer::{RgbaFrameBuffer, RgbaFrameBufferContext},
+    io::DynamicAsset,
+    stopwatch::InstantStopwatch,
+};
+
+pub struct PyHost;
+
+impl Host for PyHost {
+    type Context = PyHostContext;
+    type DebugInterface = StubDebugInterface;
+    type EmulationStopwatch = InstantStopwatch;
+    type FrameBuffer = RgbaFrameBuffer;
+    type IoExtender = StubIoExtender;
+    type TapeAsset = DynamicAsset;
+    type DiskImage = StubDiskImage;
+}
+
+pub struct PyHostContext;
+
+impl HostContext<PyHost> for PyHostContext {
+    fn frame_buffer_context(&self) -> <<PyHost as Host>::FrameBuffer as FrameBuffer>::Context {
+        RgbaFrameBufferContext::default()
+    }
+}
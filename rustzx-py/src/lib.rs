@@ -0,0 +1,264 @@
+//! Python bindings for `rustzx-core`, intended for scripted testing, automation
+//! and ML experiments. Module is built with `maturin`, see `pyproject.toml`.
+mod host;
+
+use host::{PyHost, PyHostContext};
+use pyo3::{
+    exceptions::{PyIOError, PyRuntimeError, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+use rustzx_core::{
+    host::{Screen, Snapshot, Tape},
+    zx::constants::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    zx::{keys::ZXKey, machine::ZXMachine, sound::ay::ZXAYMode},
+    BorderMode, EmulationMode, IterableEnum, RamPattern, RustzxSettings,
+};
+use rustzx_utils::{
+    frame_buffer::{compose_rgba_frame, RGBA_FRAME_SIZE},
+    io::{DynamicAsset, FileAsset, GzipAsset},
+};
+use std::{fs::File, path::Path, time::Duration};
+
+const SOUND_SAMPLE_RATE: usize = 44100;
+
+fn core_error(e: rustzx_core::error::Error) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+fn load_asset(path: &Path) -> PyResult<DynamicAsset> {
+    let file = File::open(path)?;
+    if extension(path) == "gz" {
+        return Ok(GzipAsset::new(file)?.into());
+    }
+    Ok(FileAsset::from(file).into())
+}
+
+/// Returns lowercase file extension, skipping outer `.gz` container extension
+fn file_kind(path: &Path) -> String {
+    let ext = extension(path);
+    if ext == "gz" {
+        return extension(&path.with_extension(""));
+    }
+    ext
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+/// Parses key name, e.g. `"Enter"`, `"symshift"`, `"A"` or `"5"`
+fn parse_key(name: &str) -> PyResult<ZXKey> {
+    let normalized = if name.len() == 1 && name.as_bytes()[0].is_ascii_digit() {
+        format!("n{}", name)
+    } else {
+        name.to_lowercase()
+    };
+
+    ZXKey::iter()
+        .find(|key| format!("{:?}", key).to_lowercase() == normalized)
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown key name: {}", name)))
+}
+
+/// ZX Spectrum emulator instance
+#[pyclass(name = "Emulator", unsendable)]
+struct PyEmulator {
+    emulator: rustzx_core::Emulator<PyHost>,
+}
+
+#[pymethods]
+impl PyEmulator {
+    /// Creates new emulator. `machine` is either `"48k"` or `"128k"`. If
+    /// `ram_seed` is set, RAM is filled with pseudo-random values generated
+    /// from it on power-on instead of zeros. `border_mode` is one of `"precise"`,
+    /// `"solid"` (border is filled with the last set color) or `"canvas"`
+    /// (border is not rendered)
+    #[new]
+    #[pyo3(signature = (machine = "48k", fastload = true, ram_seed = None, border_mode = "precise"))]
+    fn new(
+        machine: &str,
+        fastload: bool,
+        ram_seed: Option<u64>,
+        border_mode: &str,
+    ) -> PyResult<Self> {
+        let machine = match machine.to_lowercase().as_str() {
+            "48k" => ZXMachine::Sinclair48K,
+            "128k" => ZXMachine::Sinclair128K,
+            _ => return Err(PyValueError::new_err("Machine should be one of: 48k, 128k")),
+        };
+        let border_mode = match border_mode.to_lowercase().as_str() {
+            "precise" => BorderMode::Precise,
+            "solid" => BorderMode::SolidColor,
+            "canvas" => BorderMode::CanvasOnly,
+            _ => {
+                return Err(PyValueError::new_err(
+                    "Border mode should be one of: precise, solid, canvas",
+                ))
+            }
+        };
+
+        let settings = RustzxSettings {
+            machine,
+            emulation_mode: EmulationMode::FrameCount(1),
+            tape_fastload_enabled: fastload,
+            kempston_enabled: false,
+            mouse_enabled: false,
+            ram_pattern: ram_seed.map_or(RamPattern::Zeros, RamPattern::Random),
+            border_mode,
+            ay_mode: ZXAYMode::ABC,
+            ay_enabled: false,
+            beeper_enabled: false,
+            sound_enabled: false,
+            sound_volume: 100,
+            sound_sample_rate: SOUND_SAMPLE_RATE,
+            load_default_rom: true,
+            autoload_enabled: true,
+        };
+
+        let emulator = rustzx_core::Emulator::new(settings, PyHostContext).map_err(core_error)?;
+        Ok(Self { emulator })
+    }
+
+    /// Loads tape (`tap`, `tzx`), snapshot (`sna`, `z80`, `szx`) or screen (`scr`) file, optionally
+    /// `.gz`-compressed. Tapes are auto-loaded if possible.
+    fn load(&mut self, path: &str) -> PyResult<()> {
+        let path = Path::new(path);
+        let asset = load_asset(path)?;
+        let result = match file_kind(path).as_str() {
+            "tap" => self.emulator.load_tape(Tape::Tap(asset)),
+            "tzx" => self.emulator.load_tape(Tape::Tzx(asset)),
+            "sna" => self.emulator.load_snapshot(Snapshot::Sna(asset)),
+            "z80" => self.emulator.load_snapshot(Snapshot::Z80(asset)),
+            "szx" => self.emulator.load_snapshot(Snapshot::Szx(asset)),
+            "slt" => self.emulator.load_snapshot(Snapshot::Slt(asset)),
+            "scr" => self.emulator.load_screen(Screen::Scr(asset)),
+            _ => return Err(PyIOError::new_err("Not supported file format")),
+        };
+        result.map_err(core_error)
+    }
+
+    /// Emulates `count` frames
+    #[pyo3(signature = (count = 1))]
+    fn run_frame(&mut self, count: usize) -> PyResult<()> {
+        for _ in 0..count {
+            self.emulator
+                .emulate_frames(Duration::MAX)
+                .map_err(core_error)?;
+        }
+        Ok(())
+    }
+
+    /// Reads `length` bytes of memory starting from `address`, wrapping
+    /// around at 0xFFFF
+    #[pyo3(signature = (address, length = 1))]
+    fn read_memory<'py>(
+        &self,
+        py: Python<'py>,
+        address: u16,
+        length: usize,
+    ) -> Bound<'py, PyBytes> {
+        let data = (0..length)
+            .map(|offset| self.emulator.peek(address.wrapping_add(offset as u16)))
+            .collect::<Vec<_>>();
+        PyBytes::new(py, &data)
+    }
+
+    /// Presses or releases ZX Spectrum key, see `ZXKey` in `rustzx-core` for
+    /// the key names
+    #[pyo3(signature = (key, pressed = true))]
+    fn send_key(&mut self, key: &str, pressed: bool) -> PyResult<()> {
+        self.emulator.send_key(parse_key(key)?, pressed);
+        Ok(())
+    }
+
+    /// Kempston joystick, can be attached or detached at any moment. Raises
+    /// `ValueError` if it conflicts with the attached peripherals
+    #[getter]
+    fn kempston(&self) -> bool {
+        self.emulator.kempston_enabled()
+    }
+
+    #[setter]
+    fn set_kempston(&mut self, value: bool) -> PyResult<()> {
+        self.emulator
+            .set_kempston_enabled(value)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Kempston mouse, can be attached or detached at any moment. Raises
+    /// `ValueError` if it conflicts with the attached peripherals
+    #[getter]
+    fn mouse(&self) -> bool {
+        self.emulator.mouse_enabled()
+    }
+
+    #[setter]
+    fn set_mouse(&mut self, value: bool) -> PyResult<()> {
+        self.emulator
+            .set_mouse_enabled(value)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// AY chip, on 48K it is attached to the bus only when enabled
+    #[getter]
+    fn ay(&self) -> bool {
+        self.emulator.ay_enabled()
+    }
+
+    #[setter]
+    fn set_ay(&mut self, value: bool) {
+        self.emulator.set_ay_enabled(value);
+    }
+
+    /// Returns `(width, height, rgba_bytes)` tuple with the current frame,
+    /// including border
+    fn screenshot<'py>(&self, py: Python<'py>) -> (usize, usize, Bound<'py, PyBytes>) {
+        let border = self.emulator.border_buffer();
+        let canvas = self.emulator.screen_buffer();
+
+        let mut frame = vec![0u8; RGBA_FRAME_SIZE];
+        compose_rgba_frame(border, canvas, &mut frame);
+
+        (SCREEN_WIDTH, SCREEN_HEIGHT, PyBytes::new(py, &frame))
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "rustzx")]
+fn rustzx_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyEmulator>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names() {
+        assert_eq!(parse_key("Enter").unwrap(), ZXKey::Enter);
+        assert_eq!(parse_key("symshift").unwrap(), ZXKey::SymShift);
+        assert_eq!(parse_key("a").unwrap(), ZXKey::A);
+        assert_eq!(parse_key("5").unwrap(), ZXKey::N5);
+        assert!(parse_key("F1").is_err());
+        assert!(parse_key("55").is_err());
+    }
+
+    #[test]
+    fn file_kinds() {
+        assert_eq!(file_kind(Path::new("game.TAP")), "tap");
+        assert_eq!(file_kind(Path::new("dir/game.z80.gz")), "z80");
+        assert_eq!(file_kind(Path::new("game.gz")), "");
+        assert_eq!(file_kind(Path::new("game")), "");
+    }
+
+    #[test]
+    fn invalid_emulator_arguments() {
+        assert!(PyEmulator::new("16k", true, None, "precise").is_err());
+        assert!(PyEmulator::new("48k", true, None, "fast").is_err());
+        assert!(PyEmulator::new("128K", true, None, "Solid").is_ok());
+    }
+}
@@ -0,0 +1,58 @@
+"""Smoke tests of the bindings, run with `pytest` after `maturin develop`"""
+import pytest
+
+import rustzx
+
+SCREEN_WIDTH = 320
+SCREEN_HEIGHT = 240
+SCR_SIZE = 6912
+
+
+def test_invalid_arguments():
+    with pytest.raises(ValueError):
+        rustzx.Emulator(machine="16k")
+    with pytest.raises(ValueError):
+        rustzx.Emulator(border_mode="fast")
+    with pytest.raises(ValueError):
+        rustzx.Emulator().send_key("F1")
+
+
+def test_unsupported_file(tmp_path):
+    path = tmp_path / "game.bin"
+    path.write_bytes(b"\x00")
+    with pytest.raises(OSError):
+        rustzx.Emulator().load(str(path))
+
+
+def test_load_run_read_screenshot(tmp_path):
+    # Bitmap is filled with a stripe pattern, attributes are white ink on
+    # black paper
+    scr = bytes([0x55]) * 6144 + bytes([0x07]) * 768
+    path = tmp_path / "stripes.scr"
+    path.write_bytes(scr)
+
+    emulator = rustzx.Emulator(machine="48k")
+    # Wait for ROM to initialize, it clears the screen
+    emulator.run_frame(100)
+    emulator.load(str(path))
+    emulator.run_frame()
+    assert emulator.read_memory(0x4000, SCR_SIZE) == scr
+
+    width, height, rgba = emulator.screenshot()
+    assert (width, height) == (SCREEN_WIDTH, SCREEN_HEIGHT)
+    assert len(rgba) == width * height * 4
+    # Canvas starts at (32, 24), first pixel of the pattern is paper
+    def pixel(x, y):
+        offset = (y * width + x) * 4
+        return rgba[offset : offset + 4]
+
+    assert pixel(32, 24) != pixel(33, 24)
+    assert pixel(32, 24) == pixel(34, 24)
+
+
+def test_border_modes_have_the_same_frame_size():
+    for border_mode in ["precise", "solid", "canvas"]:
+        emulator = rustzx.Emulator(border_mode=border_mode)
+        emulator.run_frame()
+        width, height, rgba = emulator.screenshot()
+        assert len(rgba) == width * height * 4
@@ -1,8 +1,9 @@
+use crate::reference::{ImageDiff, IndexedImage, Tolerance};
 use expect_test::Expect;
 use rustzx_core::{
     host::{
-        BufferCursor, DebugInterface, FrameBuffer, FrameBufferSource, Host, HostContext,
-        IoExtender, RomFormat, RomSet, Snapshot, Tape,
+        BufferCursor, DebugInterface, FrameBuffer, FrameBufferSource, FrameBuffers, Host,
+        HostContext, IoExtender, RomFormat, RomSet, Snapshot, Tape,
     },
     poke,
     zx::{
@@ -11,10 +12,10 @@ use rustzx_core::{
         sound::ay::ZXAYMode,
         video::colors::{ZXBrightness, ZXColor},
     },
-    EmulationMode, EmulationStopReason, Emulator, RustzxSettings,
+    BorderMode, EmulationMode, EmulationStopReason, Emulator, RamPattern, RustzxSettings,
 };
 use rustzx_utils::{
-    io::{DynamicAsset, GzipAsset},
+    io::{BufferDiskImage, DynamicAsset, GzipAsset},
     palette::rgba::ORIGINAL as DEFAULT_PALETTE,
     stopwatch::InstantStopwatch,
 };
@@ -50,7 +51,7 @@ impl FrameBuffer for FrameContent {
         if context.use_gigascreen {
             unimplemented!("Gigascreen tests are not yet implemented");
         } else {
-            let buffer_size = (width * height) / 2;
+            let buffer_size = (width * height).div_ceil(2);
             Self {
                 buffer: vec![0u8; buffer_size],
                 width,
@@ -74,6 +75,30 @@ impl FrameBuffer for FrameContent {
 }
 
 impl FrameContent {
+    fn from_indexed_image(image: &IndexedImage) -> Self {
+        let buffer = image
+            .pixels
+            .chunks(2)
+            .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or_default())
+            .collect();
+        Self {
+            buffer,
+            width: image.width,
+            height: image.height,
+        }
+    }
+
+    fn to_indexed_image(&self) -> IndexedImage {
+        let pixels = (0..self.width * self.height)
+            .map(|index| (self.buffer[index / 2] >> (4 - (index % 2) * 4)) & 0x0F)
+            .collect();
+        IndexedImage {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
     pub fn to_png(&self) -> Vec<u8> {
         let mut out = vec![];
 
@@ -197,10 +222,12 @@ impl Host for TesterHost {
     type FrameBuffer = FrameContent;
     type IoExtender = DebugPort;
     type TapeAsset = DynamicAsset;
+    type DiskImage = BufferDiskImage;
 }
 
 pub struct RustZXTester {
     emulator: Emulator<TesterHost>,
+    frame_buffers: Option<FrameBuffers<FrameContent>>,
     sound_buffer: Option<Vec<i16>>,
     test_name: String,
     sync_timeout: Duration,
@@ -216,6 +243,8 @@ pub mod presets {
             tape_fastload_enabled: true,
             kempston_enabled: false,
             mouse_enabled: false,
+            ram_pattern: RamPattern::Zeros,
+            border_mode: BorderMode::Precise,
             ay_mode: ZXAYMode::ABC,
             ay_enabled: false,
             beeper_enabled: false,
@@ -234,6 +263,16 @@ pub mod presets {
         }
     }
 
+    /// +3 has no embedded ROM, therefore test programs should not depend on it
+    pub fn settings_plus3_nosound() -> RustzxSettings {
+        RustzxSettings {
+            machine: ZXMachine::SinclairPlus3,
+            load_default_rom: false,
+            autoload_enabled: false,
+            ..settings_48k_nosound()
+        }
+    }
+
     pub fn settings_48k() -> RustzxSettings {
         RustzxSettings {
             sound_enabled: true,
@@ -258,6 +297,7 @@ impl RustZXTester {
 
         Self {
             emulator,
+            frame_buffers: None,
             test_name: test_name.to_owned(),
             sound_buffer: None,
             sync_timeout: DEFAULT_SYNC_TIMEOUT,
@@ -300,6 +340,13 @@ impl RustZXTester {
             .expect("Failed to load test TAP");
     }
 
+    pub fn load_tzx(&mut self, name: impl AsRef<Path>) {
+        let asset = self.load_asset(name);
+        self.emulator
+            .load_tape(Tape::Tzx(asset))
+            .expect("Failed to load test TZX");
+    }
+
     pub fn load_sna(&mut self, name: impl AsRef<Path>) {
         let asset = self.load_asset(name);
         self.emulator
@@ -307,6 +354,27 @@ impl RustZXTester {
             .expect("Failed to load test SNA")
     }
 
+    pub fn load_z80(&mut self, name: impl AsRef<Path>) {
+        let asset = self.load_asset(name);
+        self.emulator
+            .load_snapshot(Snapshot::Z80(asset))
+            .expect("Failed to load test Z80")
+    }
+
+    pub fn load_szx(&mut self, name: impl AsRef<Path>) {
+        let asset = self.load_asset(name);
+        self.emulator
+            .load_snapshot(Snapshot::Szx(asset))
+            .expect("Failed to load test SZX")
+    }
+
+    pub fn load_slt(&mut self, name: impl AsRef<Path>) {
+        let asset = self.load_asset(name);
+        self.emulator
+            .load_snapshot(Snapshot::Slt(asset))
+            .expect("Failed to load test SLT")
+    }
+
     pub fn load_single_page_rom(&mut self, name: impl AsRef<Path>) {
         let rom_data = self.load_asset_data(name);
         struct DiagRomSet {
@@ -332,14 +400,27 @@ impl RustZXTester {
         self.emulator.load_rom(rom_set).unwrap();
     }
 
-    fn get_screen(&self) -> Vec<u8> {
+    pub fn get_screen(&self) -> Vec<u8> {
         self.emulator.screen_buffer().to_png()
     }
 
-    fn get_border(&self) -> Vec<u8> {
+    pub fn get_border(&self) -> Vec<u8> {
         self.emulator.border_buffer().to_png()
     }
 
+    /// Takes the last completed frame via frame buffers swap and returns its
+    /// screen and border, `None` if no frame was completed since the last call
+    pub fn take_frame(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let emulator = &mut self.emulator;
+        let buffers = self
+            .frame_buffers
+            .get_or_insert_with(|| emulator.new_frame_buffers());
+        if !emulator.swap_frame_buffers(buffers) {
+            return None;
+        }
+        Some((buffers.screen.to_png(), buffers.border.to_png()))
+    }
+
     fn update_sound(&mut self) {
         if let Some(sound_buffer) = &mut self.sound_buffer {
             while let Some(sample) = self.emulator.next_audio_sample() {
@@ -437,6 +518,95 @@ impl RustZXTester {
         self.compare_buffer_with_file(self.get_border(), make_border_filename(name), expect);
     }
 
+    /// Compares the screen with reference PNG or SCR image from test assets
+    pub fn compare_screen_with_reference(
+        &mut self,
+        reference: impl AsRef<Path>,
+        tolerance: &Tolerance,
+    ) -> ImageDiff {
+        let reference = self.load_reference_image(reference);
+        self.emulator
+            .screen_buffer()
+            .to_indexed_image()
+            .diff(&reference, tolerance)
+    }
+
+    /// Compares the border with reference PNG image from test assets
+    pub fn compare_border_with_reference(
+        &mut self,
+        reference: impl AsRef<Path>,
+        tolerance: &Tolerance,
+    ) -> ImageDiff {
+        let reference = self.load_reference_image(reference);
+        self.emulator
+            .border_buffer()
+            .to_indexed_image()
+            .diff(&reference, tolerance)
+    }
+
+    pub fn expect_screen_reference(
+        &mut self,
+        name: impl AsRef<Path>,
+        reference: impl AsRef<Path>,
+        tolerance: Tolerance,
+    ) {
+        let diff = self.compare_screen_with_reference(reference, &tolerance);
+        self.check_reference_diff(diff, make_screen_filename(name), &tolerance);
+    }
+
+    pub fn expect_border_reference(
+        &mut self,
+        name: impl AsRef<Path>,
+        reference: impl AsRef<Path>,
+        tolerance: Tolerance,
+    ) {
+        let diff = self.compare_border_with_reference(reference, &tolerance);
+        self.check_reference_diff(diff, make_border_filename(name), &tolerance);
+    }
+
+    fn load_reference_image(&mut self, name: impl AsRef<Path>) -> IndexedImage {
+        let name = name.as_ref();
+        // Extension of the compressed asset is taken from its stem
+        let path = match name.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Path::new(name.file_stem().unwrap_or_default()),
+            _ => name,
+        };
+        let is_scr = path
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("scr"))
+            .unwrap_or_default();
+        let data = self.load_asset_data(name);
+        if is_scr {
+            IndexedImage::from_scr(&data)
+        } else {
+            IndexedImage::from_png(&data)
+        }
+        .expect("Failed to load reference image")
+    }
+
+    fn check_reference_diff(&self, diff: ImageDiff, filename: PathBuf, tolerance: &Tolerance) {
+        if diff.is_within(tolerance) {
+            return;
+        }
+        if TestEnv::save_test_data_enabled() {
+            let diff_image = FrameContent::from_indexed_image(&diff.image).to_png();
+            self.save_actual_data(&diff_image, &filename.with_extension("diff.png"));
+        }
+        if diff.size_mismatch {
+            panic!(
+                "Frame {} size does not match reference image",
+                filename.display()
+            );
+        }
+        panic!(
+            "Frame {} differs from reference image in {} pixels within {:?}",
+            filename.display(),
+            diff.pixels,
+            diff.bounds
+                .expect("Bounds should be set for different pixels"),
+        );
+    }
+
     pub fn expect_text(&self, name: impl AsRef<Path>, text: String, expect: Expect) {
         self.compare_buffer_with_file(text.into_bytes(), make_text_filename(name), expect);
     }
@@ -561,6 +731,16 @@ impl RustZXTester {
     pub fn peek(&mut self, addr: u16) -> u8 {
         self.emulator.peek(addr)
     }
+
+    pub fn attach_ide_disk(&mut self, disk: BufferDiskImage) {
+        self.emulator.attach_ide_disk(disk);
+    }
+
+    pub fn ide_disk(&mut self) -> &mut BufferDiskImage {
+        self.emulator
+            .ide_disk()
+            .expect("IDE disk is not attached for the current test")
+    }
 }
 
 struct TestEnv;
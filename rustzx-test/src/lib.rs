@@ -1 +1,2 @@
 pub mod framework;
+pub mod reference;
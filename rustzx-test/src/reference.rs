@@ -0,0 +1,257 @@
+//! Golden-image comparison support: reference screenshots (PNG or SCR) are
+//! compared with the emulated frame pixel by pixel, while tolerance allows to
+//! mask out areas which are not stable (e.g. clock or flashing cursor)
+use anyhow::{anyhow, Context};
+use rustzx_utils::palette::rgba::ORIGINAL as DEFAULT_PALETTE;
+use std::io::Cursor;
+
+const SCR_BITMAP_SIZE: usize = 6144;
+const SCR_SIZE: usize = SCR_BITMAP_SIZE + 768;
+const SCR_WIDTH: usize = 256;
+const SCR_HEIGHT: usize = 192;
+/// Palette index which is used to highlight different pixels in diff image
+const DIFF_HIGHLIGHT: u8 = 10;
+const BLACK: u8 = 0;
+const BRIGHT_BLACK: u8 = 8;
+
+/// Image in ZX Spectrum palette, each pixel is stored as `color + bright * 8`
+#[derive(Clone, PartialEq, Eq)]
+pub struct IndexedImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl IndexedImage {
+    /// Decodes PNG image, true color images are mapped to the closest color
+    /// of the default palette, so screenshots taken by other emulators can be
+    /// used as reference. Palette images are expanded to RGB and 16-bit
+    /// samples are stripped to 8 bits, grayscale images are not supported
+    pub fn from_png(data: &[u8]) -> anyhow::Result<Self> {
+        let mut decoder = png::Decoder::new(Cursor::new(data));
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let (info, mut reader) = decoder.read_info().context("Failed to decode PNG")?;
+        let channels = match info.color_type {
+            png::ColorType::RGB => 3,
+            png::ColorType::RGBA => 4,
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => {
+                return Err(anyhow!(
+                    "Grayscale PNG can't be mapped to ZX Spectrum palette, use RGB image"
+                ))
+            }
+            color_type => return Err(anyhow!("Unsupported PNG color type {:?}", color_type)),
+        };
+        let mut buffer = vec![0u8; info.buffer_size()];
+        reader
+            .next_frame(&mut buffer)
+            .context("Failed to decode PNG")?;
+
+        let pixels = buffer
+            .chunks_exact(channels)
+            .map(|rgb| closest_palette_color(&rgb[0..3]))
+            .collect();
+        Ok(Self {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        })
+    }
+
+    /// Decodes SCR screen dump, flashing attributes are rendered in the
+    /// initial (not inverted) phase
+    pub fn from_scr(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() != SCR_SIZE {
+            return Err(anyhow!(
+                "Invalid SCR size {}, expected {}",
+                data.len(),
+                SCR_SIZE
+            ));
+        }
+        let mut pixels = Vec::with_capacity(SCR_WIDTH * SCR_HEIGHT);
+        for y in 0..SCR_HEIGHT {
+            let line_addr = ((y & 0xC0) << 5) | ((y & 0x07) << 8) | ((y & 0x38) << 2);
+            for x in 0..SCR_WIDTH {
+                let bitmap = data[line_addr | (x >> 3)];
+                let attr = data[SCR_BITMAP_SIZE + (y / 8) * 32 + x / 8];
+                let color = if bitmap & (0x80 >> (x % 8)) != 0 {
+                    attr & 0x07
+                } else {
+                    (attr >> 3) & 0x07
+                };
+                pixels.push(color + ((attr >> 6) & 0x01) * 8);
+            }
+        }
+        Ok(Self {
+            width: SCR_WIDTH,
+            height: SCR_HEIGHT,
+            pixels,
+        })
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> u8 {
+        self.pixels[x + y * self.width]
+    }
+
+    /// Compares image with the reference, pixels covered by tolerance masks
+    /// are skipped
+    pub fn diff(&self, reference: &IndexedImage, tolerance: &Tolerance) -> ImageDiff {
+        let mut diff = ImageDiff {
+            size_mismatch: (self.width, self.height) != (reference.width, reference.height),
+            pixels: 0,
+            bounds: None,
+            image: IndexedImage {
+                width: self.width,
+                height: self.height,
+                pixels: vec![0; self.pixels.len()],
+            },
+        };
+        if diff.size_mismatch {
+            return diff;
+        }
+
+        let color_mask = if tolerance.ignore_brightness {
+            0x07
+        } else {
+            0x0F
+        };
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let actual = self.pixel(x, y);
+                let index = x + y * self.width;
+                // Matching pixels are dimmed in the diff image
+                diff.image.pixels[index] = actual & 0x07;
+                let expected = normalize_black(reference.pixel(x, y));
+                if tolerance.is_masked(x, y)
+                    || (normalize_black(actual) & color_mask) == (expected & color_mask)
+                {
+                    continue;
+                }
+                diff.image.pixels[index] = DIFF_HIGHLIGHT;
+                diff.pixels += 1;
+                diff.bounds = Some(match diff.bounds {
+                    Some(bounds) => bounds.extend(x, y),
+                    None => Rect::new(x, y, 1, 1),
+                });
+            }
+        }
+        diff
+    }
+}
+
+/// Bright black has the same color as black, so SCR attributes and true color
+/// images may use either of them for the same pixels
+fn normalize_black(color: u8) -> u8 {
+    if color == BRIGHT_BLACK {
+        BLACK
+    } else {
+        color
+    }
+}
+
+fn closest_palette_color(rgb: &[u8]) -> u8 {
+    let distance = |color: &[u8; 4]| -> u32 {
+        color[0..3]
+            .iter()
+            .zip(rgb)
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+            .sum()
+    };
+    DEFAULT_PALETTE
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, color)| distance(color))
+        .map(|(index, _)| normalize_black(index as u8))
+        .expect("Palette is not empty")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        self.contains(other.x, other.y)
+            && self.contains(other.x + other.width - 1, other.y + other.height - 1)
+    }
+
+    fn extend(self, x: usize, y: usize) -> Self {
+        let left = self.x.min(x);
+        let top = self.y.min(y);
+        let right = (self.x + self.width).max(x + 1);
+        let bottom = (self.y + self.height).max(y + 1);
+        Self::new(left, top, right - left, bottom - top)
+    }
+}
+
+/// Allowed differences between the frame and the reference image
+#[derive(Clone, Default)]
+pub struct Tolerance {
+    masks: Vec<Rect>,
+    max_pixels: usize,
+    ignore_brightness: bool,
+}
+
+impl Tolerance {
+    /// Requires pixel-perfect match
+    pub fn exact() -> Self {
+        Self::default()
+    }
+
+    /// Excludes rectangle from the comparison
+    pub fn mask(mut self, rect: Rect) -> Self {
+        self.masks.push(rect);
+        self
+    }
+
+    /// Allows up to `count` different pixels outside of masks
+    pub fn max_pixels(mut self, count: usize) -> Self {
+        self.max_pixels = count;
+        self
+    }
+
+    /// Treats bright and normal variants of the color as equal (e.g. when
+    /// reference screenshot palette has the same black for both)
+    pub fn ignore_brightness(mut self) -> Self {
+        self.ignore_brightness = true;
+        self
+    }
+
+    fn is_masked(&self, x: usize, y: usize) -> bool {
+        self.masks.iter().any(|mask| mask.contains(x, y))
+    }
+}
+
+/// Result of the comparison with reference image
+pub struct ImageDiff {
+    /// Image sizes are different, pixels were not compared
+    pub size_mismatch: bool,
+    /// Count of different pixels outside of masks
+    pub pixels: usize,
+    /// Bounding box of different pixels
+    pub bounds: Option<Rect>,
+    /// Actual frame with dimmed matching pixels and highlighted different ones
+    pub image: IndexedImage,
+}
+
+impl ImageDiff {
+    pub fn is_within(&self, tolerance: &Tolerance) -> bool {
+        !self.size_mismatch && self.pixels <= tolerance.max_pixels
+    }
+}
@@ -101,6 +101,10 @@ cat "${BUILD_DIR}/loader_screen.tap" \
     "${BUILD_DIR}/simple_tape_loaderless.tap" \
     > "${BUILD_DIR}/simple_tape.tap"
 gzip --stdout "${BUILD_DIR}/simple_tape.tap" > "${OUT_DIR}/simple_tape.tap.gz"
+python3 "${SRC_DIR}/make_tzx.py" \
+    "${BUILD_DIR}/simple_tape.tap" \
+    "${BUILD_DIR}/simple_tape.tzx" \
+    && gzip --stdout "${BUILD_DIR}/simple_tape.tzx" > "${OUT_DIR}/simple_tape.tzx.gz"
 log_success "Done"
 log_unindent
 
@@ -138,3 +142,46 @@ build_sna sound 128k
 build_sna mouse 48k
 build_sna kempston_joy 48k
 build_sna keyboard 48k
+
+function convert_sna {
+    local APP_NAME="$1"
+    local EXT_PREFIX="$2"
+    local FORMAT="$3"
+
+    log_info "Converting ${APP_NAME} SNA (${EXT_PREFIX}) to ${FORMAT}..."
+    log_indent
+    python3 "${SRC_DIR}/convert_sna.py" \
+        "${BUILD_DIR}/${APP_NAME}.${EXT_PREFIX}.sna" \
+        "${BUILD_DIR}/${APP_NAME}.${EXT_PREFIX}.${FORMAT}" \
+        "${@:4}" \
+        && gzip \
+            --stdout "${BUILD_DIR}/${APP_NAME}.${EXT_PREFIX}.${FORMAT}" \
+            > "${OUT_DIR}/${APP_NAME}.${EXT_PREFIX}.${FORMAT}.gz"
+    log_success "Done"
+    log_unindent
+}
+
+convert_sna sound 48k z80
+convert_sna sound 128k z80
+convert_sna sound 48k szx --uncompressed
+convert_sna sound 128k szx
+
+log_info "Building slt_screen..."
+log_indent
+python3 "${SRC_DIR}/make_slt.py" \
+    "${SRC_DIR}/rustzx.scr" \
+    "${BUILD_DIR}/slt_screen.48k.slt" \
+    && gzip \
+        --stdout "${BUILD_DIR}/slt_screen.48k.slt" \
+        > "${OUT_DIR}/slt_screen.48k.slt.gz"
+log_success "Done"
+log_unindent
+
+log_info "Building z80 programs..."
+log_indent
+python3 "${SRC_DIR}/make_z80_programs.py" "${BUILD_DIR}"
+for PROGRAM in ide.48k.z80 paging.plus3.z80 in_timing.{48k,128k,plus3}.z80 ay_registers.128k.z80 ay_fade.128k.z80; do
+    gzip --stdout "${BUILD_DIR}/${PROGRAM}" > "${OUT_DIR}/${PROGRAM}.gz"
+done
+log_success "Done"
+log_unindent
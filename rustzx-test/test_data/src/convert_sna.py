@@ -0,0 +1,147 @@
+#!/usr/bin/env python3
+"""Converts SNA snapshot to Z80 (v1 for 48K, v3 for 128K) or SZX snapshot.
+
+Used to produce test data for the snapshot formats, which can't be directly
+generated by z88dk.
+
+Usage: convert_sna.py <input.sna> <output.z80|output.szx> [--uncompressed]
+"""
+import struct
+import sys
+import zlib
+
+PAGE_SIZE = 16384
+SNA_HEADER_SIZE = 27
+SNA_48K_SIZE = SNA_HEADER_SIZE + 3 * PAGE_SIZE
+
+
+class State:
+    def __init__(self, sna):
+        h = sna[:SNA_HEADER_SIZE]
+        self.i = h[0]
+        self.hl_alt, self.de_alt, self.bc_alt, self.af_alt = struct.unpack_from("<HHHH", h, 1)
+        self.hl, self.de, self.bc, self.iy, self.ix = struct.unpack_from("<HHHHH", h, 9)
+        self.iff = 1 if h[19] & 0x04 else 0
+        self.r = h[20]
+        self.af, self.sp = struct.unpack_from("<HH", h, 21)
+        self.im = h[25] & 0x03
+        self.border = h[26] & 0x07
+        self.is_128k = len(sna) > SNA_48K_SIZE
+        self.port_7ffd = 0
+        # bank index -> 16K data
+        self.banks = {}
+
+        if self.is_128k:
+            self.pc, self.port_7ffd = struct.unpack_from("<HB", sna, SNA_48K_SIZE)
+            paged = self.port_7ffd & 0x07
+            head = [5, 2, paged]
+            for n, bank in enumerate(head):
+                offset = SNA_HEADER_SIZE + n * PAGE_SIZE
+                self.banks[bank] = sna[offset:offset + PAGE_SIZE]
+            offset = SNA_48K_SIZE + 4
+            for bank in [0, 1, 3, 4, 6, 7]:
+                if bank == paged:
+                    continue
+                self.banks[bank] = sna[offset:offset + PAGE_SIZE]
+                offset += PAGE_SIZE
+        else:
+            ram = bytearray(sna[SNA_HEADER_SIZE:SNA_48K_SIZE])
+            # 48K SNA stores PC on the stack
+            sp = self.sp - 0x4000
+            self.pc = ram[sp] | (ram[sp + 1] << 8)
+            self.sp = (self.sp + 2) & 0xFFFF
+            # 48K banks are numbered by their address: 0x4000, 0x8000, 0xC000
+            for n in range(3):
+                self.banks[n] = bytes(ram[n * PAGE_SIZE:(n + 1) * PAGE_SIZE])
+
+
+def z80_compress(data):
+    out = bytearray()
+    i = 0
+    while i < len(data):
+        b = data[i]
+        run = 1
+        while i + run < len(data) and data[i + run] == b and run < 255:
+            run += 1
+        if run >= 5 or (b == 0xED and run >= 2):
+            out += bytes([0xED, 0xED, run, b])
+            i += run
+        else:
+            out.append(b)
+            i += 1
+            # Byte after single ED is never compressed
+            if b == 0xED and i < len(data):
+                out.append(data[i])
+                i += 1
+    return bytes(out)
+
+
+def to_z80(s, compress):
+    a, f = s.af >> 8, s.af & 0xFF
+    a_alt, f_alt = s.af_alt >> 8, s.af_alt & 0xFF
+    flags = ((s.r >> 7) & 0x01) | (s.border << 1) | (0x20 if compress and not s.is_128k else 0)
+    pc_v1 = 0 if s.is_128k else s.pc
+    header = struct.pack(
+        "<BBHHHHBBBHHHHBBHHBBB",
+        a, f, s.bc, s.hl, pc_v1, s.sp, s.i, s.r & 0x7F, flags, s.de,
+        s.bc_alt, s.de_alt, s.hl_alt, a_alt, f_alt, s.iy, s.ix, s.iff, s.iff, s.im,
+    )
+    if not s.is_128k:
+        ram = b"".join(s.banks[n] for n in range(3))
+        if compress:
+            return header + z80_compress(ram) + bytes([0x00, 0xED, 0xED, 0x00])
+        return header + ram
+
+    # v3 additional header, 128K hardware mode
+    extra = bytearray(54)
+    struct.pack_into("<HBB", extra, 0, s.pc, 4, s.port_7ffd)
+    out = header + struct.pack("<H", len(extra)) + bytes(extra)
+    for bank in range(8):
+        data = s.banks[bank]
+        if compress:
+            packed = z80_compress(data)
+            out += struct.pack("<HB", len(packed), bank + 3) + packed
+        else:
+            out += struct.pack("<HB", 0xFFFF, bank + 3) + data
+    return out
+
+
+def szx_chunk(chunk_id, data):
+    return chunk_id + struct.pack("<I", len(data)) + data
+
+
+def to_szx(s, compress):
+    machine_id = 2 if s.is_128k else 1
+    out = b"ZXST" + bytes([1, 4, machine_id, 0])
+    z80r = struct.pack(
+        "<HHHHHHHHHHHHBBBBBIBBH",
+        s.af, s.bc, s.de, s.hl, s.af_alt, s.bc_alt, s.de_alt, s.hl_alt,
+        s.ix, s.iy, s.sp, s.pc, s.i, s.r, s.iff, s.iff, s.im, 0, 0, 0, 0,
+    )
+    out += szx_chunk(b"Z80R", z80r)
+    out += szx_chunk(b"SPCR", bytes([s.border, s.port_7ffd, 0, 0, 0, 0, 0, 0]))
+    # 48K banks 0x4000, 0x8000, 0xC000 correspond to SZX pages 5, 2, 0
+    pages = range(8) if s.is_128k else [5, 2, 0]
+    for n, page in enumerate(pages):
+        data = s.banks[page if s.is_128k else n]
+        if compress:
+            out += szx_chunk(b"RAMP", struct.pack("<HB", 1, page) + zlib.compress(data))
+        else:
+            out += szx_chunk(b"RAMP", struct.pack("<HB", 0, page) + data)
+    if s.is_128k:
+        out += szx_chunk(b"AY\0\0", bytes(18))
+    return out
+
+
+def main():
+    src, dst = sys.argv[1], sys.argv[2]
+    compress = "--uncompressed" not in sys.argv
+    with open(src, "rb") as f:
+        state = State(f.read())
+    convert = to_szx if dst.lower().endswith(".szx") else to_z80
+    with open(dst, "wb") as f:
+        f.write(convert(state, compress))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,69 @@
+#!/usr/bin/env python3
+"""Generates SLT snapshot with minimal 48K program, which loads screen via
+SLT level data trap (ED FB).
+
+Usage: make_slt.py <screen.scr> <output.slt>
+"""
+import struct
+import sys
+
+from convert_sna import z80_compress
+
+CODE_ADDR = 0x8000
+STACK_ADDR = 0xFF00
+SCREEN_ADDR = 0x4000
+SCREEN_LEVEL = 1
+FILLER_LEVEL = 2
+
+SLT_DATA_TYPE_END = 0
+SLT_DATA_TYPE_LEVEL = 1
+
+CODE = bytes([
+    0xF3,                                            # DI
+    0x3E, SCREEN_LEVEL,                              # LD A, SCREEN_LEVEL
+    0x21, SCREEN_ADDR & 0xFF, SCREEN_ADDR >> 8,      # LD HL, SCREEN_ADDR
+    0xED, 0xFB,                                      # SLT trap
+    0x18, 0xFE,                                      # JR $
+])
+
+
+def main():
+    with open(sys.argv[1], "rb") as f:
+        screen = f.read()
+
+    # v1 part of the header; PC = 0 means v2/v3 snapshot; border is blue
+    header = struct.pack(
+        "<BBHHHHBBBHHHHBBHHBBB",
+        0, 0, 0, 0, 0, STACK_ADDR, 0x3F, 0, 1 << 1, 0,
+        0, 0, 0, 0, 0, 0x5C3A, 0, 0, 0, 1,
+    )
+    # v3 additional header, 48K hardware mode
+    extra = bytearray(54)
+    struct.pack_into("<HB", extra, 0, CODE_ADDR, 0)
+    out = header + struct.pack("<H", len(extra)) + bytes(extra)
+
+    ram = bytearray(3 * 16384)
+    offset = CODE_ADDR - 0x4000
+    ram[offset:offset + len(CODE)] = CODE
+    # 48K pages for 0x4000, 0x8000 and 0xC000
+    for n, page in enumerate([8, 4, 5]):
+        packed = z80_compress(bytes(ram[n * 16384:(n + 1) * 16384]))
+        out += struct.pack("<HB", len(packed), page) + packed
+
+    levels = [
+        (SLT_DATA_TYPE_LEVEL, FILLER_LEVEL, z80_compress(b"\xFF" * len(screen))),
+        (SLT_DATA_TYPE_LEVEL, SCREEN_LEVEL, z80_compress(screen)),
+    ]
+    out += b"\0\0\0SLT"
+    for data_type, number, data in levels:
+        out += struct.pack("<HHI", data_type, number, len(data))
+    out += struct.pack("<HHI", SLT_DATA_TYPE_END, 0, 0)
+    for _, _, data in levels:
+        out += data
+
+    with open(sys.argv[2], "wb") as f:
+        f.write(out)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,69 @@
+#!/usr/bin/env python3
+"""Converts TAP file to TZX. Header blocks are stored as standard speed
+blocks, data blocks as turbo speed blocks with standard ROM timings, so
+converted tape loads exactly as the original one. Information blocks are
+added to check that they are skipped during playback.
+
+Usage: make_tzx.py <input.tap> <output.tzx>
+"""
+import struct
+import sys
+
+PAUSE_MS = 1000
+
+BLOCK_STANDARD_SPEED = 0x10
+BLOCK_TURBO_SPEED = 0x11
+BLOCK_GROUP_START = 0x21
+BLOCK_GROUP_END = 0x22
+BLOCK_TEXT = 0x30
+BLOCK_ARCHIVE_INFO = 0x32
+
+ARCHIVE_INFO_TITLE = 0x00
+
+
+def tap_blocks(tap):
+    pos = 0
+    while pos < len(tap):
+        (size,) = struct.unpack_from("<H", tap, pos)
+        yield tap[pos + 2:pos + 2 + size]
+        pos += 2 + size
+
+
+def text_block(block_id, text):
+    data = text.encode("ascii")
+    return struct.pack("<BB", block_id, len(data)) + data
+
+
+def archive_info_block(title):
+    data = title.encode("ascii")
+    info = struct.pack("<BBB", 1, ARCHIVE_INFO_TITLE, len(data)) + data
+    return struct.pack("<BH", BLOCK_ARCHIVE_INFO, len(info)) + info
+
+
+def data_block(data):
+    if data[0] == 0x00:
+        return struct.pack("<BHH", BLOCK_STANDARD_SPEED, PAUSE_MS, len(data)) + data
+    # pilot, sync1, sync2, bit 0, bit 1, pilot pulses, last byte bits, pause
+    timings = struct.pack("<HHHHHHBH", 2168, 667, 735, 855, 1710, 3223, 8, PAUSE_MS)
+    size = struct.pack("<I", len(data))[0:3]
+    return bytes([BLOCK_TURBO_SPEED]) + timings + size + data
+
+
+def main():
+    with open(sys.argv[1], "rb") as f:
+        tap = f.read()
+
+    out = b"ZXTape!\x1a" + bytes([1, 20])
+    out += text_block(BLOCK_TEXT, "Converted from TAP")
+    out += archive_info_block("simple_tape")
+    out += text_block(BLOCK_GROUP_START, "simple_tape")
+    for block in tap_blocks(tap):
+        out += data_block(block)
+    out += bytes([BLOCK_GROUP_END])
+
+    with open(sys.argv[2], "wb") as f:
+        f.write(out)
+
+
+if __name__ == "__main__":
+    main()
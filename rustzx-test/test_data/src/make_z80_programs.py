@@ -0,0 +1,414 @@
+#!/usr/bin/env python3
+"""Generates Z80 (v3) snapshots with small hand-assembled test programs,
+which are hard to build with z88dk (e.g. for machines without embedded ROM).
+
+Usage: make_z80_programs.py <output_dir>
+"""
+import os
+import struct
+import sys
+
+from convert_sna import z80_compress
+
+PAGE_SIZE = 16384
+STACK_ADDR = 0xFF00
+
+HW_MODE_48K = 0
+HW_MODE_128K = 4
+HW_MODE_PLUS3 = 7
+
+
+class Asm:
+    """Minimal assembler helper: raw bytes with 16-bit absolute and 8-bit
+    relative label references"""
+
+    def __init__(self, origin):
+        self.origin = origin
+        self.items = []
+        self.labels = {}
+
+    def label(self, name):
+        self.labels[name] = self.origin + self.size()
+
+    def db(self, *data):
+        self.items.append(bytes(data))
+
+    def abs16(self, *prefix, label):
+        self.items.append((bytes(prefix), "abs", label))
+
+    def rel8(self, *prefix, label):
+        self.items.append((bytes(prefix), "rel", label))
+
+    def size(self):
+        size = 0
+        for item in self.items:
+            if isinstance(item, bytes):
+                size += len(item)
+            else:
+                size += len(item[0]) + (2 if item[1] == "abs" else 1)
+        return size
+
+    def assemble(self):
+        out = bytearray()
+        for item in self.items:
+            if isinstance(item, bytes):
+                out += item
+                continue
+            prefix, kind, label = item
+            out += prefix
+            target = self.labels[label]
+            if kind == "abs":
+                out += struct.pack("<H", target)
+            else:
+                offset = target - (self.origin + len(out) + 1)
+                out += struct.pack("<b", offset)
+        return bytes(out)
+
+
+def ld_bc(asm, value):
+    asm.db(0x01, value & 0xFF, value >> 8)
+
+
+def make_z80(hw_mode, pc, banks, port_7ffd=0, port_1ffd=0):
+    """Builds v3 snapshot; `banks` maps z80 page number to 16K data"""
+    header = struct.pack(
+        "<BBHHHHBBBHHHHBBHHBBB",
+        0, 0, 0, 0, 0, STACK_ADDR, 0x3F, 0, 0, 0,
+        0, 0, 0, 0, 0, 0x5C3A, 0, 0, 0, 1,
+    )
+    extra = bytearray(55)
+    struct.pack_into("<HBB", extra, 0, pc, hw_mode, port_7ffd)
+    extra[54] = port_1ffd
+    out = header + struct.pack("<H", len(extra)) + bytes(extra)
+    for page, data in sorted(banks.items()):
+        packed = z80_compress(data)
+        out += struct.pack("<HB", len(packed), page) + packed
+    return out
+
+
+def place(asm, addr):
+    """Returns 16K page with program placed at the given address"""
+    page = bytearray(PAGE_SIZE)
+    code = asm.assemble()
+    offset = addr % PAGE_SIZE
+    page[offset:offset + len(code)] = code
+    return bytes(page)
+
+
+# Simple 8-bit IDE ports
+IDE_DATA = 0x002F
+IDE_SECTOR_COUNT = 0x00AF
+IDE_SECTOR_NUMBER = 0x00EF
+IDE_CYLINDER_LOW = 0x012F
+IDE_CYLINDER_HIGH = 0x016F
+IDE_DRIVE_HEAD = 0x01AF
+IDE_STATUS_COMMAND = 0x01EF
+
+
+def ide_program():
+    """IDENTIFY to 0x4100, read sector 0 to 0x4000, write first 256 bytes of
+    ROM to sector 1, store status at 0x4200 and read sector 1 back to 0x4300"""
+    a = Asm(0x8000)
+    a.db(0xF3)                                  # DI
+    ld_bc(a, IDE_DRIVE_HEAD)
+    a.db(0x3E, 0xE0)                            # LD A, master + LBA
+    a.db(0xED, 0x79)                            # OUT (C), A
+    # IDENTIFY
+    a.db(0x3E, 0xEC)                            # LD A, 0xEC
+    a.abs16(0xCD, label="command")              # CALL command
+    a.db(0x21, 0x00, 0x41)                      # LD HL, 0x4100
+    a.abs16(0xCD, label="read_block")
+    # READ SECTORS (0)
+    a.db(0x1E, 0x00)                            # LD E, 0
+    a.abs16(0xCD, label="set_lba")
+    a.db(0x3E, 0x20)
+    a.abs16(0xCD, label="command")
+    a.db(0x21, 0x00, 0x40)                      # LD HL, 0x4000
+    a.abs16(0xCD, label="read_block")
+    # WRITE SECTORS (1)
+    a.db(0x1E, 0x01)                            # LD E, 1
+    a.abs16(0xCD, label="set_lba")
+    a.db(0x3E, 0x30)
+    a.abs16(0xCD, label="command")
+    a.db(0x21, 0x00, 0x00)                      # LD HL, 0x0000
+    a.abs16(0xCD, label="write_block")
+    ld_bc(a, IDE_STATUS_COMMAND)
+    a.db(0xED, 0x78)                            # IN A, (C)
+    a.db(0x32, 0x00, 0x42)                      # LD (0x4200), A
+    # READ SECTORS (1)
+    a.db(0x1E, 0x01)
+    a.abs16(0xCD, label="set_lba")
+    a.db(0x3E, 0x20)
+    a.abs16(0xCD, label="command")
+    a.db(0x21, 0x00, 0x43)                      # LD HL, 0x4300
+    a.abs16(0xCD, label="read_block")
+    a.label("halt")
+    a.rel8(0x18, label="halt")                  # JR halt
+
+    # A = command, waits for DRQ
+    a.label("command")
+    ld_bc(a, IDE_STATUS_COMMAND)
+    a.db(0xED, 0x79)                            # OUT (C), A
+    a.label("wait_drq")
+    a.db(0xED, 0x78)                            # IN A, (C)
+    a.db(0xE6, 0x08)                            # AND DRQ
+    a.rel8(0x28, label="wait_drq")              # JR Z, wait_drq
+    a.db(0xC9)                                  # RET
+
+    # E = LBA, single sector
+    a.label("set_lba")
+    ld_bc(a, IDE_SECTOR_COUNT)
+    a.db(0x3E, 0x01)                            # LD A, 1
+    a.db(0xED, 0x79)                            # OUT (C), A
+    ld_bc(a, IDE_SECTOR_NUMBER)
+    a.db(0xED, 0x59)                            # OUT (C), E
+    a.db(0xAF)                                  # XOR A
+    ld_bc(a, IDE_CYLINDER_LOW)
+    a.db(0xED, 0x79)
+    ld_bc(a, IDE_CYLINDER_HIGH)
+    a.db(0xED, 0x79)
+    a.db(0xC9)
+
+    # HL = destination, 256 data words
+    a.label("read_block")
+    ld_bc(a, IDE_DATA)
+    a.db(0x16, 0x00)                            # LD D, 0
+    a.label("read_loop")
+    a.db(0xED, 0x78)                            # IN A, (C)
+    a.db(0x77)                                  # LD (HL), A
+    a.db(0x23)                                  # INC HL
+    a.db(0x15)                                  # DEC D
+    a.rel8(0x20, label="read_loop")             # JR NZ, read_loop
+    a.db(0xC9)
+
+    # HL = source, 256 data words
+    a.label("write_block")
+    ld_bc(a, IDE_DATA)
+    a.db(0x16, 0x00)
+    a.label("write_loop")
+    a.db(0x7E)                                  # LD A, (HL)
+    a.db(0xED, 0x79)                            # OUT (C), A
+    a.db(0x23)
+    a.db(0x15)
+    a.rel8(0x20, label="write_loop")
+    a.db(0xC9)
+
+    empty = bytes(PAGE_SIZE)
+    # 48K pages for 0x4000, 0x8000 and 0xC000
+    banks = {8: empty, 4: place(a, 0x8000), 5: empty}
+    return make_z80(HW_MODE_48K, 0x8000, banks)
+
+
+def fill(a, addr, value, count):
+    a.db(0x21, addr & 0xFF, addr >> 8)          # LD HL, addr
+    a.db(0x36, value)                           # LD (HL), value
+    a.db(0x11, (addr + 1) & 0xFF, (addr + 1) >> 8)  # LD DE, addr + 1
+    ld_bc(a, count - 1)
+    a.db(0xED, 0xB0)                            # LDIR
+
+
+def plus3_paging_program():
+    """Code runs from bank 3 at 0xC000, which stays mapped in special paging
+    modes 3 (4, 7, 6, 3) and 2 (4, 5, 6, 3). Screen is drawn to bank 7 via
+    0x4000 and bank 4 is filled via 0x0000"""
+    a = Asm(0xC000)
+    a.db(0xF3)                                  # DI
+    ld_bc(a, 0x1FFD)
+    a.db(0x3E, 0x07)                            # LD A, special mode 3
+    a.db(0xED, 0x79)                            # OUT (C), A
+    fill(a, 0x4000, 0xAA, 0x1800)
+    fill(a, 0x5800, 0x38, 0x0300)
+    fill(a, 0x0000, 0x55, 0x0100)
+    ld_bc(a, 0x7FFD)
+    a.db(0x3E, 0x0B)                            # LD A, bank 3 + shadow screen
+    a.db(0xED, 0x79)
+    ld_bc(a, 0x1FFD)
+    a.db(0x3E, 0x05)                            # LD A, special mode 2
+    a.db(0xED, 0x79)
+    a.label("halt")
+    a.rel8(0x18, label="halt")
+
+    banks = {bank + 3: bytes(PAGE_SIZE) for bank in range(8)}
+    banks[3 + 3] = place(a, 0xC000)
+    return make_z80(HW_MODE_PLUS3, 0xC000, banks, port_7ffd=0x03)
+
+
+IN_TIMING_RESULTS = 0x9000
+IN_TIMING_VECTOR_TABLE = 0x8100
+IN_TIMING_HANDLER = 0x8383
+IN_TIMING_TARGET = 0x8F00
+
+
+def in_timing_program(hw_mode):
+    """Counts iterations of `LD A, D; IN A, (E); INC BC; JR loop` (33 clocks
+    without contention) between two frame interrupts for each port from the
+    table below. Counts are stored as words at 0x9000. Code is placed in
+    uncontended memory, therefore only IO contention affects the results"""
+    ports = [
+        0x00FF,  # N:4
+        0x00FE,  # N:1, C:3
+        0x40FF,  # C:1, C:1, C:1, C:1
+        0x40FE,  # C:1, C:3
+    ]
+    a = Asm(0x8000)
+    a.db(0xF3)                                  # DI
+    a.db(0x31, STACK_ADDR & 0xFF, STACK_ADDR >> 8)  # LD SP, STACK_ADDR
+    a.db(0x3E, IN_TIMING_VECTOR_TABLE >> 8)     # LD A, vector table page
+    a.db(0xED, 0x47)                            # LD I, A
+    a.db(0xED, 0x5E)                            # IM 2
+    a.db(0xDD, 0x21, IN_TIMING_RESULTS & 0xFF, IN_TIMING_RESULTS >> 8)  # LD IX
+    for port in ports:
+        a.db(0x11, port & 0xFF, port >> 8)      # LD DE, port
+        a.abs16(0xCD, label="measure")          # CALL measure
+    a.label("halt")
+    a.rel8(0x18, label="halt")                  # JR halt
+
+    # D = port high byte, E = port low byte
+    a.label("measure")
+    a.db(0x7B)                                  # LD A, E
+    a.abs16(0x32, label="in_port")              # LD (in_port), A
+    a.abs16(0x21, label="sync")                 # LD HL, sync
+    a.db(0x22, IN_TIMING_TARGET & 0xFF, IN_TIMING_TARGET >> 8)  # LD (target), HL
+    a.db(0xFB)                                  # EI
+    a.db(0x76)                                  # HALT
+    a.label("sync")
+    a.abs16(0x21, label="done")                 # LD HL, done
+    a.db(0x22, IN_TIMING_TARGET & 0xFF, IN_TIMING_TARGET >> 8)  # LD (target), HL
+    a.db(0x01, 0x00, 0x00)                      # LD BC, 0
+    a.db(0xFB)                                  # EI
+    a.label("loop")
+    a.db(0x7A)                                  # LD A, D
+    a.db(0xDB)                                  # IN A, (n)
+    a.label("in_port")
+    a.db(0xFE)
+    a.db(0x03)                                  # INC BC
+    a.rel8(0x18, label="loop")                  # JR loop
+    a.label("done")
+    a.db(0xDD, 0x71, 0x00)                      # LD (IX + 0), C
+    a.db(0xDD, 0x70, 0x01)                      # LD (IX + 1), B
+    a.db(0xDD, 0x23)                            # INC IX
+    a.db(0xDD, 0x23)                            # INC IX
+    a.db(0xC9)                                  # RET
+
+    page = bytearray(place(a, 0x8000))
+    table = IN_TIMING_VECTOR_TABLE % PAGE_SIZE
+    page[table:table + 257] = bytes([IN_TIMING_HANDLER & 0xFF]) * 257
+    # Interrupt handler drops return address and jumps to the current target
+    handler = IN_TIMING_HANDLER % PAGE_SIZE
+    page[handler:handler + 5] = bytes([
+        0xF1,                                   # POP AF
+        0x2A, IN_TIMING_TARGET & 0xFF, IN_TIMING_TARGET >> 8,  # LD HL, (target)
+        0xE9,                                   # JP (HL)
+    ])
+    page = bytes(page)
+
+    empty = bytes(PAGE_SIZE)
+    if hw_mode == HW_MODE_48K:
+        banks = {8: empty, 4: page, 5: empty}
+    else:
+        banks = {bank + 3: empty for bank in range(8)}
+        banks[2 + 3] = page
+    return make_z80(hw_mode, 0x8000, banks)
+
+
+AY_REGISTERS_RESULTS = 0x9000
+
+
+def ay_registers_program():
+    """Writes 0xFF to each of 16 AY registers and stores value read back from
+    the register to 0x9000 + register index"""
+    a = Asm(0x8000)
+    a.db(0xF3)                                  # DI
+    a.db(0x21, AY_REGISTERS_RESULTS & 0xFF, AY_REGISTERS_RESULTS >> 8)  # LD HL
+    a.db(0x1E, 0x00)                            # LD E, 0
+    a.label("loop")
+    ld_bc(a, 0xFFFD)
+    a.db(0xED, 0x59)                            # OUT (C), E
+    a.db(0x06, 0xBF)                            # LD B, 0xBF
+    a.db(0x3E, 0xFF)                            # LD A, 0xFF
+    a.db(0xED, 0x79)                            # OUT (C), A
+    a.db(0x06, 0xFF)                            # LD B, 0xFF
+    a.db(0xED, 0x78)                            # IN A, (C)
+    a.db(0x77)                                  # LD (HL), A
+    a.db(0x23)                                  # INC HL
+    a.db(0x1C)                                  # INC E
+    a.db(0x7B)                                  # LD A, E
+    a.db(0xFE, 0x10)                            # CP 16
+    a.rel8(0x20, label="loop")                  # JR NZ, loop
+    a.label("halt")
+    a.rel8(0x18, label="halt")                  # JR halt
+
+    banks = {bank + 3: bytes(PAGE_SIZE) for bank in range(8)}
+    banks[2 + 3] = place(a, 0x8000)
+    return make_z80(HW_MODE_128K, 0x8000, banks)
+
+
+
+AY_FADE_RESULTS = 0x9000
+
+
+def ay_write(a, reg, value):
+    ld_bc(a, 0xFFFD)
+    a.db(0x3E, reg)                             # LD A, reg
+    a.db(0xED, 0x79)                            # OUT (C), A
+    a.db(0x06, 0xBF)                            # LD B, 0xBF
+    a.db(0x3E, value)                           # LD A, value
+    a.db(0xED, 0x79)                            # OUT (C), A
+
+
+def ay_fade_program():
+    """Plays tone on channel A and fades it out the way some music players do:
+    volume is read back from the AY register, decremented and written again
+    until it reaches zero. Initial volume 0xEF has garbage in the unused bits,
+    so the fade takes 15 steps only if they read back as zero. Count of steps
+    is stored as word at 0x9000"""
+    a = Asm(0x8000)
+    a.db(0xF3)                                  # DI
+    ay_write(a, 7, 0x3E)                        # Tone A only
+    ay_write(a, 0, 0x80)
+    ay_write(a, 8, 0xEF)
+    a.db(0x21, 0x00, 0x00)                      # LD HL, 0
+    a.label("loop")
+    a.db(0x11, 0x00, 0x08)                      # LD DE, 0x0800
+    a.label("delay")
+    a.db(0x1B)                                  # DEC DE
+    a.db(0x7A)                                  # LD A, D
+    a.db(0xB3)                                  # OR E
+    a.rel8(0x20, label="delay")                 # JR NZ, delay
+    a.db(0x06, 0xFF)                            # LD B, 0xFF
+    a.db(0xED, 0x78)                            # IN A, (C)
+    a.db(0xB7)                                  # OR A
+    a.rel8(0x28, label="done")                  # JR Z, done
+    a.db(0x3D)                                  # DEC A
+    a.db(0x06, 0xBF)                            # LD B, 0xBF
+    a.db(0xED, 0x79)                            # OUT (C), A
+    a.db(0x23)                                  # INC HL
+    a.rel8(0x18, label="loop")                  # JR loop
+    a.label("done")
+    a.db(0x22, AY_FADE_RESULTS & 0xFF, AY_FADE_RESULTS >> 8)  # LD (results), HL
+    a.label("halt")
+    a.rel8(0x18, label="halt")                  # JR halt
+
+    banks = {bank + 3: bytes(PAGE_SIZE) for bank in range(8)}
+    banks[2 + 3] = place(a, 0x8000)
+    return make_z80(HW_MODE_128K, 0x8000, banks)
+
+
+def main():
+    out_dir = sys.argv[1]
+    programs = {
+        "ide.48k.z80": ide_program(),
+        "paging.plus3.z80": plus3_paging_program(),
+        "in_timing.48k.z80": in_timing_program(HW_MODE_48K),
+        "in_timing.128k.z80": in_timing_program(HW_MODE_128K),
+        "in_timing.plus3.z80": in_timing_program(HW_MODE_PLUS3),
+        "ay_registers.128k.z80": ay_registers_program(),
+        "ay_fade.128k.z80": ay_fade_program(),
+    }
+    for name, data in programs.items():
+        with open(os.path.join(out_dir, name), "wb") as f:
+            f.write(data)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,101 @@
+use expect_test::expect;
+use rustzx_core::zx::{
+    feedback::{FeedbackEvent, MemoryTrigger},
+    joy::kempston::KempstonKey,
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+/// Address of FRAMES system variable, which is incremented by ROM on each interrupt
+const ADDR_FRAMES: u16 = 0x5C78;
+
+fn take_feedback(t: &mut RustZXTester) -> Vec<FeedbackEvent> {
+    std::iter::from_fn(|| t.emulator().next_feedback_event()).collect()
+}
+
+#[test]
+fn kempston_fire_feedback() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.kempston_enabled = true;
+    let mut t = RustZXTester::new("kempston_fire_feedback", settings);
+
+    // Events are not collected until requested by the host
+    t.emulator().send_kempston_key(KempstonKey::Fire, true);
+    t.emulator().send_kempston_key(KempstonKey::Fire, false);
+    assert!(take_feedback(&mut t).is_empty());
+
+    t.emulator().set_feedback_enabled(true);
+    t.emulator().send_kempston_key(KempstonKey::Up, true);
+    t.emulator().send_kempston_key(KempstonKey::Fire, true);
+    // Repeated press without release is not a new event
+    t.emulator().send_kempston_key(KempstonKey::Fire, true);
+    t.emulator().send_kempston_key(KempstonKey::Fire, false);
+    t.emulator().send_kempston_key(KempstonKey::Fire, true);
+
+    expect![[r#"[KempstonFire, KempstonFire]"#]].assert_eq(&format!("{:?}", take_feedback(&mut t)));
+}
+
+#[test]
+fn memory_trigger_feedback() {
+    let mut t = RustZXTester::new("memory_trigger_feedback", presets::settings_48k_nosound());
+    // Wait for ROM to initialize
+    t.emulate_for(Duration::from_millis(2000));
+
+    t.emulator().set_feedback_enabled(true);
+    let frames_trigger = t.emulator().add_memory_trigger(MemoryTrigger {
+        addr: ADDR_FRAMES,
+        value: None,
+    });
+    let value = t.peek(ADDR_FRAMES).wrapping_add(2);
+    let value_trigger = t.emulator().add_memory_trigger(MemoryTrigger {
+        addr: ADDR_FRAMES,
+        value: Some(value),
+    });
+    // Screen is not changed while ROM waits for input
+    t.emulator().add_memory_trigger(MemoryTrigger {
+        addr: 0x4000,
+        value: None,
+    });
+
+    for _ in 0..3 {
+        t.emulate_frame();
+    }
+    let events = take_feedback(&mut t);
+
+    let count = |id| {
+        events
+            .iter()
+            .filter(|e| matches!(e, FeedbackEvent::MemoryTrigger { id: event_id, .. } if *event_id == id))
+            .count()
+    };
+    assert_eq!(count(frames_trigger), 3);
+    assert_eq!(count(value_trigger), 1);
+    assert_eq!(events.len(), 4);
+
+    t.emulator().clear_memory_triggers();
+    t.emulate_frame();
+    assert!(take_feedback(&mut t).is_empty());
+}
+
+#[test]
+fn tape_edges_feedback() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+    let mut t = RustZXTester::new("tape_edges_feedback", settings);
+    t.load_tap("simple_tape.tap.gz");
+    t.emulator().set_feedback_enabled(true);
+
+    // Stopped tape produces no signal
+    t.emulate_frame();
+    assert!(take_feedback(&mut t).is_empty());
+
+    t.emulator().play_tape();
+    let mut out = String::new();
+    for _ in 0..4 {
+        t.emulate_frame();
+        out += &format!("{:?};", take_feedback(&mut t));
+    }
+    expect![[r#"[TapeEdges(33)];[TapeEdges(32)];[TapeEdges(32)];[TapeEdges(32)];"#]]
+        .assert_eq(&out);
+}
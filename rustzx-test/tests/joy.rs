@@ -47,6 +47,36 @@ fn kempston_joy() {
     expect![[r#"00,01,03,07,0F,1F,3F,7F,FF,FE,FC,F8,F0,E0,C0,80,00,"#]].assert_eq(&out);
 }
 
+#[test]
+fn kempston_joy_hotplug() {
+    let mut t = RustZXTester::new("kempston_joy_hotplug", presets::settings_48k_nosound());
+    t.enable_debug_port();
+    t.load_sna("kempston_joy.48k.sna.gz");
+
+    let mut out = String::new();
+    let mut read_port = |t: &mut RustZXTester| {
+        t.sync_target();
+        t.emulate_frame();
+        out += &t.debug_port().take_text();
+    };
+
+    // Keys are ignored while joystick is detached
+    t.emulator().send_kempston_key(KempstonKey::Fire, true);
+    read_port(&mut t);
+
+    t.emulator().set_kempston_enabled(true).unwrap();
+    read_port(&mut t);
+    t.emulator().send_kempston_key(KempstonKey::Fire, true);
+    read_port(&mut t);
+
+    // Re-attached joystick starts with released buttons
+    t.emulator().set_kempston_enabled(false).unwrap();
+    t.emulator().set_kempston_enabled(true).unwrap();
+    read_port(&mut t);
+
+    expect![[r#"FF,00,10,00,"#]].assert_eq(&out);
+}
+
 #[test]
 fn sinclair_joy() {
     let mut t = RustZXTester::new("sinclair_joy", presets::settings_48k_nosound());
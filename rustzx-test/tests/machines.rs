@@ -0,0 +1,266 @@
+use rustzx_core::{
+    error::{Error, IoError, SnapshotLoadError},
+    host::{BufferCursor, DataRecorder, Snapshot, SnapshotRecorder},
+    zx::machine::ZXMachine,
+    RustzxSettings,
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+const SNA_HEADER_SIZE: usize = 27;
+const PROGRAM_ADDR: u16 = 0x8000;
+const STACK_ADDR: u16 = 0xFF00;
+const RESULT_ADDR: u16 = 0x9000;
+
+/// Localized models have no embedded ROM, therefore test programs should not
+/// depend on it
+fn settings_without_rom(machine: ZXMachine) -> RustzxSettings {
+    RustzxSettings {
+        machine,
+        load_default_rom: false,
+        autoload_enabled: false,
+        ..presets::settings_48k_nosound()
+    }
+}
+
+/// Builds 48K SNA snapshot, which starts `program` with disabled interrupts
+fn program_sna(program: &[u8]) -> Vec<u8> {
+    let mut sna = vec![0u8; SNA_HEADER_SIZE + 48 * 1024];
+    sna[23..25].copy_from_slice(&STACK_ADDR.to_le_bytes());
+    sna[25] = 1;
+    let ram = &mut sna[SNA_HEADER_SIZE..];
+    let offset = |addr: u16| addr as usize - 0x4000;
+    ram[offset(STACK_ADDR)..offset(STACK_ADDR) + 2].copy_from_slice(&PROGRAM_ADDR.to_le_bytes());
+    ram[offset(PROGRAM_ADDR)..offset(PROGRAM_ADDR) + program.len()].copy_from_slice(program);
+    sna
+}
+
+#[test]
+fn tc2048_hardware() {
+    let mut tester = RustZXTester::new(
+        "tc2048_hardware",
+        settings_without_rom(ZXMachine::TimexTC2048),
+    );
+    // Joystick interface is built in
+    tester.emulator().set_kempston_enabled(false).unwrap();
+    assert!(tester.emulator().kempston_enabled());
+
+    let program = [
+        0x3E, 0x3E, // LD A, 0x3E
+        0xD3, 0xFF, // OUT (0xFF), A
+        0x3E, 0x00, // LD A, 0x00
+        0xDB, 0xFF, // IN A, (0xFF)
+        0x32, 0x00, 0x90, // LD (0x9000), A
+        0x18, 0xFE, // JR $
+    ];
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(program_sna(&program))))
+        .expect("Failed to load test SNA");
+    tester.emulate_for(Duration::from_millis(20));
+    // Video mode register is readable
+    assert_eq!(tester.peek(RESULT_ADDR), 0x3E);
+}
+
+/// Program which fills bitmap and attributes of the screen at `screen_addr`
+/// and then optionally writes `video_mode` to the Timex port `0xFF`
+fn fill_screen_program(screen_addr: u16, video_mode: Option<u8>) -> Vec<u8> {
+    let fill = |addr: u16, len: u16, value: u8| {
+        let [addr_lo, addr_hi] = addr.to_le_bytes();
+        let [next_lo, next_hi] = (addr + 1).to_le_bytes();
+        let [len_lo, len_hi] = (len - 1).to_le_bytes();
+        vec![
+            0x21, addr_lo, addr_hi, // LD HL, addr
+            0x11, next_lo, next_hi, // LD DE, addr + 1
+            0x01, len_lo, len_hi, // LD BC, len - 1
+            0x36, value, // LD (HL), value
+            0xED, 0xB0, // LDIR
+        ]
+    };
+    let mut program = fill(screen_addr, 0x1800, 0x55);
+    program.extend(fill(screen_addr + 0x1800, 0x300, 0x0E));
+    if let Some(mode) = video_mode {
+        program.extend([
+            0x3E, mode, // LD A, mode
+            0xD3, 0xFF, // OUT (0xFF), A
+        ]);
+    }
+    program.extend([0x18, 0xFE]); // JR $
+    program
+}
+
+/// Returns screen after running `program` from SNA snapshot on `machine`
+fn program_screen(machine: ZXMachine, program: &[u8]) -> Vec<u8> {
+    let mut tester = RustZXTester::new("program_screen", settings_without_rom(machine));
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(program_sna(program))))
+        .expect("Failed to load test SNA");
+    tester.emulate_for(Duration::from_millis(100));
+    tester.get_screen()
+}
+
+#[test]
+fn tc2048_second_screen() {
+    let second_screen = fill_screen_program(0x6000, Some(0x01));
+    let reference = program_screen(ZXMachine::Sinclair48K, &fill_screen_program(0x4000, None));
+    // Screen at 0x6000 is displayed when selected via port 0xFF
+    assert!(program_screen(ZXMachine::TimexTC2048, &second_screen) == reference);
+    // 48K has no second screen
+    assert!(program_screen(ZXMachine::Sinclair48K, &second_screen) != reference);
+    // Selecting the first screen again hides it
+    let first_screen = fill_screen_program(0x6000, Some(0x00));
+    assert!(program_screen(ZXMachine::TimexTC2048, &first_screen) != reference);
+}
+
+#[test]
+fn localized_models_load_base_machine_snapshots() {
+    for (machine, base, snapshot) in [
+        (
+            ZXMachine::TimexTC2048,
+            ZXMachine::Sinclair48K,
+            "in_timing.48k.z80.gz",
+        ),
+        (
+            ZXMachine::Sinclair128KSpanish,
+            ZXMachine::Sinclair128K,
+            "in_timing.128k.z80.gz",
+        ),
+    ] {
+        let ram = [machine, base].map(|machine| {
+            let mut tester = RustZXTester::new(
+                "localized_models_load_base_machine_snapshots",
+                settings_without_rom(machine),
+            );
+            tester.load_z80(snapshot);
+            tester.emulate_for(Duration::from_millis(200));
+            (0..tester.emulator().ram_pages_count())
+                .flat_map(|page| tester.emulator().ram_page(page as u8).to_vec())
+                .collect::<Vec<_>>()
+        });
+        assert!(ram[0] == ram[1], "{:?} differs from {:?}", machine, base);
+    }
+}
+
+/// Collects recorded snapshot into a shared buffer
+struct VecRecorder<'a>(&'a mut Vec<u8>);
+
+impl DataRecorder for VecRecorder<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+/// Fills the second screen (0x6000) of 48K RAM bank at 0x4000 the same way
+/// as `fill_screen_program`
+fn fill_second_screen(bank: &mut [u8]) {
+    bank[0x2000..0x3800].fill(0x55);
+    bank[0x3800..0x3B00].fill(0x0E);
+}
+
+/// Builds v3 Z80 snapshot of TC2048 with the second screen selected, which
+/// loops at 0x8000 with disabled interrupts
+fn tc2048_z80() -> Vec<u8> {
+    let mut z80 = vec![0u8; 30];
+    z80[8..10].copy_from_slice(&STACK_ADDR.to_le_bytes());
+    z80.extend_from_slice(&54u16.to_le_bytes());
+    let mut extra = [0u8; 54];
+    extra[0..2].copy_from_slice(&PROGRAM_ADDR.to_le_bytes());
+    extra[2] = 14; // TC2048 hardware mode
+    extra[4] = 0x01; // Port 0xFF, second screen
+    z80.extend_from_slice(&extra);
+    let mut pages = [8, 4, 5].map(|page| (page, vec![0u8; 16 * 1024]));
+    fill_second_screen(&mut pages[0].1);
+    pages[1].1[..2].copy_from_slice(&[0x18, 0xFE]); // JR $
+    for (page, data) in pages {
+        z80.extend_from_slice(&[0xFF, 0xFF, page]);
+        z80.extend_from_slice(&data);
+    }
+    z80
+}
+
+/// Builds SZX snapshot of TC2048 with the second screen selected via SCLD
+/// chunk, which loops at 0x8000 with disabled interrupts
+fn tc2048_szx() -> Vec<u8> {
+    fn push_chunk(szx: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+        szx.extend_from_slice(id);
+        szx.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        szx.extend_from_slice(data);
+    }
+
+    let mut regs = [0u8; 37];
+    regs[20..22].copy_from_slice(&STACK_ADDR.to_le_bytes());
+    regs[22..24].copy_from_slice(&PROGRAM_ADDR.to_le_bytes());
+    let mut szx = b"ZXST\x01\x04\x08\x00".to_vec();
+    push_chunk(&mut szx, b"Z80R", &regs);
+    push_chunk(&mut szx, b"SPCR", &[0u8; 8]);
+    push_chunk(&mut szx, b"SCLD", &[0x00, 0x01]);
+    for page in [5, 2, 0] {
+        let mut ramp = vec![0u8, 0, page];
+        ramp.resize(3 + 16 * 1024, 0);
+        match page {
+            5 => fill_second_screen(&mut ramp[3..]),
+            2 => ramp[3..5].copy_from_slice(&[0x18, 0xFE]), // JR $
+            _ => {}
+        }
+        push_chunk(&mut szx, b"RAMP", &ramp);
+    }
+    szx
+}
+
+#[test]
+fn tc2048_snapshots() {
+    let reference = program_screen(ZXMachine::Sinclair48K, &fill_screen_program(0x4000, None));
+    for (name, snapshot) in [
+        ("z80", Snapshot::Z80(BufferCursor::new(tc2048_z80()))),
+        ("szx", Snapshot::Szx(BufferCursor::new(tc2048_szx()))),
+    ] {
+        let mut snapshot = snapshot;
+        assert_eq!(
+            snapshot.machine().unwrap(),
+            ZXMachine::TimexTC2048,
+            "{}",
+            name
+        );
+        let mut tester = RustZXTester::new(
+            "tc2048_snapshots",
+            settings_without_rom(ZXMachine::TimexTC2048),
+        );
+        tester.emulator().load_snapshot(snapshot).unwrap();
+        tester.emulate_for(Duration::from_millis(100));
+        assert!(tester.get_screen() == reference, "{}", name);
+
+        // Saved snapshot keeps the selected screen
+        let mut z80 = vec![];
+        tester
+            .emulator()
+            .save_snapshot(SnapshotRecorder::Z80(VecRecorder(&mut z80)))
+            .unwrap();
+        let mut loaded = RustZXTester::new(
+            "tc2048_snapshots",
+            settings_without_rom(ZXMachine::TimexTC2048),
+        );
+        loaded
+            .emulator()
+            .load_snapshot(Snapshot::Z80(BufferCursor::new(z80)))
+            .unwrap();
+        loaded.emulate_for(Duration::from_millis(100));
+        assert!(loaded.get_screen() == reference, "{} saved as z80", name);
+    }
+
+    // Timex video modes are not available on 48K
+    for snapshot in [
+        Snapshot::Z80(BufferCursor::new(tc2048_z80())),
+        Snapshot::Szx(BufferCursor::new(tc2048_szx())),
+    ] {
+        let mut tester = RustZXTester::new(
+            "tc2048_snapshots",
+            settings_without_rom(ZXMachine::Sinclair48K),
+        );
+        assert!(matches!(
+            tester.emulator().load_snapshot(snapshot),
+            Err(Error::SnapshotLoad(SnapshotLoadError::MachineNotSupported))
+        ));
+    }
+}
@@ -0,0 +1,48 @@
+use rustzx_core::{RamPattern, RustzxSettings};
+use rustzx_test::framework::{presets, RustZXTester};
+
+fn ram_snapshot(tester: &mut RustZXTester) -> Vec<u8> {
+    (0x4000..=0xFFFF).map(|addr| tester.peek(addr)).collect()
+}
+
+fn settings_with_pattern(ram_pattern: RamPattern) -> RustzxSettings {
+    RustzxSettings {
+        ram_pattern,
+        ..presets::settings_48k_nosound()
+    }
+}
+
+#[test]
+fn ram_pattern_stripes() {
+    let mut tester = RustZXTester::new(
+        "ram_pattern_stripes",
+        settings_with_pattern(RamPattern::Stripes),
+    );
+
+    assert_eq!(tester.peek(0x4000), 0x00);
+    assert_eq!(tester.peek(0x407F), 0x00);
+    assert_eq!(tester.peek(0x4080), 0xFF);
+    assert_eq!(tester.peek(0x40FF), 0xFF);
+    assert_eq!(tester.peek(0x4100), 0x00);
+}
+
+#[test]
+fn ram_pattern_random_is_reproducible() {
+    let mut first = RustZXTester::new(
+        "ram_pattern_random_1",
+        settings_with_pattern(RamPattern::Random(42)),
+    );
+    let mut second = RustZXTester::new(
+        "ram_pattern_random_2",
+        settings_with_pattern(RamPattern::Random(42)),
+    );
+    let mut other = RustZXTester::new(
+        "ram_pattern_random_3",
+        settings_with_pattern(RamPattern::Random(43)),
+    );
+
+    let ram = ram_snapshot(&mut first);
+    assert_eq!(ram, ram_snapshot(&mut second));
+    assert_ne!(ram, ram_snapshot(&mut other));
+    assert!(ram.iter().any(|b| *b != ram[0]));
+}
@@ -1,5 +1,9 @@
 use expect_test::expect;
-use rustzx_core::zx::mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection};
+use rustzx_core::zx::{
+    machine::ZXMachine,
+    mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection},
+    peripherals::Peripheral,
+};
 use rustzx_test::framework::{presets, RustZXTester};
 use std::time::Duration;
 
@@ -112,3 +116,33 @@ fn kempston_mouse() {
         expect![[r#"I+2mija0+YU60eHjAehkN9MpfgMli2ym7pMoChVbcFo="#]],
     );
 }
+
+#[test]
+fn kempston_mouse_conflicts_with_joystick() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.mouse_enabled = true;
+    settings.kempston_enabled = true;
+    assert_eq!(
+        settings.validate().unwrap_err().to_string(),
+        "Kempston mouse responds on port 0x001F of Kempston joystick, they can't be enabled together"
+    );
+    // Timex joystick interface is built in
+    settings.kempston_enabled = false;
+    settings.machine = ZXMachine::TimexTC2048;
+    assert!(settings.validate().is_err());
+    settings.machine = ZXMachine::Sinclair48K;
+    settings.validate().unwrap();
+
+    let mut tester = RustZXTester::new("kempston_mouse_conflicts_with_joystick", settings);
+    let emulator = tester.emulator();
+    assert!(emulator.check_peripheral(Peripheral::KempstonJoy).is_err());
+    // Conflicting peripheral is not attached
+    assert!(emulator.set_kempston_enabled(true).is_err());
+    assert!(!emulator.kempston_enabled());
+    emulator.set_mouse_enabled(false).unwrap();
+    emulator.check_peripheral(Peripheral::KempstonJoy).unwrap();
+    emulator.check_peripheral(Peripheral::Ide).unwrap();
+    emulator.set_kempston_enabled(true).unwrap();
+    assert!(emulator.set_mouse_enabled(true).is_err());
+    assert!(!emulator.mouse_enabled());
+}
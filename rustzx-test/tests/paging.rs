@@ -0,0 +1,26 @@
+use expect_test::expect;
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+// `paging.plus3.z80` program runs from bank 3, fills shadow screen (bank 7)
+// and bank 4 using special paging mode 3 (banks 4, 7, 6, 3), then switches to
+// shadow screen and special paging mode 2 (banks 4, 5, 6, 3)
+
+#[test]
+fn plus3_special_paging() {
+    let mut tester = RustZXTester::new("plus3_special_paging", presets::settings_plus3_nosound());
+    tester.load_z80("paging.plus3.z80.gz");
+    tester.emulate_for(Duration::from_millis(100));
+
+    // Special mode 2: banks 4, 5, 6, 3
+    assert_eq!(tester.peek(0x0000), 0x55);
+    assert_eq!(tester.peek(0x4000), 0x00);
+    assert_eq!(tester.peek(0x8000), 0x00);
+    assert_eq!(tester.peek(0xC000), 0xF3);
+
+    // Shadow screen (bank 7) was filled via special mode 3
+    tester.expect_screen(
+        "shadow",
+        expect![[r#"D4ENOkjc5EAA4L7aSg4E8ORabNiS5B7n9ppSjyS38sQ="#]],
+    );
+}
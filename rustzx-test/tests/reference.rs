@@ -0,0 +1,124 @@
+use rustzx_test::{
+    framework::{presets, RustZXTester},
+    reference::{IndexedImage, Rect, Tolerance},
+};
+use std::time::Duration;
+
+/// Loaded screen is overwritten by the BASIC report in the last two lines
+fn report_mask() -> Rect {
+    Rect::new(0, 176, 256, 16)
+}
+
+#[test]
+fn loaded_screen_matches_scr() {
+    for (name, settings) in [
+        ("48k", presets::settings_48k_nosound()),
+        ("128k", presets::settings_128k_nosound()),
+    ] {
+        let mut tester = RustZXTester::new("loaded_screen_matches_scr", settings);
+        tester.load_tap("simple_tape.tap.gz");
+        tester.emulate_for(Duration::from_millis(100));
+        tester.expect_screen_reference(
+            name,
+            "src/rustzx.scr",
+            Tolerance::exact().mask(report_mask()),
+        );
+
+        let diff = tester.compare_screen_with_reference("src/rustzx.scr", &Tolerance::exact());
+        assert!(!diff.is_within(&Tolerance::exact()));
+        assert!(report_mask().contains_rect(&diff.bounds.unwrap()));
+    }
+}
+
+#[test]
+fn png_reference_roundtrip() {
+    let mut tester = RustZXTester::new("png_reference_roundtrip", presets::settings_48k_nosound());
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(100));
+
+    let screen = IndexedImage::from_png(&tester.get_screen()).unwrap();
+    let border = IndexedImage::from_png(&tester.get_border()).unwrap();
+    let scr = IndexedImage::from_scr(&std::fs::read("test_data/src/rustzx.scr").unwrap()).unwrap();
+
+    let diff = screen.diff(&screen, &Tolerance::exact());
+    assert_eq!(diff.pixels, 0);
+    assert!(diff.bounds.is_none());
+    assert!(screen.diff(&border, &Tolerance::exact()).size_mismatch);
+
+    let diff = screen.diff(&scr, &Tolerance::exact());
+    let tolerance = Tolerance::exact().max_pixels(diff.pixels);
+    assert!(screen.diff(&scr, &tolerance).is_within(&tolerance));
+    let tolerance = Tolerance::exact().max_pixels(diff.pixels - 1);
+    assert!(!screen.diff(&scr, &tolerance).is_within(&tolerance));
+}
+
+/// Builds SCR with empty bitmap and the given attribute for the whole screen
+fn scr_with_attribute(attr: u8) -> Vec<u8> {
+    let mut scr = vec![0u8; 6912];
+    scr[6144..].fill(attr);
+    scr
+}
+
+fn encode_png(color: png::ColorType, depth: png::BitDepth, palette: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut encoder = png::Encoder::new(&mut out, 2, 1);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    if !palette.is_empty() {
+        encoder.set_palette(palette.to_vec());
+    }
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(data).unwrap();
+    drop(writer);
+    out
+}
+
+#[test]
+fn bright_black_matches_black() {
+    let black = IndexedImage::from_scr(&scr_with_attribute(0x00)).unwrap();
+    let bright_black = IndexedImage::from_scr(&scr_with_attribute(0x40)).unwrap();
+    assert_eq!(black.diff(&bright_black, &Tolerance::exact()).pixels, 0);
+    assert_eq!(bright_black.diff(&black, &Tolerance::exact()).pixels, 0);
+
+    // Bright blue paper is still different from blue one
+    let blue = IndexedImage::from_scr(&scr_with_attribute(0x08)).unwrap();
+    let bright_blue = IndexedImage::from_scr(&scr_with_attribute(0x48)).unwrap();
+    assert_eq!(
+        blue.diff(&bright_blue, &Tolerance::exact()).pixels,
+        256 * 192
+    );
+}
+
+#[test]
+fn png_reference_color_types() {
+    use png::{BitDepth, ColorType};
+
+    // Black and bright blue pixels
+    let indexed = encode_png(
+        ColorType::Indexed,
+        BitDepth::Eight,
+        &[0, 0, 0, 0, 0, 0xFF],
+        &[0, 1],
+    );
+    assert_eq!(IndexedImage::from_png(&indexed).unwrap().pixels, vec![0, 9]);
+    let rgb16 = encode_png(
+        ColorType::RGB,
+        BitDepth::Sixteen,
+        &[],
+        &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF],
+    );
+    assert_eq!(IndexedImage::from_png(&rgb16).unwrap().pixels, vec![0, 9]);
+
+    let grayscale = encode_png(ColorType::Grayscale, BitDepth::Eight, &[], &[0, 0xFF]);
+    assert!(matches!(
+        IndexedImage::from_png(&grayscale),
+        Err(e) if e.to_string().contains("Grayscale")
+    ));
+    let grayscale_alpha = encode_png(
+        ColorType::GrayscaleAlpha,
+        BitDepth::Eight,
+        &[],
+        &[0, 0xFF, 0xFF, 0xFF],
+    );
+    assert!(IndexedImage::from_png(&grayscale_alpha).is_err());
+}
@@ -0,0 +1,33 @@
+use expect_test::expect;
+use rustzx_core::zx::keys::ZXKey;
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+#[test]
+fn basic_report_48k() {
+    let mut t = RustZXTester::new("basic_report_48k", presets::settings_48k_nosound());
+    // Wait for ROM to initialize
+    t.emulate_for(Duration::from_millis(2000));
+
+    // Copyright message is not a report
+    expect![[r#"© 1982 Sinclair Research Ltd"#]].assert_eq(&t.emulator().screen_text_row(23));
+    assert_eq!(t.emulator().poll_basic_report(), None);
+
+    // PRINT 1/0
+    t.send_keystrokes(
+        &[
+            &[ZXKey::P],
+            &[ZXKey::N1],
+            &[ZXKey::SymShift, ZXKey::V],
+            &[ZXKey::N0],
+            &[ZXKey::Enter],
+        ],
+        Duration::from_millis(100),
+    );
+    t.emulate_for(Duration::from_millis(100));
+
+    expect![[r#"Some("6 Number too big, 0:1")"#]]
+        .assert_eq(&format!("{:?}", t.emulator().poll_basic_report()));
+    // Report is returned only once
+    assert_eq!(t.emulator().poll_basic_report(), None);
+}
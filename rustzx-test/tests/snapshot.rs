@@ -0,0 +1,460 @@
+use expect_test::expect;
+use rustzx_core::{
+    error::{Error, IoError, SnapshotLoadError},
+    host::{BufferCursor, DataRecorder, Snapshot, SnapshotRecorder},
+    zx::machine::ZXMachine,
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use rustzx_utils::io::GzipAsset;
+use std::{fs::File, time::Duration};
+
+const SZX_PROGRAM_ADDR: u16 = 0x8000;
+const SZX_STACK_ADDR: u16 = 0xFF00;
+
+/// Builds 48K SZX snapshot, which starts `program` in IM 1 with interrupts
+/// enabled. Z80R chunk can be adjusted by `z80_regs`, `chunks` are appended
+fn build_szx_48k(
+    program: &[u8],
+    z80_regs: impl FnOnce(&mut [u8]),
+    chunks: &[(&[u8; 4], &[u8])],
+) -> Vec<u8> {
+    fn push_chunk(szx: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+        szx.extend_from_slice(id);
+        szx.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        szx.extend_from_slice(data);
+    }
+
+    let mut regs = [0u8; 37];
+    regs[20..22].copy_from_slice(&SZX_STACK_ADDR.to_le_bytes());
+    regs[22..24].copy_from_slice(&SZX_PROGRAM_ADDR.to_le_bytes());
+    regs[26] = 1; // IFF1
+    regs[27] = 1; // IFF2
+    regs[28] = 1; // IM
+    z80_regs(&mut regs);
+
+    let mut szx = b"ZXST\x01\x04\x01\x00".to_vec();
+    push_chunk(&mut szx, b"Z80R", &regs);
+    push_chunk(&mut szx, b"SPCR", &[0u8; 8]);
+    for page in [5, 2, 0] {
+        let mut ramp = vec![0u8, 0, page];
+        ramp.resize(3 + 16 * 1024, 0);
+        if page == 2 {
+            ramp[3..3 + program.len()].copy_from_slice(program);
+        }
+        push_chunk(&mut szx, b"RAMP", &ramp);
+    }
+    for (id, data) in chunks {
+        push_chunk(&mut szx, id, data);
+    }
+    szx
+}
+
+/// Builds v3 Z80 snapshot of 48K-compatible machine, which loops at 0x8000
+/// with disabled interrupts. Additional header and memory at 0x4000 can be
+/// adjusted by `extra` and `screen_page`
+fn build_z80_v3_48k(
+    hardware_mode: u8,
+    extra: impl FnOnce(&mut [u8]),
+    screen_page: impl FnOnce(&mut [u8]),
+) -> Vec<u8> {
+    let mut z80 = vec![0u8; 30];
+    z80[8..10].copy_from_slice(&SZX_STACK_ADDR.to_le_bytes());
+    z80.extend_from_slice(&54u16.to_le_bytes());
+    let mut extra_header = [0u8; 54];
+    extra_header[0..2].copy_from_slice(&SZX_PROGRAM_ADDR.to_le_bytes());
+    extra_header[2] = hardware_mode;
+    extra(&mut extra_header);
+    z80.extend_from_slice(&extra_header);
+    // Pages at 0x4000, 0x8000 and 0xC000
+    let mut pages = [8, 4, 5].map(|page| (page, vec![0u8; 16 * 1024]));
+    screen_page(&mut pages[0].1);
+    // JR $
+    pages[1].1[..2].copy_from_slice(&[0x18, 0xFE]);
+    for (page, data) in pages {
+        // Uncompressed block
+        z80.extend_from_slice(&[0xFF, 0xFF, page]);
+        z80.extend_from_slice(&data);
+    }
+    z80
+}
+
+fn load_szx_48k(name: &str, szx: Vec<u8>) -> (RustZXTester, rustzx_core::Result<()>) {
+    let mut tester = RustZXTester::new(name, presets::settings_48k_nosound());
+    let result = tester
+        .emulator()
+        .load_snapshot(Snapshot::Szx(BufferCursor::new(szx)));
+    (tester, result)
+}
+
+// Snapshots are converted from `sound.*.sna`, therefore resulting sound should
+// be the same as in `sound.rs` tests
+
+#[test]
+fn z80_v1_48k() {
+    let mut tester = RustZXTester::new("z80_v1_48k", presets::settings_48k());
+    tester.load_z80("sound.48k.z80.gz");
+    tester.start_sound_capture();
+    tester.emulate_for(Duration::from_secs(2));
+    tester.expect_sound(
+        "beeper_plus_ay",
+        expect![[r#"VL6mpyMvPF0/w+NxeTZVGI5vCm+gI1WrPvAw5UfIZLw="#]],
+    );
+}
+
+#[test]
+fn z80_v3_128k() {
+    let mut tester = RustZXTester::new("z80_v3_128k", presets::settings_128k());
+    tester.load_z80("sound.128k.z80.gz");
+    tester.start_sound_capture();
+    tester.emulate_for(Duration::from_secs(2));
+    tester.expect_sound(
+        "beeper_plus_ay",
+        expect![[r#"EbHRIPmkRHnhP5zlUrq6Ih2ELc8FHag+M6aIT7ZOKn8="#]],
+    );
+}
+
+#[test]
+fn szx_48k() {
+    let mut tester = RustZXTester::new("szx_48k", presets::settings_48k());
+    tester.load_szx("sound.48k.szx.gz");
+    tester.start_sound_capture();
+    tester.emulate_for(Duration::from_secs(2));
+    tester.expect_sound(
+        "beeper_plus_ay",
+        expect![[r#"VL6mpyMvPF0/w+NxeTZVGI5vCm+gI1WrPvAw5UfIZLw="#]],
+    );
+}
+
+#[test]
+fn szx_128k() {
+    let mut tester = RustZXTester::new("szx_128k", presets::settings_128k());
+    tester.load_szx("sound.128k.szx.gz");
+    tester.start_sound_capture();
+    tester.emulate_for(Duration::from_secs(2));
+    tester.expect_sound(
+        "beeper_plus_ay",
+        expect![[r#"EbHRIPmkRHnhP5zlUrq6Ih2ELc8FHag+M6aIT7ZOKn8="#]],
+    );
+}
+
+#[test]
+fn slt_level_trap() {
+    let mut tester = RustZXTester::new("slt_level_trap", presets::settings_48k_nosound());
+    tester.load_slt("slt_screen.48k.slt.gz");
+    // Program loads screen via level data trap and loops forever
+    tester.emulate_for(Duration::from_millis(100));
+    tester.expect_screen(
+        "loaded",
+        expect![[r#"zoRX/GvcS0zqOJj3V0cmoZe56CNK2nXiJeH8pF8u1eg="#]],
+    );
+}
+
+#[test]
+fn machine_state_is_format_independent() {
+    let mut z80 = RustZXTester::new("state_z80", presets::settings_128k());
+    z80.load_z80("sound.128k.z80.gz");
+    let mut szx = RustZXTester::new("state_szx", presets::settings_128k());
+    szx.load_szx("sound.128k.szx.gz");
+
+    let state = z80.emulator().machine_state();
+    assert_eq!(state, szx.emulator().machine_state());
+    assert_eq!(state.port_1ffd, None);
+    assert!(state.port_7ffd.is_some());
+    assert!(state.ay_registers.is_some());
+
+    let pages = z80.emulator().ram_pages_count();
+    assert_eq!(pages, 8);
+    for page in 0..pages as u8 {
+        assert_eq!(z80.emulator().ram_page(page), szx.emulator().ram_page(page));
+    }
+}
+
+#[test]
+fn sna_128k_on_48k_machine() {
+    let mut tester = RustZXTester::new("sna_128k_on_48k_machine", presets::settings_48k_nosound());
+    let file = File::open("test_data/sound.128k.sna.gz").expect("Failed to open test SNA");
+    let sna = GzipAsset::new(file)
+        .expect("Failed to decompress gz")
+        .into_vec();
+    let result = tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)));
+    assert!(matches!(
+        result,
+        Err(Error::SnapshotLoad(SnapshotLoadError::MachineNotSupported))
+    ));
+}
+
+#[test]
+fn snapshot_machine_detection() {
+    let load = |name: &str| {
+        let file = File::open(format!("test_data/{}", name)).expect("Failed to open snapshot");
+        BufferCursor::new(
+            GzipAsset::new(file)
+                .expect("Failed to decompress gz")
+                .into_vec(),
+        )
+    };
+    let snapshots = [
+        (
+            Snapshot::Sna(load("sound.48k.sna.gz")),
+            ZXMachine::Sinclair48K,
+        ),
+        (
+            Snapshot::Sna(load("sound.128k.sna.gz")),
+            ZXMachine::Sinclair128K,
+        ),
+        (
+            Snapshot::Z80(load("sound.48k.z80.gz")),
+            ZXMachine::Sinclair48K,
+        ),
+        (
+            Snapshot::Z80(load("sound.128k.z80.gz")),
+            ZXMachine::Sinclair128K,
+        ),
+        (
+            Snapshot::Z80(load("paging.plus3.z80.gz")),
+            ZXMachine::SinclairPlus3,
+        ),
+        (
+            Snapshot::Szx(load("sound.48k.szx.gz")),
+            ZXMachine::Sinclair48K,
+        ),
+        (
+            Snapshot::Szx(load("sound.128k.szx.gz")),
+            ZXMachine::Sinclair128K,
+        ),
+        (
+            Snapshot::Slt(load("slt_screen.48k.slt.gz")),
+            ZXMachine::Sinclair48K,
+        ),
+    ];
+    for (mut snapshot, expected) in snapshots {
+        assert_eq!(snapshot.machine().unwrap(), expected);
+        // Snapshot is still loadable after detection
+        let settings = match expected {
+            ZXMachine::Sinclair48K => presets::settings_48k_nosound(),
+            _ => presets::settings_128k(),
+        };
+        if expected != ZXMachine::SinclairPlus3 {
+            let mut tester = RustZXTester::new("snapshot_machine_detection", settings);
+            tester.emulator().load_snapshot(snapshot).unwrap();
+        }
+    }
+}
+
+#[test]
+fn szx_frame_position_and_halt() {
+    // HALT, PC points after the instruction
+    let szx = build_szx_48k(
+        &[0x76],
+        |regs| {
+            regs[22..24].copy_from_slice(&(SZX_PROGRAM_ADDR + 1).to_le_bytes());
+            regs[29..33].copy_from_slice(&1000u32.to_le_bytes());
+            regs[34] = 0x02;
+        },
+        &[],
+    );
+    let (mut tester, result) = load_szx_48k("szx_frame_position_and_halt", szx);
+    result.unwrap();
+    let state = tester.emulator().machine_state();
+    assert_eq!(state.frame_clocks, 1000);
+    assert!(state.cpu.halted);
+    assert_eq!(state.cpu.pc, SZX_PROGRAM_ADDR);
+}
+
+#[test]
+fn szx_ei_last() {
+    // LD HL, 0x9000; JR $
+    let program = [0x21, 0x00, 0x90, 0x18, 0xFE];
+    // Interrupt is active at the frame start, so return address pushed by
+    // the interrupt shows if the first instruction was executed before it
+    for (flags, return_addr) in [(0x00, SZX_PROGRAM_ADDR), (0x01, SZX_PROGRAM_ADDR + 3)] {
+        let szx = build_szx_48k(&program, |regs| regs[34] = flags, &[]);
+        let (mut tester, result) = load_szx_48k("szx_ei_last", szx);
+        result.unwrap();
+        tester.emulate_for(Duration::from_millis(1));
+        let pushed = u16::from_le_bytes([
+            tester.peek(SZX_STACK_ADDR - 2),
+            tester.peek(SZX_STACK_ADDR - 1),
+        ]);
+        assert_eq!(pushed, return_addr);
+    }
+}
+
+#[test]
+fn szx_joystick_chunk() {
+    let szx = build_szx_48k(&[], |_| {}, &[(b"JOY\0", &[0, 0, 0, 0, 0, 8])]);
+    let (mut tester, result) = load_szx_48k("szx_joystick_chunk", szx);
+    result.unwrap();
+    assert!(tester.emulator().kempston_enabled());
+
+    // Fuller joystick is not emulated
+    let szx = build_szx_48k(&[], |_| {}, &[(b"JOY\0", &[0, 0, 0, 0, 1, 8])]);
+    let (_, result) = load_szx_48k("szx_joystick_chunk", szx);
+    assert!(matches!(
+        result,
+        Err(Error::SnapshotLoad(
+            SnapshotLoadError::PeripheralNotSupported
+        ))
+    ));
+}
+
+#[test]
+fn szx_keyboard_chunk() {
+    let szx = build_szx_48k(&[], |_| {}, &[(b"KEYB", &[0, 0, 0, 0, 0])]);
+    load_szx_48k("szx_keyboard_chunk", szx).1.unwrap();
+
+    // Issue 2 keyboard is not emulated
+    let szx = build_szx_48k(&[], |_| {}, &[(b"KEYB", &[1, 0, 0, 0, 0])]);
+    assert!(matches!(
+        load_szx_48k("szx_keyboard_chunk", szx).1,
+        Err(Error::SnapshotLoad(
+            SnapshotLoadError::PeripheralNotSupported
+        ))
+    ));
+}
+
+#[test]
+fn szx_mouse_chunk() {
+    let szx = build_szx_48k(&[], |_| {}, &[(b"AMXM", &[2, 0, 0, 0, 0, 0, 0])]);
+    let (mut tester, result) = load_szx_48k("szx_mouse_chunk", szx);
+    result.unwrap();
+    assert!(tester.emulator().mouse_enabled());
+
+    // AMX mouse is not emulated
+    let szx = build_szx_48k(&[], |_| {}, &[(b"AMXM", &[1, 0, 0, 0, 0, 0, 0])]);
+    assert!(matches!(
+        load_szx_48k("szx_mouse_chunk", szx).1,
+        Err(Error::SnapshotLoad(
+            SnapshotLoadError::PeripheralNotSupported
+        ))
+    ));
+}
+
+#[test]
+fn szx_unsupported_peripheral_chunk() {
+    for id in [b"IF1\0", b"DIDE", b"ROM\0", b"SCLD"] {
+        let szx = build_szx_48k(&[], |_| {}, &[(id, &[0; 16])]);
+        assert!(
+            matches!(
+                load_szx_48k("szx_unsupported_peripheral_chunk", szx).1,
+                Err(Error::SnapshotLoad(
+                    SnapshotLoadError::PeripheralNotSupported
+                ))
+            ),
+            "{:?} chunk was accepted",
+            id
+        );
+    }
+}
+
+#[test]
+fn z80_paged_interface_rom() {
+    let load = |hardware_mode, extra: fn(&mut [u8])| {
+        let z80 = build_z80_v3_48k(hardware_mode, extra, |_| {});
+        let mut tester =
+            RustZXTester::new("z80_paged_interface_rom", presets::settings_48k_nosound());
+        tester
+            .emulator()
+            .load_snapshot(Snapshot::Z80(BufferCursor::new(z80)))
+    };
+    let unsupported = |result| {
+        matches!(
+            result,
+            Err(Error::SnapshotLoad(
+                SnapshotLoadError::PeripheralNotSupported
+            ))
+        )
+    };
+
+    // Attached interfaces don't matter until their ROM is paged in
+    load(1, |_| {}).unwrap();
+    load(3, |_| {}).unwrap();
+    // 48K + IF1
+    assert!(unsupported(load(1, |extra| extra[4] = 0xFF)));
+    // 48K + MGT
+    assert!(unsupported(load(3, |extra| extra[51] = 0xFF)));
+    // MGT flag has no meaning for IF1 and vice versa
+    load(1, |extra| extra[51] = 0xFF).unwrap();
+    load(3, |extra| extra[4] = 0xFF).unwrap();
+}
+
+/// Collects recorded snapshot into a shared buffer
+struct VecRecorder<'a>(&'a mut Vec<u8>);
+
+impl DataRecorder for VecRecorder<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+#[test]
+fn sna_on_plus3_machine() {
+    let mut tester = RustZXTester::new("sna_on_plus3_machine", presets::settings_plus3_nosound());
+    let file = File::open("test_data/sound.128k.sna.gz").expect("Failed to open test SNA");
+    let sna = GzipAsset::new(file)
+        .expect("Failed to decompress gz")
+        .into_vec();
+    let result = tester
+        .emulator()
+        .load_snapshot(Snapshot::Sna(BufferCursor::new(sna)));
+    assert!(matches!(
+        result,
+        Err(Error::SnapshotLoad(SnapshotLoadError::MachineNotSupported))
+    ));
+
+    let mut data = vec![];
+    let result = tester
+        .emulator()
+        .save_snapshot(SnapshotRecorder::Sna(VecRecorder(&mut data)));
+    assert!(matches!(
+        result,
+        Err(Error::SnapshotLoad(SnapshotLoadError::MachineNotSupported))
+    ));
+}
+
+#[test]
+fn z80_save_plus3_roundtrip() {
+    let mut saved = RustZXTester::new(
+        "z80_save_plus3_roundtrip",
+        presets::settings_plus3_nosound(),
+    );
+    saved.load_z80("paging.plus3.z80.gz");
+    saved.emulate_for(Duration::from_millis(100));
+
+    let mut data = vec![];
+    saved
+        .emulator()
+        .save_snapshot(SnapshotRecorder::Z80(VecRecorder(&mut data)))
+        .expect("Failed to save Z80");
+
+    let mut loaded = RustZXTester::new(
+        "z80_save_plus3_roundtrip",
+        presets::settings_plus3_nosound(),
+    );
+    loaded
+        .emulator()
+        .load_snapshot(Snapshot::Z80(BufferCursor::new(data)))
+        .expect("Failed to load saved Z80");
+
+    let expected = saved.emulator().machine_state();
+    let mut actual = loaded.emulator().machine_state();
+    // Z80 format doesn't keep internal MEMPTR register
+    actual.cpu.mem_ptr = expected.cpu.mem_ptr;
+    assert_eq!(actual.cpu, expected.cpu);
+    assert_eq!(actual.border, expected.border);
+    assert_eq!(actual.port_7ffd, expected.port_7ffd);
+    assert!(actual.port_1ffd.is_some());
+    assert_eq!(actual.port_1ffd, expected.port_1ffd);
+    for page in 0..saved.emulator().ram_pages_count() as u8 {
+        assert_eq!(
+            saved.emulator().ram_page(page),
+            loaded.emulator().ram_page(page)
+        );
+    }
+    for addr in [0x0000, 0x4000, 0x8000, 0xC000] {
+        assert_eq!(saved.peek(addr), loaded.peek(addr));
+    }
+}
@@ -10,7 +10,7 @@ fn sound_48k() {
     tester.emulate_for(Duration::from_secs(2));
     tester.expect_sound(
         "beeper_plus_ay",
-        expect![[r#"bEAIV7LVE7591l6VUrCE1ySbEyBSNeVwLjWhDm9Jlpc="#]],
+        expect![[r#"VL6mpyMvPF0/w+NxeTZVGI5vCm+gI1WrPvAw5UfIZLw="#]],
     );
 }
 
@@ -22,6 +22,45 @@ fn sound_128k() {
     tester.emulate_for(Duration::from_secs(2));
     tester.expect_sound(
         "beeper_plus_ay",
-        expect![[r#"u8WCHu89dFvnMInLGaDFV4ha6FatBtXLJ6szqiUg+ys="#]],
+        expect![[r#"EbHRIPmkRHnhP5zlUrq6Ih2ELc8FHag+M6aIT7ZOKn8="#]],
+    );
+}
+
+// `ay_registers.128k.z80` program writes 0xFF to each AY register and stores
+// values read back to 0x9000..0x9010
+#[test]
+fn ay_register_readback_128k() {
+    let mut tester = RustZXTester::new("ay_register_readback_128k", presets::settings_128k());
+    tester.load_z80("ay_registers.128k.z80.gz");
+    tester.emulate_for(Duration::from_millis(20));
+
+    let regs = (0..16)
+        .map(|reg| tester.peek(0x9000 + reg))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        regs,
+        [
+            0xFF, 0x0F, 0xFF, 0x0F, 0xFF, 0x0F, 0x1F, 0xFF, 0x1F, 0x1F, 0x1F, 0xFF, 0xFF, 0x0F,
+            0xFF, 0xFF,
+        ]
+    );
+}
+
+// `ay_fade.128k.z80` program fades out the tone by reading volume back from
+// the AY register and writing it decremented. Volume is initially written
+// with garbage in the unused bits; if they were read back, the fade would run
+// through envelope mode values for 239 steps instead of 15
+#[test]
+fn ay_read_modify_write_fade_128k() {
+    let mut tester = RustZXTester::new("ay_read_modify_write_fade_128k", presets::settings_128k());
+    tester.load_z80("ay_fade.128k.z80.gz");
+    tester.start_sound_capture();
+    tester.emulate_for(Duration::from_millis(500));
+
+    let steps = u16::from_le_bytes([tester.peek(0x9000), tester.peek(0x9001)]);
+    assert_eq!(steps, 15);
+    tester.expect_sound(
+        "fade",
+        expect!["H/boGB+nv4mCDp/Y1BjbJEgAGlgRqA7A1pThVCbR93w="],
     );
 }
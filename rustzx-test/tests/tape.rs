@@ -1,5 +1,9 @@
 use expect_test::expect;
-use rustzx_core::zx::keys::ZXKey;
+use rustzx_core::{
+    error::{Error, TapeLoadError},
+    host::{BufferCursor, DataBlockTimings, TapeBlock, TapeBlockSource, TzxParser},
+    zx::keys::ZXKey,
+};
 use rustzx_test::framework::{presets, RustZXTester};
 use std::time::Duration;
 
@@ -70,6 +74,35 @@ fn no_fastload() {
     );
 }
 
+#[test]
+fn tape_block_tracking() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("tape_block_tracking", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(100));
+    assert_eq!(tester.emulator().tape_block(), None);
+
+    // Header block pilot tone is playing
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(1000));
+    assert_eq!(tester.emulator().tape_block(), Some(0));
+
+    // Header block with the pause after it is finished
+    tester.emulate_for(Duration::from_millis(6000));
+    assert_eq!(tester.emulator().tape_block(), Some(1));
+
+    // Stopped tape keeps its position
+    tester.emulator().stop_tape();
+    tester.emulate_for(Duration::from_millis(1000));
+    assert_eq!(tester.emulator().tape_block(), Some(1));
+
+    tester.emulator().rewind_tape().unwrap();
+    assert_eq!(tester.emulator().tape_block(), None);
+}
+
 #[test]
 fn no_fastload_128k() {
     let mut settings = presets::settings_128k_nosound();
@@ -227,3 +260,171 @@ fn fastload_128k() {
         expect![[r#"tmGY7e4h+XA3px6BcqnCXF83NEdBqVw8PW9sQtpMAvM="#]],
     );
 }
+
+#[test]
+fn tzx_no_fastload() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+
+    // TZX version of `simple_tape` uses the same timings, so it should load
+    // exactly as TAP in `no_fastload` test
+    let mut tester = RustZXTester::new("tzx_no_fastload", settings);
+    tester.load_tzx("simple_tape.tzx.gz");
+    tester.emulate_for(Duration::from_millis(2000));
+    tester.send_keystrokes(
+        &[
+            &[ZXKey::J],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::Enter],
+        ],
+        Duration::from_millis(100),
+    );
+    tester.emulate_for(Duration::from_millis(100));
+
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(2000));
+    tester.expect_border(
+        "sync_pulses",
+        expect![[r#"Oc++rVrRSea7L5+dCz066kS/mPzhKZ8MhhvVo+8r5iY="#]],
+    );
+    // Text, archive info and group start blocks are skipped
+    assert_eq!(tester.emulator().tape_block(), Some(3));
+
+    tester.emulate_for(Duration::from_millis(3200));
+    tester.expect_screen(
+        "block_1",
+        expect![[r#"+o3MYnfBeDMtimIE/+6+o2/9h1OgtZ8izbO7b/jOiMc="#]],
+    );
+
+    tester.emulate_for(Duration::from_millis(45000));
+    tester.expect_screen(
+        "block_2",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+    assert_eq!(tester.emulator().tape_block(), None);
+}
+
+#[test]
+fn tzx_fastload() {
+    let mut tester = RustZXTester::new("tzx_fastload", presets::settings_48k_nosound());
+    tester.load_tzx("simple_tape.tzx.gz");
+    tester.emulate_for(Duration::from_millis(100));
+    tester.expect_screen(
+        "loaded",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
+fn tzx_image(blocks: &[&[u8]]) -> Vec<u8> {
+    let mut tzx = b"ZXTape!\x1A\x01\x14".to_vec();
+    for block in blocks {
+        tzx.extend_from_slice(block);
+    }
+    tzx
+}
+
+fn tzx_blocks(tzx: Vec<u8>) -> rustzx_core::Result<Vec<(usize, TapeBlock)>> {
+    let mut parser = TzxParser::from_asset(BufferCursor::new(tzx));
+    let mut blocks = vec![];
+    while let Some(block) = parser.next_block()? {
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+#[test]
+fn tzx_loops_and_jumps() {
+    let tzx = tzx_image(&[
+        // 0: loop start, 2 repetitions
+        &[0x24, 0x02, 0x00],
+        // 1: pure tone
+        &[0x12, 0x10, 0x00, 0x05, 0x00],
+        // 2: loop end
+        &[0x25],
+        // 3: jump to block 5
+        &[0x23, 0x02, 0x00],
+        // 4: skipped pause
+        &[0x20, 0x64, 0x00],
+        // 5: pulse sequence
+        &[0x13, 0x02, 0x01, 0x00, 0x02, 0x00],
+        // 6: pause with zero length stops the tape
+        &[0x20, 0x00, 0x00],
+    ]);
+
+    let tone = TapeBlock::PureTone {
+        pulse: 0x10,
+        pulses: 5,
+    };
+    assert_eq!(
+        tzx_blocks(tzx).unwrap(),
+        vec![
+            (1, tone.clone()),
+            (1, tone),
+            (5, TapeBlock::Pulses(vec![1, 2])),
+            (6, TapeBlock::Stop),
+        ]
+    );
+}
+
+#[test]
+fn tzx_invalid_files() {
+    assert!(matches!(
+        tzx_blocks(b"ZXTape?\x1A\x01\x14".to_vec()),
+        Err(Error::TapeLoad(TapeLoadError::InvalidTzxFile))
+    ));
+    // Generalized data block
+    assert!(matches!(
+        tzx_blocks(tzx_image(&[&[0x19, 0x00, 0x00, 0x00, 0x00]])),
+        Err(Error::TapeLoad(TapeLoadError::UnsupportedTzxBlock(0x19)))
+    ));
+    // Jump back over the text description only
+    assert!(matches!(
+        tzx_blocks(tzx_image(&[
+            &[0x30, 0x01, b'A'],
+            &[0x23, 0xFF, 0xFF],
+            &[0x12, 0x10, 0x00, 0x05, 0x00],
+        ])),
+        Err(Error::TapeLoad(TapeLoadError::InvalidTzxFile))
+    ));
+    // Jump back to the loop start, loop end is never reached
+    assert!(matches!(
+        tzx_blocks(tzx_image(&[
+            &[0x24, 0x02, 0x00],
+            &[0x23, 0xFF, 0xFF],
+            &[0x25],
+        ])),
+        Err(Error::TapeLoad(TapeLoadError::InvalidTzxFile))
+    ));
+    // Loop without playable blocks
+    assert!(matches!(
+        tzx_blocks(tzx_image(&[&[0x24, 0x02, 0x00], &[0x30, 0x00], &[0x25]])),
+        Err(Error::TapeLoad(TapeLoadError::InvalidTzxFile))
+    ));
+}
+
+#[test]
+fn tzx_empty_standard_speed_block() {
+    let tzx = tzx_image(&[
+        // 0: empty block with pause is only a pause
+        &[0x10, 0x64, 0x00, 0x00, 0x00],
+        // 1: empty block without pause is skipped
+        &[0x10, 0x00, 0x00, 0x00, 0x00],
+        // 2: header block
+        &[0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0xFF],
+    ]);
+    assert_eq!(
+        tzx_blocks(tzx).unwrap(),
+        vec![
+            (0, TapeBlock::Pause { ms: 100 }),
+            (
+                2,
+                TapeBlock::Data {
+                    timings: DataBlockTimings::standard(0x00, 0),
+                    data: vec![0x00, 0xFF],
+                }
+            ),
+        ]
+    );
+}
@@ -0,0 +1,252 @@
+use expect_test::expect;
+use rustzx_core::{
+    host::{BufferCursor, Snapshot},
+    zx::keys::ZXKey,
+    RustzxSettings,
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+// `in_timing.*.z80` programs count `IN A, (n)` loop iterations during a single
+// frame for ports with different contention patterns (see test_data sources)
+
+const RESULTS_ADDR: u16 = 0x9000;
+const PORTS_COUNT: u16 = 4;
+
+fn measure_in_timing(test_name: &str, settings: RustzxSettings, snapshot: &str) -> Vec<u16> {
+    let mut tester = RustZXTester::new(test_name, settings);
+    tester.load_z80(snapshot);
+    tester.emulate_for(Duration::from_millis(200));
+
+    (0..PORTS_COUNT)
+        .map(|idx| {
+            let addr = RESULTS_ADDR + idx * 2;
+            u16::from_le_bytes([tester.peek(addr), tester.peek(addr + 1)])
+        })
+        .collect()
+}
+
+fn format_counts(counts: &[u16]) -> String {
+    let names = ["N:4", "N:1,C:3", "C:1,C:1,C:1,C:1", "C:1,C:3"];
+    names
+        .iter()
+        .zip(counts)
+        .map(|(name, count)| format!("{}: {}\n", name, count))
+        .collect()
+}
+
+#[test]
+fn in_timing_48k() {
+    let counts = measure_in_timing(
+        "in_timing_48k",
+        presets::settings_48k_nosound(),
+        "in_timing.48k.z80.gz",
+    );
+    // Contended IO cycles should always take more time
+    assert!(counts[1..].iter().all(|count| *count < counts[0]));
+    expect![[r#"
+        N:4: 2115
+        N:1,C:3: 2060
+        C:1,C:1,C:1,C:1: 1964
+        C:1,C:3: 2028
+    "#]]
+    .assert_eq(&format_counts(&counts));
+}
+
+#[test]
+fn in_timing_128k() {
+    let counts = measure_in_timing(
+        "in_timing_128k",
+        presets::settings_128k_nosound(),
+        "in_timing.128k.z80.gz",
+    );
+    assert!(counts[1..].iter().all(|count| *count < counts[0]));
+    expect![[r#"
+        N:4: 2146
+        N:1,C:3: 2099
+        C:1,C:1,C:1,C:1: 2010
+        C:1,C:3: 2087
+    "#]]
+    .assert_eq(&format_counts(&counts));
+}
+
+#[test]
+fn in_timing_plus3() {
+    let counts = measure_in_timing(
+        "in_timing_plus3",
+        presets::settings_plus3_nosound(),
+        "in_timing.plus3.z80.gz",
+    );
+    // IO is never contended on +3
+    assert!(counts.iter().all(|count| *count == counts[0]));
+    expect![[r#"
+        N:4: 2146
+        N:1,C:3: 2146
+        C:1,C:1,C:1,C:1: 2146
+        C:1,C:3: 2146
+    "#]]
+    .assert_eq(&format_counts(&counts));
+}
+
+const IO_PROGRAM_ADDR: u16 = 0x8000;
+
+/// Returns count of clocks taken by IO cycle of `IN A, (C)` instruction, which
+/// reads given `port` with the IO cycle starting at `start` frame clock.
+/// Instruction is executed from uncontended memory of SZX snapshot of the given
+/// machine (`1` - 48K, `2` - 128K)
+fn measure_io_cycle(settings: RustzxSettings, szx_machine: u8, port: u16, start: u32) -> usize {
+    fn push_chunk(szx: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+        szx.extend_from_slice(id);
+        szx.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        szx.extend_from_slice(data);
+    }
+
+    // Two M1 cycles of `IN A, (C)` take 8 clocks before IO cycle
+    let instruction_start = start - 8;
+    let mut regs = [0u8; 37];
+    regs[2..4].copy_from_slice(&port.to_le_bytes());
+    regs[20..22].copy_from_slice(&0xFF00u16.to_le_bytes());
+    regs[22..24].copy_from_slice(&IO_PROGRAM_ADDR.to_le_bytes());
+    regs[29..33].copy_from_slice(&instruction_start.to_le_bytes());
+
+    let mut szx = b"ZXST\x01\x04\x00\x00".to_vec();
+    szx[6] = szx_machine;
+    push_chunk(&mut szx, b"Z80R", &regs);
+    push_chunk(&mut szx, b"SPCR", &[0u8; 8]);
+    for page in [5, 2, 0] {
+        let mut ramp = vec![0u8, 0, page];
+        ramp.resize(3 + 16 * 1024, 0);
+        if page == 2 {
+            // IN A, (C); JR $
+            ramp[3..7].copy_from_slice(&[0xED, 0x78, 0x18, 0xFE]);
+        }
+        push_chunk(&mut szx, b"RAMP", &ramp);
+    }
+
+    let mut tester = RustZXTester::new("io_cycle", settings);
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Szx(BufferCursor::new(szx)))
+        .expect("Failed to load test SZX");
+    tester.emulate_until_breakpoint(IO_PROGRAM_ADDR + 2, Duration::from_millis(100));
+    tester.emulator().machine_state().frame_clocks - start as usize
+}
+
+/// Published IO cycle timings of N:4, N:1 C:3, C:1 C:1 C:1 C:1 and C:1 C:3
+/// patterns, when IO cycle starts at the given offset from the first contended
+/// clock of the frame. Contention delays are 6, 5, 4, 3, 2, 1, 0, 0 starting
+/// from the first contended clock
+const IO_CYCLE_TIMINGS: [(isize, [usize; 4]); 3] = [
+    // N:1 C:3 is delayed by 5 clocks, C:1 C:3 by 6 clocks, C:1 C:1 C:1 C:1 by
+    // 6 clocks at its first and third clocks
+    (0, [4, 9, 16, 10]),
+    // Only the third clock of C:1 C:1 C:1 C:1 hits non-zero delay
+    (6, [4, 4, 10, 4]),
+    // The first clock is not contended yet
+    (-1, [4, 10, 16, 10]),
+];
+
+/// Ports with N:4, N:1 C:3, C:1 C:1 C:1 C:1 and C:1 C:3 patterns
+const IO_PATTERN_PORTS: [u16; 4] = [0x80FF, 0x80FE, 0x40FF, 0x40FE];
+
+/// Checks IO cycle timings of the machine with given first contended clock
+fn check_io_cycle_timings(settings: fn() -> RustzxSettings, szx_machine: u8, first: usize) {
+    for port in IO_PATTERN_PORTS {
+        assert_eq!(measure_io_cycle(settings(), szx_machine, port, 1000), 4);
+    }
+    for (offset, timings) in IO_CYCLE_TIMINGS {
+        let start = first.wrapping_add_signed(offset) as u32;
+        for (port, expected) in IO_PATTERN_PORTS.into_iter().zip(timings) {
+            let clocks = measure_io_cycle(settings(), szx_machine, port, start);
+            assert_eq!(clocks, expected, "port {:04X} at {}", port, start);
+        }
+    }
+}
+
+#[test]
+fn io_cycle_timing_48k() {
+    check_io_cycle_timings(presets::settings_48k_nosound, 1, 14335);
+}
+
+#[test]
+fn io_cycle_timing_128k() {
+    check_io_cycle_timings(presets::settings_128k_nosound, 2, 14361);
+}
+
+const IR_LOOP_ADDR: u16 = 0x8000;
+const IR_LOOP_END_ADDR: u16 = 0x800E;
+
+/// Returns frame clocks spent by the loop of `INC HL` instructions, which
+/// perform cycles without memory request with IR on the address bus. Loop is
+/// started at the beginning of the frame from v3 Z80 snapshot with given
+/// hardware mode and z80 pages at 0x4000 and 0x8000
+fn measure_ir_loop(settings: RustzxSettings, hardware_mode: u8, pages: [u8; 2], i: u8) -> usize {
+    let mut z80 = vec![0u8; 30];
+    z80[8..10].copy_from_slice(&0xFF00u16.to_le_bytes());
+    z80[10] = i;
+    z80.extend_from_slice(&54u16.to_le_bytes());
+    let mut extra_header = [0u8; 54];
+    extra_header[0..2].copy_from_slice(&IR_LOOP_ADDR.to_le_bytes());
+    extra_header[2] = hardware_mode;
+    z80.extend_from_slice(&extra_header);
+
+    // LD B, 0; loop: 10 x INC HL; DJNZ loop; JR $
+    let mut program = vec![0x06, 0x00];
+    program.extend_from_slice(&[0x23; 10]);
+    program.extend_from_slice(&[0x10, 0xF4, 0x18, 0xFE]);
+    let mut program_page = vec![0u8; 16 * 1024];
+    program_page[..program.len()].copy_from_slice(&program);
+    for (page, data) in [(pages[0], vec![0u8; 16 * 1024]), (pages[1], program_page)] {
+        // Uncompressed block
+        z80.extend_from_slice(&[0xFF, 0xFF, page]);
+        z80.extend_from_slice(&data);
+    }
+
+    let mut tester = RustZXTester::new("ir_loop", settings);
+    tester
+        .emulator()
+        .load_snapshot(Snapshot::Z80(BufferCursor::new(z80)))
+        .expect("Failed to load test Z80");
+    tester.emulate_until_breakpoint(IR_LOOP_END_ADDR, Duration::from_millis(100));
+    tester.emulator().machine_state().frame_clocks
+}
+
+#[test]
+fn no_mreq_timing_48k() {
+    let settings = presets::settings_48k_nosound;
+    let uncontended = measure_ir_loop(settings(), 0, [8, 4], 0x80);
+    let contended = measure_ir_loop(settings(), 0, [8, 4], 0x40);
+    assert!(contended > uncontended);
+}
+
+#[test]
+fn no_mreq_timing_plus3() {
+    // +2A/+3 gate array contends only memory requests, bank 5 at 0x4000 is
+    // contended for them
+    let settings = presets::settings_plus3_nosound;
+    let uncontended = measure_ir_loop(settings(), 7, [8, 5], 0x80);
+    let contended = measure_ir_loop(settings(), 7, [8, 5], 0x40);
+    assert_eq!(contended, uncontended);
+}
+
+#[test]
+fn halt_ratio_48k() {
+    let mut tester = RustZXTester::new("halt_ratio_48k", presets::settings_48k_nosound());
+    tester.emulate_for(Duration::from_secs(3));
+    // ROM keyboard routine busy-waits for a key
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().last_frame_halt_ratio(), 0.0);
+
+    // PAUSE 0 waits for a key in the HALT loop
+    tester.send_keystrokes(
+        &[&[ZXKey::M], &[ZXKey::N0], &[ZXKey::Enter]],
+        Duration::from_millis(100),
+    );
+    tester.emulate_for(Duration::from_millis(100));
+    tester.emulate_frame();
+    assert!(tester.emulator().last_frame_halt_ratio() > 0.9);
+
+    tester.send_keystrokes(&[&[ZXKey::Space]], Duration::from_millis(100));
+    tester.emulate_frame();
+    assert_eq!(tester.emulator().last_frame_halt_ratio(), 0.0);
+}
@@ -0,0 +1,59 @@
+use expect_test::expect;
+use rustzx_core::BorderMode;
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+#[test]
+fn border_modes() {
+    let run = |border_mode| {
+        let mut settings = presets::settings_48k_nosound();
+        settings.tape_fastload_enabled = false;
+        settings.border_mode = border_mode;
+
+        let mut tester = RustZXTester::new("border_modes", settings);
+        tester.load_tap("simple_tape.tap.gz");
+        tester.emulator().play_tape();
+        tester.emulate_for(Duration::from_millis(2000));
+        tester
+    };
+
+    // Border is filled with the last color in solid color mode and is not
+    // updated in canvas-only mode, while screen is not affected
+    for border_mode in [
+        BorderMode::Precise,
+        BorderMode::SolidColor,
+        BorderMode::CanvasOnly,
+    ] {
+        run(border_mode).expect_screen(
+            "empty",
+            expect![[r#"nI+vo8GaRwKwWTPTP2f22Wcgm9nEwMlm16+Cmzird2w="#]],
+        );
+    }
+    run(BorderMode::SolidColor).expect_border(
+        "solid_color",
+        expect![[r#"u6sjPawE2sxFRtsuWv29+BwTigc9uER9NaiyxLkVaF0="#]],
+    );
+    run(BorderMode::CanvasOnly).expect_border(
+        "canvas_only",
+        expect![[r#"uVCahnzh1mk7ctifK508eiz/BaoCWrihPHcoZjmKjWc="#]],
+    );
+}
+
+#[test]
+fn frame_buffers_swap() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+
+    let mut tester = RustZXTester::new("frame_buffers_swap", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulator().play_tape();
+
+    // Border stripes change every frame, so taken frame should be exactly the
+    // last completed one
+    for _ in 0..3 {
+        tester.emulate_frame();
+        let expected = (tester.get_screen(), tester.get_border());
+        assert!(tester.take_frame() == Some(expected));
+        assert!(tester.take_frame().is_none());
+    }
+}
@@ -0,0 +1,154 @@
+//! Ready-to-use frame buffer implementation, which produces plain RGBA pixel data.
+//! Suitable for direct upload to GPU textures of the game engines and GUI frameworks.
+use crate::palette::rgba::ORIGINAL as DEFAULT_PALETTE;
+use alloc::{vec, vec::Vec};
+use rustzx_core::{
+    host::{FrameBuffer, FrameBufferSource},
+    zx::{
+        constants::{CANVAS_HEIGHT, CANVAS_WIDTH, CANVAS_X, CANVAS_Y, SCREEN_HEIGHT, SCREEN_WIDTH},
+        video::colors::{ZXBrightness, ZXColor},
+    },
+};
+
+pub const RGBA_PIXEL_SIZE: usize = 4;
+/// Size of the full frame (canvas with border) composed by [compose_rgba_frame]
+pub const RGBA_FRAME_SIZE: usize = SCREEN_WIDTH * SCREEN_HEIGHT * RGBA_PIXEL_SIZE;
+
+#[derive(Clone)]
+pub struct RgbaFrameBufferContext {
+    /// 8 normal colors followed by 8 bright colors
+    pub palette: [[u8; 4]; 16],
+}
+
+impl Default for RgbaFrameBufferContext {
+    fn default() -> Self {
+        Self {
+            palette: DEFAULT_PALETTE,
+        }
+    }
+}
+
+/// Frame buffer with 8-bit RGBA pixel layout, rows are tightly packed
+pub struct RgbaFrameBuffer {
+    buffer: Vec<u8>,
+    palette: [[u8; 4]; 16],
+    width: usize,
+    height: usize,
+}
+
+impl FrameBuffer for RgbaFrameBuffer {
+    type Context = RgbaFrameBufferContext;
+
+    fn new(
+        width: usize,
+        height: usize,
+        _source: FrameBufferSource,
+        context: Self::Context,
+    ) -> Self {
+        Self {
+            buffer: vec![0u8; width * height * RGBA_PIXEL_SIZE],
+            palette: context.palette,
+            width,
+            height,
+        }
+    }
+
+    fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness) {
+        let pos = (y * self.width + x) * RGBA_PIXEL_SIZE;
+        let index = (color as u8 + brightness as u8 * 8) as usize;
+        self.buffer[pos..pos + RGBA_PIXEL_SIZE].copy_from_slice(&self.palette[index]);
+    }
+}
+
+impl RgbaFrameBuffer {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns RGBA pixel data, `width * height * 4` bytes
+    pub fn rgba_data(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+/// Composes full frame from the border buffer and canvas buffer produced by emulator.
+/// `target` size should be [RGBA_FRAME_SIZE]. Single pixel border buffer of
+/// `SolidColor` and `CanvasOnly` border modes fills the whole border area.
+pub fn compose_rgba_frame(border: &RgbaFrameBuffer, canvas: &RgbaFrameBuffer, target: &mut [u8]) {
+    if border.rgba_data().len() == RGBA_PIXEL_SIZE {
+        for pixel in target.chunks_exact_mut(RGBA_PIXEL_SIZE) {
+            pixel.copy_from_slice(border.rgba_data());
+        }
+    } else {
+        target.copy_from_slice(border.rgba_data());
+    }
+    let canvas_row_size = CANVAS_WIDTH * RGBA_PIXEL_SIZE;
+    for (y, row) in canvas
+        .rgba_data()
+        .chunks_exact(canvas_row_size)
+        .take(CANVAS_HEIGHT)
+        .enumerate()
+    {
+        let offset = ((CANVAS_Y + y) * SCREEN_WIDTH + CANVAS_X) * RGBA_PIXEL_SIZE;
+        target[offset..offset + canvas_row_size].copy_from_slice(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(width: usize, height: usize, color: Option<ZXColor>) -> RgbaFrameBuffer {
+        let mut buffer =
+            RgbaFrameBuffer::new(width, height, FrameBufferSource::Border, Default::default());
+        if let Some(color) = color {
+            for y in 0..height {
+                for x in 0..width {
+                    buffer.set_color(x, y, color, ZXBrightness::Normal);
+                }
+            }
+        }
+        buffer
+    }
+
+    fn compose(border: &RgbaFrameBuffer) -> Vec<u8> {
+        let canvas = buffer(CANVAS_WIDTH, CANVAS_HEIGHT, Some(ZXColor::Blue));
+        let mut frame = vec![0xAA; RGBA_FRAME_SIZE];
+        compose_rgba_frame(border, &canvas, &mut frame);
+        frame
+    }
+
+    fn pixel(frame: &[u8], x: usize, y: usize) -> &[u8] {
+        let pos = (y * SCREEN_WIDTH + x) * RGBA_PIXEL_SIZE;
+        &frame[pos..pos + RGBA_PIXEL_SIZE]
+    }
+
+    #[test]
+    fn compose_frame_in_each_border_mode() {
+        let red = DEFAULT_PALETTE[ZXColor::Red as usize];
+        let blue = DEFAULT_PALETTE[ZXColor::Blue as usize];
+        let canvas_pixel = (CANVAS_X + 10, CANVAS_Y + 10);
+        let last_pixel = (SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1);
+
+        // Precise mode: border buffer covers the whole frame
+        let frame = compose(&buffer(SCREEN_WIDTH, SCREEN_HEIGHT, Some(ZXColor::Red)));
+        assert_eq!(pixel(&frame, 0, 0), red);
+        assert_eq!(pixel(&frame, last_pixel.0, last_pixel.1), red);
+        assert_eq!(pixel(&frame, canvas_pixel.0, canvas_pixel.1), blue);
+
+        // Solid color mode: single pixel is the color of the whole border
+        let frame = compose(&buffer(1, 1, Some(ZXColor::Red)));
+        assert_eq!(pixel(&frame, 0, 0), red);
+        assert_eq!(pixel(&frame, last_pixel.0, last_pixel.1), red);
+        assert_eq!(pixel(&frame, canvas_pixel.0, canvas_pixel.1), blue);
+
+        // Canvas-only mode: border pixel is never set
+        let frame = compose(&buffer(1, 1, None));
+        assert_eq!(pixel(&frame, 0, 0), [0; 4]);
+        assert_eq!(pixel(&frame, canvas_pixel.0, canvas_pixel.1), blue);
+    }
+}
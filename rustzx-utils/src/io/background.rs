@@ -0,0 +1,190 @@
+use rustzx_core::{
+    error::{Error, IoError},
+    host::{TapeBlock, TapeBlockSource},
+    Result,
+};
+
+use std::{
+    fmt::Display,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// Count of blocks which worker is allowed to parse ahead
+const READ_AHEAD_BLOCKS: usize = 4;
+
+enum Command {
+    NextBlock,
+    Rewind,
+}
+
+struct Response {
+    /// Incremented on each rewind, allows to drop blocks which were parsed
+    /// before the rewind
+    generation: usize,
+    block: Result<Option<(usize, TapeBlock)>>,
+}
+
+/// Tape block source which is parsed by the worker thread. Emulation thread
+/// only sends commands (next block, rewind) to the worker and receives
+/// already parsed blocks, so slow operations (unpacking, parsing and seeking
+/// of large tape images) don't stall the emulation thread. Worker keeps a few
+/// blocks parsed ahead, so receiving of the block waits only if worker had no time to
+/// parse them yet; rewind never blocks.
+pub struct BackgroundTape {
+    commands: Sender<Command>,
+    responses: Receiver<Response>,
+    generation: usize,
+}
+
+impl BackgroundTape {
+    /// Spawns worker thread which creates block source via `open` and then
+    /// parses it
+    pub fn spawn<F, S, E>(open: F) -> Self
+    where
+        F: FnOnce() -> std::result::Result<S, E> + Send + 'static,
+        S: TapeBlockSource,
+        E: Display,
+    {
+        let (commands, commands_rx) = mpsc::channel();
+        let (responses_tx, responses) = mpsc::channel();
+        thread::spawn(move || match open() {
+            Ok(source) => worker(source, commands_rx, responses_tx),
+            Err(e) => log::error!("Failed to open tape: {:#}", e),
+        });
+
+        let tape = Self {
+            commands,
+            responses,
+            generation: 0,
+        };
+        tape.request_blocks(READ_AHEAD_BLOCKS);
+        tape
+    }
+
+    fn request_blocks(&self, count: usize) {
+        for _ in 0..count {
+            // Worker errors are reported on the next receive
+            let _ = self.commands.send(Command::NextBlock);
+        }
+    }
+}
+
+fn worker(
+    mut source: impl TapeBlockSource,
+    commands: Receiver<Command>,
+    responses: Sender<Response>,
+) {
+    let mut generation = 0;
+    let mut rewind_error = None;
+    for command in commands {
+        match command {
+            Command::NextBlock => {
+                let block = match rewind_error.take() {
+                    Some(e) => Err(e),
+                    None => source.next_block(),
+                };
+                let response = Response { generation, block };
+                // Stop if tape was dropped on the receiving side
+                if responses.send(response).is_err() {
+                    return;
+                }
+            }
+            Command::Rewind => {
+                generation += 1;
+                rewind_error = source.rewind().err();
+            }
+        }
+    }
+}
+
+impl TapeBlockSource for BackgroundTape {
+    fn next_block(&mut self) -> Result<Option<(usize, TapeBlock)>> {
+        loop {
+            let response = self
+                .responses
+                .recv()
+                .map_err(|_| Error::from(IoError::HostAssetImplFailed))?;
+            if response.generation == self.generation {
+                self.request_blocks(1);
+                return response.block;
+            }
+        }
+    }
+
+    fn rewind(&mut self) -> Result<()> {
+        self.generation += 1;
+        let _ = self.commands.send(Command::Rewind);
+        self.request_blocks(READ_AHEAD_BLOCKS);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustzx_core::host::{BufferCursor, TapParser, TzxParser};
+    use std::{vec, vec::Vec};
+
+    fn tap_image(blocks: &[&[u8]]) -> Vec<u8> {
+        let mut tap = vec![];
+        for block in blocks {
+            tap.extend_from_slice(&(block.len() as u16).to_le_bytes());
+            tap.extend_from_slice(block);
+        }
+        tap
+    }
+
+    fn collect_blocks(source: &mut impl TapeBlockSource) -> Vec<(usize, TapeBlock)> {
+        std::iter::from_fn(|| source.next_block().unwrap()).collect()
+    }
+
+    #[test]
+    fn background_tape_matches_parser() {
+        let tap = tap_image(&[&[0x00, 1, 2, 3], &[0xFF, 4, 5], &[0xFF; 600]]);
+        let expected = collect_blocks(&mut TapParser::from_asset(BufferCursor::new(tap.clone())));
+
+        let mut tape = BackgroundTape::spawn(move || {
+            Ok::<_, Error>(TapParser::from_asset(BufferCursor::new(tap)))
+        });
+        assert_eq!(collect_blocks(&mut tape), expected);
+
+        tape.rewind().unwrap();
+        assert_eq!(collect_blocks(&mut tape), expected);
+    }
+
+    #[test]
+    fn background_tape_drops_blocks_parsed_before_rewind() {
+        let blocks = (0..16u8).map(|i| vec![0xFF, i]).collect::<Vec<_>>();
+        let tap = tap_image(&blocks.iter().map(Vec::as_slice).collect::<Vec<_>>());
+        let mut tape = BackgroundTape::spawn(move || {
+            Ok::<_, Error>(TapParser::from_asset(BufferCursor::new(tap)))
+        });
+
+        for expected_index in 0..3 {
+            let (index, _) = tape.next_block().unwrap().unwrap();
+            assert_eq!(index, expected_index);
+        }
+        tape.rewind().unwrap();
+        let (index, _) = tape.next_block().unwrap().unwrap();
+        assert_eq!(index, 0);
+    }
+
+    #[test]
+    fn background_tape_reports_parse_errors() {
+        let mut tape = BackgroundTape::spawn(|| {
+            Ok::<_, Error>(TzxParser::from_asset(BufferCursor::new(
+                b"NotATape!!".to_vec(),
+            )))
+        });
+        assert!(tape.next_block().is_err());
+    }
+
+    #[test]
+    fn background_tape_reports_open_failure() {
+        let mut tape = BackgroundTape::spawn(|| {
+            Err::<TapParser<BufferCursor<Vec<u8>>>, _>(IoError::HostAssetImplFailed)
+        });
+        assert!(tape.next_block().is_err());
+    }
+}
@@ -0,0 +1,242 @@
+use rustzx_core::{
+    error::IoError,
+    host::{DiskImage, DISK_SECTOR_SIZE},
+};
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    vec,
+    vec::Vec,
+};
+
+const HDF_SIGNATURE: &[u8] = b"RS-IDE\x1A";
+const HDF_HEADER_SIZE: usize = 11;
+const HDF_FLAGS_OFFSET: usize = 8;
+const HDF_DATA_OFFSET_OFFSET: usize = 9;
+const HDF_FLAG_HALVED_SECTORS: u8 = 0x01;
+
+/// Disk image backed by the file. Both raw images and HDF (RS-IDE) images
+/// are supported. For HDF images with halved sectors only low byte of each
+/// data word is stored in the file
+pub struct FileDiskImage {
+    file: File,
+    data_offset: u64,
+    halved: bool,
+    sector_count: u32,
+    /// Sectors written to the read-only image, `None` if writes go to the file
+    written_sectors: Option<HashMap<u32, [u8; DISK_SECTOR_SIZE]>>,
+}
+
+impl FileDiskImage {
+    pub fn new(file: File) -> io::Result<Self> {
+        Self::open(file, false)
+    }
+
+    /// Creates image which never modifies the file: written sectors are kept
+    /// in memory, so several emulators can share the same image file
+    pub fn new_read_only(file: File) -> io::Result<Self> {
+        Self::open(file, true)
+    }
+
+    fn open(mut file: File, read_only: bool) -> io::Result<Self> {
+        let file_size = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(0))?;
+
+        let mut header = [0u8; HDF_HEADER_SIZE];
+        let is_hdf = file_size >= HDF_HEADER_SIZE as u64
+            && file.read_exact(&mut header).is_ok()
+            && header.starts_with(HDF_SIGNATURE);
+
+        let (data_offset, halved) = if is_hdf {
+            let offset = u16::from_le_bytes([
+                header[HDF_DATA_OFFSET_OFFSET],
+                header[HDF_DATA_OFFSET_OFFSET + 1],
+            ]);
+            let halved = header[HDF_FLAGS_OFFSET] & HDF_FLAG_HALVED_SECTORS != 0;
+            (offset as u64, halved)
+        } else {
+            (0, false)
+        };
+
+        let stored_sector_size = if halved {
+            DISK_SECTOR_SIZE / 2
+        } else {
+            DISK_SECTOR_SIZE
+        };
+        let sector_count = file_size.saturating_sub(data_offset) / stored_sector_size as u64;
+
+        Ok(Self {
+            file,
+            data_offset,
+            halved,
+            sector_count: sector_count.min(u32::MAX as u64) as u32,
+            written_sectors: read_only.then(HashMap::new),
+        })
+    }
+
+    fn stored_sector_size(&self) -> usize {
+        if self.halved {
+            DISK_SECTOR_SIZE / 2
+        } else {
+            DISK_SECTOR_SIZE
+        }
+    }
+
+    fn seek_sector(&mut self, lba: u32) -> Result<(), IoError> {
+        let offset = self.data_offset + lba as u64 * self.stored_sector_size() as u64;
+        self.file.seek(SeekFrom::Start(offset)).map_err(|e| {
+            log::error!("Failed to seek disk image: {}", e);
+            IoError::HostAssetImplFailed
+        })?;
+        Ok(())
+    }
+}
+
+impl DiskImage for FileDiskImage {
+    fn sector_count(&self) -> u32 {
+        self.sector_count
+    }
+
+    fn read_sector(
+        &mut self,
+        lba: u32,
+        buffer: &mut [u8; DISK_SECTOR_SIZE],
+    ) -> Result<(), IoError> {
+        if let Some(sector) = self
+            .written_sectors
+            .as_ref()
+            .and_then(|sectors| sectors.get(&lba))
+        {
+            buffer.copy_from_slice(sector);
+            return Ok(());
+        }
+        self.seek_sector(lba)?;
+        let mut stored = vec![0u8; self.stored_sector_size()];
+        self.file.read_exact(&mut stored).map_err(|e| {
+            log::error!("Failed to read disk image sector: {}", e);
+            IoError::HostAssetImplFailed
+        })?;
+        if self.halved {
+            for (word, byte) in buffer.chunks_exact_mut(2).zip(stored) {
+                word[0] = byte;
+                word[1] = 0;
+            }
+        } else {
+            buffer.copy_from_slice(&stored);
+        }
+        Ok(())
+    }
+
+    fn write_sector(&mut self, lba: u32, buffer: &[u8; DISK_SECTOR_SIZE]) -> Result<(), IoError> {
+        if let Some(sectors) = &mut self.written_sectors {
+            if lba >= self.sector_count {
+                return Err(IoError::UnexpectedEof);
+            }
+            // Halved sector keeps only low bytes, as if it was written to the file
+            let mut sector = *buffer;
+            if self.halved {
+                sector
+                    .iter_mut()
+                    .skip(1)
+                    .step_by(2)
+                    .for_each(|byte| *byte = 0);
+            }
+            sectors.insert(lba, sector);
+            return Ok(());
+        }
+        self.seek_sector(lba)?;
+        let stored: Vec<u8> = if self.halved {
+            buffer.iter().step_by(2).copied().collect()
+        } else {
+            buffer.to_vec()
+        };
+        self.file.write_all(&stored).map_err(|e| {
+            log::error!("Failed to write disk image sector: {}", e);
+            IoError::HostAssetImplFailed
+        })
+    }
+}
+
+/// In-memory raw disk image
+pub struct BufferDiskImage {
+    data: Vec<u8>,
+}
+
+impl BufferDiskImage {
+    /// Creates new zero-filled disk image with the given count of sectors
+    pub fn new(sector_count: u32) -> Self {
+        Self {
+            data: vec![0; sector_count as usize * DISK_SECTOR_SIZE],
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    fn sector_range(&self, lba: u32) -> Result<core::ops::Range<usize>, IoError> {
+        let start = lba as usize * DISK_SECTOR_SIZE;
+        let end = start + DISK_SECTOR_SIZE;
+        if end > self.data.len() {
+            return Err(IoError::UnexpectedEof);
+        }
+        Ok(start..end)
+    }
+}
+
+impl DiskImage for BufferDiskImage {
+    fn sector_count(&self) -> u32 {
+        (self.data.len() / DISK_SECTOR_SIZE) as u32
+    }
+
+    fn read_sector(
+        &mut self,
+        lba: u32,
+        buffer: &mut [u8; DISK_SECTOR_SIZE],
+    ) -> Result<(), IoError> {
+        let range = self.sector_range(lba)?;
+        buffer.copy_from_slice(&self.data[range]);
+        Ok(())
+    }
+
+    fn write_sector(&mut self, lba: u32, buffer: &[u8; DISK_SECTOR_SIZE]) -> Result<(), IoError> {
+        let range = self.sector_range(lba)?;
+        self.data[range].copy_from_slice(buffer);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, format, fs, process};
+
+    #[test]
+    fn read_only_image_keeps_file_unchanged() {
+        let path = env::temp_dir().join(format!("rustzx-disk-{}.img", process::id()));
+        let image = (0..4 * DISK_SECTOR_SIZE)
+            .map(|i| (i / DISK_SECTOR_SIZE) as u8)
+            .collect::<Vec<_>>();
+        fs::write(&path, &image).unwrap();
+
+        let mut disk = FileDiskImage::new_read_only(File::open(&path).unwrap()).unwrap();
+        assert_eq!(disk.sector_count(), 4);
+        let mut sector = [0u8; DISK_SECTOR_SIZE];
+        disk.read_sector(2, &mut sector).unwrap();
+        assert_eq!(sector, [2; DISK_SECTOR_SIZE]);
+
+        disk.write_sector(2, &[0xAA; DISK_SECTOR_SIZE]).unwrap();
+        disk.read_sector(2, &mut sector).unwrap();
+        assert_eq!(sector, [0xAA; DISK_SECTOR_SIZE]);
+        assert!(disk.write_sector(4, &[0xAA; DISK_SECTOR_SIZE]).is_err());
+
+        assert_eq!(fs::read(&path).unwrap(), image);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,3 +1,5 @@
+mod background;
+mod disk;
 mod file;
 mod gzip;
 
@@ -8,12 +10,16 @@ use rustzx_core::{
 
 use std::boxed::Box;
 
+pub use background::BackgroundTape;
+pub use disk::{BufferDiskImage, FileDiskImage};
 pub use file::FileAsset;
 pub use gzip::GzipAsset;
 
-pub trait DynamicAssetImpl: LoadableAsset + SeekableAsset {}
+/// Asset which can be type-erased into [`DynamicAsset`]. Assets are `Send`,
+/// so opened asset can be passed to the worker thread (e.g. [`BackgroundTape`])
+pub trait DynamicAssetImpl: LoadableAsset + SeekableAsset + Send {}
 
-impl<T: AsRef<[u8]>> DynamicAssetImpl for BufferCursor<T> {}
+impl<T: AsRef<[u8]> + Send> DynamicAssetImpl for BufferCursor<T> {}
 
 pub struct DynamicAsset {
     inner: Box<dyn DynamicAssetImpl>,
@@ -3,9 +3,13 @@
 #[cfg(feature = "std")]
 extern crate std;
 
+extern crate alloc;
+
+pub mod frame_buffer;
 pub mod palette;
 #[cfg(feature = "std")]
 pub mod stopwatch;
+pub mod svg;
 
 #[cfg(all(feature = "std"))]
 pub mod io;
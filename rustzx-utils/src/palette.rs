@@ -19,4 +19,151 @@ pub mod rgba {
         0xFFFF00FF_u32.to_be_bytes(),
         0xFFFFFFFF_u32.to_be_bytes(),
     ];
+
+    /// Color-blind safe palette for deuteranopia (green deficiency). Hues are kept close to
+    /// the original ones, while colors stay distinguishable and ordered by lightness as on
+    /// the original hardware, so bright/dark contrast of the game graphics is preserved
+    pub const DEUTERANOPIA: [[u8; 4]; 16] = [
+        // normal
+        0x000000FF_u32.to_be_bytes(),
+        0x0A0745FF_u32.to_be_bytes(),
+        0x5B1801FF_u32.to_be_bytes(),
+        0x8D0563FF_u32.to_be_bytes(),
+        0x06911DFF_u32.to_be_bytes(),
+        0x08A0E1FF_u32.to_be_bytes(),
+        0xBFC505FF_u32.to_be_bytes(),
+        0xCDCDCDFF_u32.to_be_bytes(),
+        // bright
+        0x000000FF_u32.to_be_bytes(),
+        0x0C0956FF_u32.to_be_bytes(),
+        0x711E01FF_u32.to_be_bytes(),
+        0xAF067BFF_u32.to_be_bytes(),
+        0x07B424FF_u32.to_be_bytes(),
+        0x0AC7FFFF_u32.to_be_bytes(),
+        0xEEF506FF_u32.to_be_bytes(),
+        0xFFFFFFFF_u32.to_be_bytes(),
+    ];
+
+    /// Color-blind safe palette for protanopia (red deficiency), see [DEUTERANOPIA] for
+    /// details. Reds are lighter, as they look too dark for protanopes in original palette
+    pub const PROTANOPIA: [[u8; 4]; 16] = [
+        // normal
+        0x000000FF_u32.to_be_bytes(),
+        0x010249FF_u32.to_be_bytes(),
+        0x9F0A0BFF_u32.to_be_bytes(),
+        0xC411C2FF_u32.to_be_bytes(),
+        0x058914FF_u32.to_be_bytes(),
+        0x17A0DAFF_u32.to_be_bytes(),
+        0xF2BD0DFF_u32.to_be_bytes(),
+        0xCDCDCDFF_u32.to_be_bytes(),
+        // bright
+        0x000000FF_u32.to_be_bytes(),
+        0x01025BFF_u32.to_be_bytes(),
+        0xC60C0EFF_u32.to_be_bytes(),
+        0xF415F1FF_u32.to_be_bytes(),
+        0x06AA19FF_u32.to_be_bytes(),
+        0x1DC7FFFF_u32.to_be_bytes(),
+        0xFFEB10FF_u32.to_be_bytes(),
+        0xFFFFFFFF_u32.to_be_bytes(),
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::rgba::*;
+
+    type Matrix = [[f32; 3]; 3];
+
+    // Full severity color vision deficiency simulation matrices for linear RGB,
+    // from Machado, Oliveira and Fernandes (2009)
+    const PROTANOPIA_SIM: Matrix = [
+        [0.152286, 1.052583, -0.204868],
+        [0.114503, 0.786281, 0.099216],
+        [-0.003882, -0.048116, 1.051998],
+    ];
+    const DEUTERANOPIA_SIM: Matrix = [
+        [0.367322, 0.860646, -0.227968],
+        [0.280085, 0.672501, 0.047413],
+        [-0.011820, 0.042940, 0.968881],
+    ];
+    const NORMAL_VISION: Matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+    /// Minimal CIE76 color difference between colors of the same brightness
+    const MIN_DELTA_E: f32 = 30.0;
+
+    fn srgb_to_linear(c: u8) -> f32 {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    /// Converts color to CIELAB as seen with given color vision
+    fn perceived_lab(vision: &Matrix, color: [u8; 4]) -> [f32; 3] {
+        let rgb = [0, 1, 2].map(|i| srgb_to_linear(color[i]));
+        let [r, g, b] =
+            vision.map(|row| (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]).clamp(0.0, 1.0));
+        let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+        let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+        let f = |t: f32| {
+            if t > 0.008856 {
+                t.cbrt()
+            } else {
+                7.787 * t + 16.0 / 116.0
+            }
+        };
+        [
+            116.0 * f(y) - 16.0,
+            500.0 * (f(x) - f(y)),
+            200.0 * (f(y) - f(z)),
+        ]
+    }
+
+    /// Checks that colors are distinguishable and ordered by lightness (as their
+    /// codes are) for given vision. Returns minimal color difference on success
+    fn check_palette(palette: &[[u8; 4]; 16], vision: &Matrix) -> Option<f32> {
+        let mut min_delta = f32::MAX;
+        for colors in palette.chunks(8) {
+            let lab = colors
+                .iter()
+                .map(|c| perceived_lab(vision, *c))
+                .collect::<std::vec::Vec<_>>();
+            if lab.windows(2).any(|pair| pair[0][0] >= pair[1][0]) {
+                return None;
+            }
+            for i in 0..lab.len() {
+                for j in i + 1..lab.len() {
+                    let delta = (0..3)
+                        .map(|k| (lab[i][k] - lab[j][k]).powi(2))
+                        .sum::<f32>()
+                        .sqrt();
+                    min_delta = min_delta.min(delta);
+                }
+            }
+        }
+        Some(min_delta)
+    }
+
+    #[test]
+    fn color_blind_palettes_are_distinguishable() {
+        for (palette, vision) in [
+            (&DEUTERANOPIA, &DEUTERANOPIA_SIM),
+            (&PROTANOPIA, &PROTANOPIA_SIM),
+        ] {
+            assert!(check_palette(palette, vision).unwrap() >= MIN_DELTA_E);
+            assert!(check_palette(palette, &NORMAL_VISION).unwrap() >= MIN_DELTA_E);
+        }
+    }
+
+    #[test]
+    fn original_palette_is_not_color_blind_safe() {
+        // Red is almost indistinguishable from black for protanopes
+        assert!(check_palette(&ORIGINAL, &PROTANOPIA_SIM).is_none());
+        assert!(check_palette(&ORIGINAL, &DEUTERANOPIA_SIM).unwrap() < MIN_DELTA_E);
+    }
 }
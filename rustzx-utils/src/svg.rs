@@ -0,0 +1,258 @@
+//! Vector export of the frame. Areas of the same color are traced into
+//! outlines and written as SVG paths, so the image can be scaled to any size
+//! without blur (e.g. for high-resolution prints of Spectrum artwork)
+use crate::frame_buffer::RGBA_PIXEL_SIZE;
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt::Write;
+
+const NO_EDGE: u32 = u32::MAX;
+
+/// Converts RGBA image to SVG document. `scale` sets the default display size
+/// of the document, alpha channel is ignored
+pub fn rgba_to_svg(data: &[u8], width: usize, height: usize, scale: usize) -> String {
+    let mut colors: Vec<[u8; 3]> = Vec::new();
+    let mut counts: Vec<usize> = Vec::new();
+    let pixels = data
+        .chunks_exact(RGBA_PIXEL_SIZE)
+        .take(width * height)
+        .map(|pixel| {
+            let rgb = [pixel[0], pixel[1], pixel[2]];
+            let index = match colors.iter().position(|color| *color == rgb) {
+                Some(index) => index,
+                None => {
+                    colors.push(rgb);
+                    counts.push(0);
+                    colors.len() - 1
+                }
+            };
+            counts[index] += 1;
+            index
+        })
+        .collect::<Vec<_>>();
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" \
+         width=\"{sw}\" height=\"{sh}\" shape-rendering=\"crispEdges\">",
+        w = width,
+        h = height,
+        sw = width * scale,
+        sh = height * scale,
+    );
+    // The most used color is drawn as background to keep the document small
+    let background = (0..colors.len()).max_by_key(|index| counts[*index]);
+    if let Some(background) = background {
+        let _ = writeln!(
+            svg,
+            "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            width,
+            height,
+            hex_color(colors[background])
+        );
+    }
+    let mut tracer = OutlineTracer::new(width, height);
+    for (index, color) in colors.iter().enumerate() {
+        if Some(index) == background {
+            continue;
+        }
+        let _ = write!(svg, "<path fill=\"{}\" d=\"", hex_color(*color));
+        tracer.trace(&pixels, index, &mut svg);
+        svg.push_str("\"/>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn hex_color(rgb: [u8; 3]) -> String {
+    let mut s = String::with_capacity(7);
+    let _ = write!(s, "#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]);
+    s
+}
+
+/// Traces outlines of the same-colored areas on the grid of pixel corners.
+/// Each boundary side of a pixel becomes an edge directed clockwise around
+/// the pixel, edges are then chained into closed loops. Hole outlines get the
+/// opposite direction, so areas are filled correctly with nonzero fill rule
+struct OutlineTracer {
+    width: usize,
+    height: usize,
+    /// Up to two outgoing edges (end vertex indices) for each grid vertex,
+    /// vertex is shared by two edges of the same loop only in pixel corners
+    /// touching diagonally
+    edges: Vec<[u32; 2]>,
+}
+
+impl OutlineTracer {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            edges: vec![[NO_EDGE; 2]; (width + 1) * (height + 1)],
+        }
+    }
+
+    fn vertex(&self, x: usize, y: usize) -> u32 {
+        (y * (self.width + 1) + x) as u32
+    }
+
+    fn add_edge(&mut self, from: u32, to: u32) {
+        let slots = &mut self.edges[from as usize];
+        let slot = if slots[0] == NO_EDGE { 0 } else { 1 };
+        slots[slot] = to;
+    }
+
+    fn coords(&self, vertex: u32) -> (i32, i32) {
+        let stride = self.width as u32 + 1;
+        ((vertex % stride) as i32, (vertex / stride) as i32)
+    }
+
+    /// Takes outgoing edge of the vertex, edge in `direction` is preferred
+    fn take_edge(&mut self, from: u32, direction: (i32, i32)) -> Option<u32> {
+        let (x, y) = self.coords(from);
+        let slots = self.edges[from as usize];
+        let slot = (0..2)
+            .filter(|slot| slots[*slot] != NO_EDGE)
+            .min_by_key(|slot| {
+                let (tx, ty) = self.coords(slots[*slot]);
+                (tx - x, ty - y) != direction
+            })?;
+        Some(core::mem::replace(
+            &mut self.edges[from as usize][slot],
+            NO_EDGE,
+        ))
+    }
+
+    /// Writes path data of all outlines of the color `index`
+    fn trace(&mut self, pixels: &[usize], index: usize, out: &mut String) {
+        let (width, height) = (self.width, self.height);
+        let filled = |x: isize, y: isize| {
+            x >= 0
+                && y >= 0
+                && (x as usize) < width
+                && (y as usize) < height
+                && pixels[y as usize * width + x as usize] == index
+        };
+        let mut starts = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let (ix, iy) = (x as isize, y as isize);
+                if !filled(ix, iy) {
+                    continue;
+                }
+                let top_left = self.vertex(x, y);
+                let top_right = self.vertex(x + 1, y);
+                let bottom_right = self.vertex(x + 1, y + 1);
+                let bottom_left = self.vertex(x, y + 1);
+                if !filled(ix, iy - 1) {
+                    self.add_edge(top_left, top_right);
+                    starts.push(top_left);
+                }
+                if !filled(ix + 1, iy) {
+                    self.add_edge(top_right, bottom_right);
+                }
+                if !filled(ix, iy + 1) {
+                    self.add_edge(bottom_right, bottom_left);
+                }
+                if !filled(ix - 1, iy) {
+                    self.add_edge(bottom_left, top_left);
+                }
+            }
+        }
+
+        // Every loop has at least one top edge, so loops are started from them
+        for start in starts {
+            let mut points = Vec::new();
+            let mut vertex = start;
+            // Loop is started from the top edge, which goes right. Loops of
+            // diagonally touching pixels are kept separate by turning right
+            // (inside the pixel) in the shared corner
+            let mut direction = (1, 0);
+            while let Some(next) = self.take_edge(vertex, direction) {
+                let (x, y) = self.coords(vertex);
+                let (nx, ny) = self.coords(next);
+                direction = (-(ny - y), nx - x);
+                points.push(vertex);
+                vertex = next;
+            }
+            if !points.is_empty() {
+                self.write_loop(&points, out);
+            }
+        }
+    }
+
+    /// Writes closed loop as path commands, points on the straight lines are
+    /// skipped
+    fn write_loop(&self, points: &[u32], out: &mut String) {
+        let is_corner = |i: usize| {
+            let (px, py) = self.coords(points[(i + points.len() - 1) % points.len()]);
+            let (nx, ny) = self.coords(points[(i + 1) % points.len()]);
+            px != nx && py != ny
+        };
+        let mut corners = (0..points.len()).filter(|i| is_corner(*i));
+        let first = match corners.next() {
+            Some(first) => first,
+            None => return,
+        };
+        let (x, y) = self.coords(points[first]);
+        let _ = write!(out, "M{} {}", x, y);
+        let mut last_y = y;
+        for i in corners {
+            let (x, y) = self.coords(points[i]);
+            if y == last_y {
+                let _ = write!(out, "H{}", x);
+            } else {
+                let _ = write!(out, "V{}", y);
+            }
+            last_y = y;
+        }
+        out.push('Z');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(rows: &[&str]) -> (Vec<u8>, usize, usize) {
+        let data = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .flat_map(|c| match c {
+                '#' => [0, 0, 0, 255],
+                _ => [255, 255, 255, 255],
+            })
+            .collect();
+        (data, rows[0].len(), rows.len())
+    }
+
+    #[test]
+    fn svg_traces_outlines_and_holes() {
+        let (data, width, height) = image(&[
+            "....", //
+            ".###", //
+            ".#.#", //
+            ".###", //
+            "....", //
+        ]);
+        assert_eq!(
+            rgba_to_svg(&data, width, height, 2),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 4 5\" \
+             width=\"8\" height=\"10\" shape-rendering=\"crispEdges\">\n\
+             <rect width=\"4\" height=\"5\" fill=\"#ffffff\"/>\n\
+             <path fill=\"#000000\" d=\"M1 1H4V4H1ZM2 3H3V2H2Z\"/>\n\
+             </svg>\n"
+        );
+    }
+
+    #[test]
+    fn svg_traces_diagonal_pixels() {
+        let (data, width, height) = image(&[
+            "#..", //
+            ".#.", //
+            "...", //
+        ]);
+        let svg = rgba_to_svg(&data, width, height, 1);
+        assert!(svg.contains("<path fill=\"#000000\" d=\"M0 0H1V1H0ZM1 1H2V2H1Z\"/>"));
+    }
+}
@@ -77,6 +77,18 @@ impl Z80 {
         self.halted
     }
 
+    /// Changes halted state, PC should point to the `HALT` instruction. Used to
+    /// restore CPU state from snapshots
+    pub fn set_halted(&mut self, value: bool) {
+        self.halted = value;
+    }
+
+    /// Skips interrupt check before the next instruction, as after `EI` or
+    /// prefix byte. Used to restore CPU state from snapshots
+    pub fn set_skip_interrupt(&mut self, value: bool) {
+        self.skip_interrupt = value;
+    }
+
     /// Returns current interrupt mode
     pub fn get_im(&self) -> IntMode {
         self.int_mode
@@ -0,0 +1,131 @@
+//! TOML configuration file support. Config provides defaults for the settings,
+//! which were not set via command line
+use anyhow::Context;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+const CONFIG_DIR: &str = "rustzx";
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub video: VideoConfig,
+    pub osd: OsdConfig,
+    pub accessibility: AccessibilityConfig,
+    pub stats: StatsConfig,
+    /// Action to hotkey bindings, e.g. `quick_save = "Ctrl+F1"`
+    pub hotkeys: BTreeMap<String, String>,
+    pub autofire: AutofireConfig,
+    /// Hotkey to macro bindings, e.g. `"Ctrl+L" = "J Sym+P Sym+P Enter"`
+    pub macros: BTreeMap<String, String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VideoConfig {
+    /// Same values as for `--palette`
+    pub palette: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OsdConfig {
+    pub scale: Option<usize>,
+    pub high_contrast: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccessibilityConfig {
+    pub screen_reader: bool,
+    pub tts_command: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutofireConfig {
+    /// Presses per second, same as `--autofire-kempston`
+    pub kempston: Option<u32>,
+    /// Presses per second, same as `--autofire-sinclair`
+    pub sinclair: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsConfig {
+    pub disabled: bool,
+    pub osd: bool,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&data)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    /// Returns path to the config in the user configuration directory
+    /// (`$XDG_CONFIG_HOME`/`~/.config` or `%APPDATA%` on Windows)
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = if cfg!(windows) {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            std::env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        };
+        config_home.map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_parses_known_fields() {
+        let config: Config = toml::from_str(
+            r#"
+            [video]
+            palette = "protanopia"
+
+            [osd]
+            scale = 2
+            high_contrast = true
+
+            [hotkeys]
+            quick_save = "Ctrl+S"
+
+            [autofire]
+            kempston = 10
+
+            [macros]
+            "Ctrl+L" = "J Sym+P Sym+P Enter"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.video.palette.as_deref(), Some("protanopia"));
+        assert_eq!(config.osd.scale, Some(2));
+        assert!(config.osd.high_contrast);
+        assert_eq!(
+            config.hotkeys.get("quick_save").map(String::as_str),
+            Some("Ctrl+S")
+        );
+        assert_eq!(config.autofire.kempston, Some(10));
+        assert_eq!(config.autofire.sinclair, None);
+        assert_eq!(
+            config.macros.get("Ctrl+L").map(String::as_str),
+            Some("J Sym+P Sym+P Enter")
+        );
+    }
+
+    #[test]
+    fn config_rejects_unknown_fields() {
+        assert!(toml::from_str::<Config>("[video]\nscale = 2\n").is_err());
+    }
+}
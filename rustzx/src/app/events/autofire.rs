@@ -0,0 +1,85 @@
+//! Autofire: while fire button is held, it is repeatedly pressed and released
+//! with the configured rate
+use std::time::{Duration, Instant};
+
+/// Faster autofire would not be noticed by software, which polls joystick
+/// once per 50 Hz frame
+pub const MAX_AUTOFIRE_RATE: u32 = 25;
+
+pub struct Autofire {
+    /// Duration of the button press, release takes the same time
+    half_period: Duration,
+    held_since: Option<Instant>,
+    /// Button state passed to the emulator
+    pressed: bool,
+}
+
+impl Autofire {
+    /// Creates autofire with `rate` presses per second
+    pub fn new(rate: u32) -> Self {
+        Self {
+            half_period: Duration::from_secs(1) / (rate.clamp(1, MAX_AUTOFIRE_RATE) * 2),
+            held_since: None,
+            pressed: false,
+        }
+    }
+
+    /// Updates physical button state, returns new button state for the
+    /// emulator if it was changed
+    pub fn set_held(&mut self, held: bool, now: Instant) -> Option<bool> {
+        match (held, self.held_since) {
+            (true, None) => self.held_since = Some(now),
+            (false, Some(_)) => self.held_since = None,
+            // Keyboard auto-repeat
+            _ => return None,
+        }
+        self.update(now)
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.held_since.is_some()
+    }
+
+    /// Returns new button state for the emulator if it should be changed at `now`
+    pub fn update(&mut self, now: Instant) -> Option<bool> {
+        let pressed = match self.held_since {
+            Some(since) => {
+                let elapsed = now.saturating_duration_since(since);
+                (elapsed.as_nanos() / self.half_period.as_nanos()).is_multiple_of(2)
+            }
+            None => false,
+        };
+        if pressed == self.pressed {
+            return None;
+        }
+        self.pressed = pressed;
+        Some(pressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autofire_toggles_while_held() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        // 10 presses per second, 50 ms press and 50 ms release
+        let mut autofire = Autofire::new(10);
+
+        assert_eq!(autofire.update(start), None);
+        assert_eq!(autofire.set_held(true, start), Some(true));
+        assert_eq!(autofire.set_held(true, ms(10)), None);
+        assert_eq!(autofire.update(ms(40)), None);
+        assert_eq!(autofire.update(ms(50)), Some(false));
+        assert_eq!(autofire.update(ms(60)), None);
+        assert_eq!(autofire.update(ms(120)), Some(true));
+        assert_eq!(autofire.set_held(false, ms(130)), Some(false));
+        assert_eq!(autofire.update(ms(200)), None);
+        // Released while in the released phase
+        assert_eq!(autofire.set_held(true, ms(300)), Some(true));
+        assert_eq!(autofire.update(ms(360)), Some(false));
+        assert_eq!(autofire.set_held(false, ms(370)), None);
+    }
+}
@@ -1,6 +1,14 @@
 //! Real events SDL backend
-use super::{Event, EventDevice};
-use crate::{app::settings::Settings, backends::SDL_CONTEXT};
+use super::{
+    autofire::Autofire,
+    hotkeys::{HotkeyAction, HotkeyMatch, HotkeyTable, KeyCombo, Modifiers},
+    macros::{InputKey, Macro, MacroPlayer, MacroRecorder},
+    Event, EventDevice, RumblePulse,
+};
+use crate::{
+    app::{menu::MenuKey, settings::Settings},
+    backends::SDL_CONTEXT,
+};
 use rustzx_core::{
     zx::{
         joy::{
@@ -13,11 +21,13 @@ use rustzx_core::{
     EmulationMode,
 };
 use sdl2::{
+    controller::{Button, GameController},
     event::Event as SdlEvent,
-    keyboard::Scancode,
+    keyboard::{Mod, Scancode},
     mouse::{MouseButton, MouseUtil},
-    EventPump,
+    EventPump, GameControllerSubsystem,
 };
+use std::time::{Duration, Instant};
 
 /// Represents SDL Envets backend
 pub struct EventsSdl {
@@ -30,14 +40,28 @@ pub struct EventsSdl {
     enable_joy_keyaboard_layer: bool,
     mouse_x_counter: i32,
     mouse_y_counter: i32,
+    // Gamepads are used for rumble feedback, menu navigation and kempston joy
+    game_controller: Option<GameControllerSubsystem>,
+    controllers: Vec<GameController>,
+    hotkeys: HotkeyTable<Scancode>,
+    menu_mode: bool,
+    kempston_autofire: Option<Autofire>,
+    /// Autofire of the first and the second sinclair joysticks
+    sinclair_autofire: Option<[Autofire; 2]>,
+    /// Macros from config, bound to hotkeys
+    macros: Vec<Macro>,
+    recorded_macro: Option<Macro>,
+    macro_recorder: Option<MacroRecorder>,
+    macro_player: MacroPlayer,
+    /// Event received while waiting in `wait_event`
+    waited_event: Option<SdlEvent>,
 }
 
 impl EventsSdl {
-    /// constructs new event backend from setttigs/
-    /// Settings will be used in future for key bindings sittings
-    pub fn new(settings: &Settings) -> EventsSdl {
+    /// constructs new event backend from setttigs
+    pub fn new(settings: &Settings) -> anyhow::Result<EventsSdl> {
         // init event system
-        let (event_pump, mouse) = SDL_CONTEXT.with(|sdl| {
+        let (event_pump, mouse, game_controller) = SDL_CONTEXT.with(|sdl| {
             let context = sdl.borrow_mut();
             let pump = context
                 .event_pump()
@@ -45,10 +69,19 @@ impl EventsSdl {
 
             let mouse = context.mouse();
 
-            (pump, mouse)
+            // Already connected gamepads are reported via `ControllerDeviceAdded`
+            // events after subsystem initialization
+            let game_controller = context
+                .game_controller()
+                .map_err(|e| log::warn!("Failed to initialize gamepad subsystem: {}", e))
+                .ok();
+
+            (pump, mouse, game_controller)
         });
 
-        EventsSdl {
+        let hotkeys = settings.hotkeys.map_keys(Scancode::from_name)?;
+
+        Ok(EventsSdl {
             event_pump,
             mouse,
             mouse_enabled: settings.enable_mouse,
@@ -58,7 +91,20 @@ impl EventsSdl {
             mouse_sensitivity: settings.mouse_sensitivity,
             mouse_x_counter: 0,
             mouse_y_counter: 0,
-        }
+            game_controller,
+            controllers: Vec::new(),
+            hotkeys,
+            menu_mode: false,
+            kempston_autofire: settings.autofire_kempston.map(Autofire::new),
+            sinclair_autofire: settings
+                .autofire_sinclair
+                .map(|rate| [Autofire::new(rate), Autofire::new(rate)]),
+            macros: settings.macros.clone(),
+            recorded_macro: None,
+            macro_recorder: None,
+            macro_player: MacroPlayer::default(),
+            waited_event: None,
+        })
     }
 
     fn lock_mouse(&mut self) {
@@ -203,43 +249,207 @@ impl EventsSdl {
         sinclair_event.map(|(n, k)| Event::Sinclair(n, k, pressed))
     }
 
-    fn scancode_to_emulator_event(
+    /// Matches key press against the hotkey table. Returns `None` if key was
+    /// not consumed by hotkeys and should be processed further
+    fn scancode_to_hotkey_event(
         &mut self,
         scancode: Option<Scancode>,
+        keymod: Mod,
         pressed: bool,
+        repeat: bool,
+    ) -> Option<Option<Event>> {
+        let scancode = scancode.filter(|code| pressed && !is_modifier(*code))?;
+        if repeat {
+            // Repeated hotkey presses are not passed as ZX keys either
+            return self.hotkeys.contains_key(&scancode).then_some(None);
+        }
+        let combo = KeyCombo {
+            modifiers: sdl_keymod_to_modifiers(keymod),
+            key: scancode,
+        };
+        match self.hotkeys.key_down(combo) {
+            HotkeyMatch::None => None,
+            HotkeyMatch::Pending => Some(None),
+            HotkeyMatch::Action(action) => Some(self.hotkey_action_event(action)),
+            HotkeyMatch::Macro(index) => {
+                self.macro_player.play(&self.macros[index], Instant::now());
+                Some(None)
+            }
+        }
+    }
+
+    /// Translates key press to menu navigation, other keys are ignored in menu mode
+    fn scancode_to_menu_event(
+        &mut self,
+        scancode: Option<Scancode>,
+        keymod: Mod,
+        repeat: bool,
     ) -> Option<Event> {
-        if let (Some(code), true) = (scancode, pressed) {
-            match code {
-                Scancode::F1 => Some(Event::QuickSave),
-                Scancode::F2 => Some(Event::QuickLoad),
-                Scancode::F3 => Some(Event::ChangeSpeed(EmulationMode::FrameCount(1))),
-                Scancode::F4 => Some(Event::ChangeSpeed(EmulationMode::FrameCount(2))),
-                Scancode::F5 => Some(Event::ChangeSpeed(EmulationMode::Max)),
-                Scancode::F6 => Some(Event::SwitchFrameTrace),
-                Scancode::F9 => {
-                    self.enable_joy_keyaboard_layer = !self.enable_joy_keyaboard_layer;
-                    Some(Event::ChangeJoyKeyboardLayer(
-                        self.enable_joy_keyaboard_layer,
-                    ))
+        let scancode = scancode?;
+        // Menu can be closed with its hotkey
+        if !repeat && !is_modifier(scancode) {
+            let combo = KeyCombo {
+                modifiers: sdl_keymod_to_modifiers(keymod),
+                key: scancode,
+            };
+            match self.hotkeys.key_down(combo) {
+                HotkeyMatch::Action(HotkeyAction::ToggleMenu) => return Some(Event::ToggleMenu),
+                HotkeyMatch::Pending => return None,
+                _ => {}
+            }
+        }
+        let menu_key = match scancode {
+            Scancode::Up => MenuKey::Up,
+            Scancode::Down => MenuKey::Down,
+            Scancode::Return | Scancode::Space | Scancode::Right => MenuKey::Select,
+            Scancode::Escape | Scancode::Backspace | Scancode::Left => MenuKey::Back,
+            _ => return None,
+        };
+        Some(Event::Menu(menu_key))
+    }
+
+    fn controller_button_event(&mut self, button: Button, pressed: bool) -> Option<Event> {
+        if matches!(button, Button::Start | Button::Guide) {
+            return pressed.then_some(Event::ToggleMenu);
+        }
+        if self.menu_mode {
+            let menu_key = match button {
+                Button::DPadUp => MenuKey::Up,
+                Button::DPadDown => MenuKey::Down,
+                Button::A | Button::DPadRight => MenuKey::Select,
+                Button::B | Button::DPadLeft => MenuKey::Back,
+                _ => return None,
+            };
+            return pressed.then_some(Event::Menu(menu_key));
+        }
+        if !self.kempston_enabled {
+            return None;
+        }
+        let kempston_key = match button {
+            Button::DPadUp => KempstonKey::Up,
+            Button::DPadDown => KempstonKey::Down,
+            Button::DPadLeft => KempstonKey::Left,
+            Button::DPadRight => KempstonKey::Right,
+            Button::A => KempstonKey::Fire,
+            _ => return None,
+        };
+        Some(Event::Kempston(kempston_key, pressed))
+    }
+
+    fn hotkey_action_event(&mut self, action: HotkeyAction) -> Option<Event> {
+        match action {
+            HotkeyAction::QuickSave => Some(Event::QuickSave),
+            HotkeyAction::QuickLoad => Some(Event::QuickLoad),
+            HotkeyAction::SpeedNormal => Some(Event::ChangeSpeed(EmulationMode::FrameCount(1))),
+            HotkeyAction::SpeedDouble => Some(Event::ChangeSpeed(EmulationMode::FrameCount(2))),
+            HotkeyAction::SpeedMax => Some(Event::ChangeSpeed(EmulationMode::Max)),
+            HotkeyAction::FrameTrace => Some(Event::SwitchFrameTrace),
+            HotkeyAction::JoyKeyboardLayer => {
+                self.enable_joy_keyaboard_layer = !self.enable_joy_keyaboard_layer;
+                Some(Event::ChangeJoyKeyboardLayer(
+                    self.enable_joy_keyaboard_layer,
+                ))
+            }
+            HotkeyAction::InsertTape => Some(Event::InsertTape),
+            HotkeyAction::StopTape => Some(Event::StopTape),
+            HotkeyAction::UnlockMouse => {
+                self.unlock_mouse();
+                None
+            }
+            HotkeyAction::ToggleMenu => Some(Event::ToggleMenu),
+            HotkeyAction::RecordMacro => match self.macro_recorder.take() {
+                Some(recorder) => {
+                    self.recorded_macro = Some(recorder.finish());
+                    Some(Event::MacroRecording(false))
                 }
-                Scancode::Insert => Some(Event::InsertTape),
-                Scancode::Delete => Some(Event::StopTape),
-                Scancode::Escape => {
-                    self.unlock_mouse();
-                    None
+                None => {
+                    self.macro_recorder = Some(MacroRecorder::start(Instant::now()));
+                    Some(Event::MacroRecording(true))
                 }
-                _ => None,
+            },
+            HotkeyAction::PlayMacro => {
+                // Recorded macro is not played until recording is finished
+                if let (Some(recorded), None) = (&self.recorded_macro, &self.macro_recorder) {
+                    self.macro_player.play(recorded, Instant::now());
+                }
+                None
             }
-        } else {
-            None
+            HotkeyAction::CaptureSvg => Some(Event::CaptureSvg),
+            HotkeyAction::Exit => Some(Event::Exit),
         }
     }
-}
 
-impl EventDevice for EventsSdl {
-    /// get last event
-    fn pop_event(&mut self) -> Option<Event> {
-        if let Some(event) = self.event_pump.poll_event() {
+    /// Returns due events of the playing macro and autofire
+    fn generated_event(&mut self, now: Instant) -> Option<Event> {
+        if self.menu_mode {
+            return None;
+        }
+        while let Some((key, pressed)) = self.macro_player.poll(now) {
+            if let Some(event) = self.apply_autofire(key, pressed, now) {
+                return Some(event);
+            }
+        }
+        if let Some(pressed) = self
+            .kempston_autofire
+            .as_mut()
+            .and_then(|autofire| autofire.update(now))
+        {
+            return Some(Event::Kempston(KempstonKey::Fire, pressed));
+        }
+        if let Some(autofire) = &mut self.sinclair_autofire {
+            let joysticks = [SinclairJoyNum::Fist, SinclairJoyNum::Second];
+            for (num, autofire) in joysticks.into_iter().zip(autofire) {
+                if let Some(pressed) = autofire.update(now) {
+                    return Some(Event::Sinclair(num, SinclairKey::Fire, pressed));
+                }
+            }
+        }
+        None
+    }
+
+    /// Records key events when macro recording is active and applies autofire
+    fn process_input(&mut self, event: Event, now: Instant) -> Option<Event> {
+        let (key, pressed) = match InputKey::from_event(&event) {
+            Some(key) => key,
+            None => return Some(event),
+        };
+        if let Some(recorder) = &mut self.macro_recorder {
+            recorder.record(key, pressed, now);
+        }
+        self.apply_autofire(key, pressed, now)
+    }
+
+    /// Fire button events are replaced with autofire state changes when
+    /// autofire is enabled for the joystick
+    fn apply_autofire(&mut self, key: InputKey, pressed: bool, now: Instant) -> Option<Event> {
+        let autofire = match key {
+            InputKey::Kempston(KempstonKey::Fire) => self.kempston_autofire.as_mut(),
+            InputKey::Sinclair(num, SinclairKey::Fire) => {
+                let index = match num {
+                    SinclairJoyNum::Fist => 0,
+                    SinclairJoyNum::Second => 1,
+                };
+                self.sinclair_autofire
+                    .as_mut()
+                    .map(|autofire| &mut autofire[index])
+            }
+            _ => None,
+        };
+        match autofire {
+            Some(autofire) => autofire
+                .set_held(pressed, now)
+                .map(|pressed| key.to_event(pressed)),
+            None => Some(key.to_event(pressed)),
+        }
+    }
+
+    /// Translates the next SDL event
+    fn poll_sdl_event(&mut self) -> Option<Event> {
+        let event = self
+            .waited_event
+            .take()
+            .or_else(|| self.event_pump.poll_event());
+        if let Some(event) = event {
             // if event found
             match event {
                 // exot requested
@@ -247,15 +457,35 @@ impl EventDevice for EventsSdl {
                 // if any key pressed
                 action @ SdlEvent::KeyDown { .. } | action @ SdlEvent::KeyUp { .. } => {
                     // assemble tuple from scancode and its state
-                    let (scancode, pressed) = match action {
-                        SdlEvent::KeyDown { scancode: code, .. } => (code, true),
-                        SdlEvent::KeyUp { scancode: code, .. } => (code, false),
+                    let (scancode, keymod, repeat, pressed) = match action {
+                        SdlEvent::KeyDown {
+                            scancode: code,
+                            keymod,
+                            repeat,
+                            ..
+                        } => (code, keymod, repeat, true),
+                        SdlEvent::KeyUp {
+                            scancode: code,
+                            keymod,
+                            repeat,
+                            ..
+                        } => (code, keymod, repeat, false),
                         _ => unreachable!(),
                     };
 
+                    // Key releases are still passed to the emulator to avoid stuck keys
+                    if self.menu_mode && pressed {
+                        return self.scancode_to_menu_event(scancode, keymod, repeat);
+                    }
+
+                    if let Some(event) =
+                        self.scancode_to_hotkey_event(scancode, keymod, pressed, repeat)
+                    {
+                        return event;
+                    }
+
                     // Form highest priority event to lowest
-                    self.scancode_to_emulator_event(scancode, pressed)
-                        .or_else(|| self.scancode_to_kempston_event(scancode, pressed))
+                    self.scancode_to_kempston_event(scancode, pressed)
                         .or_else(|| self.scancode_to_sinclair_event(scancode, pressed))
                         .or_else(|| self.scancode_to_zxkey_event(scancode, pressed))
                         .or_else(|| self.scancode_to_compound_key_event(scancode, pressed))
@@ -325,6 +555,29 @@ impl EventDevice for EventsSdl {
                     }
                 }
                 SdlEvent::DropFile { filename, .. } => Some(Event::OpenFile(filename.into())),
+                SdlEvent::ControllerDeviceAdded { which, .. } => {
+                    if let Some(subsystem) = &self.game_controller {
+                        match subsystem.open(which) {
+                            Ok(controller) => {
+                                log::info!("Gamepad `{}` connected", controller.name());
+                                self.controllers.push(controller);
+                            }
+                            Err(e) => log::warn!("Failed to open gamepad: {}", e),
+                        }
+                    }
+                    None
+                }
+                SdlEvent::ControllerButtonDown { button, .. } => {
+                    self.controller_button_event(button, true)
+                }
+                SdlEvent::ControllerButtonUp { button, .. } => {
+                    self.controller_button_event(button, false)
+                }
+                SdlEvent::ControllerDeviceRemoved { which, .. } => {
+                    self.controllers
+                        .retain(|controller| controller.instance_id() != which);
+                    None
+                }
                 _ => None,
             }
         } else {
@@ -333,6 +586,72 @@ impl EventDevice for EventsSdl {
     }
 }
 
+impl EventDevice for EventsSdl {
+    /// get last event
+    fn pop_event(&mut self) -> Option<Event> {
+        let now = Instant::now();
+        if let Some(event) = self.generated_event(now) {
+            return Some(event);
+        }
+        let event = self.poll_sdl_event()?;
+        self.process_input(event, now)
+    }
+
+    fn wait_event(&mut self, timeout: Duration) {
+        // Macro and autofire events are generated by time
+        let generating = self.macro_player.is_playing()
+            || self.kempston_autofire.iter().any(Autofire::is_held)
+            || self
+                .sinclair_autofire
+                .iter()
+                .flatten()
+                .any(Autofire::is_held);
+        if self.waited_event.is_some() || generating {
+            return;
+        }
+        self.waited_event = self
+            .event_pump
+            .wait_event_timeout(timeout.as_millis() as u32);
+    }
+
+    fn rumble(&mut self, pulse: RumblePulse) {
+        let duration_ms = pulse.duration.as_millis() as u32;
+        for controller in &mut self.controllers {
+            // Not all gamepads have rumble motors, this is not an error
+            let _ = controller.set_rumble(pulse.low_frequency, pulse.high_frequency, duration_ms);
+        }
+    }
+
+    fn set_menu_mode(&mut self, enabled: bool) {
+        self.menu_mode = enabled;
+        if enabled {
+            self.unlock_mouse();
+        }
+    }
+}
+
+fn sdl_keymod_to_modifiers(keymod: Mod) -> Modifiers {
+    Modifiers {
+        ctrl: keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+        shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+        alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+    }
+}
+
+fn is_modifier(scancode: Scancode) -> bool {
+    matches!(
+        scancode,
+        Scancode::LCtrl
+            | Scancode::RCtrl
+            | Scancode::LShift
+            | Scancode::RShift
+            | Scancode::LAlt
+            | Scancode::RAlt
+            | Scancode::LGui
+            | Scancode::RGui
+    )
+}
+
 fn sdl_mouse_button_to_kempston(button: MouseButton) -> Option<KempstonMouseButton> {
     match button {
         MouseButton::Left => Some(KempstonMouseButton::Left),
@@ -0,0 +1,389 @@
+//! Declarative hotkey table. Emulator actions are bound to key combinations
+//! with modifiers (e.g. `Ctrl+F1`) or to chords of several combinations,
+//! pressed one after another (e.g. `Ctrl+K S`). Bindings can be changed via
+//! config file to resolve conflicts with keys used by ZX Spectrum software.
+//! Input macros from config file are bound to hotkeys too
+use anyhow::anyhow;
+use std::{collections::BTreeMap, str::FromStr};
+use strum::{EnumString, IntoStaticStr};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum HotkeyAction {
+    QuickSave,
+    QuickLoad,
+    SpeedNormal,
+    SpeedDouble,
+    SpeedMax,
+    FrameTrace,
+    JoyKeyboardLayer,
+    InsertTape,
+    StopTape,
+    UnlockMouse,
+    ToggleMenu,
+    RecordMacro,
+    PlayMacro,
+    CaptureSvg,
+    Exit,
+}
+
+const DEFAULT_HOTKEYS: &[(HotkeyAction, &str)] = &[
+    (HotkeyAction::QuickSave, "F1"),
+    (HotkeyAction::QuickLoad, "F2"),
+    (HotkeyAction::SpeedNormal, "F3"),
+    (HotkeyAction::SpeedDouble, "F4"),
+    (HotkeyAction::SpeedMax, "F5"),
+    (HotkeyAction::FrameTrace, "F6"),
+    (HotkeyAction::RecordMacro, "F7"),
+    (HotkeyAction::PlayMacro, "F8"),
+    (HotkeyAction::JoyKeyboardLayer, "F9"),
+    (HotkeyAction::InsertTape, "Insert"),
+    (HotkeyAction::StopTape, "Delete"),
+    (HotkeyAction::UnlockMouse, "Escape"),
+    (HotkeyAction::ToggleMenu, "F10"),
+    (HotkeyAction::CaptureSvg, "F12"),
+];
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct KeyCombo<K> {
+    pub modifiers: Modifiers,
+    pub key: K,
+}
+
+impl<K: PartialEq> KeyCombo<K> {
+    /// Checks if `pressed` key combination triggers this one. If `exact` is
+    /// false, combination without modifiers is triggered with any modifiers held
+    fn matches(&self, pressed: &KeyCombo<K>, exact: bool) -> bool {
+        self.key == pressed.key
+            && (self.modifiers == pressed.modifiers
+                || (!exact && self.modifiers == Modifiers::default()))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum HotkeyTarget {
+    Action(HotkeyAction),
+    /// Index of the macro from config
+    Macro(usize),
+}
+
+impl HotkeyTarget {
+    fn describe(self) -> String {
+        match self {
+            HotkeyTarget::Action(action) => format!("`{}`", <&str>::from(action)),
+            HotkeyTarget::Macro(index) => format!("macro #{}", index + 1),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Hotkey<K> {
+    chord: Vec<KeyCombo<K>>,
+    target: HotkeyTarget,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum HotkeyMatch {
+    /// Key press is not a part of any hotkey
+    None,
+    /// Key press started or continued a chord, next key press is expected
+    Pending,
+    Action(HotkeyAction),
+    /// Index of the macro, which was bound via [`HotkeyTable::bind_macro`]
+    Macro(usize),
+}
+
+/// Hotkey table with key type `K`. Keys are parsed as names (`String`) and then
+/// translated to the backend-specific type via [`HotkeyTable::map_keys`]
+#[derive(Clone, Debug)]
+pub struct HotkeyTable<K> {
+    hotkeys: Vec<Hotkey<K>>,
+    /// Key presses of the currently entered chord
+    pending: Vec<KeyCombo<K>>,
+}
+
+impl Default for HotkeyTable<String> {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new()).expect("Default hotkeys should be valid")
+    }
+}
+
+impl HotkeyTable<String> {
+    /// Builds table from default bindings, overridden by `bindings` (action
+    /// name to keys). Empty keys string unbinds the action
+    pub fn new(bindings: &BTreeMap<String, String>) -> anyhow::Result<Self> {
+        let mut all_bindings = DEFAULT_HOTKEYS
+            .iter()
+            .map(|(action, keys)| (*action, keys.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        for (name, keys) in bindings {
+            let action = HotkeyAction::from_str(name)
+                .map_err(|_| anyhow!("Unknown hotkey action `{}`", name))?;
+            all_bindings.insert(action, keys.clone());
+        }
+
+        let mut table = Self {
+            hotkeys: Vec::new(),
+            pending: Vec::new(),
+        };
+        for (action, keys) in all_bindings {
+            if keys.trim().is_empty() {
+                continue;
+            }
+            table.bind(&keys, HotkeyTarget::Action(action))?;
+        }
+        Ok(table)
+    }
+
+    /// Binds macro with the given index to `keys`
+    pub fn bind_macro(&mut self, keys: &str, index: usize) -> anyhow::Result<()> {
+        self.bind(keys, HotkeyTarget::Macro(index))
+    }
+
+    fn bind(&mut self, keys: &str, target: HotkeyTarget) -> anyhow::Result<()> {
+        let chord = parse_chord(keys)
+            .map_err(|e| anyhow!("Invalid hotkey `{}` for {}: {}", keys, target.describe(), e))?;
+        // Hotkey which is a prefix of another one would make the latter unreachable
+        if let Some(other) = self
+            .hotkeys
+            .iter()
+            .find(|other| other.chord.starts_with(&chord) || chord.starts_with(&other.chord))
+        {
+            return Err(anyhow!(
+                "Hotkeys for {} and {} conflict",
+                other.target.describe(),
+                target.describe()
+            ));
+        }
+        self.hotkeys.push(Hotkey { chord, target });
+        Ok(())
+    }
+
+    /// Translates key names to the backend key type, `map` returns `None` for
+    /// unknown key names
+    pub fn map_keys<K>(&self, map: impl Fn(&str) -> Option<K>) -> anyhow::Result<HotkeyTable<K>> {
+        let hotkeys = self
+            .hotkeys
+            .iter()
+            .map(|hotkey| {
+                let chord = hotkey
+                    .chord
+                    .iter()
+                    .map(|combo| {
+                        let key = map(&combo.key)
+                            .ok_or_else(|| anyhow!("Unknown hotkey key `{}`", combo.key))?;
+                        Ok(KeyCombo {
+                            modifiers: combo.modifiers,
+                            key,
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                Ok(Hotkey {
+                    chord,
+                    target: hotkey.target,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(HotkeyTable {
+            hotkeys,
+            pending: Vec::new(),
+        })
+    }
+}
+
+impl<K: Clone + PartialEq> HotkeyTable<K> {
+    /// Returns true if key is used in any hotkey
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.hotkeys
+            .iter()
+            .any(|hotkey| hotkey.chord.iter().any(|combo| &combo.key == key))
+    }
+
+    /// Processes key press, modifier keys themselves should not be passed here.
+    /// Hotkeys with exactly the same modifiers have priority, hotkeys without
+    /// modifiers are triggered with any modifiers held too
+    pub fn key_down(&mut self, combo: KeyCombo<K>) -> HotkeyMatch {
+        self.pending.push(combo.clone());
+        for exact in [true, false] {
+            if let Some(result) = self.match_pending(exact) {
+                return result;
+            }
+        }
+        let chord_interrupted = self.pending.len() > 1;
+        self.pending.clear();
+        if chord_interrupted {
+            // Unfinished chord is dropped, but key still can start a new one
+            return self.key_down(combo);
+        }
+        HotkeyMatch::None
+    }
+
+    fn match_pending(&mut self, exact: bool) -> Option<HotkeyMatch> {
+        let mut chord_started = false;
+        for hotkey in &self.hotkeys {
+            let prefix_matches = hotkey.chord.len() >= self.pending.len()
+                && hotkey
+                    .chord
+                    .iter()
+                    .zip(&self.pending)
+                    .all(|(combo, pressed)| combo.matches(pressed, exact));
+            if prefix_matches {
+                if hotkey.chord.len() == self.pending.len() {
+                    self.pending.clear();
+                    return Some(match hotkey.target {
+                        HotkeyTarget::Action(action) => HotkeyMatch::Action(action),
+                        HotkeyTarget::Macro(index) => HotkeyMatch::Macro(index),
+                    });
+                }
+                chord_started = true;
+            }
+        }
+        chord_started.then_some(HotkeyMatch::Pending)
+    }
+}
+
+/// Parses space-separated list of key combinations, e.g. `Ctrl+Shift+K S`.
+/// Spaces in key names are written as `_` (e.g. `Keypad_5`)
+fn parse_chord(s: &str) -> anyhow::Result<Vec<KeyCombo<String>>> {
+    s.split_whitespace()
+        .map(|combo| {
+            let mut parts = combo.split('+').collect::<Vec<_>>();
+            let key = parts.pop().filter(|key| !key.is_empty());
+            let key = key.ok_or_else(|| anyhow!("Key is missing in `{}`", combo))?;
+            let mut modifiers = Modifiers::default();
+            for modifier in parts {
+                match modifier.to_lowercase().as_str() {
+                    "ctrl" | "control" => modifiers.ctrl = true,
+                    "shift" => modifiers.shift = true,
+                    "alt" => modifiers.alt = true,
+                    _ => return Err(anyhow!("Unknown modifier `{}`", modifier)),
+                }
+            }
+            Ok(KeyCombo {
+                modifiers,
+                key: key.replace('_', " ").to_lowercase(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combo(s: &str) -> KeyCombo<String> {
+        parse_chord(s).unwrap().remove(0)
+    }
+
+    fn bindings(list: &[(&str, &str)]) -> BTreeMap<String, String> {
+        list.iter()
+            .map(|(a, k)| (a.to_string(), k.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn hotkeys_match_modifiers_and_chords() {
+        let mut table = HotkeyTable::new(&bindings(&[
+            ("quick_save", "Ctrl+S"),
+            ("exit", "Ctrl+X Ctrl+C"),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            table.key_down(combo("F2")),
+            HotkeyMatch::Action(HotkeyAction::QuickLoad)
+        );
+        assert_eq!(table.key_down(combo("S")), HotkeyMatch::None);
+        assert_eq!(table.key_down(combo("Shift+S")), HotkeyMatch::None);
+        assert_eq!(table.key_down(combo("F1")), HotkeyMatch::None);
+        assert_eq!(
+            table.key_down(combo("ctrl+s")),
+            HotkeyMatch::Action(HotkeyAction::QuickSave)
+        );
+        assert_eq!(table.key_down(combo("Ctrl+X")), HotkeyMatch::Pending);
+        assert_eq!(
+            table.key_down(combo("Ctrl+C")),
+            HotkeyMatch::Action(HotkeyAction::Exit)
+        );
+        // Interrupted chord, key press is matched on its own
+        assert_eq!(table.key_down(combo("Ctrl+X")), HotkeyMatch::Pending);
+        assert_eq!(
+            table.key_down(combo("Ctrl+S")),
+            HotkeyMatch::Action(HotkeyAction::QuickSave)
+        );
+    }
+
+    #[test]
+    fn hotkeys_without_modifiers_ignore_held_modifiers() {
+        let mut table = HotkeyTable::new(&bindings(&[
+            ("speed_max", "Shift+F4"),
+            ("exit", "Ctrl+K Q"),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            table.key_down(combo("Shift+F1")),
+            HotkeyMatch::Action(HotkeyAction::QuickSave)
+        );
+        assert_eq!(
+            table.key_down(combo("Ctrl+Alt+F2")),
+            HotkeyMatch::Action(HotkeyAction::QuickLoad)
+        );
+        // Exact match has priority
+        assert_eq!(
+            table.key_down(combo("Shift+F4")),
+            HotkeyMatch::Action(HotkeyAction::SpeedMax)
+        );
+        assert_eq!(
+            table.key_down(combo("Ctrl+F4")),
+            HotkeyMatch::Action(HotkeyAction::SpeedDouble)
+        );
+        // Hotkeys with modifiers still require them
+        assert_eq!(table.key_down(combo("K")), HotkeyMatch::None);
+        assert_eq!(table.key_down(combo("Ctrl+K")), HotkeyMatch::Pending);
+        assert_eq!(
+            table.key_down(combo("Shift+Q")),
+            HotkeyMatch::Action(HotkeyAction::Exit)
+        );
+    }
+
+    #[test]
+    fn hotkeys_reject_unknown_keys() {
+        let table = HotkeyTable::new(&bindings(&[("exit", "Ctrl+Foo")])).unwrap();
+        assert!(table.map_keys(|key| (key != "foo").then_some(())).is_err());
+    }
+
+    #[test]
+    fn hotkeys_reject_conflicts() {
+        assert!(HotkeyTable::new(&bindings(&[("quick_save", "F2")])).is_err());
+        assert!(HotkeyTable::new(&bindings(&[("exit", "F1 Q")])).is_err());
+        // Conflict is resolved by unbinding
+        assert!(HotkeyTable::new(&bindings(&[("quick_save", ""), ("exit", "F1 Q"),])).is_ok());
+        assert!(HotkeyTable::new(&bindings(&[("exit", "Meta+Q")])).is_err());
+        assert!(HotkeyTable::new(&bindings(&[("quit", "Q")])).is_err());
+    }
+
+    #[test]
+    fn hotkeys_bind_macros() {
+        let mut table = HotkeyTable::default();
+        table.bind_macro("Ctrl+L", 0).unwrap();
+        table.bind_macro("Ctrl+K R", 1).unwrap();
+        assert!(table.bind_macro("F1", 2).is_err());
+        assert!(table.bind_macro("Ctrl+K", 2).is_err());
+
+        assert_eq!(table.key_down(combo("Ctrl+L")), HotkeyMatch::Macro(0));
+        assert_eq!(table.key_down(combo("Ctrl+K")), HotkeyMatch::Pending);
+        assert_eq!(table.key_down(combo("R")), HotkeyMatch::Macro(1));
+        assert_eq!(
+            table.key_down(combo("F7")),
+            HotkeyMatch::Action(HotkeyAction::RecordMacro)
+        );
+    }
+}
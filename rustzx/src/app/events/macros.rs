@@ -0,0 +1,326 @@
+//! Input macros: sequences of ZX key and joystick events with timings, which
+//! are replayed on hotkey. Macros are either defined in config file as a list
+//! of key combinations (e.g. `J Sym+P Sym+P Enter`) or recorded at runtime
+use super::Event;
+use anyhow::anyhow;
+use rustzx_core::zx::{
+    joy::{
+        kempston::KempstonKey,
+        sinclair::{SinclairJoyNum, SinclairKey},
+    },
+    keys::{CompoundKey, ZXKey},
+};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// ROM keyboard routine needs a key to be held for a few frames and released
+/// for a few more frames before the same key is registered again
+const KEY_PRESS_TIME: Duration = Duration::from_millis(100);
+const KEY_RELEASE_TIME: Duration = Duration::from_millis(100);
+
+/// Emulated input key, which can be recorded and replayed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputKey {
+    ZX(ZXKey),
+    Compound(CompoundKey),
+    Kempston(KempstonKey),
+    Sinclair(SinclairJoyNum, SinclairKey),
+}
+
+impl InputKey {
+    /// Returns key and its state for key events
+    pub fn from_event(event: &Event) -> Option<(InputKey, bool)> {
+        match *event {
+            Event::ZXKey(key, pressed) => Some((InputKey::ZX(key), pressed)),
+            Event::CompoundKey(key, pressed) => Some((InputKey::Compound(key), pressed)),
+            Event::Kempston(key, pressed) => Some((InputKey::Kempston(key), pressed)),
+            Event::Sinclair(num, key, pressed) => Some((InputKey::Sinclair(num, key), pressed)),
+            _ => None,
+        }
+    }
+
+    pub fn to_event(self, pressed: bool) -> Event {
+        match self {
+            InputKey::ZX(key) => Event::ZXKey(key, pressed),
+            InputKey::Compound(key) => Event::CompoundKey(key, pressed),
+            InputKey::Kempston(key) => Event::Kempston(key, pressed),
+            InputKey::Sinclair(num, key) => Event::Sinclair(num, key, pressed),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct MacroStep {
+    /// Delay after the previous step
+    delay: Duration,
+    key: InputKey,
+    pressed: bool,
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Macro {
+    steps: Vec<MacroStep>,
+}
+
+impl Macro {
+    /// Parses space-separated list of ZX key combinations, which are pressed
+    /// one after another, e.g. `J Sym+P Sym+P Enter`
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let mut steps = Vec::new();
+        for combo in s.split_whitespace() {
+            let keys = combo
+                .split('+')
+                .map(|name| {
+                    zx_key_from_name(name).ok_or_else(|| anyhow!("Unknown ZX key `{}`", name))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            for (index, key) in keys.iter().enumerate() {
+                let delay = if index == 0 && !steps.is_empty() {
+                    KEY_RELEASE_TIME
+                } else {
+                    Duration::ZERO
+                };
+                steps.push(MacroStep {
+                    delay,
+                    key: InputKey::ZX(*key),
+                    pressed: true,
+                });
+            }
+            for (index, key) in keys.iter().enumerate() {
+                steps.push(MacroStep {
+                    delay: if index == 0 {
+                        KEY_PRESS_TIME
+                    } else {
+                        Duration::ZERO
+                    },
+                    key: InputKey::ZX(*key),
+                    pressed: false,
+                });
+            }
+        }
+        if steps.is_empty() {
+            return Err(anyhow!("Macro is empty"));
+        }
+        Ok(Self { steps })
+    }
+}
+
+/// Letters and digits are named as is, other keys are `Enter`, `Space`, `Caps`
+/// (Caps Shift) and `Sym` (Symbol Shift)
+fn zx_key_from_name(name: &str) -> Option<ZXKey> {
+    let key = match name.to_lowercase().as_str() {
+        "enter" => ZXKey::Enter,
+        "space" => ZXKey::Space,
+        "caps" => ZXKey::Shift,
+        "sym" => ZXKey::SymShift,
+        "0" => ZXKey::N0,
+        "1" => ZXKey::N1,
+        "2" => ZXKey::N2,
+        "3" => ZXKey::N3,
+        "4" => ZXKey::N4,
+        "5" => ZXKey::N5,
+        "6" => ZXKey::N6,
+        "7" => ZXKey::N7,
+        "8" => ZXKey::N8,
+        "9" => ZXKey::N9,
+        "a" => ZXKey::A,
+        "b" => ZXKey::B,
+        "c" => ZXKey::C,
+        "d" => ZXKey::D,
+        "e" => ZXKey::E,
+        "f" => ZXKey::F,
+        "g" => ZXKey::G,
+        "h" => ZXKey::H,
+        "i" => ZXKey::I,
+        "j" => ZXKey::J,
+        "k" => ZXKey::K,
+        "l" => ZXKey::L,
+        "m" => ZXKey::M,
+        "n" => ZXKey::N,
+        "o" => ZXKey::O,
+        "p" => ZXKey::P,
+        "q" => ZXKey::Q,
+        "r" => ZXKey::R,
+        "s" => ZXKey::S,
+        "t" => ZXKey::T,
+        "u" => ZXKey::U,
+        "v" => ZXKey::V,
+        "w" => ZXKey::W,
+        "x" => ZXKey::X,
+        "y" => ZXKey::Y,
+        "z" => ZXKey::Z,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Records key events with their timings
+pub struct MacroRecorder {
+    last_event: Instant,
+    steps: Vec<MacroStep>,
+    /// Keys which are pressed at the moment
+    pressed: Vec<InputKey>,
+}
+
+impl MacroRecorder {
+    pub fn start(now: Instant) -> Self {
+        Self {
+            last_event: now,
+            steps: Vec::new(),
+            pressed: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, key: InputKey, pressed: bool, now: Instant) {
+        let is_pressed = self.pressed.contains(&key);
+        // Keyboard auto-repeat and releases of the keys pressed before
+        // recording was started are skipped
+        if pressed == is_pressed {
+            return;
+        }
+        if pressed {
+            self.pressed.push(key);
+        } else {
+            self.pressed.retain(|k| *k != key);
+        }
+        self.steps.push(MacroStep {
+            delay: now.saturating_duration_since(self.last_event),
+            key,
+            pressed,
+        });
+        self.last_event = now;
+    }
+
+    /// Finishes recording, keys which are still held are released at the end
+    pub fn finish(mut self) -> Macro {
+        // Delay before the first key press is not a part of the macro
+        if let Some(first) = self.steps.first_mut() {
+            first.delay = Duration::ZERO;
+        }
+        for key in self.pressed {
+            self.steps.push(MacroStep {
+                delay: KEY_PRESS_TIME,
+                key,
+                pressed: false,
+            });
+        }
+        Macro { steps: self.steps }
+    }
+}
+
+/// Replays macros, events are returned via [`MacroPlayer::poll`] when they are due
+#[derive(Default)]
+pub struct MacroPlayer {
+    queue: VecDeque<(Instant, InputKey, bool)>,
+}
+
+impl MacroPlayer {
+    /// Starts macro playback, previously playing macro is replaced
+    pub fn play(&mut self, input_macro: &Macro, now: Instant) {
+        // Keys pressed by the replaced macro should not stay stuck
+        let released = self
+            .queue
+            .iter()
+            .filter(|(_, _, pressed)| !pressed)
+            .map(|(_, key, _)| (now, *key, false))
+            .collect::<Vec<_>>();
+        self.queue.clear();
+        self.queue.extend(released);
+
+        let mut time = now;
+        for step in &input_macro.steps {
+            time += step.delay;
+            self.queue.push_back((time, step.key, step.pressed));
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Returns the next event which is due at `now`
+    pub fn poll(&mut self, now: Instant) -> Option<(InputKey, bool)> {
+        match self.queue.front() {
+            Some((time, _, _)) if *time <= now => self
+                .queue
+                .pop_front()
+                .map(|(_, key, pressed)| (key, pressed)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll_all(player: &mut MacroPlayer, now: Instant) -> Vec<(InputKey, bool)> {
+        std::iter::from_fn(|| player.poll(now)).collect()
+    }
+
+    #[test]
+    fn macro_parse_and_play() {
+        let load = Macro::parse("J sym+P").unwrap();
+        let start = Instant::now();
+        let mut player = MacroPlayer::default();
+        player.play(&load, start);
+
+        assert_eq!(
+            poll_all(&mut player, start),
+            vec![(InputKey::ZX(ZXKey::J), true)]
+        );
+        assert_eq!(
+            poll_all(&mut player, start + KEY_PRESS_TIME),
+            vec![(InputKey::ZX(ZXKey::J), false)]
+        );
+        assert_eq!(
+            poll_all(&mut player, start + KEY_PRESS_TIME + KEY_RELEASE_TIME),
+            vec![
+                (InputKey::ZX(ZXKey::SymShift), true),
+                (InputKey::ZX(ZXKey::P), true)
+            ]
+        );
+        assert_eq!(
+            poll_all(&mut player, start + Duration::from_secs(1)),
+            vec![
+                (InputKey::ZX(ZXKey::SymShift), false),
+                (InputKey::ZX(ZXKey::P), false)
+            ]
+        );
+
+        assert!(Macro::parse("").is_err());
+        assert!(Macro::parse("J Ctrl+P").is_err());
+    }
+
+    #[test]
+    fn macro_record() {
+        let start = Instant::now();
+        let fire = InputKey::Kempston(KempstonKey::Fire);
+        let mut recorder = MacroRecorder::start(start);
+        // Released before recording was started
+        recorder.record(InputKey::ZX(ZXKey::Q), false, start);
+        recorder.record(fire, true, start + Duration::from_millis(500));
+        recorder.record(fire, true, start + Duration::from_millis(550));
+        recorder.record(
+            InputKey::ZX(ZXKey::O),
+            true,
+            start + Duration::from_millis(600),
+        );
+        recorder.record(fire, false, start + Duration::from_millis(700));
+        let recorded = recorder.finish();
+
+        let mut player = MacroPlayer::default();
+        player.play(&recorded, start);
+        assert_eq!(poll_all(&mut player, start), vec![(fire, true)]);
+        assert_eq!(
+            poll_all(&mut player, start + Duration::from_millis(200)),
+            vec![(InputKey::ZX(ZXKey::O), true), (fire, false)]
+        );
+        assert_eq!(
+            poll_all(&mut player, start + Duration::from_secs(1)),
+            vec![(InputKey::ZX(ZXKey::O), false)]
+        );
+    }
+}
@@ -1,7 +1,11 @@
 //! platform-independent traits. Submodules with backends will be selectable
 //! via cargo features in future
+mod autofire;
 mod events_sdl;
+mod hotkeys;
+mod macros;
 
+use crate::app::menu::MenuKey;
 use rustzx_core::{
     zx::{
         joy::{
@@ -13,9 +17,12 @@ use rustzx_core::{
     },
     EmulationMode,
 };
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
+pub use autofire::MAX_AUTOFIRE_RATE;
 pub use events_sdl::EventsSdl;
+pub use hotkeys::HotkeyTable;
+pub use macros::Macro;
 
 // Event type
 pub enum Event {
@@ -23,7 +30,10 @@ pub enum Event {
     CompoundKey(CompoundKey, bool),
     Kempston(KempstonKey, bool),
     Sinclair(SinclairJoyNum, SinclairKey, bool),
-    MouseMove { x: i8, y: i8 },
+    MouseMove {
+        x: i8,
+        y: i8,
+    },
     MouseButton(KempstonMouseButton, bool),
     MouseWheel(KempstonMouseWheelDirection),
     SwitchFrameTrace,
@@ -34,11 +44,41 @@ pub enum Event {
     QuickSave,
     QuickLoad,
     OpenFile(PathBuf),
+    ToggleMenu,
+    Menu(MenuKey),
+    /// Macro recording was started or finished
+    MacroRecording(bool),
+    CaptureSvg,
     Exit,
 }
 
+/// Gamepad rumble pulse, motor intensities are in `0..=0xFFFF` range
+#[derive(Clone, Copy, Default)]
+pub struct RumblePulse {
+    pub low_frequency: u16,
+    pub high_frequency: u16,
+    pub duration: Duration,
+}
+
+impl RumblePulse {
+    /// Combines two pulses into one, which is strong and long enough for both
+    pub fn merge(self, other: RumblePulse) -> RumblePulse {
+        RumblePulse {
+            low_frequency: self.low_frequency.max(other.low_frequency),
+            high_frequency: self.high_frequency.max(other.high_frequency),
+            duration: self.duration.max(other.duration),
+        }
+    }
+}
+
 /// provides event response interface
 pub trait EventDevice {
     // get last event
     fn pop_event(&mut self) -> Option<Event>;
+    /// blocks until host event arrives or `timeout` passes
+    fn wait_event(&mut self, timeout: Duration);
+    /// plays rumble pulse on all connected gamepads which support it
+    fn rumble(&mut self, pulse: RumblePulse);
+    /// switches keyboard and gamepad input to menu navigation
+    fn set_menu_mode(&mut self, enabled: bool);
 }
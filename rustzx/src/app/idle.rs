@@ -0,0 +1,106 @@
+//! Idle machine detection: when emulated machine waits for input (e.g. at the
+//! BASIC prompt), the frame does not change and no sound is played, so host can
+//! be polled less often and redundant texture uploads can be skipped to save
+//! power. Emulation itself keeps running in real time
+const HALT_HEAVY_RATIO: f32 = 0.9;
+/// Quiet frames before the machine is considered idle, ROM keyboard routines
+/// busy-wait without HALT, so frames are checked for longer in that case
+const IDLE_FRAMES: usize = 100;
+/// Programs waiting in the HALT loop (e.g. `PAUSE 0`) are detected faster
+const IDLE_HALTED_FRAMES: usize = 25;
+/// Frame changes which are rarer than this are not treated as activity, so
+/// flashing cursor and attributes do not prevent idle detection
+const MIN_ACTIVITY_INTERVAL: usize = 8;
+
+/// What happened during the emulated frame
+pub struct FrameActivity {
+    /// Part of the frame which CPU spent in HALT state
+    pub halt_ratio: f32,
+    /// Screen or border were changed since the previous frame
+    pub frame_changed: bool,
+    /// Non-silent sound was produced
+    pub sound: bool,
+}
+
+#[derive(Default)]
+pub struct IdleDetector {
+    quiet_frames: usize,
+    halted_frames: usize,
+    frames_since_change: usize,
+}
+
+impl IdleDetector {
+    pub fn update(&mut self, activity: FrameActivity) {
+        self.frames_since_change += 1;
+        let animated = activity.frame_changed && self.frames_since_change < MIN_ACTIVITY_INTERVAL;
+        if activity.frame_changed {
+            self.frames_since_change = 0;
+        }
+        if animated || activity.sound {
+            self.wake();
+            return;
+        }
+        self.quiet_frames += 1;
+        if activity.halt_ratio >= HALT_HEAVY_RATIO {
+            self.halted_frames += 1;
+        } else {
+            self.halted_frames = 0;
+        }
+    }
+
+    /// Resets detection, called on any host input
+    pub fn wake(&mut self) {
+        self.quiet_frames = 0;
+        self.halted_frames = 0;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.quiet_frames >= IDLE_FRAMES
+            || (self.quiet_frames >= IDLE_HALTED_FRAMES && self.halted_frames >= IDLE_HALTED_FRAMES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(halt_ratio: f32, frame_changed: bool) -> FrameActivity {
+        FrameActivity {
+            halt_ratio,
+            frame_changed,
+            sound: false,
+        }
+    }
+
+    #[test]
+    fn idle_detection() {
+        let mut idle = IdleDetector::default();
+        // HALT loop
+        for _ in 0..IDLE_HALTED_FRAMES {
+            assert!(!idle.is_idle());
+            idle.update(frame(1.0, false));
+        }
+        assert!(idle.is_idle());
+        idle.wake();
+        assert!(!idle.is_idle());
+
+        // Busy-waiting ROM with flashing cursor
+        for index in 0..IDLE_FRAMES {
+            assert!(!idle.is_idle());
+            idle.update(frame(0.0, index % 16 == 0));
+        }
+        assert!(idle.is_idle());
+
+        // Animation and sound are activity
+        idle.update(frame(1.0, true));
+        idle.update(frame(1.0, true));
+        assert!(!idle.is_idle());
+        for _ in 0..IDLE_FRAMES {
+            idle.update(FrameActivity {
+                sound: true,
+                ..frame(1.0, false)
+            });
+        }
+        assert!(!idle.is_idle());
+    }
+}
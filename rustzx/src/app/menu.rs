@@ -0,0 +1,371 @@
+//! In-emulator menu, which is drawn via OSD and navigated with keyboard or
+//! gamepad. Gives access to emulator functions without external UI, so the
+//! emulator can be used on TV/gamepad-only setups
+use crate::{app::osd::OsdMenu, host};
+use rustzx_core::{zx::machine::ZXMachine, EmulationMode};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuKey {
+    Up,
+    Down,
+    Select,
+    Back,
+}
+
+/// Action requested via menu, performed by the application
+#[derive(PartialEq, Eq, Debug)]
+pub enum MenuAction {
+    OpenFile(PathBuf),
+    /// Recreates emulator with the given machine, also used for reset
+    SetMachine(ZXMachine),
+    QuickSave,
+    QuickLoad,
+    PlayTape,
+    StopTape,
+    RewindTape,
+    ChangeSpeed(EmulationMode),
+    SetKempston(bool),
+    Exit,
+}
+
+/// Current emulator state, which is shown in the menu
+pub struct MenuStatus {
+    pub machine: ZXMachine,
+    /// Machine for which custom ROM was provided
+    pub rom_machine: Option<ZXMachine>,
+    pub speed: EmulationMode,
+    pub kempston: bool,
+}
+
+enum Page {
+    Main,
+    Files {
+        dir: PathBuf,
+        entries: Vec<FileEntry>,
+    },
+    Tape,
+    Machine,
+    Settings,
+}
+
+struct FileEntry {
+    label: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+enum Command {
+    Resume,
+    Open(fn(&Menu) -> Page),
+    Browse(PathBuf),
+    /// Performs action and closes menu
+    Action(MenuAction),
+    /// Performs action and keeps menu open, used for settings
+    Toggle(MenuAction),
+}
+
+struct MenuItem {
+    label: String,
+    command: Command,
+}
+
+impl MenuItem {
+    fn new(label: impl Into<String>, command: Command) -> Self {
+        Self {
+            label: label.into(),
+            command,
+        }
+    }
+}
+
+pub struct Menu {
+    /// Open pages with selected item index, empty if menu is closed
+    pages: Vec<(Page, usize)>,
+    /// Directory which is shown first in file browser
+    files_dir: PathBuf,
+}
+
+impl Menu {
+    pub fn new(files_dir: PathBuf) -> Self {
+        Self {
+            pages: Vec::new(),
+            // Absolute path is required to navigate to parent directories
+            files_dir: files_dir.canonicalize().unwrap_or(files_dir),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.pages.is_empty()
+    }
+
+    pub fn open(&mut self) {
+        self.pages = vec![(Page::Main, 0)];
+    }
+
+    pub fn close(&mut self) {
+        self.pages.clear();
+    }
+
+    /// Handles navigation key, returns action if it was selected
+    pub fn input(&mut self, key: MenuKey, status: &MenuStatus) -> Option<MenuAction> {
+        let items = self.items(status);
+        let (_, selected) = self.pages.last_mut()?;
+        if items.is_empty() && key != MenuKey::Back {
+            return None;
+        }
+        match key {
+            MenuKey::Up => {
+                *selected = selected.checked_sub(1).unwrap_or(items.len() - 1);
+                None
+            }
+            MenuKey::Down => {
+                *selected = (*selected + 1) % items.len();
+                None
+            }
+            MenuKey::Back => {
+                self.pages.pop();
+                None
+            }
+            MenuKey::Select => {
+                let item = items.into_iter().nth(*selected)?;
+                match item.command {
+                    Command::Resume => {
+                        self.close();
+                        None
+                    }
+                    Command::Open(page) => {
+                        let page = page(self);
+                        self.pages.push((page, 0));
+                        None
+                    }
+                    Command::Browse(dir) => {
+                        let entries = read_dir_entries(&dir);
+                        self.files_dir = dir.clone();
+                        self.pages.pop();
+                        self.pages.push((Page::Files { dir, entries }, 0));
+                        None
+                    }
+                    Command::Action(action) => {
+                        if let MenuAction::OpenFile(path) = &action {
+                            if let Some(dir) = path.parent() {
+                                self.files_dir = dir.to_owned();
+                            }
+                        }
+                        self.close();
+                        Some(action)
+                    }
+                    Command::Toggle(action) => Some(action),
+                }
+            }
+        }
+    }
+
+    /// Returns menu contents for OSD, `None` if menu is closed
+    pub fn view(&self, status: &MenuStatus) -> Option<OsdMenu> {
+        let (page, selected) = self.pages.last()?;
+        let title = match page {
+            Page::Main => "RustZX".to_owned(),
+            Page::Files { dir, .. } => dir.display().to_string(),
+            Page::Tape => "Tape".to_owned(),
+            Page::Machine => "Machine".to_owned(),
+            Page::Settings => "Settings".to_owned(),
+        };
+        Some(OsdMenu {
+            title,
+            items: self.items(status).into_iter().map(|i| i.label).collect(),
+            selected: *selected,
+        })
+    }
+
+    fn items(&self, status: &MenuStatus) -> Vec<MenuItem> {
+        let page = match self.pages.last() {
+            Some((page, _)) => page,
+            None => return Vec::new(),
+        };
+        match page {
+            Page::Main => vec![
+                MenuItem::new("Resume", Command::Resume),
+                MenuItem::new(
+                    "Load file...",
+                    Command::Open(|menu| Page::Files {
+                        dir: menu.files_dir.clone(),
+                        entries: read_dir_entries(&menu.files_dir),
+                    }),
+                ),
+                MenuItem::new("Quick save", Command::Action(MenuAction::QuickSave)),
+                MenuItem::new("Quick load", Command::Action(MenuAction::QuickLoad)),
+                MenuItem::new("Tape...", Command::Open(|_| Page::Tape)),
+                MenuItem::new("Machine...", Command::Open(|_| Page::Machine)),
+                MenuItem::new("Settings...", Command::Open(|_| Page::Settings)),
+                MenuItem::new("Exit", Command::Action(MenuAction::Exit)),
+            ],
+            Page::Files { dir, entries } => {
+                let mut items = Vec::new();
+                if let Some(parent) = dir.parent() {
+                    items.push(MenuItem::new("..", Command::Browse(parent.to_owned())));
+                }
+                items.extend(entries.iter().map(|entry| {
+                    let command = if entry.is_dir {
+                        Command::Browse(entry.path.clone())
+                    } else {
+                        Command::Action(MenuAction::OpenFile(entry.path.clone()))
+                    };
+                    MenuItem::new(entry.label.clone(), command)
+                }));
+                items
+            }
+            Page::Tape => vec![
+                MenuItem::new("Play", Command::Action(MenuAction::PlayTape)),
+                MenuItem::new("Stop", Command::Action(MenuAction::StopTape)),
+                MenuItem::new("Rewind", Command::Action(MenuAction::RewindTape)),
+            ],
+            Page::Machine => {
+                let mut machines = vec![
+                    (ZXMachine::Sinclair48K, "ZX Spectrum 48K"),
+                    (ZXMachine::Sinclair128K, "ZX Spectrum 128K"),
+                ];
+                // Machines without embedded ROM are available only with custom ROM
+                match status.rom_machine {
+                    Some(ZXMachine::SinclairPlus3) => {
+                        machines.push((ZXMachine::SinclairPlus3, "ZX Spectrum +3"))
+                    }
+                    Some(ZXMachine::Sinclair128KSpanish) => machines
+                        .push((ZXMachine::Sinclair128KSpanish, "ZX Spectrum 128K (Spanish)")),
+                    Some(ZXMachine::TimexTC2048) => {
+                        machines.push((ZXMachine::TimexTC2048, "Timex TC2048"))
+                    }
+                    _ => {}
+                }
+                let mut items = machines
+                    .into_iter()
+                    .map(|(machine, name)| {
+                        let mark = if machine == status.machine { '*' } else { ' ' };
+                        MenuItem::new(
+                            format!("{} {}", mark, name),
+                            Command::Action(MenuAction::SetMachine(machine)),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                items.push(MenuItem::new(
+                    "Reset",
+                    Command::Action(MenuAction::SetMachine(status.machine)),
+                ));
+                items
+            }
+            Page::Settings => {
+                let (speed, next_speed) = match status.speed {
+                    EmulationMode::FrameCount(1) => ("x1", EmulationMode::FrameCount(2)),
+                    EmulationMode::FrameCount(_) => ("x2", EmulationMode::Max),
+                    EmulationMode::Max => ("max", EmulationMode::FrameCount(1)),
+                };
+                let kempston = if status.kempston { "on" } else { "off" };
+                vec![
+                    MenuItem::new(
+                        format!("Speed: {}", speed),
+                        Command::Toggle(MenuAction::ChangeSpeed(next_speed)),
+                    ),
+                    MenuItem::new(
+                        format!("Kempston joystick: {}", kempston),
+                        Command::Toggle(MenuAction::SetKempston(!status.kempston)),
+                    ),
+                ]
+            }
+        }
+    }
+}
+
+/// Returns sorted subdirectories and supported files of the directory
+fn read_dir_entries(dir: &Path) -> Vec<FileEntry> {
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) => {
+            log::warn!("Failed to read directory {}: {}", dir.display(), e);
+            return Vec::new();
+        }
+    };
+    let mut entries = read_dir
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let is_dir = path.is_dir();
+            if !is_dir && !host::is_supported_file(&path) {
+                return None;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let label = if is_dir { format!("{}/", name) } else { name };
+            Some(FileEntry {
+                label,
+                path,
+                is_dir,
+            })
+        })
+        .collect::<Vec<_>>();
+    // Directories first
+    entries.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| a.label.to_lowercase().cmp(&b.label.to_lowercase()))
+    });
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS: MenuStatus = MenuStatus {
+        machine: ZXMachine::Sinclair48K,
+        rom_machine: None,
+        speed: EmulationMode::FrameCount(1),
+        kempston: true,
+    };
+
+    #[test]
+    fn menu_navigation() {
+        let mut menu = Menu::new(PathBuf::from("."));
+        assert!(menu.view(&STATUS).is_none());
+        menu.open();
+
+        // Selection wraps around
+        assert_eq!(menu.input(MenuKey::Up, &STATUS), None);
+        assert_eq!(menu.view(&STATUS).unwrap().selected, 7);
+        assert_eq!(menu.input(MenuKey::Down, &STATUS), None);
+
+        // Machine page, 128K
+        menu.input(MenuKey::Down, &STATUS);
+        menu.input(MenuKey::Down, &STATUS);
+        menu.input(MenuKey::Down, &STATUS);
+        menu.input(MenuKey::Down, &STATUS);
+        menu.input(MenuKey::Down, &STATUS);
+        menu.input(MenuKey::Select, &STATUS);
+        let view = menu.view(&STATUS).unwrap();
+        assert_eq!(view.title, "Machine");
+        assert_eq!(
+            view.items,
+            vec!["* ZX Spectrum 48K", "  ZX Spectrum 128K", "Reset"]
+        );
+        menu.input(MenuKey::Down, &STATUS);
+        assert_eq!(
+            menu.input(MenuKey::Select, &STATUS),
+            Some(MenuAction::SetMachine(ZXMachine::Sinclair128K))
+        );
+        assert!(!menu.is_open());
+
+        // Settings are changed without closing the menu, back closes pages
+        menu.open();
+        for _ in 0..6 {
+            menu.input(MenuKey::Down, &STATUS);
+        }
+        menu.input(MenuKey::Select, &STATUS);
+        assert_eq!(
+            menu.input(MenuKey::Select, &STATUS),
+            Some(MenuAction::ChangeSpeed(EmulationMode::FrameCount(2)))
+        );
+        assert!(menu.is_open());
+        menu.input(MenuKey::Back, &STATUS);
+        assert_eq!(menu.view(&STATUS).unwrap().title, "RustZX");
+        menu.input(MenuKey::Back, &STATUS);
+        assert!(!menu.is_open());
+    }
+}
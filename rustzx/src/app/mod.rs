@@ -1,9 +1,100 @@
 //! This module provides main application class.
+mod config;
 mod events;
+mod idle;
+mod menu;
+mod osd;
 mod rustzx;
+mod screen_reader;
 mod settings;
 mod sound;
+mod state_diff;
+mod stats;
+mod sweep;
+mod tape_notes;
 pub(crate) mod video;
 
+use anyhow::Context;
+use std::path::Path;
+
 // main re-export
-pub use self::{rustzx::RustzxApp, settings::Settings};
+pub use self::{
+    rustzx::RustzxApp,
+    settings::{Command, Settings},
+};
+
+/// Prints available sound output devices for the selected backend
+pub fn print_sound_devices(settings: &Settings) -> anyhow::Result<()> {
+    for name in sound::device_names(settings.sound_backend)? {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+/// Prints play time statistics, the most played files first
+pub fn print_stats(settings: &Settings) -> anyhow::Result<()> {
+    let path = settings
+        .stats_path()
+        .ok_or_else(|| anyhow::anyhow!("Failed to detect user config directory"))?;
+    let stats = stats::PlayStats::load(&path)?;
+    let games = stats.games();
+    if games.is_empty() {
+        println!("No statistics recorded yet");
+        return Ok(());
+    }
+    println!("{:>10}  {:>5}  {:<10}  Name", "Play time", "Loads", "Last");
+    for game in games {
+        println!(
+            "{:>10}  {:>5}  {:<10}  {}",
+            stats::format_play_time(game.play_time),
+            game.loads,
+            stats::format_date(game.last_played),
+            game.name
+        );
+    }
+    Ok(())
+}
+
+/// Prints differences between two snapshots: registers, peripherals state and
+/// changed memory ranges
+pub fn print_state_diff(settings: &Settings, first: &Path, second: &Path) -> anyhow::Result<()> {
+    let first = state_diff::LoadedState::load(settings, first)?;
+    let second = state_diff::LoadedState::load(settings, second)?;
+    let lines = state_diff::diff_states(&first, &second);
+    if lines.is_empty() {
+        println!("States are identical");
+    }
+    for line in lines {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Runs compatibility sweep over the snapshots and tapes in `dir` and writes
+/// CSV report to `output`
+pub fn run_sweep(
+    settings: &Settings,
+    dir: &Path,
+    frames: usize,
+    threads: Option<usize>,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let files = sweep::collect_files(dir)?;
+    let threads = threads
+        .or_else(|| std::thread::available_parallelism().ok().map(Into::into))
+        .unwrap_or(1);
+    let results = sweep::run(settings, &files, frames, threads);
+    std::fs::write(output, sweep::to_csv(&results))
+        .with_context(|| format!("Failed to write sweep report {}", output.display()))?;
+
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    println!(
+        "{} files: {} ok, {} hangs, {} errors, {} crashes",
+        results.len(),
+        count(sweep::SweepStatus::Ok),
+        count(sweep::SweepStatus::Hang),
+        count(sweep::SweepStatus::Error),
+        count(sweep::SweepStatus::Crash),
+    );
+    Ok(())
+}
@@ -0,0 +1,317 @@
+//! On-screen display, which renders short text messages over the emulated screen
+mod font;
+
+use rustzx_core::zx::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use rustzx_utils::frame_buffer::RGBA_PIXEL_SIZE;
+use std::time::{Duration, Instant};
+
+/// OSD covers the whole emulated screen, including border
+pub const OSD_WIDTH: usize = SCREEN_WIDTH;
+pub const OSD_HEIGHT: usize = SCREEN_HEIGHT;
+
+/// How long message is shown
+const MESSAGE_DURATION: Duration = Duration::from_secs(2);
+/// Distance from the message box to the screen edge in pixels
+const MARGIN: usize = 4;
+/// Distance from the text to the message box edge in glyph pixels
+const PADDING: usize = 2;
+
+struct OsdStyle {
+    text: [u8; 4],
+    background: [u8; 4],
+    /// Optional frame around message box
+    frame: Option<[u8; 4]>,
+}
+
+const NORMAL_STYLE: OsdStyle = OsdStyle {
+    text: [0xFF, 0xFF, 0xFF, 0xFF],
+    background: [0x00, 0x00, 0x00, 0xA0],
+    frame: None,
+};
+
+/// Opaque black box with yellow text and white frame
+const HIGH_CONTRAST_STYLE: OsdStyle = OsdStyle {
+    text: [0xFF, 0xFF, 0x00, 0xFF],
+    background: [0x00, 0x00, 0x00, 0xFF],
+    frame: Some([0xFF, 0xFF, 0xFF, 0xFF]),
+};
+
+/// Menu contents, drawn in the center of the screen
+pub struct OsdMenu {
+    pub title: String,
+    pub items: Vec<String>,
+    pub selected: usize,
+}
+
+pub struct Osd {
+    buffer: Vec<u8>,
+    scale: usize,
+    style: &'static OsdStyle,
+    message: Option<(String, Instant)>,
+    menu: Option<OsdMenu>,
+    dirty: bool,
+}
+
+impl Osd {
+    pub fn new(scale: usize, high_contrast: bool) -> Self {
+        Self {
+            buffer: vec![0; OSD_WIDTH * OSD_HEIGHT * RGBA_PIXEL_SIZE],
+            scale,
+            style: if high_contrast {
+                &HIGH_CONTRAST_STYLE
+            } else {
+                &NORMAL_STYLE
+            },
+            message: None,
+            menu: None,
+            dirty: false,
+        }
+    }
+
+    /// Shows message at the bottom of the screen, replacing the previous one
+    pub fn show_message(&mut self, text: impl Into<String>) {
+        self.show_message_for(text, MESSAGE_DURATION);
+    }
+
+    /// Shows message for the given `duration`, replacing the previous one
+    pub fn show_message_for(&mut self, text: impl Into<String>, duration: Duration) {
+        self.message = Some((text.into(), Instant::now() + duration));
+        self.dirty = true;
+    }
+
+    /// Shows menu, or hides it if `None` is passed
+    pub fn set_menu(&mut self, menu: Option<OsdMenu>) {
+        self.menu = menu;
+        self.dirty = true;
+    }
+
+    /// Returns true if there is something to draw
+    pub fn visible(&self) -> bool {
+        self.message.is_some() || self.menu.is_some()
+    }
+
+    /// Updates OSD state, returns true if OSD contents were changed and
+    /// should be uploaded again
+    pub fn update(&mut self) -> bool {
+        if let Some((_, expires)) = &self.message {
+            if Instant::now() >= *expires {
+                self.message = None;
+                self.dirty = true;
+            }
+        }
+        if !self.dirty {
+            return false;
+        }
+        self.dirty = false;
+        self.render();
+        true
+    }
+
+    pub fn rgba_data(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn render(&mut self) {
+        self.buffer.fill(0);
+        if let Some(menu) = self.menu.take() {
+            self.render_menu(&menu);
+            self.menu = Some(menu);
+        }
+        if let Some((text, _)) = &self.message {
+            self.render_message(&text.clone());
+        }
+    }
+
+    fn render_menu(&mut self, menu: &OsdMenu) {
+        let glyph_size = font::GLYPH_SIZE * self.scale;
+        let padding = PADDING * self.scale;
+        let max_chars = (OSD_WIDTH - (MARGIN + padding) * 2) / glyph_size;
+        // Title and separator line take two rows
+        let max_rows = (OSD_HEIGHT - (MARGIN + padding) * 2) / glyph_size - 2;
+        let visible_rows = menu.items.len().min(max_rows);
+        // Scroll to keep the selected item visible
+        let first_row = (menu.selected + 1)
+            .saturating_sub(visible_rows)
+            .min(menu.items.len() - visible_rows);
+
+        let title = truncate_text(&menu.title, max_chars);
+        let items = menu.items[first_row..first_row + visible_rows]
+            .iter()
+            .map(|item| truncate_text(item, max_chars))
+            .collect::<Vec<_>>();
+        let longest_line = items
+            .iter()
+            .chain(std::iter::once(&title))
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let box_width = longest_line * glyph_size + padding * 2;
+        let box_height = (visible_rows + 2) * glyph_size + padding * 2;
+        let box_x = (OSD_WIDTH - box_width) / 2;
+        let box_y = (OSD_HEIGHT - box_height) / 2;
+        self.draw_box(box_x, box_y, box_width, box_height);
+
+        let text_x = box_x + padding;
+        let mut y = box_y + padding;
+        self.draw_text(text_x, y, &title, self.style.text);
+        y += glyph_size;
+        self.fill_rect(
+            text_x,
+            y + glyph_size / 2,
+            box_width - padding * 2,
+            self.scale,
+            self.style.text,
+        );
+        y += glyph_size;
+        for (row, item) in items.iter().enumerate() {
+            if first_row + row == menu.selected {
+                // Selected item is drawn in inverse video
+                let mut text_color = self.style.background;
+                text_color[3] = 0xFF;
+                self.fill_rect(
+                    text_x,
+                    y,
+                    box_width - padding * 2,
+                    glyph_size,
+                    self.style.text,
+                );
+                self.draw_text(text_x, y, item, text_color);
+            } else {
+                self.draw_text(text_x, y, item, self.style.text);
+            }
+            y += glyph_size;
+        }
+    }
+
+    fn render_message(&mut self, text: &str) {
+        let glyph_size = font::GLYPH_SIZE * self.scale;
+        let padding = PADDING * self.scale;
+        let max_chars = (OSD_WIDTH - (MARGIN + padding) * 2) / glyph_size;
+        let lines = wrap_text(text, max_chars);
+        let longest_line = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        let box_width = longest_line * glyph_size + padding * 2;
+        let box_height = (lines.len() * glyph_size + padding * 2).min(OSD_HEIGHT - MARGIN * 2);
+        let box_x = (OSD_WIDTH - box_width) / 2;
+        let box_y = OSD_HEIGHT - MARGIN - box_height;
+
+        self.draw_box(box_x, box_y, box_width, box_height);
+
+        for (row, line) in lines.iter().enumerate() {
+            let y = box_y + padding + row * glyph_size;
+            if y + glyph_size > box_y + box_height {
+                break;
+            }
+            self.draw_text(box_x + padding, y, line, self.style.text);
+        }
+    }
+
+    /// Draws box background and frame
+    fn draw_box(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        self.fill_rect(x, y, width, height, self.style.background);
+        if let Some(frame) = self.style.frame {
+            let w = self.scale;
+            self.fill_rect(x, y, width, w, frame);
+            self.fill_rect(x, y + height - w, width, w, frame);
+            self.fill_rect(x, y, w, height, frame);
+            self.fill_rect(x + width - w, y, w, height, frame);
+        }
+    }
+
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, color: [u8; 4]) {
+        let glyph_size = font::GLYPH_SIZE * self.scale;
+        for (col, ch) in text.chars().enumerate() {
+            self.draw_glyph(x + col * glyph_size, y, ch, color);
+        }
+    }
+
+    fn draw_glyph(&mut self, x: usize, y: usize, ch: char, color: [u8; 4]) {
+        let code = if (' '..='\x7F').contains(&ch) {
+            ch as u8
+        } else {
+            b'?'
+        };
+        let glyph = font::GLYPHS[(code - font::FIRST_CHAR) as usize];
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..font::GLYPH_SIZE {
+                if bits & (0x80 >> col) != 0 {
+                    self.fill_rect(
+                        x + col * self.scale,
+                        y + row * self.scale,
+                        self.scale,
+                        self.scale,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 4]) {
+        for row in y..(y + height).min(OSD_HEIGHT) {
+            for col in x..(x + width).min(OSD_WIDTH) {
+                let pos = (row * OSD_WIDTH + col) * RGBA_PIXEL_SIZE;
+                self.buffer[pos..pos + RGBA_PIXEL_SIZE].copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+/// Cuts text to `max_chars`, cut part is replaced with `..`
+fn truncate_text(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_owned();
+    }
+    let mut text = text
+        .chars()
+        .take(max_chars.saturating_sub(2))
+        .collect::<String>();
+    text.push_str("..");
+    text
+}
+
+/// Splits text to lines of `max_chars` length at word boundaries, too long
+/// words are split as well
+fn wrap_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word = word.chars().collect::<Vec<_>>();
+            let line_len = line.chars().count();
+            if line_len != 0 && line_len + 1 + word.len() > max_chars {
+                lines.push(std::mem::take(&mut line));
+            }
+            while word.len() > max_chars {
+                let rest = word.split_off(max_chars);
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_text_splits_at_word_boundaries() {
+        assert_eq!(wrap_text("Tape: play", 20), vec!["Tape: play"]);
+        assert_eq!(
+            wrap_text("Loaded some game.tap", 10),
+            vec!["Loaded", "some", "game.tap"]
+        );
+        assert_eq!(wrap_text("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+    }
+}
@@ -4,9 +4,15 @@
 
 use crate::{
     app::{
-        events::{Event, EventDevice, EventsSdl},
-        settings::{Settings, SoundBackend},
+        events::{Event, EventDevice, EventsSdl, RumblePulse},
+        idle::{FrameActivity, IdleDetector},
+        menu::{Menu, MenuAction, MenuStatus},
+        osd::{Osd, OSD_HEIGHT, OSD_WIDTH},
+        screen_reader::ScreenReader,
+        settings::{DisplayRate, Settings, SoundBackend},
         sound::{SoundDevice, DEFAULT_SAMPLE_RATE},
+        stats::{format_play_time, PlayStats},
+        tape_notes::{TapeNotes, TAPE_NOTES_EXTENSION},
         video::{Rect, TextureInfo, VideoDevice, VideoSdl},
     },
     host::{self, AppHost, AppHostContext, DetectedFileKind},
@@ -14,27 +20,78 @@ use crate::{
 use anyhow::{anyhow, Context};
 use rustzx_core::{
     host::SnapshotRecorder,
-    zx::constants::{
-        CANVAS_HEIGHT, CANVAS_WIDTH, CANVAS_X, CANVAS_Y, FPS, SCREEN_HEIGHT, SCREEN_WIDTH,
+    zx::{
+        constants::{
+            CANVAS_HEIGHT, CANVAS_WIDTH, CANVAS_X, CANVAS_Y, FPS, SCREEN_HEIGHT, SCREEN_WIDTH,
+        },
+        feedback::FeedbackEvent,
+        machine::ZXMachine,
     },
-    Emulator,
+    EmulationMode, Emulator,
+};
+use rustzx_utils::{
+    frame_buffer::{compose_rgba_frame, RGBA_FRAME_SIZE},
+    io::FileAsset,
+    svg::rgba_to_svg,
 };
-use rustzx_utils::io::FileAsset;
 use std::{
     fs::{self, File},
     path::{Path, PathBuf},
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 /// max 100 ms interval in `max frames` speed mode
 const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
 
+/// how long tape note is shown, notes are usually longer than status messages
+const TAPE_NOTE_DURATION: Duration = Duration::from_secs(5);
+
+/// max count of frames emulated at once to catch up with display-paced presentation
+const MAX_CATCH_UP_FRAMES: usize = 5;
+/// time before the expected display refresh at which display-paced loop wakes up
+const PRESENT_MARGIN: Duration = Duration::from_millis(2);
+/// remaining time below which `sleep_until` spins instead of sleeping
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+/// count of frames emulated per host poll while machine is idle
+const IDLE_POLL_FRAMES: u32 = 5;
+
 /// returns frame length from given `fps`
 fn frame_length(fps: usize) -> Duration {
     Duration::from_millis((1000_f64 / fps as f64) as u64)
 }
 
+/// Copy of the last emulated frame, which is used to detect frame changes and
+/// to skip redundant texture uploads
+#[derive(Default)]
+struct FrameCache {
+    screen: Vec<u8>,
+    border: Vec<u8>,
+    screen_dirty: bool,
+    border_dirty: bool,
+    /// Previous border texture does not contain the current border yet
+    border_prev_stale: bool,
+}
+
+impl FrameCache {
+    /// Stores the frame, returns true if it was changed
+    fn update(&mut self, screen: &[u8], border: &[u8]) -> bool {
+        let screen_changed = self.screen != screen;
+        if screen_changed {
+            self.screen.clear();
+            self.screen.extend_from_slice(screen);
+            self.screen_dirty = true;
+        }
+        let border_changed = self.border != border;
+        if border_changed {
+            self.border.clear();
+            self.border.extend_from_slice(border);
+            self.border_dirty = true;
+        }
+        screen_changed || border_changed
+    }
+}
+
 /// Application instance type
 pub struct RustzxApp {
     /// main emulator object
@@ -44,9 +101,22 @@ pub struct RustzxApp {
     video: Box<dyn VideoDevice>,
     events: Box<dyn EventDevice>,
     tex_border: TextureInfo,
+    tex_border_prev: TextureInfo,
     tex_canvas: TextureInfo,
+    tex_osd: TextureInfo,
+    frame_cache: FrameCache,
+    idle: IdleDetector,
+    osd: Osd,
+    menu: Menu,
+    screen_reader: Option<ScreenReader>,
+    tape_notes: Option<TapeNotes>,
+    stats: Option<PlayStats>,
+    /// Custom ROM is used only for the machine it was provided for
+    custom_rom: Option<(ZXMachine, PathBuf)>,
     scale: u32,
     settings: Settings,
+    /// Main loop paces every emulated frame separately
+    frame_paced: bool,
 
     enable_frame_trace: bool,
     enable_joy_keyaboard_layer: bool,
@@ -65,23 +135,37 @@ impl RustzxApp {
             None
         };
         let mut video = Box::new(VideoSdl::new(&settings));
-        let tex_border = video.gen_texture(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
         let tex_canvas = video.gen_texture(CANVAS_WIDTH as u32, CANVAS_HEIGHT as u32);
+        let tex_osd = video.gen_texture(OSD_WIDTH as u32, OSD_HEIGHT as u32);
+        // OSD texture is always drawn with alpha blending
+        video.set_texture_alpha(tex_osd, Some(0xFF));
+        let osd = Osd::new(settings.osd_scale.unwrap_or(1), settings.osd_high_contrast);
+        let screen_reader = settings
+            .screen_reader
+            .then(|| ScreenReader::new(settings.tts_command.as_deref()));
+        let stats = if settings.disable_stats {
+            None
+        } else {
+            // Broken statistics file should not prevent emulator from running
+            settings.stats_path().and_then(|path| {
+                PlayStats::load(&path)
+                    .map_err(|e| log::warn!("{:#}, statistics are disabled", e))
+                    .ok()
+            })
+        };
         let scale = settings.scale as u32;
-        let events = Box::new(EventsSdl::new(&settings));
+        let events = Box::new(EventsSdl::new(&settings)?);
         let sample_rate = snd
             .as_ref()
             .map(|s| s.sample_rate())
             .unwrap_or(DEFAULT_SAMPLE_RATE);
 
-        let mut emulator = Emulator::new(settings.to_rustzx_settings(sample_rate), AppHostContext)
-            .map_err(|e| anyhow!("Failed to construct emulator: {}", e))?;
-
-        if let Some(rom) = settings.rom.as_ref() {
-            emulator
-                .load_rom(host::load_rom(rom, settings.machine)?)
-                .map_err(|e| anyhow!("Emulator failed to load rom: {}", e))?;
-        }
+        let mut emulator = create_emulator(&settings, sample_rate)?;
+        // Border buffer is a single pixel in non-precise border modes, texture
+        // is stretched to the border area on render
+        let border = emulator.border_buffer();
+        let tex_border = video.gen_texture(border.width() as u32, border.height() as u32);
+        let tex_border_prev = video.gen_texture(border.width() as u32, border.height() as u32);
         if let Some(snapshot) = settings.snap.as_ref() {
             emulator
                 .load_snapshot(host::load_snapshot(snapshot)?)
@@ -99,6 +183,14 @@ impl RustzxApp {
         }
 
         let file_autodetect = settings.file_autodetect.clone();
+        let menu = Menu::new(
+            file_autodetect
+                .as_ref()
+                .and_then(|path| path.parent())
+                .map(Path::to_owned)
+                .unwrap_or_else(|| PathBuf::from(".")),
+        );
+        let custom_rom = settings.rom.clone().map(|rom| (settings.machine, rom));
 
         let mut app = RustzxApp {
             emulator,
@@ -106,16 +198,37 @@ impl RustzxApp {
             video,
             events,
             tex_border,
+            tex_border_prev,
             tex_canvas,
+            tex_osd,
+            frame_cache: FrameCache::default(),
+            idle: IdleDetector::default(),
+            osd,
+            menu,
+            screen_reader,
+            tape_notes: None,
+            stats,
+            custom_rom,
             scale,
             settings,
+            frame_paced: false,
             enable_frame_trace: cfg!(debug_assertions),
             enable_joy_keyaboard_layer: false,
         };
 
+        if let Some(snap) = app.settings.snap.clone() {
+            app.start_game_stats(&snap);
+        }
+        if let Some(tape) = app.settings.tape.clone() {
+            app.load_tape_notes(&tape)?;
+            app.start_game_stats(&tape);
+        }
         if let Some(file) = file_autodetect.as_ref() {
             app.load_file_autodetect(file)?;
         }
+        if let Some(summary) = app.stats_summary() {
+            app.osd.show_message(summary);
+        }
 
         app.update_window_title();
 
@@ -137,96 +250,83 @@ impl RustzxApp {
     }
 
     pub fn start(&mut self) -> anyhow::Result<()> {
-        let scale = self.scale;
-        'emulator: loop {
-            let frame_target_dt = frame_length(FPS);
-            // absolute start time
-            let frame_start = Instant::now();
-            // Emulate all requested frames
-            let emulator_dt = self
-                .emulator
-                .emulate_frames(MAX_FRAME_TIME)
-                .map_err(|e| anyhow!("Emulation step failed: {:#?}", e))?
-                .duration;
-            // if sound enabled sound ganeration allowed then move samples to sound thread
-            if let Some(ref mut snd) = self.snd {
-                // if can be turned off even on speed change, so check it everytime
-                if self.emulator.have_sound() {
-                    while let Some(sample) = self.emulator.next_audio_sample() {
-                        snd.send_sample(sample);
-                    }
-                }
+        let result = self.run();
+        if let Some(stats) = &mut self.stats {
+            if let Err(e) = stats.save() {
+                log::warn!("{:#}", e);
             }
+        }
+        result
+    }
 
-            self.video
-                .update_texture(self.tex_border, self.emulator.border_buffer().rgba_data());
-            self.video
-                .update_texture(self.tex_canvas, self.emulator.screen_buffer().rgba_data());
+    fn run(&mut self) -> anyhow::Result<()> {
+        if self.settings.low_latency {
+            self.set_frame_paced();
+            return self.run_low_latency();
+        }
+        match self.display_refresh_rate() {
+            Some(refresh_rate) => {
+                self.set_frame_paced();
+                self.run_display_paced(refresh_rate)
+            }
+            None => self.run_sleep_paced(),
+        }
+    }
 
-            self.video.begin();
-            self.video.draw_texture_2d(
-                self.tex_border,
-                Some(Rect::new(
-                    0,
-                    0,
-                    SCREEN_WIDTH as u32 * scale,
-                    SCREEN_HEIGHT as u32 * scale,
-                )),
-            );
-            self.video.draw_texture_2d(
-                self.tex_canvas,
-                Some(Rect::new(
-                    CANVAS_X as i32 * scale as i32,
-                    CANVAS_Y as i32 * scale as i32,
-                    CANVAS_WIDTH as u32 * scale,
-                    CANVAS_HEIGHT as u32 * scale,
-                )),
-            );
-            self.video.end();
-            // check all events
-            while let Some(event) = self.events.pop_event() {
-                match event {
-                    Event::Exit => {
-                        break 'emulator;
-                    }
-                    Event::ZXKey(key, state) => {
-                        self.emulator.send_key(key, state);
-                    }
-                    Event::SwitchFrameTrace => {
-                        self.enable_frame_trace = !self.enable_frame_trace;
-                        self.update_window_title();
-                    }
-                    Event::ChangeJoyKeyboardLayer(value) => {
-                        self.enable_joy_keyaboard_layer = value;
-                        self.update_window_title();
-                    }
-                    Event::ChangeSpeed(speed) => {
-                        self.emulator.set_speed(speed);
-                    }
-                    Event::Kempston(key, state) => {
-                        self.emulator.send_kempston_key(key, state);
-                    }
-                    Event::Sinclair(num, key, state) => {
-                        self.emulator.send_sinclair_key(num, key, state);
-                    }
-                    Event::CompoundKey(key, state) => {
-                        self.emulator.send_compound_key(key, state);
-                    }
-                    Event::MouseMove { x, y } => {
-                        self.emulator.send_mouse_pos_diff(x, y);
-                    }
-                    Event::MouseButton(button, pressed) => {
-                        self.emulator.send_mouse_button(button, pressed);
-                    }
-                    Event::MouseWheel(direction) => {
-                        self.emulator.send_mouse_wheel(direction);
-                    }
-                    Event::InsertTape => self.emulator.play_tape(),
-                    Event::StopTape => self.emulator.stop_tape(),
-                    Event::OpenFile(path) => self.load_file_autodetect(&path)?,
-                    Event::QuickSave => self.quick_save()?,
-                    Event::QuickLoad => self.quick_load()?,
+    fn set_frame_paced(&mut self) {
+        self.frame_paced = true;
+        self.apply_speed();
+    }
+
+    /// Passes speed to the emulator. Loops which pace every emulated frame apply
+    /// speed multiplier by shortening the frame, so emulator produces single frames
+    fn apply_speed(&mut self) {
+        let speed = match self.settings.speed {
+            EmulationMode::FrameCount(_) if self.frame_paced => EmulationMode::FrameCount(1),
+            speed => speed,
+        };
+        self.emulator.set_speed(speed);
+    }
+
+    /// Returns host time of one emulated frame for the frame-paced loops, `None`
+    /// in max speed mode, which is not paced
+    fn frame_target_dt(&self) -> Option<Duration> {
+        match self.settings.speed {
+            EmulationMode::FrameCount(frames) => {
+                Some(Duration::from_secs_f64(1.0 / (FPS * frames.max(1)) as f64))
+            }
+            EmulationMode::Max => None,
+        }
+    }
+
+    /// Returns display refresh rate if display-paced presentation was requested
+    fn display_refresh_rate(&self) -> Option<usize> {
+        match self.settings.display_rate? {
+            DisplayRate::Hz(rate) => Some(rate),
+            DisplayRate::Auto => {
+                let rate = self.video.display_refresh_rate();
+                if rate.is_none() {
+                    log::warn!("Failed to detect display refresh rate, using 50 Hz pacing");
                 }
+                rate
+            }
+        }
+    }
+
+    /// Classic loop: one present per emulated frame, remaining frame time is slept
+    fn run_sleep_paced(&mut self) -> anyhow::Result<()> {
+        loop {
+            let frame_target_dt = frame_length(FPS);
+            // absolute start time
+            let frame_start = Instant::now();
+            let emulator_dt = self.emulate_frame()?;
+            self.update_textures();
+            self.render(None);
+            if !self.process_events()? {
+                break;
+            }
+            if self.idle.is_idle() && !self.run_idle()? {
+                break;
             }
             // how long emulation iteration was
             let emulation_dt = frame_start.elapsed();
@@ -240,7 +340,7 @@ impl RustzxApp {
             // change window header
             if self.enable_frame_trace {
                 log::trace!(
-                    "EMUALTOR: {:7.3}ms; FRAME:{:7.3}ms",
+                    "EMULATOR: {:7.3}ms; FRAME:{:7.3}ms",
                     emulator_dt.as_millis(),
                     frame_dt.as_millis()
                 );
@@ -249,6 +349,500 @@ impl RustzxApp {
         Ok(())
     }
 
+    /// Low latency loop: frame time is slept *before* the frame, so events are
+    /// polled right before emulation starts. Most games read input in the
+    /// interrupt handler at the start of the frame, so input gets to the game
+    /// as fresh as possible, and the rendered frame is presented immediately
+    fn run_low_latency(&mut self) -> anyhow::Result<()> {
+        let mut next_frame = Instant::now();
+        loop {
+            sleep_until(next_frame);
+            if !self.process_events()? {
+                break;
+            }
+            let emulator_dt = self.emulate_frame()?;
+            self.update_textures();
+            self.render(None);
+            if self.idle.is_idle() {
+                if !self.run_idle()? {
+                    break;
+                }
+                next_frame = Instant::now();
+            }
+
+            let now = Instant::now();
+            match self.frame_target_dt() {
+                Some(frame_target_dt) => {
+                    next_frame += frame_target_dt;
+                    if now > next_frame + frame_target_dt * MAX_CATCH_UP_FRAMES as u32 {
+                        // Emulation can't keep up, drop accumulated lag
+                        next_frame = now;
+                    }
+                }
+                // Max speed: next frame is emulated right away
+                None => next_frame = now,
+            }
+            if self.enable_frame_trace {
+                log::trace!("EMULATOR: {:7.3}ms", emulator_dt.as_millis());
+            }
+        }
+        Ok(())
+    }
+
+    /// High refresh rate loop: window is presented on every display refresh, and
+    /// emulated frames are produced when their 50 Hz deadline passes. As emulated
+    /// time advances in exact frame steps, each frame is shown for an evenly
+    /// distributed number of refreshes (e.g. 3,3,3,3,2 on 144 Hz display)
+    fn run_display_paced(&mut self, refresh_rate: usize) -> anyhow::Result<()> {
+        log::info!("Presenting frames at {} Hz display rate", refresh_rate);
+        let present_interval = Duration::from_secs_f64(1.0 / refresh_rate as f64);
+        let mut next_frame = Instant::now();
+        loop {
+            let now = Instant::now();
+            // Speed may be changed on any frame
+            let frame_target_dt = self.frame_target_dt();
+            let mut frames_emulated = 0;
+            while now >= next_frame {
+                if frames_emulated == MAX_CATCH_UP_FRAMES {
+                    // Emulation can't keep up, drop accumulated lag
+                    next_frame = now + frame_target_dt.unwrap_or_default();
+                    break;
+                }
+                let emulator_dt = self.emulate_frame()?;
+                frames_emulated += 1;
+                if self.enable_frame_trace {
+                    log::trace!("EMULATOR: {:7.3}ms", emulator_dt.as_millis());
+                }
+                match frame_target_dt {
+                    Some(frame_target_dt) => next_frame += frame_target_dt,
+                    // Max speed: one emulation step per present
+                    None => break,
+                }
+            }
+            if frames_emulated != 0 {
+                self.update_textures();
+            }
+
+            let border_alpha =
+                frame_target_dt
+                    .filter(|_| self.settings.border_blend)
+                    .map(|frame_target_dt| {
+                        // Phase of the current frame: 0 right after it was emulated, 1 at
+                        // the moment the next one is due
+                        let remaining = next_frame.saturating_duration_since(now);
+                        let phase = 1.0 - remaining.as_secs_f64() / frame_target_dt.as_secs_f64();
+                        (phase.clamp(0.0, 1.0) * 255.0) as u8
+                    });
+            self.render(border_alpha);
+            let presented = Instant::now();
+
+            if !self.process_events()? {
+                break;
+            }
+            if self.idle.is_idle() {
+                if !self.run_idle()? {
+                    break;
+                }
+                next_frame = Instant::now();
+            }
+            // Vsync-enabled present returns right after display refresh, so wake up
+            // a bit before the next one to have a frame ready in time
+            sleep_until(presented + present_interval.saturating_sub(PRESENT_MARGIN));
+        }
+        Ok(())
+    }
+
+    /// Idle loop, which is used while machine waits for input: frames are
+    /// emulated in batches between host polls and window is redrawn only when
+    /// something changes. Any input returns to the regular loop immediately.
+    /// Returns `false` if application should exit
+    fn run_idle(&mut self) -> anyhow::Result<bool> {
+        log::debug!("Machine is idle, throttling host polling");
+        let frame_target_dt = Duration::from_secs_f64(1.0 / FPS as f64);
+        let mut next_frame = Instant::now() + frame_target_dt;
+        while self.idle.is_idle() {
+            let batch_end = next_frame + frame_target_dt * (IDLE_POLL_FRAMES - 1);
+            self.events
+                .wait_event(batch_end.saturating_duration_since(Instant::now()));
+            let now = Instant::now();
+            let mut frames_emulated = 0;
+            while now >= next_frame {
+                if frames_emulated == IDLE_POLL_FRAMES {
+                    // Host was suspended, drop accumulated lag
+                    next_frame = now + frame_target_dt;
+                    break;
+                }
+                self.emulate_frame()?;
+                next_frame += frame_target_dt;
+                frames_emulated += 1;
+            }
+            // Window is redrawn also after non-input events (e.g. when it was
+            // uncovered), as they wake the loop too
+            if self.update_textures() || frames_emulated == 0 || self.osd.visible() {
+                self.render(None);
+            }
+            if !self.process_events()? {
+                return Ok(false);
+            }
+        }
+        log::debug!("Machine is active again");
+        Ok(true)
+    }
+
+    /// Emulates all requested frames and passes produced samples to the sound
+    /// device, returns time spent on emulation
+    fn emulate_frame(&mut self) -> anyhow::Result<Duration> {
+        // Emulation is paused while menu is open
+        if self.menu.is_open() {
+            return Ok(Duration::ZERO);
+        }
+        // Emulate all requested frames
+        let info = self
+            .emulator
+            .emulate_frames(MAX_FRAME_TIME)
+            .map_err(|e| anyhow!("Emulation step failed: {:#?}", e))?;
+        // Tape position and play time advance in emulated time, which differs
+        // from the host time on non-realtime speeds
+        let emulated_dt = frame_length(FPS) * info.frames as u32;
+        // if sound enabled sound ganeration allowed then move samples to sound thread
+        let mut sound = false;
+        if let Some(ref mut snd) = self.snd {
+            snd.maintain();
+            // if can be turned off even on speed change, so check it everytime
+            if self.emulator.have_sound() {
+                let mut first_sample = None;
+                while let Some(sample) = self.emulator.next_audio_sample() {
+                    let first = *first_sample.get_or_insert((sample.left, sample.right));
+                    sound |= first != (sample.left, sample.right);
+                    snd.send_sample(sample);
+                }
+            }
+        }
+        let frame_changed = self.frame_cache.update(
+            self.emulator.screen_buffer().rgba_data(),
+            self.emulator.border_buffer().rgba_data(),
+        );
+        // Throttling makes sense only for real-time emulation
+        if self.settings.disable_idle_throttle
+            || self.settings.speed != EmulationMode::FrameCount(1)
+        {
+            self.idle.wake();
+        } else {
+            self.idle.update(FrameActivity {
+                halt_ratio: self.emulator.last_frame_halt_ratio(),
+                frame_changed,
+                sound,
+            });
+        }
+        if let Some(stats) = &mut self.stats {
+            if let Err(e) = stats.add_play_time(emulated_dt) {
+                log::warn!("{:#}", e);
+            }
+        }
+        if self.settings.rumble {
+            self.process_feedback();
+        }
+        if let Some(screen_reader) = &mut self.screen_reader {
+            if let Some(report) = self.emulator.poll_basic_report() {
+                screen_reader.announce(&report);
+                self.osd.show_message(report);
+            }
+        }
+        if let Some(tape_notes) = &mut self.tape_notes {
+            let notes = tape_notes.update(self.emulator.tape_block(), emulated_dt);
+            if !notes.is_empty() {
+                let text = notes.join("\n");
+                if let Some(screen_reader) = &mut self.screen_reader {
+                    screen_reader.announce(&text);
+                }
+                self.osd.show_message_for(text, TAPE_NOTE_DURATION);
+            }
+        }
+        Ok(info.duration)
+    }
+
+    /// Translates emulator feedback events into a single rumble pulse
+    fn process_feedback(&mut self) {
+        let mut pulse: Option<RumblePulse> = None;
+        while let Some(event) = self.emulator.next_feedback_event() {
+            let event_pulse = rumble_pulse(event);
+            pulse = Some(pulse.map_or(event_pulse, |pulse| pulse.merge(event_pulse)));
+        }
+        if let Some(pulse) = pulse {
+            self.events.rumble(pulse);
+        }
+    }
+
+    /// Uploads changed emulator buffers to textures, previous border is kept for
+    /// blending. Returns false if textures were already up to date
+    fn update_textures(&mut self) -> bool {
+        let cache = &mut self.frame_cache;
+        let updated = cache.screen_dirty || cache.border_dirty || cache.border_prev_stale;
+        if cache.border_dirty || cache.border_prev_stale {
+            // Changed border is uploaded once more to get into the previous
+            // border texture too
+            cache.border_prev_stale = cache.border_dirty;
+            cache.border_dirty = false;
+            std::mem::swap(&mut self.tex_border, &mut self.tex_border_prev);
+            self.video
+                .update_texture(self.tex_border, self.emulator.border_buffer().rgba_data());
+        }
+        if cache.screen_dirty {
+            cache.screen_dirty = false;
+            self.video
+                .update_texture(self.tex_canvas, self.emulator.screen_buffer().rgba_data());
+        }
+        updated
+    }
+
+    /// Draws current frame. If `border_alpha` is set, current border is blended
+    /// over the previous one with the given opacity
+    fn render(&mut self, border_alpha: Option<u8>) {
+        let scale = self.scale;
+        let border_rect = Rect::new(
+            0,
+            0,
+            SCREEN_WIDTH as u32 * scale,
+            SCREEN_HEIGHT as u32 * scale,
+        );
+
+        if self.osd.update() {
+            self.video
+                .update_texture(self.tex_osd, self.osd.rgba_data());
+        }
+
+        self.video.begin();
+        if border_alpha.is_some() {
+            // Textures are swapped on update, so previous border may still have
+            // blending alpha set
+            self.video.set_texture_alpha(self.tex_border_prev, None);
+            self.video
+                .draw_texture_2d(self.tex_border_prev, Some(border_rect));
+        }
+        self.video.set_texture_alpha(self.tex_border, border_alpha);
+        self.video
+            .draw_texture_2d(self.tex_border, Some(border_rect));
+        self.video.draw_texture_2d(
+            self.tex_canvas,
+            Some(Rect::new(
+                CANVAS_X as i32 * scale as i32,
+                CANVAS_Y as i32 * scale as i32,
+                CANVAS_WIDTH as u32 * scale,
+                CANVAS_HEIGHT as u32 * scale,
+            )),
+        );
+        if self.osd.visible() {
+            self.video.draw_texture_2d(self.tex_osd, Some(border_rect));
+        }
+        self.video.end();
+    }
+
+    /// Handles all pending events, returns `false` if application should exit
+    fn process_events(&mut self) -> anyhow::Result<bool> {
+        while let Some(event) = self.events.pop_event() {
+            self.idle.wake();
+            match event {
+                Event::Exit => {
+                    return Ok(false);
+                }
+                Event::ZXKey(key, state) => {
+                    self.emulator.send_key(key, state);
+                }
+                Event::SwitchFrameTrace => {
+                    self.enable_frame_trace = !self.enable_frame_trace;
+                    self.update_window_title();
+                }
+                Event::ChangeJoyKeyboardLayer(value) => {
+                    self.enable_joy_keyaboard_layer = value;
+                    self.update_window_title();
+                }
+                Event::ChangeSpeed(speed) => self.change_speed(speed),
+                Event::Kempston(key, state) => {
+                    self.emulator.send_kempston_key(key, state);
+                }
+                Event::Sinclair(num, key, state) => {
+                    self.emulator.send_sinclair_key(num, key, state);
+                }
+                Event::CompoundKey(key, state) => {
+                    self.emulator.send_compound_key(key, state);
+                }
+                Event::MouseMove { x, y } => {
+                    self.emulator.send_mouse_pos_diff(x, y);
+                }
+                Event::MouseButton(button, pressed) => {
+                    self.emulator.send_mouse_button(button, pressed);
+                }
+                Event::MouseWheel(direction) => {
+                    self.emulator.send_mouse_wheel(direction);
+                }
+                Event::InsertTape => {
+                    self.emulator.play_tape();
+                    self.osd.show_message("Tape: play");
+                }
+                Event::StopTape => {
+                    self.emulator.stop_tape();
+                    self.osd.show_message("Tape: stop");
+                }
+                Event::OpenFile(path) => self.open_file(&path)?,
+                Event::QuickSave => {
+                    self.quick_save()?;
+                    self.osd.show_message("Quick save");
+                }
+                Event::QuickLoad => self.quick_load()?,
+                Event::CaptureSvg => match self.capture_svg() {
+                    Ok(path) => self.osd.show_message(format!("Saved {}", path.display())),
+                    Err(e) => {
+                        log::error!("{:#}", e);
+                        self.osd.show_message("Failed to save SVG capture");
+                    }
+                },
+                Event::MacroRecording(recording) => {
+                    self.osd.show_message(if recording {
+                        "Macro: recording"
+                    } else {
+                        "Macro: recorded"
+                    });
+                }
+                Event::ToggleMenu => {
+                    if self.menu.is_open() {
+                        self.menu.close();
+                    } else {
+                        self.menu.open();
+                    }
+                    self.update_menu();
+                }
+                Event::Menu(key) => {
+                    let action = self.menu.input(key, &self.menu_status());
+                    match action {
+                        Some(MenuAction::Exit) => return Ok(false),
+                        Some(action) => {
+                            // Errors are shown to user instead of stopping the emulator
+                            if let Err(e) = self.perform_menu_action(action) {
+                                log::error!("{:#}", e);
+                                self.osd.show_message(format!("Error: {:#}", e));
+                            }
+                        }
+                        None => {}
+                    }
+                    self.update_menu();
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn change_speed(&mut self, speed: EmulationMode) {
+        self.settings.speed = speed;
+        self.apply_speed();
+        self.osd.show_message(match speed {
+            EmulationMode::FrameCount(frames) => format!("Speed: x{}", frames),
+            EmulationMode::Max => "Speed: max".to_owned(),
+        });
+    }
+
+    fn menu_status(&self) -> MenuStatus {
+        MenuStatus {
+            machine: self.settings.machine,
+            rom_machine: self.custom_rom.as_ref().map(|(machine, _)| *machine),
+            speed: self.settings.speed,
+            kempston: self.emulator.kempston_enabled(),
+        }
+    }
+
+    /// Passes menu state to OSD and event device
+    fn update_menu(&mut self) {
+        self.osd.set_menu(self.menu.view(&self.menu_status()));
+        self.events.set_menu_mode(self.menu.is_open());
+    }
+
+    fn perform_menu_action(&mut self, action: MenuAction) -> anyhow::Result<()> {
+        match action {
+            MenuAction::OpenFile(path) => self.open_file(&path)?,
+            MenuAction::SetMachine(machine) => self.set_machine(machine)?,
+            MenuAction::QuickSave => {
+                self.quick_save()?;
+                self.osd.show_message("Quick save");
+            }
+            MenuAction::QuickLoad => self.quick_load()?,
+            MenuAction::PlayTape => {
+                self.emulator.play_tape();
+                self.osd.show_message("Tape: play");
+            }
+            MenuAction::StopTape => {
+                self.emulator.stop_tape();
+                self.osd.show_message("Tape: stop");
+            }
+            MenuAction::RewindTape => {
+                self.emulator
+                    .rewind_tape()
+                    .map_err(|e| anyhow!("Failed to rewind tape: {}", e))?;
+                self.osd.show_message("Tape: rewind");
+            }
+            MenuAction::ChangeSpeed(speed) => self.change_speed(speed),
+            MenuAction::SetKempston(value) => {
+                self.emulator
+                    .set_kempston_enabled(value)
+                    .map_err(|e| anyhow!("Can't attach Kempston joystick: {}", e))?;
+            }
+            MenuAction::Exit => unreachable!("Exit is handled by the caller"),
+        }
+        Ok(())
+    }
+
+    /// Recreates emulator with the given machine type, loaded files are lost
+    fn set_machine(&mut self, machine: ZXMachine) -> anyhow::Result<()> {
+        let prev_machine = std::mem::replace(&mut self.settings.machine, machine);
+        self.settings.rom = match &self.custom_rom {
+            Some((rom_machine, rom)) if *rom_machine == machine => Some(rom.clone()),
+            _ => None,
+        };
+        let sample_rate = self
+            .snd
+            .as_ref()
+            .map(|s| s.sample_rate())
+            .unwrap_or(DEFAULT_SAMPLE_RATE);
+        // Kempston joy could be changed via menu
+        let kempston_enabled = self.emulator.kempston_enabled();
+        match create_emulator(&self.settings, sample_rate) {
+            Ok(emulator) => self.emulator = emulator,
+            Err(e) => {
+                self.settings.machine = prev_machine;
+                return Err(e);
+            }
+        }
+        self.apply_speed();
+        self.emulator
+            .set_kempston_enabled(kempston_enabled)
+            .map_err(|e| anyhow!("Can't attach Kempston joystick: {}", e))?;
+        // Tape is not inserted to the new emulator
+        self.tape_notes = None;
+        if let Some(stats) = &mut self.stats {
+            stats.stop_game();
+        }
+        self.osd.show_message(match machine {
+            ZXMachine::Sinclair48K => "Machine: 48K",
+            ZXMachine::Sinclair128K => "Machine: 128K",
+            ZXMachine::SinclairPlus3 => "Machine: +3",
+            ZXMachine::Sinclair128KSpanish => "Machine: 128K (Spanish)",
+            ZXMachine::TimexTC2048 => "Machine: TC2048",
+        });
+        Ok(())
+    }
+
+    /// Loads file selected by user and reports it via OSD
+    fn open_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.load_file_autodetect(path)?;
+        if let Some(name) = path.file_name() {
+            let mut message = format!("Loaded {}", name.to_string_lossy());
+            if let Some(summary) = self.stats_summary() {
+                message.push('\n');
+                message.push_str(&summary);
+            }
+            self.osd.show_message(message);
+        }
+        Ok(())
+    }
+
     fn load_file_autodetect(&mut self, path: &Path) -> anyhow::Result<()> {
         match host::detect_file_type(path)? {
             DetectedFileKind::Snapshot => {
@@ -257,11 +851,14 @@ impl RustzxApp {
                     .map_err(|e| {
                         anyhow!("Emulator failed to load auto-detected snapshot: {}", e)
                     })?;
+                self.start_game_stats(path);
             }
             DetectedFileKind::Tape => {
                 self.emulator
                     .load_tape(host::load_tape(path)?)
                     .map_err(|e| anyhow!("Emulator failed to load auto-detected tape: {}", e))?;
+                self.load_tape_notes(path)?;
+                self.start_game_stats(path);
             }
             DetectedFileKind::Screen => self
                 .emulator
@@ -271,6 +868,44 @@ impl RustzxApp {
         Ok(())
     }
 
+    fn start_game_stats(&mut self, path: &Path) {
+        if let Some(stats) = &mut self.stats {
+            if let Err(e) = stats.start_game(path) {
+                log::warn!("{:#}", e);
+            }
+        }
+    }
+
+    /// Returns play time statistics of the current game if they should be
+    /// shown on OSD
+    fn stats_summary(&self) -> Option<String> {
+        if !self.settings.stats_osd {
+            return None;
+        }
+        let game = self.stats.as_ref()?.current_game()?;
+        Some(format!(
+            "Played {}, loaded {} times",
+            format_play_time(game.play_time),
+            game.loads
+        ))
+    }
+
+    /// Loads notes for the tape, notes file from settings has priority over
+    /// `.notes` file next to the tape
+    fn load_tape_notes(&mut self, tape: &Path) -> anyhow::Result<()> {
+        let path = match self.settings.tape_notes.clone() {
+            Some(path) => path,
+            None => tape.with_extension(TAPE_NOTES_EXTENSION),
+        };
+        self.tape_notes = if path.exists() {
+            log::info!("Using tape notes {}", path.display());
+            Some(TapeNotes::load(&path)?)
+        } else {
+            None
+        };
+        Ok(())
+    }
+
     fn quick_save(&mut self) -> anyhow::Result<()> {
         let new_path = self.last_quick_snapshot_path();
         let prev_path = self.prev_quick_snapshot_path();
@@ -282,7 +917,12 @@ impl RustzxApp {
             fs::rename(&new_path, &prev_path)?;
         }
 
-        let recorder = SnapshotRecorder::Sna(FileAsset::from(File::create(new_path)?));
+        let file = FileAsset::from(File::create(new_path)?);
+        let recorder = if self.quick_snapshot_is_z80() {
+            SnapshotRecorder::Z80(file)
+        } else {
+            SnapshotRecorder::Sna(file)
+        };
         self.emulator
             .save_snapshot(recorder)
             .map_err(|e| anyhow!("Failed to save qick snapshot: {}", e))?;
@@ -293,26 +933,136 @@ impl RustzxApp {
         let last_snapshot_path = self.last_quick_snapshot_path();
         if !last_snapshot_path.exists() {
             log::warn!("Quick snapshot was not found");
+            self.osd.show_message("No quick snapshot");
             return Ok(());
         }
         self.emulator
             .load_snapshot(host::load_snapshot(&last_snapshot_path)?)
             .map_err(|e| anyhow!("Emulator failed to load quick snapshot: {}", e))?;
+        self.osd.show_message("Quick load");
         Ok(())
     }
 
+    /// Saves current frame with border as SVG, returns path of the saved file
+    fn capture_svg(&mut self) -> anyhow::Result<PathBuf> {
+        let mut frame = vec![0u8; RGBA_FRAME_SIZE];
+        compose_rgba_frame(
+            self.emulator.border_buffer(),
+            self.emulator.screen_buffer(),
+            &mut frame,
+        );
+        let svg = rgba_to_svg(&frame, SCREEN_WIDTH, SCREEN_HEIGHT, self.scale as usize);
+
+        let name = self
+            .settings
+            .file_autodetect
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "rustzx".to_owned());
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let dir = self
+            .settings
+            .capture_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("."));
+        let path = dir.join(format!("{}-{}.svg", name, timestamp));
+        fs::write(&path, svg)
+            .with_context(|| format!("Failed to write SVG capture {}", path.display()))?;
+        Ok(path)
+    }
+
     fn last_quick_snapshot_path(&self) -> PathBuf {
-        if let Some(path) = self.settings.file_autodetect.as_ref() {
-            return path.with_extension(".rustzx.last.sna");
-        }
-        Path::new("default.rustzx.last.sna").to_owned()
+        self.quick_snapshot_path("last")
     }
 
     fn prev_quick_snapshot_path(&self) -> PathBuf {
+        self.quick_snapshot_path("prev")
+    }
+
+    /// Quick snapshots of +2A/+3 are saved as z80, because SNA can't keep
+    /// 1FFD port state
+    fn quick_snapshot_is_z80(&self) -> bool {
+        self.settings.machine.base_machine() == ZXMachine::SinclairPlus3
+    }
+
+    fn quick_snapshot_path(&self, kind: &str) -> PathBuf {
+        let format = if self.quick_snapshot_is_z80() {
+            "z80"
+        } else {
+            "sna"
+        };
+        let extension = format!(".rustzx.{}.{}", kind, format);
         if let Some(path) = self.settings.file_autodetect.as_ref() {
-            return path.with_extension(".rustzx.prev.sna");
+            return path.with_extension(extension);
         }
-        Path::new("default.rustzx.prev.sna").to_owned()
+        Path::new("default").with_extension(&extension[1..])
+    }
+}
+
+/// Constructs emulator and attaches ROM and peripherals from settings
+pub(super) fn create_emulator(
+    settings: &Settings,
+    sample_rate: usize,
+) -> anyhow::Result<Emulator<AppHost>> {
+    let host_context = AppHostContext {
+        palette: settings.palette.unwrap_or_default().rgba(),
+    };
+    let rustzx_settings = settings.to_rustzx_settings(sample_rate);
+    let mut emulator = Emulator::new(rustzx_settings, host_context)
+        .map_err(|e| anyhow!("Failed to construct emulator: {}", e))?;
+
+    if let Some(rom) = settings.rom.as_ref() {
+        emulator
+            .load_rom(host::load_rom(rom, settings.machine)?)
+            .map_err(|e| anyhow!("Emulator failed to load rom: {}", e))?;
+    }
+    if settings.rumble {
+        emulator.set_feedback_enabled(true);
+        for trigger in &settings.rumble_trigger {
+            emulator.add_memory_trigger(*trigger);
+        }
+    }
+    if let Some(disk) = settings.ide.as_ref() {
+        emulator.attach_ide_disk(host::load_disk_image(disk, settings.ide_read_only)?);
+    }
+    Ok(emulator)
+}
+
+/// Sleeps until `deadline`. OS sleep is used for the most of the interval, and
+/// the last part is spinned to avoid coarse sleep granularity
+fn sleep_until(deadline: Instant) {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining > SPIN_THRESHOLD {
+        thread::sleep(remaining - SPIN_THRESHOLD);
+    }
+    while Instant::now() < deadline {
+        thread::yield_now();
+    }
+}
+
+fn rumble_pulse(event: FeedbackEvent) -> RumblePulse {
+    match event {
+        // Light buzz which follows tape signal activity. Pulse is a bit longer
+        // than a frame to keep it continuous while tape is loading
+        FeedbackEvent::TapeEdges(edges) => RumblePulse {
+            low_frequency: (edges.min(128) * 0x80) as u16,
+            high_frequency: 0,
+            duration: Duration::from_millis(40),
+        },
+        FeedbackEvent::KempstonFire => RumblePulse {
+            low_frequency: 0,
+            high_frequency: 0xC000,
+            duration: Duration::from_millis(60),
+        },
+        FeedbackEvent::MemoryTrigger { .. } => RumblePulse {
+            low_frequency: 0xFFFF,
+            high_frequency: 0xFFFF,
+            duration: Duration::from_millis(200),
+        },
     }
 }
 
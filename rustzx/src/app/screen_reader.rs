@@ -0,0 +1,46 @@
+//! Accessibility support: announces BASIC reports, which appear when a program
+//! stops, via standard output (for terminal screen readers) and optional
+//! text-to-speech command
+use std::process::{Child, Command, Stdio};
+
+pub struct ScreenReader {
+    /// Program and its arguments, report text is appended as the last argument
+    tts_command: Option<(String, Vec<String>)>,
+    speech: Option<Child>,
+}
+
+impl ScreenReader {
+    pub fn new(tts_command: Option<&str>) -> Self {
+        let tts_command = tts_command.and_then(|command| {
+            let mut parts = command.split_whitespace().map(String::from);
+            parts.next().map(|program| (program, parts.collect()))
+        });
+        Self {
+            tts_command,
+            speech: None,
+        }
+    }
+
+    pub fn announce(&mut self, report: &str) {
+        println!("{}", report);
+        if let Some((program, args)) = &self.tts_command {
+            // New report interrupts the previous one. Speech is not waited
+            // for, so emulation is not blocked
+            if let Some(mut speech) = self.speech.take() {
+                let _ = speech.kill();
+                let _ = speech.wait();
+            }
+            let result = Command::new(program)
+                .args(args)
+                .arg(report)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            match result {
+                Ok(speech) => self.speech = Some(speech),
+                Err(e) => log::warn!("Failed to run text-to-speech command `{}`: {}", program, e),
+            }
+        }
+    }
+}
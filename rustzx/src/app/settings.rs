@@ -1,7 +1,14 @@
+use crate::app::{
+    config::Config,
+    events::{HotkeyTable, Macro, MAX_AUTOFIRE_RATE},
+    stats::STATS_FILE,
+};
+use anyhow::Context;
 use rustzx_core::{
-    zx::{machine::ZXMachine, sound::ay::ZXAYMode},
-    EmulationMode, RustzxSettings,
+    zx::{feedback::MemoryTrigger, machine::ZXMachine, sound::ay::ZXAYMode},
+    BorderMode, EmulationMode, RamPattern, RustzxSettings,
 };
+use rustzx_utils::palette;
 use std::path::PathBuf;
 use structopt::StructOpt;
 use strum::{EnumString, EnumVariantNames, VariantNames};
@@ -21,13 +28,18 @@ pub enum SoundBackend {
 }
 
 /// Structure to handle all emulator runtime settings
-#[derive(StructOpt)]
+#[derive(Clone, StructOpt)]
 #[structopt(about = env!("CARGO_PKG_DESCRIPTION"))]
 #[structopt(name = "RustZX")]
 pub struct Settings {
     /// Specify machine type for launch. Possible values:
     ///   [`48k`, `48`] - Sinclair ZX Spectrum 48K
     ///   [`128k`, `128`] - Sinclair ZX Spectrum 128K
+    ///   [`plus3`, `+3`] - Sinclair ZX Spectrum +2A/+3, ROM (e.g. +3e) should be
+    ///   provided via `--rom`
+    ///   [`128k-es`, `128es`] - Spanish ZX Spectrum 128K/+2, ROM should be provided
+    ///   via `--rom`
+    ///   [`tc2048`] - Timex Computer 2048, ROM should be provided via `--rom`
     #[structopt(verbatim_doc_comment, short, long, default_value = "48k", parse(try_from_str = machine_from_str))]
     pub machine: ZXMachine,
     /// Set emulation speed at emualtor start-up. Can be specified as deciamal non-zero
@@ -44,16 +56,98 @@ pub struct Settings {
     /// Set windows scale for emulator. Can be set as decimal non-zero value. Defaults to 2
     #[structopt(short, long, default_value = "2", parse(try_from_str = scale_from_str))]
     pub scale: usize,
+    /// Present emulated frames in sync with high refresh rate display (e.g. 120 or 144 Hz)
+    /// instead of 50 Hz sleep-based pacing. Can be set to display refresh rate in Hz or to
+    /// `auto` to detect it
+    #[structopt(long, parse(try_from_str = display_rate_from_str))]
+    pub display_rate: Option<DisplayRate>,
+    /// Blend border of the previous and the current frame on intermediate presents, which
+    /// makes fast border effects (e.g. tape loading stripes) smoother. Requires `--display-rate`
+    #[structopt(long, requires = "display-rate")]
+    pub border_blend: bool,
+    /// Set border rendering mode. Possible values:
+    ///   `precise` - border is rendered with precise beam timings (default)
+    ///   `solid` - border is filled with the last set color, faster but loses
+    ///   border effects
+    ///   `canvas` - border is not rendered
+    #[structopt(verbatim_doc_comment, long, default_value = "precise", parse(try_from_str = border_mode_from_str))]
+    pub border_mode: BorderMode,
+    /// Minimize input latency for fast-paced games: events are polled right before the
+    /// frame is emulated and the frame is presented as soon as it is rendered, without
+    /// vsync (may cause tearing)
+    #[structopt(long, conflicts_with = "display-rate")]
+    pub low_latency: bool,
+    /// Disable idle throttling. By default, when emulated machine waits for input and
+    /// nothing changes on the screen, window is redrawn only on changes and input is
+    /// polled less often to save power
+    #[structopt(long = "noidle-throttle")]
+    pub disable_idle_throttle: bool,
+    /// Set color palette. Possible values:
+    ///   `original` - original ZX Spectrum colors
+    ///   `deuteranopia` - color-blind safe palette for deuteranopia (green deficiency)
+    ///   `protanopia` - color-blind safe palette for protanopia (red deficiency)
+    #[structopt(verbatim_doc_comment, long, parse(try_from_str = palette_from_str))]
+    pub palette: Option<PaletteKind>,
+    /// Set on-screen display text scale [1..=4]. Defaults to 1
+    #[structopt(long, parse(try_from_str = osd_scale_from_str))]
+    pub osd_scale: Option<usize>,
+    /// Use high-contrast on-screen display style
+    #[structopt(long)]
+    pub osd_high_contrast: bool,
+    /// Announce BASIC reports (e.g. `0 OK, 0:1`), which appear when program stops, on
+    /// standard output and on-screen display
+    #[structopt(long)]
+    pub screen_reader: bool,
+    /// Speak announced BASIC reports with the given text-to-speech command (e.g. `espeak`),
+    /// report text is passed as the last argument
+    #[structopt(long, requires = "screen-reader")]
+    pub tts_command: Option<String>,
+    /// Set path to TOML config file. If not set, `rustzx/config.toml` from the user config
+    /// directory is used when it exists. Command line options take precedence over config
+    #[structopt(long)]
+    pub config: Option<PathBuf>,
+    /// Disable play time statistics tracking
+    #[structopt(long = "nostats")]
+    pub disable_stats: bool,
+    /// Show play time statistics on-screen when file is loaded
+    #[structopt(long, conflicts_with = "disable-stats")]
+    pub stats_osd: bool,
+    /// Hotkey bindings, can be changed only via config file
+    #[structopt(skip)]
+    pub hotkeys: HotkeyTable<String>,
+    /// Input macros, bound to hotkeys in the same order. Can be set only via config file
+    #[structopt(skip)]
+    pub macros: Vec<Macro>,
+    /// Enable autofire of kempston joy fire button with the given rate (presses per second)
+    #[structopt(long, parse(try_from_str = autofire_rate_from_str))]
+    pub autofire_kempston: Option<u32>,
+    /// Enable autofire of sinclair joys fire buttons with the given rate (presses per second)
+    #[structopt(long, parse(try_from_str = autofire_rate_from_str))]
+    pub autofire_sinclair: Option<u32>,
     /// Disable kempston joy support. If enabled, arrow and `Alt` keys are bound by default
     /// to the kempston joy
     #[structopt(long = "nokempston")]
     pub disable_kempston: bool,
-    /// Enables kempston mouse support. If enabled, locks mouse in application
+    /// Enables kempston mouse support. If enabled, locks mouse in application. Mouse
+    /// responds on the kempston joy port, so kempston joy is disabled
     #[structopt(long = "mouse")]
     pub enable_mouse: bool,
     /// Sets mouse sensitivity [1..=100]. Defaults to 20
     #[structopt(long = "mouse-sensitivity", default_value = "20")]
     pub mouse_sensitivity: usize,
+    /// Enable gamepad rumble feedback on tape loading, kempston fire and memory triggers
+    #[structopt(long)]
+    pub rumble: bool,
+    /// Add rumble trigger on RAM write to the given address (e.g. lives counter). Can be set
+    /// as `ADDR` or `ADDR=VALUE` to react only on specific value, numbers are decimal or
+    /// hex with `0x` prefix. Can be used multiple times
+    #[structopt(
+        long,
+        requires = "rumble",
+        number_of_values = 1,
+        parse(try_from_str = memory_trigger_from_str)
+    )]
+    pub rumble_trigger: Vec<MemoryTrigger>,
     /// Set AY-3-8910 sound chip mode. Can be set to `mono`, `abc`(stereo) or `acb`(stereo)
     /// Defaults to `abc`
     #[structopt(long, default_value = "abc", parse(try_from_str = ay_mode_from_str))]
@@ -84,32 +178,212 @@ pub struct Settings {
         possible_values = &SoundBackend::VARIANTS
     )]
     pub sound_backend: SoundBackend,
-    /// Set path to custom rom file. in case of multipart ROMs for 128k, the first part file,
-    /// extension of which should end with `.0`
+    /// Set sound output device by name, system default device is used if not set.
+    /// See `--list-sound-devices` for available names. If device is lost during emulation,
+    /// emulator will try to reopen it or switch to the default device
+    #[structopt(long)]
+    pub sound_device: Option<String>,
+    /// Print available sound output devices for selected sound backend and exit
+    #[structopt(long)]
+    pub list_sound_devices: bool,
+    /// Set path to custom rom file. in case of multipart ROMs for 128k, 128k-es and +3, the first part
+    /// file, extension of which should end with `.0`. +3 ROM can be also provided as a single
+    /// 64K file
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub rom: Option<PathBuf>,
     /// Set tape file path. Only `.tap` files are supported currently
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub tape: Option<PathBuf>,
+    /// Show notes from the given file via OSD when the tape reaches specific blocks. By
+    /// default, `.notes` file with the same name as the loaded tape is used if present
+    #[structopt(long)]
+    pub tape_notes: Option<PathBuf>,
+    /// Set directory for SVG captures of the screen (`F12`). Defaults to the current directory
+    #[structopt(long)]
+    pub capture_dir: Option<PathBuf>,
     /// Set snapshot file path. Only `.sna` files are supported currently
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub snap: Option<PathBuf>,
     /// Set screen file to load. Only `.scr` files are supported currently
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub screen: Option<PathBuf>,
+    /// Set RAM contents on power-on. Possible values:
+    ///   `zeros` - RAM is filled with zeros (default)
+    ///   `stripes` - alternating stripes of 0x00 and 0xFF, as on real hardware
+    ///   `random` - pseudo-random values, see `--ram-seed`
+    #[structopt(verbatim_doc_comment, long, parse(try_from_str = ram_pattern_from_str))]
+    pub ram_pattern: Option<RamPatternKind>,
+    /// Set seed for `random` RAM pattern. The same seed always produces the same RAM contents,
+    /// which is useful for reproducible runs. Random seed is used if not set
+    #[structopt(long, requires = "ram-pattern")]
+    pub ram_seed: Option<u64>,
+    /// Attach hard disk image to the simple 8-bit IDE interface (as used by +3e ROMs).
+    /// Raw and `.hdf` images are supported
+    #[structopt(long)]
+    pub ide: Option<PathBuf>,
+    /// Keep disk image file unchanged, sectors written by the emulated machine
+    /// are kept in memory. Set by the commands which run several emulators
+    #[structopt(skip)]
+    pub ide_read_only: bool,
 
     /// Load provided file to emulator. Emulator will perform autodetect of format if possible
     pub file_autodetect: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Commands, which are run instead of the emulator
+#[derive(Clone, StructOpt)]
+pub enum Command {
+    /// Print play time statistics of the loaded files
+    Stats,
+    /// Print differences between two snapshots (registers, peripherals and
+    /// memory). Each snapshot is loaded into the machine from its header,
+    /// `--machine` selects the localized model of the same machine
+    Diff { first: PathBuf, second: PathBuf },
+    /// Run every snapshot and tape from the directory without window and
+    /// write crashes, hangs and final screen hashes to CSV report
+    Sweep {
+        dir: PathBuf,
+        /// Frames to emulate for each file
+        #[structopt(long, default_value = "1500")]
+        frames: usize,
+        /// Worker threads count, defaults to the available CPU cores count
+        #[structopt(long)]
+        threads: Option<usize>,
+        /// Report file path
+        #[structopt(short, long, default_value = "sweep.csv")]
+        output: PathBuf,
+    },
 }
 
 fn machine_from_str(s: &str) -> Result<ZXMachine, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "48k" | "48" => Ok(ZXMachine::Sinclair48K),
         "128k" | "128" => Ok(ZXMachine::Sinclair128K),
+        "plus3" | "+3" => Ok(ZXMachine::SinclairPlus3),
+        "128k-es" | "128es" => Ok(ZXMachine::Sinclair128KSpanish),
+        "tc2048" => Ok(ZXMachine::TimexTC2048),
         s => Err(anyhow::anyhow!("Invalid machine type `{}`", s)),
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub enum DisplayRate {
+    Auto,
+    Hz(usize),
+}
+
+fn display_rate_from_str(s: &str) -> Result<DisplayRate, anyhow::Error> {
+    match s.to_lowercase().as_str() {
+        "auto" => Ok(DisplayRate::Auto),
+        s => {
+            let rate: std::num::NonZeroUsize = s
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid display rate `{}`", s))?;
+            Ok(DisplayRate::Hz(rate.into()))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub enum PaletteKind {
+    #[default]
+    Original,
+    Deuteranopia,
+    Protanopia,
+}
+
+impl PaletteKind {
+    pub fn rgba(self) -> [[u8; 4]; 16] {
+        match self {
+            PaletteKind::Original => palette::rgba::ORIGINAL,
+            PaletteKind::Deuteranopia => palette::rgba::DEUTERANOPIA,
+            PaletteKind::Protanopia => palette::rgba::PROTANOPIA,
+        }
+    }
+}
+
+fn palette_from_str(s: &str) -> Result<PaletteKind, anyhow::Error> {
+    match s.to_lowercase().as_str() {
+        "original" => Ok(PaletteKind::Original),
+        "deuteranopia" => Ok(PaletteKind::Deuteranopia),
+        "protanopia" => Ok(PaletteKind::Protanopia),
+        s => Err(anyhow::anyhow!("Invalid palette `{}`", s)),
+    }
+}
+
+fn osd_scale_from_str(s: &str) -> Result<usize, anyhow::Error> {
+    let scale = s
+        .parse::<usize>()
+        .map_err(|_| anyhow::anyhow!("Invalid OSD scale `{}`", s))?;
+    validate_osd_scale(scale)
+}
+
+fn autofire_rate_from_str(s: &str) -> Result<u32, anyhow::Error> {
+    let rate = s
+        .parse::<u32>()
+        .map_err(|_| anyhow::anyhow!("Invalid autofire rate `{}`", s))?;
+    validate_autofire_rate(rate)
+}
+
+fn validate_autofire_rate(rate: u32) -> Result<u32, anyhow::Error> {
+    if !(1..=MAX_AUTOFIRE_RATE).contains(&rate) {
+        anyhow::bail!(
+            "Autofire rate `{}` is out of [1..={}] range",
+            rate,
+            MAX_AUTOFIRE_RATE
+        );
+    }
+    Ok(rate)
+}
+
+fn validate_osd_scale(scale: usize) -> Result<usize, anyhow::Error> {
+    if !(1..=4).contains(&scale) {
+        anyhow::bail!("OSD scale `{}` is out of [1..=4] range", scale);
+    }
+    Ok(scale)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RamPatternKind {
+    Zeros,
+    Stripes,
+    Random,
+}
+
+fn ram_pattern_from_str(s: &str) -> Result<RamPatternKind, anyhow::Error> {
+    match s.to_lowercase().as_str() {
+        "zeros" => Ok(RamPatternKind::Zeros),
+        "stripes" => Ok(RamPatternKind::Stripes),
+        "random" => Ok(RamPatternKind::Random),
+        s => Err(anyhow::anyhow!("Invalid RAM pattern `{}`", s)),
+    }
+}
+
+fn memory_trigger_from_str(s: &str) -> Result<MemoryTrigger, anyhow::Error> {
+    fn parse_number<T: TryFrom<u32>>(s: &str) -> Option<T> {
+        let number = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => s.parse(),
+        };
+        number.ok().and_then(|n| T::try_from(n).ok())
+    }
+
+    let invalid = || anyhow::anyhow!("Invalid memory trigger `{}`", s);
+    let (addr, value) = match s.split_once('=') {
+        Some((addr, value)) => (addr, Some(value)),
+        None => (s, None),
+    };
+    let addr = parse_number::<u16>(addr.trim()).ok_or_else(invalid)?;
+    let value = match value {
+        Some(value) => Some(parse_number::<u8>(value.trim()).ok_or_else(invalid)?),
+        None => None,
+    };
+    Ok(MemoryTrigger { addr, value })
+}
+
 fn emulation_speed_from_str(s: &str) -> Result<EmulationMode, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "max" => Ok(EmulationMode::Max),
@@ -131,6 +405,15 @@ fn scale_from_str(s: &str) -> Result<usize, anyhow::Error> {
     Ok(scale.into())
 }
 
+fn border_mode_from_str(s: &str) -> Result<BorderMode, anyhow::Error> {
+    match s.to_lowercase().as_str() {
+        "precise" => Ok(BorderMode::Precise),
+        "solid" => Ok(BorderMode::SolidColor),
+        "canvas" => Ok(BorderMode::CanvasOnly),
+        s => Err(anyhow::anyhow!("Invalid border mode `{}`", s)),
+    }
+}
+
 fn ay_mode_from_str(s: &str) -> Result<ZXAYMode, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "mono" => Ok(ZXAYMode::Mono),
@@ -172,16 +455,110 @@ fn sound_sample_rate_from_str(s: &str) -> Result<usize, anyhow::Error> {
 }
 
 impl Settings {
+    /// Parses command line and applies config file on top of it
+    pub fn load() -> anyhow::Result<Settings> {
+        let mut settings = Settings::from_args();
+        settings.validate_ram_seed()?;
+        let config_path = match settings.config.clone() {
+            Some(path) => Some(path),
+            None => Config::default_path().filter(|path| path.exists()),
+        };
+        if let Some(path) = config_path {
+            settings.apply_config(Config::load(&path)?)?;
+        }
+        if let Some(warning) = settings.mouse_kempston_warning() {
+            log::warn!("{}", warning);
+        }
+        Ok(settings)
+    }
+
+    /// Returns warning when kempston joy is implicitly disabled by `--mouse`,
+    /// which responds on the same port
+    fn mouse_kempston_warning(&self) -> Option<&'static str> {
+        (self.enable_mouse && !self.disable_kempston).then_some(
+            "Kempston joy is disabled, because kempston mouse responds on its port; \
+             pass `--nokempston` together with `--mouse` to hide this warning",
+        )
+    }
+
+    fn validate_ram_seed(&self) -> anyhow::Result<()> {
+        if self.ram_seed.is_some() && self.ram_pattern != Some(RamPatternKind::Random) {
+            anyhow::bail!("`--ram-seed` can be used only with `--ram-pattern random`");
+        }
+        Ok(())
+    }
+
+    /// Fills settings which were not set via command line from config
+    fn apply_config(&mut self, config: Config) -> anyhow::Result<()> {
+        if self.palette.is_none() {
+            self.palette = config
+                .video
+                .palette
+                .as_deref()
+                .map(palette_from_str)
+                .transpose()?;
+        }
+        if self.osd_scale.is_none() {
+            self.osd_scale = config.osd.scale.map(validate_osd_scale).transpose()?;
+        }
+        self.osd_high_contrast |= config.osd.high_contrast;
+        self.hotkeys = HotkeyTable::new(&config.hotkeys)?;
+        for (keys, text) in &config.macros {
+            let input_macro =
+                Macro::parse(text).with_context(|| format!("Invalid macro for `{}`", keys))?;
+            self.hotkeys.bind_macro(keys, self.macros.len())?;
+            self.macros.push(input_macro);
+        }
+        if self.autofire_kempston.is_none() {
+            self.autofire_kempston = config
+                .autofire
+                .kempston
+                .map(validate_autofire_rate)
+                .transpose()?;
+        }
+        if self.autofire_sinclair.is_none() {
+            self.autofire_sinclair = config
+                .autofire
+                .sinclair
+                .map(validate_autofire_rate)
+                .transpose()?;
+        }
+        self.screen_reader |= config.accessibility.screen_reader;
+        if self.tts_command.is_none() {
+            self.tts_command = config.accessibility.tts_command;
+        }
+        self.disable_stats |= config.stats.disabled;
+        self.stats_osd |= config.stats.osd && !self.disable_stats;
+        Ok(())
+    }
+
+    /// Returns path to the play time statistics file, which is placed next to
+    /// the config file
+    pub fn stats_path(&self) -> Option<PathBuf> {
+        let config_path = self.config.clone().or_else(Config::default_path)?;
+        Some(config_path.with_file_name(STATS_FILE))
+    }
+
     pub fn to_rustzx_settings(&self, sound_sample_rate: usize) -> RustzxSettings {
-        let ay_enabled = (matches!(self.machine, ZXMachine::Sinclair128K) || self.force_enable_ay)
-            && (!self.force_disable_ay);
+        let ay_enabled =
+            (self.machine.has_ay() || self.force_enable_ay) && (!self.force_disable_ay);
+
+        let ram_pattern = match self.ram_pattern.unwrap_or(RamPatternKind::Zeros) {
+            RamPatternKind::Zeros => RamPattern::Zeros,
+            RamPatternKind::Stripes => RamPattern::Stripes,
+            RamPatternKind::Random => RamPattern::Random(self.ram_seed.unwrap_or_else(random_seed)),
+        };
 
         RustzxSettings {
             machine: self.machine,
             emulation_mode: self.speed,
             tape_fastload_enabled: !self.disable_fastload,
-            kempston_enabled: !self.disable_kempston,
+            // Mouse responds on the kempston joy port, which is reported by
+            // `mouse_kempston_warning` on settings load
+            kempston_enabled: !self.disable_kempston && !self.enable_mouse,
             mouse_enabled: self.enable_mouse,
+            ram_pattern,
+            border_mode: self.border_mode,
             ay_mode: self.ay_mode,
             ay_enabled,
             beeper_enabled: !self.disable_beeper,
@@ -193,3 +570,65 @@ impl Settings {
         }
     }
 }
+
+fn random_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Settings> {
+        let settings =
+            Settings::from_iter_safe(std::iter::once("rustzx").chain(args.iter().copied()))?;
+        settings.validate_ram_seed()?;
+        Ok(settings)
+    }
+
+    #[test]
+    fn ram_seed_requires_random_pattern() {
+        assert_eq!(parse(&[]).unwrap().ram_pattern, None);
+        assert!(parse(&["--ram-seed", "1"]).is_err());
+        assert!(parse(&["--ram-pattern", "stripes", "--ram-seed", "1"]).is_err());
+        let settings = parse(&["--ram-pattern", "random", "--ram-seed", "1"]).unwrap();
+        assert!(matches!(
+            settings.to_rustzx_settings(44100).ram_pattern,
+            RamPattern::Random(1)
+        ));
+    }
+
+    #[test]
+    fn mouse_disables_kempston() {
+        let settings = parse(&["--mouse"]).unwrap();
+        assert!(settings.mouse_kempston_warning().is_some());
+        let settings = settings.to_rustzx_settings(44100);
+        assert!(settings.mouse_enabled && !settings.kempston_enabled);
+        settings.validate().unwrap();
+
+        // Explicitly disabled kempston joy is not reported
+        let settings = parse(&["--mouse", "--nokempston"]).unwrap();
+        assert!(settings.mouse_kempston_warning().is_none());
+        assert!(!settings.to_rustzx_settings(44100).kempston_enabled);
+        assert!(parse(&[]).unwrap().mouse_kempston_warning().is_none());
+    }
+
+    #[test]
+    fn border_mode_is_passed_to_emulator() {
+        let border_mode =
+            |args: &[&str]| parse(args).unwrap().to_rustzx_settings(44100).border_mode;
+        assert_eq!(border_mode(&[]), BorderMode::Precise);
+        assert_eq!(
+            border_mode(&["--border-mode", "solid"]),
+            BorderMode::SolidColor
+        );
+        assert_eq!(
+            border_mode(&["--border-mode", "canvas"]),
+            BorderMode::CanvasOnly
+        );
+        assert!(parse(&["--border-mode", "fast"]).is_err());
+    }
+}
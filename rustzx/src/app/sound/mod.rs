@@ -1,7 +1,9 @@
 #[cfg(feature = "sound-cpal")]
 mod sound_cpal;
 mod sound_sdl;
+use crate::app::settings::SoundBackend;
 use rustzx_core::zx::sound::sample::SoundSample;
+use std::{sync::Arc, time::Duration};
 
 #[cfg(feature = "sound-cpal")]
 pub use sound_cpal::SoundCpal;
@@ -10,14 +12,30 @@ pub use sound_sdl::SoundSdl;
 pub const CHANNEL_COUNT: usize = 2;
 pub const DEFAULT_SAMPLE_RATE: usize = 44100;
 pub const DEFAULT_LATENCY: usize = 512;
+/// Minimal interval between attempts to reopen lost sound device
+pub const DEVICE_REOPEN_INTERVAL: Duration = Duration::from_secs(1);
 
 pub type ZXSample = SoundSample<f32>;
+pub type SampleProducer = ringbuf::Producer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>;
+pub type SampleConsumer = ringbuf::Consumer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>;
 
 pub trait SoundDevice {
     /// Send new sample to the sound device
     fn send_sample(&mut self, sample: ZXSample);
     /// Return selected device sample rate
     fn sample_rate(&self) -> usize;
+    /// Checks output stream state and reopens it if the device was lost (e.g.
+    /// USB headset was unplugged). Should be called periodically
+    fn maintain(&mut self);
+}
+
+/// Returns names of output devices available for the given backend
+pub fn device_names(backend: SoundBackend) -> anyhow::Result<Vec<String>> {
+    match backend {
+        SoundBackend::Sdl => SoundSdl::device_names(),
+        #[cfg(feature = "sound-cpal")]
+        SoundBackend::Cpal => SoundCpal::device_names(),
+    }
 }
 
 pub fn ringbuf_size_from_sample_rate(sample_rate: usize) -> usize {
@@ -1,77 +1,89 @@
 //! Real Audio SDL backend
 use crate::app::{
     settings::Settings,
-    sound::{ringbuf_size_from_sample_rate, SoundDevice, ZXSample, CHANNEL_COUNT},
+    sound::{
+        ringbuf_size_from_sample_rate, SampleConsumer, SampleProducer, SoundDevice, ZXSample,
+        CHANNEL_COUNT, DEVICE_REOPEN_INTERVAL,
+    },
 };
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 pub struct SoundCpal {
-    tx: ringbuf::Producer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    tx: SampleProducer,
     sample_rate: usize,
     // Keep stream alive until Drop
     _stream: cpal::Stream,
+    device_name: Option<String>,
+    // Set from stream error callback when output device disappears
+    device_lost: Arc<AtomicBool>,
+    last_reopen_attempt: Option<Instant>,
 }
+
 impl SoundCpal {
     /// Constructs sound backend from settings
     pub fn new(settings: &Settings) -> anyhow::Result<SoundCpal> {
         let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .ok_or_else(|| anyhow::anyhow!("Failed to acquire cpal sound host"))?;
-
-        let config = device
-            .supported_output_configs()?
-            .find(|c| {
-                if let Some(sample_rate) = settings.sound_sample_rate {
-                    if sample_rate < c.min_sample_rate().0 as usize
-                        || sample_rate > c.max_sample_rate().0 as usize
-                    {
-                        return false;
-                    }
-                }
-
-                // Find any stereo config
-                (c.channels() as usize % CHANNEL_COUNT == 0) && c.channels() != 0
-            })
-            .ok_or_else(|| {
-                anyhow::anyhow!("Sound device does not support required configuration")
-            })?;
-
-        let config = if let Some(sample_rate) = settings.sound_sample_rate {
-            config.with_sample_rate(cpal::SampleRate(sample_rate as u32))
-        } else {
-            config.with_max_sample_rate()
+        let device = match settings.sound_device.as_deref() {
+            Some(name) => find_device(&host, name)?
+                .ok_or_else(|| anyhow::anyhow!("Sound device `{}` not found", name))?,
+            None => default_device(&host)?,
         };
 
-        let sample_rate = config.sample_rate().0 as usize;
-
-        let ringbuf_size = ringbuf_size_from_sample_rate(sample_rate);
-        let ringbuf = ringbuf::HeapRb::<ZXSample>::new(ringbuf_size);
-        let (tx, rx) = ringbuf.split();
-
-        let stream = match config.sample_format() {
-            cpal::SampleFormat::I16 => create_stream::<i16>(&device, &config.into(), rx)?,
-            cpal::SampleFormat::U16 => create_stream::<u16>(&device, &config.into(), rx)?,
-            cpal::SampleFormat::F32 => create_stream::<f32>(&device, &config.into(), rx)?,
-            cpal::SampleFormat::I8 => create_stream::<i8>(&device, &config.into(), rx)?,
-            cpal::SampleFormat::I32 => create_stream::<i32>(&device, &config.into(), rx)?,
-            cpal::SampleFormat::I64 => create_stream::<i64>(&device, &config.into(), rx)?,
-            cpal::SampleFormat::U8 => create_stream::<u8>(&device, &config.into(), rx)?,
-            cpal::SampleFormat::U32 => create_stream::<u32>(&device, &config.into(), rx)?,
-            cpal::SampleFormat::U64 => create_stream::<u64>(&device, &config.into(), rx)?,
-            cpal::SampleFormat::F64 => create_stream::<f64>(&device, &config.into(), rx)?,
-            _ => {
-                anyhow::bail!("Device has unsupported audio sample format")
-            }
-        };
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let (stream, tx, sample_rate) =
+            open_stream(&device, settings.sound_sample_rate, device_lost.clone())?;
 
         Ok(SoundCpal {
             tx,
             sample_rate,
             _stream: stream,
+            device_name: settings.sound_device.clone(),
+            device_lost,
+            last_reopen_attempt: None,
         })
     }
+
+    /// Returns names of all available output devices
+    pub fn device_names() -> anyhow::Result<Vec<String>> {
+        let host = cpal::default_host();
+        Ok(host
+            .output_devices()?
+            .filter_map(|device| device.name().ok())
+            .collect())
+    }
+
+    /// Reopens stream on the selected device, or on the default device if
+    /// selected one is not available anymore. Sample rate is preserved, as
+    /// emulator is already configured for it
+    fn reopen(&mut self) -> anyhow::Result<()> {
+        let host = cpal::default_host();
+        let device = match self.device_name.as_deref() {
+            Some(name) => find_device(&host, name)?,
+            None => None,
+        };
+        let device = match device {
+            Some(device) => device,
+            None => default_device(&host)?,
+        };
+
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let (stream, tx, _) = open_stream(&device, Some(self.sample_rate), device_lost.clone())?;
+        self._stream = stream;
+        self.tx = tx;
+        self.device_lost = device_lost;
+        log::info!(
+            "Sound output switched to `{}`",
+            device.name().unwrap_or_default()
+        );
+        Ok(())
+    }
 }
 
 impl SoundDevice for SoundCpal {
@@ -83,12 +95,100 @@ impl SoundDevice for SoundCpal {
     fn sample_rate(&self) -> usize {
         self.sample_rate
     }
+
+    fn maintain(&mut self) {
+        if !self.device_lost.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let now = Instant::now();
+        if let Some(last_attempt) = self.last_reopen_attempt {
+            if now.duration_since(last_attempt) < DEVICE_REOPEN_INTERVAL {
+                return;
+            }
+        }
+        // Only the first failure is reported to avoid log flooding
+        let first_attempt = self.last_reopen_attempt.is_none();
+        self.last_reopen_attempt = Some(now);
+
+        match self.reopen() {
+            Ok(()) => self.last_reopen_attempt = None,
+            Err(e) if first_attempt => {
+                log::warn!("Sound device lost, failed to reopen sound stream: {:#}", e)
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+fn default_device(host: &cpal::Host) -> anyhow::Result<cpal::Device> {
+    host.default_output_device()
+        .ok_or_else(|| anyhow::anyhow!("Failed to acquire cpal sound host"))
+}
+
+fn find_device(host: &cpal::Host, name: &str) -> anyhow::Result<Option<cpal::Device>> {
+    Ok(host
+        .output_devices()?
+        .find(|device| device.name().map(|n| n == name).unwrap_or_default()))
+}
+
+fn open_stream(
+    device: &cpal::Device,
+    sample_rate: Option<usize>,
+    device_lost: Arc<AtomicBool>,
+) -> anyhow::Result<(cpal::Stream, SampleProducer, usize)> {
+    let config = device
+        .supported_output_configs()?
+        .find(|c| {
+            if let Some(sample_rate) = sample_rate {
+                if sample_rate < c.min_sample_rate().0 as usize
+                    || sample_rate > c.max_sample_rate().0 as usize
+                {
+                    return false;
+                }
+            }
+
+            // Find any stereo config
+            (c.channels() as usize % CHANNEL_COUNT == 0) && c.channels() != 0
+        })
+        .ok_or_else(|| anyhow::anyhow!("Sound device does not support required configuration"))?;
+
+    let config = if let Some(sample_rate) = sample_rate {
+        config.with_sample_rate(cpal::SampleRate(sample_rate as u32))
+    } else {
+        config.with_max_sample_rate()
+    };
+
+    let sample_rate = config.sample_rate().0 as usize;
+
+    let ringbuf_size = ringbuf_size_from_sample_rate(sample_rate);
+    let ringbuf = ringbuf::HeapRb::<ZXSample>::new(ringbuf_size);
+    let (tx, rx) = ringbuf.split();
+
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::I16 => create_stream::<i16>(device, &config.into(), rx, device_lost)?,
+        cpal::SampleFormat::U16 => create_stream::<u16>(device, &config.into(), rx, device_lost)?,
+        cpal::SampleFormat::F32 => create_stream::<f32>(device, &config.into(), rx, device_lost)?,
+        cpal::SampleFormat::I8 => create_stream::<i8>(device, &config.into(), rx, device_lost)?,
+        cpal::SampleFormat::I32 => create_stream::<i32>(device, &config.into(), rx, device_lost)?,
+        cpal::SampleFormat::I64 => create_stream::<i64>(device, &config.into(), rx, device_lost)?,
+        cpal::SampleFormat::U8 => create_stream::<u8>(device, &config.into(), rx, device_lost)?,
+        cpal::SampleFormat::U32 => create_stream::<u32>(device, &config.into(), rx, device_lost)?,
+        cpal::SampleFormat::U64 => create_stream::<u64>(device, &config.into(), rx, device_lost)?,
+        cpal::SampleFormat::F64 => create_stream::<f64>(device, &config.into(), rx, device_lost)?,
+        _ => {
+            anyhow::bail!("Device has unsupported audio sample format")
+        }
+    };
+
+    Ok((stream, tx, sample_rate))
 }
 
 fn create_stream<T>(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
-    mut samples_rx: ringbuf::Consumer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    mut samples_rx: SampleConsumer,
+    device_lost: Arc<AtomicBool>,
 ) -> anyhow::Result<cpal::Stream>
 where
     T: cpal::FromSample<f32> + cpal::SizedSample,
@@ -116,7 +216,11 @@ where
                 frame[2..channels].fill(cpal::Sample::EQUILIBRIUM);
             }
         },
-        |_| {},
+        move |err| {
+            if let cpal::StreamError::DeviceNotAvailable = err {
+                device_lost.store(true, Ordering::Relaxed);
+            }
+        },
         None,
     )?;
     stream.play()?;
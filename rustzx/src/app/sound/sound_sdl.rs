@@ -2,18 +2,21 @@ use crate::{
     app::{
         settings::Settings,
         sound::{
-            ringbuf_size_from_sample_rate, SoundDevice, ZXSample, CHANNEL_COUNT, DEFAULT_LATENCY,
-            DEFAULT_SAMPLE_RATE,
+            ringbuf_size_from_sample_rate, SampleConsumer, SampleProducer, SoundDevice, ZXSample,
+            CHANNEL_COUNT, DEFAULT_LATENCY, DEFAULT_SAMPLE_RATE, DEVICE_REOPEN_INTERVAL,
         },
     },
     backends::SDL_CONTEXT,
 };
-use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
-use std::sync::Arc;
+use sdl2::{
+    audio::{AudioCallback, AudioDevice, AudioSpecDesired, AudioStatus},
+    AudioSubsystem,
+};
+use std::time::Instant;
 
 /// Struct which used in SDL audio callback
 struct SdlCallback {
-    samples: ringbuf::Consumer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    samples: SampleConsumer,
 }
 
 impl AudioCallback for SdlCallback {
@@ -36,20 +39,19 @@ impl AudioCallback for SdlCallback {
 
 /// Represents SDL audio backend
 pub struct SoundSdl {
-    sender: ringbuf::Producer<ZXSample, Arc<ringbuf::HeapRb<ZXSample>>>,
+    sender: SampleProducer,
     sample_rate: usize,
-    _device: AudioDevice<SdlCallback>, // Should be alive until Drop invocation
+    device: AudioDevice<SdlCallback>, // Should be alive until Drop invocation
+    audio: AudioSubsystem,
+    device_name: Option<String>,
+    latency: usize,
+    last_reopen_attempt: Option<Instant>,
 }
 
 impl SoundSdl {
     /// constructs sound backend from settings
     pub fn new(settings: &Settings) -> anyhow::Result<SoundSdl> {
-        let mut audio_subsystem = None;
-        SDL_CONTEXT.with(|sdl| {
-            audio_subsystem = sdl.borrow_mut().audio().ok();
-        });
-        let audio = audio_subsystem
-            .ok_or_else(|| anyhow::anyhow!("Failed to initialize SDL audio backend"))?;
+        let audio = audio_subsystem()?;
 
         // Basically, SDL shits its pants if desired sound sample rate is not specified
         let sample_rate = settings.sound_sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE);
@@ -57,26 +59,44 @@ impl SoundSdl {
         // SDL sets awfully big latency by default
         let latency = settings.sound_latency.unwrap_or(DEFAULT_LATENCY);
 
-        let desired_spec = AudioSpecDesired {
-            freq: Some(sample_rate as i32),
-            channels: Some(CHANNEL_COUNT as u8),
-            samples: Some(latency as u16),
-        };
-        let ringbuf_size = ringbuf_size_from_sample_rate(sample_rate);
-        let ringbuf = ringbuf::HeapRb::<ZXSample>::new(ringbuf_size);
-        let (tx, rx) = ringbuf.split();
-
-        let device_handle = audio
-            .open_playback(None, &desired_spec, |_| SdlCallback { samples: rx })
-            .map_err(|e| anyhow::anyhow!("Failed to start SDL sound stream: {}", e))?;
-        device_handle.resume();
+        let device_name = settings.sound_device.clone();
+        let (device, sender) = open_device(&audio, device_name.as_deref(), sample_rate, latency)?;
 
         Ok(SoundSdl {
-            sender: tx,
+            sender,
             sample_rate,
-            _device: device_handle,
+            device,
+            audio,
+            device_name,
+            latency,
+            last_reopen_attempt: None,
         })
     }
+
+    /// Returns names of all available output devices
+    pub fn device_names() -> anyhow::Result<Vec<String>> {
+        let audio = audio_subsystem()?;
+        let count = audio.num_audio_playback_devices().unwrap_or_default();
+        Ok((0..count)
+            .filter_map(|idx| audio.audio_playback_device_name(idx).ok())
+            .collect())
+    }
+
+    /// Reopens stream on the selected device, or on the default device if
+    /// selected one is not available anymore
+    fn reopen(&mut self) -> anyhow::Result<()> {
+        let (device, sender) = open_device(
+            &self.audio,
+            self.device_name.as_deref(),
+            self.sample_rate,
+            self.latency,
+        )
+        .or_else(|_| open_device(&self.audio, None, self.sample_rate, self.latency))?;
+        self.device = device;
+        self.sender = sender;
+        log::info!("Sound output device reopened");
+        Ok(())
+    }
 }
 
 impl SoundDevice for SoundSdl {
@@ -88,4 +108,61 @@ impl SoundDevice for SoundSdl {
     fn sample_rate(&self) -> usize {
         self.sample_rate
     }
+
+    fn maintain(&mut self) {
+        // Device is never paused, therefore stopped state means that SDL
+        // has lost the device
+        if self.device.status() != AudioStatus::Stopped {
+            return;
+        }
+
+        let now = Instant::now();
+        if let Some(last_attempt) = self.last_reopen_attempt {
+            if now.duration_since(last_attempt) < DEVICE_REOPEN_INTERVAL {
+                return;
+            }
+        }
+        // Only the first failure is reported to avoid log flooding
+        let first_attempt = self.last_reopen_attempt.is_none();
+        self.last_reopen_attempt = Some(now);
+
+        match self.reopen() {
+            Ok(()) => self.last_reopen_attempt = None,
+            Err(e) if first_attempt => {
+                log::warn!("Sound device lost, failed to reopen sound stream: {:#}", e)
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+fn audio_subsystem() -> anyhow::Result<AudioSubsystem> {
+    let mut audio_subsystem = None;
+    SDL_CONTEXT.with(|sdl| {
+        audio_subsystem = sdl.borrow_mut().audio().ok();
+    });
+    audio_subsystem.ok_or_else(|| anyhow::anyhow!("Failed to initialize SDL audio backend"))
+}
+
+fn open_device(
+    audio: &AudioSubsystem,
+    name: Option<&str>,
+    sample_rate: usize,
+    latency: usize,
+) -> anyhow::Result<(AudioDevice<SdlCallback>, SampleProducer)> {
+    let desired_spec = AudioSpecDesired {
+        freq: Some(sample_rate as i32),
+        channels: Some(CHANNEL_COUNT as u8),
+        samples: Some(latency as u16),
+    };
+    let ringbuf_size = ringbuf_size_from_sample_rate(sample_rate);
+    let ringbuf = ringbuf::HeapRb::<ZXSample>::new(ringbuf_size);
+    let (tx, rx) = ringbuf.split();
+
+    let device_handle = audio
+        .open_playback(name, &desired_spec, |_| SdlCallback { samples: rx })
+        .map_err(|e| anyhow::anyhow!("Failed to start SDL sound stream: {}", e))?;
+    device_handle.resume();
+
+    Ok((device_handle, tx))
 }
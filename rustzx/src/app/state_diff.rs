@@ -0,0 +1,279 @@
+//! Structured diff of two emulator states (snapshots), which helps to find why
+//! the state misbehaves e.g. after the emulator core upgrade
+use super::{rustzx::create_emulator, settings::Settings, sound::DEFAULT_SAMPLE_RATE};
+use crate::host;
+use anyhow::Context;
+use rustzx_core::{
+    state::{CpuState, MachineState},
+    zx::machine::ZXMachine,
+};
+use std::{ops::Range, path::Path};
+
+const PAGE_SIZE: usize = 16 * 1024;
+/// Changed memory ranges separated by fewer unchanged bytes are reported as a
+/// single range to keep the output readable
+const MERGE_GAP: usize = 8;
+
+/// State of the loaded snapshot
+pub struct LoadedState {
+    pub machine: MachineState,
+    pub ram: Vec<Vec<u8>>,
+}
+
+impl LoadedState {
+    /// Loads snapshot into the freshly constructed emulator. Machine is
+    /// detected from the snapshot header; machine from the settings is used
+    /// only if it is the same machine or its localized model
+    pub fn load(settings: &Settings, path: &Path) -> anyhow::Result<Self> {
+        let mut snapshot = host::load_snapshot(path)?;
+        let machine = snapshot
+            .machine()
+            .map_err(|e| anyhow::anyhow!("{}", e))
+            .with_context(|| format!("Failed to detect machine of snapshot {}", path.display()))?;
+        let mut settings = settings.clone();
+        if !settings.machine.can_load_snapshot_of(machine) {
+            settings.machine = machine;
+            // Custom ROM is provided for the machine from the settings
+            settings.rom = None;
+        }
+
+        let mut emulator = create_emulator(&settings, DEFAULT_SAMPLE_RATE)?;
+        emulator
+            .load_snapshot(snapshot)
+            .map_err(|e| anyhow::anyhow!("{}", e))
+            .with_context(|| {
+                format!(
+                    "Failed to load snapshot {} as {:?} machine",
+                    path.display(),
+                    settings.machine
+                )
+            })?;
+        let ram = (0..emulator.ram_pages_count())
+            .map(|page| emulator.ram_page(page as u8).to_vec())
+            .collect();
+        Ok(Self {
+            machine: emulator.machine_state(),
+            ram,
+        })
+    }
+}
+
+/// Returns human-readable lines of differences between two states, empty
+/// when states are identical
+pub fn diff_states(first: &LoadedState, second: &LoadedState) -> Vec<String> {
+    let mut lines = Vec::new();
+    let (a, b) = (&first.machine, &second.machine);
+    if a.machine != b.machine {
+        lines.push(format!("Machine: {:?} -> {:?}", a.machine, b.machine));
+    }
+
+    push_section(
+        &mut lines,
+        "Registers",
+        cpu_fields(&a.cpu).into_iter().zip(cpu_fields(&b.cpu)),
+    );
+    push_section(
+        &mut lines,
+        "Peripherals",
+        peripheral_fields(a).into_iter().zip(peripheral_fields(b)),
+    );
+
+    let mut memory = Vec::new();
+    for (page, (a_data, b_data)) in first.ram.iter().zip(&second.ram).enumerate() {
+        let base = page_base(a.machine, page);
+        for range in changed_ranges(a_data, b_data) {
+            memory.push(format!(
+                "  page {}: {:04X}..={:04X} ({} bytes)",
+                page,
+                base + range.start,
+                base + range.end - 1,
+                range.len()
+            ));
+        }
+    }
+    if first.ram.len() != second.ram.len() {
+        memory.push(format!(
+            "  RAM pages count: {} -> {}",
+            first.ram.len(),
+            second.ram.len()
+        ));
+    }
+    if !memory.is_empty() {
+        lines.push("Memory:".to_owned());
+        lines.extend(memory);
+    }
+    lines
+}
+
+fn push_section(
+    lines: &mut Vec<String>,
+    title: &str,
+    fields: impl Iterator<Item = ((&'static str, String), (&'static str, String))>,
+) {
+    let changed = fields
+        .filter(|((_, a), (_, b))| a != b)
+        .map(|((name, a), (_, b))| format!("  {:<8} {:>6} -> {}", name, a, b))
+        .collect::<Vec<_>>();
+    if !changed.is_empty() {
+        lines.push(format!("{}:", title));
+        lines.extend(changed);
+    }
+}
+
+fn cpu_fields(cpu: &CpuState) -> Vec<(&'static str, String)> {
+    let word = |value: u16| format!("{:04X}", value);
+    let byte = |value: u8| format!("{:02X}", value);
+    vec![
+        ("AF", word(cpu.af)),
+        ("BC", word(cpu.bc)),
+        ("DE", word(cpu.de)),
+        ("HL", word(cpu.hl)),
+        ("AF'", word(cpu.af_alt)),
+        ("BC'", word(cpu.bc_alt)),
+        ("DE'", word(cpu.de_alt)),
+        ("HL'", word(cpu.hl_alt)),
+        ("IX", word(cpu.ix)),
+        ("IY", word(cpu.iy)),
+        ("SP", word(cpu.sp)),
+        ("PC", word(cpu.pc)),
+        ("MEMPTR", word(cpu.mem_ptr)),
+        ("I", byte(cpu.i)),
+        ("R", byte(cpu.r)),
+        ("IFF1", cpu.iff1.to_string()),
+        ("IFF2", cpu.iff2.to_string()),
+        ("IM", cpu.im.to_string()),
+        ("HALTED", cpu.halted.to_string()),
+    ]
+}
+
+fn peripheral_fields(state: &MachineState) -> Vec<(&'static str, String)> {
+    const AY_NAMES: [&str; 16] = [
+        "AY R0", "AY R1", "AY R2", "AY R3", "AY R4", "AY R5", "AY R6", "AY R7", "AY R8", "AY R9",
+        "AY R10", "AY R11", "AY R12", "AY R13", "AY R14", "AY R15",
+    ];
+    let port = |value: Option<u8>| value.map_or_else(|| "-".to_owned(), |v| format!("{:02X}", v));
+
+    let mut fields = vec![
+        ("Border", format!("{:?}", state.border)),
+        ("T-states", state.frame_clocks.to_string()),
+        ("7FFD", port(state.port_7ffd)),
+        ("1FFD", port(state.port_1ffd)),
+    ];
+    for (reg, name) in AY_NAMES.iter().enumerate() {
+        fields.push((name, port(state.ay_registers.map(|regs| regs[reg]))));
+    }
+    fields
+}
+
+/// Returns address of the first byte of the RAM page for 48K machine and zero
+/// for paged machines, where addresses are reported as offsets in the page
+fn page_base(machine: ZXMachine, page: usize) -> usize {
+    match machine.base_machine() {
+        ZXMachine::Sinclair48K => (page + 1) * PAGE_SIZE,
+        _ => 0,
+    }
+}
+
+/// Returns ranges of bytes which differ in two buffers of the same size
+fn changed_ranges(a: &[u8], b: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (index, _) in a.iter().zip(b).enumerate().filter(|(_, (a, b))| a != b) {
+        match ranges.last_mut() {
+            Some(last) if index - last.end < MERGE_GAP => last.end = index + 1,
+            _ => ranges.push(index..index + 1),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustzx_core::zx::video::colors::ZXColor;
+    use structopt::StructOpt;
+
+    fn state(machine: ZXMachine, ram: Vec<Vec<u8>>) -> LoadedState {
+        let cpu = CpuState {
+            af: 0xFFFF,
+            bc: 0,
+            de: 0,
+            hl: 0,
+            af_alt: 0,
+            bc_alt: 0,
+            de_alt: 0,
+            hl_alt: 0,
+            ix: 0,
+            iy: 0x5C3A,
+            sp: 0xFF00,
+            pc: 0x8000,
+            mem_ptr: 0,
+            i: 0x3F,
+            r: 0,
+            iff1: true,
+            iff2: true,
+            im: 1,
+            halted: false,
+        };
+        LoadedState {
+            machine: MachineState {
+                machine,
+                cpu,
+                border: ZXColor::White,
+                frame_clocks: 0,
+                port_7ffd: None,
+                port_1ffd: None,
+                ay_registers: None,
+            },
+            ram,
+        }
+    }
+
+    #[test]
+    fn snapshots_are_loaded_on_their_machines() {
+        let settings = Settings::from_iter_safe(["rustzx", "--machine", "48k"]).unwrap();
+        let test_data = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustzx-test/test_data");
+        let first = LoadedState::load(&settings, &test_data.join("sound.48k.sna.gz")).unwrap();
+        let second = LoadedState::load(&settings, &test_data.join("sound.128k.z80.gz")).unwrap();
+        assert_eq!(first.machine.machine, ZXMachine::Sinclair48K);
+        assert_eq!(second.machine.machine, ZXMachine::Sinclair128K);
+        let lines = diff_states(&first, &second);
+        assert_eq!(lines[0], "Machine: Sinclair48K -> Sinclair128K");
+        assert!(lines.contains(&"  RAM pages count: 3 -> 8".to_owned()));
+    }
+
+    #[test]
+    fn changed_ranges_are_merged() {
+        let a = [0u8; 32];
+        let mut b = a;
+        b[1] = 1;
+        b[4] = 1;
+        b[20] = 1;
+        b[21] = 1;
+        assert_eq!(changed_ranges(&a, &b), vec![1..5, 20..22]);
+        assert!(changed_ranges(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn state_diff_lines() {
+        let first = state(ZXMachine::Sinclair48K, vec![vec![0; PAGE_SIZE]; 3]);
+        assert!(diff_states(&first, &first).is_empty());
+
+        let mut second = state(ZXMachine::Sinclair48K, vec![vec![0; PAGE_SIZE]; 3]);
+        second.machine.cpu.pc = 0x8003;
+        second.machine.cpu.iff1 = false;
+        second.machine.border = ZXColor::Blue;
+        second.ram[1][0x10] = 0xC9;
+        assert_eq!(
+            diff_states(&first, &second),
+            vec![
+                "Registers:",
+                "  PC         8000 -> 8003",
+                "  IFF1       true -> false",
+                "Peripherals:",
+                "  Border    White -> Blue",
+                "Memory:",
+                "  page 1: 8010..=8010 (1 bytes)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Play time statistics. Play time, load count and last played time are
+//! tracked per loaded file, keyed by file contents hash (so renamed or moved
+//! files keep their statistics), and persisted in `stats.toml` next to the
+//! config file
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+pub const STATS_FILE: &str = "stats.toml";
+
+/// Statistics are saved periodically, so play time is not lost on crash
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameStats {
+    /// File name on the last load
+    pub name: String,
+    /// Play time in seconds
+    pub play_time: u64,
+    pub loads: u64,
+    /// Unix timestamp of the last load
+    pub last_played: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct StatsFile {
+    /// Games by file hash
+    games: BTreeMap<String, GameStats>,
+}
+
+pub struct PlayStats {
+    path: PathBuf,
+    file: StatsFile,
+    /// Hash of the currently played file
+    current: Option<String>,
+    /// Play time which is not accounted in whole seconds yet
+    pending_time: Duration,
+    last_save: Instant,
+}
+
+impl PlayStats {
+    /// Loads statistics from `path`, missing file is treated as empty
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = if path.exists() {
+            let data = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read stats file {}", path.display()))?;
+            toml::from_str(&data)
+                .with_context(|| format!("Failed to parse stats file {}", path.display()))?
+        } else {
+            StatsFile::default()
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            file,
+            current: None,
+            pending_time: Duration::ZERO,
+            last_save: Instant::now(),
+        })
+    }
+
+    /// Starts play session of the loaded file
+    pub fn start_game(&mut self, path: &Path) -> anyhow::Result<()> {
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read {} for stats", path.display()))?;
+        let hash = format!("{:016x}", fnv1a_hash(&data));
+        self.pending_time = Duration::ZERO;
+
+        let game = self.file.games.entry(hash.clone()).or_default();
+        if let Some(name) = path.file_name() {
+            game.name = name.to_string_lossy().into_owned();
+        }
+        game.loads += 1;
+        game.last_played = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.current = Some(hash);
+        Ok(())
+    }
+
+    /// Stops tracking of the current play session (e.g. when the machine is reset)
+    pub fn stop_game(&mut self) {
+        self.current = None;
+    }
+
+    /// Returns statistics of the current game
+    pub fn current_game(&self) -> Option<&GameStats> {
+        self.file.games.get(self.current.as_ref()?)
+    }
+
+    /// Adds play time to the current game, statistics are saved periodically
+    pub fn add_play_time(&mut self, time: Duration) -> anyhow::Result<()> {
+        let game = match self.current.as_ref() {
+            Some(hash) => self
+                .file
+                .games
+                .get_mut(hash)
+                .expect("Current game should exist"),
+            None => return Ok(()),
+        };
+        self.pending_time += time;
+        let seconds = self.pending_time.as_secs();
+        game.play_time += seconds;
+        self.pending_time -= Duration::from_secs(seconds);
+
+        if self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.last_save = Instant::now();
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let data = toml::to_string(&self.file)?;
+        std::fs::write(&self.path, data)
+            .with_context(|| format!("Failed to write stats file {}", self.path.display()))
+    }
+
+    /// Returns all games, the most played first
+    pub fn games(&self) -> Vec<&GameStats> {
+        let mut games = self.file.games.values().collect::<Vec<_>>();
+        games.sort_by(|a, b| b.play_time.cmp(&a.play_time).then(a.name.cmp(&b.name)));
+        games
+    }
+}
+
+/// Formats duration in seconds as `H:MM:SS`
+pub fn format_play_time(seconds: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Formats unix timestamp as `YYYY-MM-DD` (UTC)
+pub fn format_date(timestamp: u64) -> String {
+    // Civil from days algorithm by Howard Hinnant
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// 64-bit FNV-1a hash, which is stable between builds unlike std hashers
+pub fn fnv1a_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF29CE484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001B3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_formatting() {
+        assert_eq!(format_play_time(3 * 3600 + 5 * 60 + 7), "3:05:07");
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951782400), "2000-02-29");
+        assert_eq!(format_date(1790000000), "2026-09-21");
+        assert_eq!(fnv1a_hash(b"a"), 0xAF63DC4C8601EC8C);
+    }
+}
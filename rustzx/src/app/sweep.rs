@@ -0,0 +1,356 @@
+//! Compatibility sweep: runs every snapshot and tape from the directory
+//! headlessly for the given frames count on a pool of threads, and records
+//! crashes, hangs and final screen hashes into CSV report
+use super::{rustzx::create_emulator, settings::Settings, sound::DEFAULT_SAMPLE_RATE, stats};
+use crate::host::{self, AppHost, DetectedFileKind};
+use anyhow::{anyhow, Context};
+use rustzx_core::{zx::machine::ZXMachine, EmulationMode, Emulator};
+use std::{
+    fmt::Write as _,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Host time limit for a single frame emulation
+const FRAME_TIME_LIMIT: Duration = Duration::from_secs(1);
+/// Loop is reported as hang when PC stays in the window of this size at the
+/// end of each frame for `HANG_FRAMES` frames. With interrupts enabled the
+/// screen also should stay unchanged, as programs wait for input this way
+const LOOP_WINDOW: u16 = 32;
+const HANG_FRAMES: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SweepStatus {
+    Ok,
+    /// CPU is halted or loops with interrupts disabled
+    Hang,
+    /// File failed to load or emulator returned an error
+    Error,
+    /// Emulator panicked
+    Crash,
+}
+
+impl SweepStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Hang => "hang",
+            Self::Error => "error",
+            Self::Crash => "crash",
+        }
+    }
+}
+
+pub struct SweepResult {
+    pub file: PathBuf,
+    pub status: SweepStatus,
+    /// Machine which was used to run the file
+    pub machine: Option<ZXMachine>,
+    /// Count of emulated frames
+    pub frames: usize,
+    /// PC at the end of emulation, `None` when emulation was not started
+    pub pc: Option<u16>,
+    /// Hash of the final screen (without border)
+    pub screen_hash: Option<u64>,
+    pub message: String,
+}
+
+/// Returns snapshots and tapes from the directory, sorted by name
+pub fn collect_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && host::is_supported_file(path))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+/// Runs all files on `threads` worker threads, results are returned in the
+/// order of `files`
+pub fn run(
+    settings: &Settings,
+    files: &[PathBuf],
+    frames: usize,
+    threads: usize,
+) -> Vec<SweepResult> {
+    // All workers share the same disk image
+    let settings = &Settings {
+        ide_read_only: true,
+        ..settings.clone()
+    };
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(files.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let file = match files.get(index) {
+                    Some(file) => file,
+                    None => break,
+                };
+                let result = run_file(settings, file, frames);
+                log::info!("{}: {}", file.display(), result.status.as_str());
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn run_file(settings: &Settings, file: &Path, frames: usize) -> SweepResult {
+    let mut result = SweepResult {
+        file: file.to_owned(),
+        status: SweepStatus::Ok,
+        machine: None,
+        frames: 0,
+        pc: None,
+        screen_hash: None,
+        message: String::new(),
+    };
+    let run = panic::catch_unwind(AssertUnwindSafe(|| {
+        emulate_file(settings, file, frames, &mut result)
+    }));
+    match run {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            result.status = SweepStatus::Error;
+            result.message = format!("{:#}", e);
+        }
+        Err(payload) => {
+            result.status = SweepStatus::Crash;
+            result.message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Unknown panic".to_owned());
+        }
+    }
+    result
+}
+
+fn emulate_file(
+    settings: &Settings,
+    file: &Path,
+    frames: usize,
+    result: &mut SweepResult,
+) -> anyhow::Result<()> {
+    let mut emulator = match host::detect_file_type(file)? {
+        DetectedFileKind::Snapshot => load_snapshot(settings, file)?,
+        DetectedFileKind::Tape => {
+            let mut emulator = create_emulator(settings, DEFAULT_SAMPLE_RATE)?;
+            emulator
+                .load_tape(host::load_tape(file)?)
+                .map_err(|e| anyhow!("Failed to load tape: {}", e))?;
+            emulator
+        }
+        DetectedFileKind::Screen => return Err(anyhow!("Screen files are not supported")),
+    };
+    emulator.set_sound(false);
+    emulator.set_speed(EmulationMode::FrameCount(1));
+    result.machine = Some(emulator.machine_state().machine);
+
+    let mut hang = HangDetector::default();
+    while result.frames < frames {
+        let emulation = emulator.emulate_frames(FRAME_TIME_LIMIT);
+        let cpu = emulator.machine_state().cpu;
+        let screen_hash = stats::fnv1a_hash(emulator.screen_buffer().rgba_data());
+        let screen_changed = result.screen_hash != Some(screen_hash);
+        result.pc = Some(cpu.pc);
+        result.screen_hash = Some(screen_hash);
+        emulation.map_err(|e| anyhow!("Emulation failed: {}", e))?;
+        result.frames += 1;
+
+        // Tape loaders spin in small loops while tape is playing
+        if emulator.tape_block().is_some() {
+            hang = HangDetector::default();
+            continue;
+        }
+        if let Some(message) = hang.update(cpu.pc, cpu.iff1, cpu.halted, screen_changed) {
+            result.status = SweepStatus::Hang;
+            result.message = message;
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Tracks PC at the end of each frame to detect hangs
+#[derive(Default)]
+struct HangDetector {
+    loop_start: u16,
+    loop_frames: usize,
+}
+
+impl HangDetector {
+    /// Returns hang description when the program is considered hung
+    fn update(
+        &mut self,
+        pc: u16,
+        iff1: bool,
+        halted: bool,
+        screen_changed: bool,
+    ) -> Option<String> {
+        if halted && !iff1 {
+            return Some("HALT with interrupts disabled".to_owned());
+        }
+        let in_window = pc.wrapping_sub(self.loop_start) < LOOP_WINDOW;
+        if self.loop_frames != 0 && in_window && !(iff1 && screen_changed) {
+            self.loop_frames += 1;
+        } else {
+            self.loop_start = pc;
+            self.loop_frames = 1;
+        }
+        if self.loop_frames < HANG_FRAMES {
+            return None;
+        }
+        Some(if iff1 {
+            format!("Loop with unchanged screen at {:04X}", self.loop_start)
+        } else {
+            format!("Loop with interrupts disabled at {:04X}", self.loop_start)
+        })
+    }
+}
+
+/// Loads snapshot into the machine from the settings. When snapshot is not
+/// compatible with it and custom ROM is not used, other machines with embedded
+/// ROMs are tried
+fn load_snapshot(settings: &Settings, file: &Path) -> anyhow::Result<Emulator<AppHost>> {
+    let mut machines = vec![settings.machine];
+    if settings.rom.is_none() {
+        machines.extend(
+            [ZXMachine::Sinclair48K, ZXMachine::Sinclair128K]
+                .into_iter()
+                .filter(|machine| *machine != settings.machine),
+        );
+    }
+    let mut first_error = None;
+    for machine in machines {
+        let mut settings = settings.clone();
+        settings.machine = machine;
+        let mut emulator = create_emulator(&settings, DEFAULT_SAMPLE_RATE)?;
+        match emulator.load_snapshot(host::load_snapshot(file)?) {
+            Ok(()) => return Ok(emulator),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(anyhow!(
+        "Failed to load snapshot: {:?}",
+        first_error.expect("At least one machine should be tried")
+    ))
+}
+
+/// Formats results as CSV with header
+pub fn to_csv(results: &[SweepResult]) -> String {
+    let mut csv = String::from("file,status,machine,frames,pc,screen_hash,message\n");
+    for result in results {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{}",
+            csv_field(&result.file.display().to_string()),
+            result.status.as_str(),
+            result
+                .machine
+                .map(|machine| format!("{:?}", machine))
+                .unwrap_or_default(),
+            result.frames,
+            result
+                .pc
+                .map(|pc| format!("{:04X}", pc))
+                .unwrap_or_default(),
+            result
+                .screen_hash
+                .map(|hash| format!("{:016x}", hash))
+                .unwrap_or_default(),
+            csv_field(&result.message),
+        );
+    }
+    csv
+}
+
+/// Quotes CSV field when needed
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_detector(frames: &[(u16, bool, bool)]) -> Option<String> {
+        let mut detector = HangDetector::default();
+        frames
+            .iter()
+            .cycle()
+            .take(HANG_FRAMES * 2)
+            .find_map(|&(pc, iff1, screen_changed)| {
+                detector.update(pc, iff1, false, screen_changed)
+            })
+    }
+
+    #[test]
+    fn sweep_hang_detection() {
+        assert_eq!(
+            HangDetector::default().update(0x8000, false, true, false),
+            Some("HALT with interrupts disabled".to_owned())
+        );
+        assert_eq!(
+            run_detector(&[(0x8000, false, true), (0x8010, false, false)]),
+            Some("Loop with interrupts disabled at 8000".to_owned())
+        );
+        assert_eq!(
+            run_detector(&[(0x9000, true, false), (0x901F, true, false)]),
+            Some("Loop with unchanged screen at 9000".to_owned())
+        );
+        // Waiting for input with animated screen
+        assert_eq!(run_detector(&[(0x9000, true, true)]), None);
+        // Main loop which runs through the bigger part of the program
+        assert_eq!(
+            run_detector(&[(0x9000, true, false), (0x9100, true, false)]),
+            None
+        );
+    }
+
+    #[test]
+    fn sweep_csv_report() {
+        let results = [
+            SweepResult {
+                file: PathBuf::from("games/game.tap"),
+                status: SweepStatus::Ok,
+                machine: Some(ZXMachine::Sinclair48K),
+                frames: 500,
+                pc: Some(0x8000),
+                screen_hash: Some(0xABCDEF),
+                message: String::new(),
+            },
+            SweepResult {
+                file: PathBuf::from("games/broken, \"v2\".z80"),
+                status: SweepStatus::Error,
+                machine: None,
+                frames: 0,
+                pc: None,
+                screen_hash: None,
+                message: "Failed to load snapshot".to_owned(),
+            },
+        ];
+        assert_eq!(
+            to_csv(&results),
+            "file,status,machine,frames,pc,screen_hash,message\n\
+             games/game.tap,ok,Sinclair48K,500,8000,0000000000abcdef,\n\
+             \"games/broken, \"\"v2\"\".z80\",error,,0,,,Failed to load snapshot\n"
+        );
+    }
+}
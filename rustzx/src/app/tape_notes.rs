@@ -0,0 +1,187 @@
+//! Tape notes: sidecar text file with notes, which are shown via OSD when tape
+//! reaches the given block (e.g. "stop the tape now" for multi-load games or
+//! level passwords). Each line has `<block>[+<seconds>] <text>` format, where
+//! block numbers start from 1 and optional seconds are counted in emulated
+//! time from the block start. Empty lines and lines starting with `#` are ignored
+use anyhow::{anyhow, Context};
+use std::{path::Path, time::Duration};
+
+/// File extension of the notes file, which is looked up next to the tape
+pub const TAPE_NOTES_EXTENSION: &str = "notes";
+
+struct TapeNote {
+    /// Zero-based block index
+    block: usize,
+    offset: Duration,
+    text: String,
+}
+
+pub struct TapeNotes {
+    notes: Vec<TapeNote>,
+    shown: Vec<bool>,
+    current_block: Option<usize>,
+    /// Emulated time since the current block start
+    block_time: Duration,
+}
+
+impl TapeNotes {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read tape notes {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid tape notes {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut notes = Vec::new();
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let note = parse_note(line).with_context(|| format!("Line {}", line_index + 1))?;
+            notes.push(note);
+        }
+        Ok(Self {
+            shown: vec![false; notes.len()],
+            notes,
+            current_block: None,
+            block_time: Duration::ZERO,
+        })
+    }
+
+    /// Updates tape position after `elapsed` emulated time, returns notes
+    /// which became active. Notes of the block are shown again when the block
+    /// is loaded again (e.g. after tape rewind). When several blocks are
+    /// passed between updates (e.g. with fast loading), all notes of the
+    /// passed blocks are returned
+    pub fn update(&mut self, block: Option<usize>, elapsed: Duration) -> Vec<&str> {
+        let mut active = Vec::new();
+        if block != self.current_block {
+            let passed = match (self.current_block, block) {
+                (Some(previous), Some(current)) if current > previous => previous + 1..current,
+                (None, Some(current)) => 0..current,
+                _ => 0..0,
+            };
+            self.current_block = block;
+            self.block_time = Duration::ZERO;
+            for (note, shown) in self.notes.iter().zip(self.shown.iter_mut()) {
+                if passed.contains(&note.block) {
+                    *shown = true;
+                    active.push(note.text.as_str());
+                } else if Some(note.block) == block {
+                    *shown = false;
+                }
+            }
+        } else {
+            self.block_time += elapsed;
+        }
+
+        let block_time = self.block_time;
+        active.extend(
+            self.notes
+                .iter()
+                .zip(self.shown.iter_mut())
+                .filter(|(note, shown)| {
+                    !**shown && Some(note.block) == block && note.offset <= block_time
+                })
+                .map(|(note, shown)| {
+                    *shown = true;
+                    note.text.as_str()
+                }),
+        );
+        active
+    }
+}
+
+fn parse_note(line: &str) -> anyhow::Result<TapeNote> {
+    let (position, text) = line
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| anyhow!("Note text is missing"))?;
+    let (block, offset) = match position.split_once('+') {
+        Some((block, offset)) => (block, Some(offset)),
+        None => (position, None),
+    };
+    let block = block
+        .parse::<usize>()
+        .ok()
+        .filter(|block| *block != 0)
+        .ok_or_else(|| anyhow!("Invalid block number `{}`", block))?;
+    let offset = match offset {
+        Some(offset) => offset
+            .parse::<f64>()
+            .ok()
+            .filter(|offset| offset.is_finite() && *offset >= 0.0)
+            .map(Duration::from_secs_f64)
+            .ok_or_else(|| anyhow!("Invalid time offset `{}`", offset))?,
+        None => Duration::ZERO,
+    };
+    Ok(TapeNote {
+        block: block - 1,
+        offset,
+        text: text.trim().to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: &str = "
+        # Side A
+        2 Loading level 1
+        3+1.5 Stop the tape now
+        3 Password: ZX
+    ";
+
+    #[test]
+    fn tape_notes_follow_tape_position() {
+        let mut notes = TapeNotes::parse(NOTES).unwrap();
+        let frame = Duration::from_millis(20);
+
+        assert!(notes.update(None, frame).is_empty());
+        assert!(notes.update(Some(0), frame).is_empty());
+        assert_eq!(notes.update(Some(1), frame), vec!["Loading level 1"]);
+        assert!(notes.update(Some(1), frame).is_empty());
+        assert_eq!(notes.update(Some(2), frame), vec!["Password: ZX"]);
+        assert!(notes
+            .update(Some(2), Duration::from_millis(1400))
+            .is_empty());
+        assert_eq!(
+            notes.update(Some(2), Duration::from_millis(100)),
+            vec!["Stop the tape now"]
+        );
+        // Notes are shown again after rewind
+        assert!(notes.update(None, frame).is_empty());
+        assert!(notes.update(Some(0), frame).is_empty());
+        assert_eq!(notes.update(Some(1), frame), vec!["Loading level 1"]);
+    }
+
+    #[test]
+    fn tape_notes_of_passed_blocks() {
+        let mut notes = TapeNotes::parse(NOTES).unwrap();
+        let frame = Duration::from_millis(20);
+
+        // Fast loading passes several blocks during a single frame
+        assert_eq!(
+            notes.update(Some(3), frame),
+            vec!["Loading level 1", "Stop the tape now", "Password: ZX"]
+        );
+        assert!(notes.update(Some(3), frame).is_empty());
+        // Blocks before the previous one are not passed again
+        assert!(notes.update(Some(4), frame).is_empty());
+        // Loops and jumps back only activate the current block
+        assert_eq!(notes.update(Some(1), frame), vec!["Loading level 1"]);
+        assert_eq!(
+            notes.update(Some(3), frame),
+            vec!["Stop the tape now", "Password: ZX"]
+        );
+    }
+
+    #[test]
+    fn tape_notes_reject_invalid_lines() {
+        assert!(TapeNotes::parse("0 Text").is_err());
+        assert!(TapeNotes::parse("1").is_err());
+        assert!(TapeNotes::parse("1+x Text").is_err());
+        assert!(TapeNotes::parse("A Text").is_err());
+    }
+}
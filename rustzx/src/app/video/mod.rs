@@ -1,9 +1,7 @@
 //! platform-independent traits. Submodules with backends will be selectable
 //! via cargo features in future
-mod palette;
 mod video_sdl;
 
-pub use palette::Palette;
 pub use video_sdl::VideoSdl;
 
 /// Texture id binging
@@ -15,6 +13,7 @@ pub struct TextureInfo {
 }
 
 /// Simple rect struct
+#[derive(Clone, Copy)]
 pub struct Rect {
     x: i32,
     y: i32,
@@ -41,6 +40,10 @@ pub trait VideoDevice {
     fn begin(&mut self);
     /// draws plain texure into destination rect
     fn draw_texture_2d(&mut self, tex: TextureInfo, rect: Option<Rect>);
+    /// sets texture opacity for the next draws, `None` disables blending
+    fn set_texture_alpha(&mut self, tex: TextureInfo, alpha: Option<u8>);
+    /// returns refresh rate of the display with window, if known
+    fn display_refresh_rate(&self) -> Option<usize>;
     /// finishes rendering
     fn end(&mut self);
 }
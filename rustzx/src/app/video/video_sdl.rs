@@ -4,7 +4,7 @@ use rustzx_core::zx::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
 use sdl2::{
     pixels::PixelFormatEnum as PixelFormat,
     rect::Rect as SdlRect,
-    render::{Canvas, Texture, TextureCreator},
+    render::{BlendMode, Canvas, Texture, TextureCreator},
     video::{Window, WindowContext},
 };
 use std::collections::HashMap;
@@ -37,11 +37,12 @@ impl VideoSdl {
                 .opengl()
                 .build()
                 .expect("[ERROR] Sdl window build fail");
-            let renderer = window
-                .into_canvas()
-                .present_vsync()
-                .build()
-                .expect("[ERROR] Sdl Canvas build error");
+            let mut canvas = window.into_canvas();
+            // Waiting for vsync on present adds up to a display refresh of latency
+            if !settings.low_latency {
+                canvas = canvas.present_vsync();
+            }
+            let renderer = canvas.build().expect("[ERROR] Sdl Canvas build error");
             let texture_creator = renderer.texture_creator();
             VideoSdl {
                 renderer,
@@ -114,6 +115,32 @@ impl VideoDevice for VideoSdl {
             .expect("[ERROR] Can't draw texture");
     }
 
+    fn set_texture_alpha(&mut self, tex: TextureInfo, alpha: Option<u8>) {
+        let tex = self
+            .texteres
+            .get_mut(&tex)
+            .expect("[ERROR] Wrong texrure ID on alpha change");
+        match alpha {
+            Some(alpha) => {
+                tex.set_blend_mode(BlendMode::Blend);
+                tex.set_alpha_mod(alpha);
+            }
+            None => {
+                tex.set_blend_mode(BlendMode::None);
+                tex.set_alpha_mod(0xFF);
+            }
+        }
+    }
+
+    fn display_refresh_rate(&self) -> Option<usize> {
+        self.renderer
+            .window()
+            .display_mode()
+            .ok()
+            .and_then(|mode| usize::try_from(mode.refresh_rate).ok())
+            .filter(|rate| *rate != 0)
+    }
+
     fn end(&mut self) {
         // display buffer
         self.renderer.present();
@@ -1,23 +1,27 @@
-mod frame_buffer;
-
 use anyhow::{anyhow, bail, Context};
-use frame_buffer::{FrameBufferContext, RgbaFrameBuffer};
 use rustzx_core::{
     host::{
-        FrameBuffer, Host, HostContext, RomFormat, RomSet, Screen, Snapshot, StubDebugInterface,
-        StubIoExtender, Tape,
+        BufferCursor, FrameBuffer, Host, HostContext, LoadableAsset, RomFormat, RomSet, Screen,
+        Snapshot, StubDebugInterface, StubIoExtender, TapParser, Tape, TapeBlockSource, TzxParser,
     },
     zx::machine::ZXMachine,
 };
 use rustzx_utils::{
-    io::{DynamicAsset, FileAsset, GzipAsset},
+    frame_buffer::{RgbaFrameBuffer, RgbaFrameBufferContext},
+    io::{BackgroundTape, DynamicAsset, FileAsset, FileDiskImage, GzipAsset},
     stopwatch::InstantStopwatch,
 };
-use std::{collections::VecDeque, fs::File, path::Path};
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    path::Path,
+};
 
-const SUPPORTED_SNAPSHOT_FORMATS: [&str; 1] = ["sna"];
-const SUPPORTED_TAPE_FORMATS: [&str; 1] = ["tap"];
+const SUPPORTED_SNAPSHOT_FORMATS: [&str; 4] = ["sna", "z80", "szx", "slt"];
+const SUPPORTED_TAPE_FORMATS: [&str; 2] = ["tap", "tzx"];
 const SUPPORTED_SCREEN_FORMATS: [&str; 1] = ["scr"];
+const ROM_PAGE_SIZE: usize = 16 * 1024;
+const PLUS3_ROM_PAGES: usize = 4;
 
 pub struct AppHost;
 
@@ -28,13 +32,19 @@ impl Host for AppHost {
     type FrameBuffer = RgbaFrameBuffer;
     type IoExtender = StubIoExtender;
     type TapeAsset = DynamicAsset;
+    type DiskImage = FileDiskImage;
 }
 
-pub struct AppHostContext;
+pub struct AppHostContext {
+    /// 8 normal colors followed by 8 bright colors
+    pub palette: [[u8; 4]; 16],
+}
 
 impl HostContext<AppHost> for AppHostContext {
     fn frame_buffer_context(&self) -> <<AppHost as Host>::FrameBuffer as FrameBuffer>::Context {
-        FrameBufferContext
+        RgbaFrameBufferContext {
+            palette: self.palette,
+        }
     }
 }
 
@@ -66,10 +76,14 @@ pub enum DetectedContainerKind {
 }
 
 pub fn load_asset(path: &Path) -> anyhow::Result<DynamicAsset> {
-    let container_kind = detect_container(path);
-
     let file = File::open(path).with_context(|| "Failed to open tape file")?;
+    file_into_asset(file, detect_container(path))
+}
 
+fn file_into_asset(
+    file: File,
+    container_kind: DetectedContainerKind,
+) -> anyhow::Result<DynamicAsset> {
     match container_kind {
         DetectedContainerKind::None => Ok(FileAsset::from(file).into()),
         DetectedContainerKind::Gzip => {
@@ -88,9 +102,22 @@ pub fn load_tape(path: &Path) -> anyhow::Result<Tape<DynamicAsset>> {
         bail!("Provided tape file does not exist");
     }
 
-    load_asset(path)
-        .map(Tape::Tap)
-        .with_context(|| "Failed to load tape file")
+    let asset = load_asset(path).with_context(|| "Failed to load tape file")?;
+    let source: Box<dyn TapeBlockSource + Send> = if file_extension_matches(path, "tzx") {
+        let mut parser = TzxParser::from_asset(asset);
+        parser
+            .check_header()
+            .map_err(|e| anyhow!("Invalid tzx file: {}", e))?;
+        Box::new(parser)
+    } else {
+        Box::new(TapParser::from_asset(asset))
+    };
+
+    // Tape is parsed by the worker thread to keep emulation loop responsive
+    // on large tape files. File is opened and unpacked beforehand, so its
+    // errors are reported to the caller
+    let tape = BackgroundTape::spawn(move || anyhow::Ok(source));
+    Ok(Tape::Blocks(Box::new(tape)))
 }
 
 pub fn load_snapshot(path: &Path) -> anyhow::Result<Snapshot<DynamicAsset>> {
@@ -102,9 +129,16 @@ pub fn load_snapshot(path: &Path) -> anyhow::Result<Snapshot<DynamicAsset>> {
         bail!("Provided snapshot file does not exist");
     }
 
-    load_asset(path)
-        .map(Snapshot::Sna)
-        .with_context(|| "Failed to load snapshot file")
+    let asset = load_asset(path).with_context(|| "Failed to load snapshot file")?;
+    if file_extension_matches(path, "z80") {
+        Ok(Snapshot::Z80(asset))
+    } else if file_extension_matches(path, "szx") {
+        Ok(Snapshot::Szx(asset))
+    } else if file_extension_matches(path, "slt") {
+        Ok(Snapshot::Slt(asset))
+    } else {
+        Ok(Snapshot::Sna(asset))
+    }
 }
 
 pub fn load_screen(path: &Path) -> anyhow::Result<Screen<DynamicAsset>> {
@@ -121,13 +155,33 @@ pub fn load_screen(path: &Path) -> anyhow::Result<Screen<DynamicAsset>> {
         .with_context(|| "Failed to load screen file")
 }
 
+/// Opens disk image file. Read-only image keeps sectors written by the
+/// emulated machine in memory
+pub fn load_disk_image(path: &Path, read_only: bool) -> anyhow::Result<FileDiskImage> {
+    if !path.exists() {
+        bail!("Provided disk image file does not exist");
+    }
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(!read_only)
+        .open(path)
+        .with_context(|| "Failed to open disk image file")?;
+    if read_only {
+        FileDiskImage::new_read_only(file)
+    } else {
+        FileDiskImage::new(file)
+    }
+    .with_context(|| "Failed to load disk image")
+}
+
 fn load_rom_asset(path: &Path) -> anyhow::Result<DynamicAsset> {
     load_asset(path).with_context(|| "Failed to load rom asset")
 }
 
 pub fn load_rom(path: &Path, machine: ZXMachine) -> anyhow::Result<FileRomSet> {
     match machine {
-        ZXMachine::Sinclair48K => {
+        ZXMachine::Sinclair48K | ZXMachine::TimexTC2048 => {
             if !path.exists() {
                 bail!("Provided 48K ROM file does not exist")
             }
@@ -138,43 +192,67 @@ pub fn load_rom(path: &Path, machine: ZXMachine) -> anyhow::Result<FileRomSet> {
                 ]),
             })
         }
-        ZXMachine::Sinclair128K => {
-            let rom0_path = path;
-            if !file_extension_matches(rom0_path, "0") {
+        ZXMachine::Sinclair128K | ZXMachine::Sinclair128KSpanish => {
+            if !file_extension_matches(path, "0") {
                 bail!("128K ROM filename should end with '.0' extension");
             }
-            if !rom0_path.exists() {
-                bail!("Provided 128K ROM0 file does not exist");
+            load_multipart_rom(path, 2, "128K")
+        }
+        ZXMachine::SinclairPlus3 => {
+            if file_extension_matches(path, "0") {
+                return load_multipart_rom(path, PLUS3_ROM_PAGES, "+3");
+            }
+            if !path.exists() {
+                bail!("Provided +3 ROM file does not exist")
             }
-            let rom1_path = if is_container(rom0_path) {
-                let container_ext = rom0_path.extension().unwrap().to_string_lossy();
-                let mut new_path = rom0_path.to_owned();
-                new_path.set_extension(""); // removes just container extension
-                new_path.with_extension(format!("1.{}", container_ext))
-            } else {
-                rom0_path.to_owned().with_extension("1")
-            };
-
-            if !rom1_path.exists() {
-                bail!("Provided 128K ROM1 file does not exist");
+
+            // Single 64K file with all ROM pages
+            let mut asset = load_rom_asset(path)?;
+            let mut pages = VecDeque::with_capacity(PLUS3_ROM_PAGES);
+            for _ in 0..PLUS3_ROM_PAGES {
+                let mut page = vec![0u8; ROM_PAGE_SIZE];
+                asset
+                    .read_exact(&mut page)
+                    .map_err(|e| anyhow!("+3 ROM load failed: {}", e))?;
+                pages.push_back(BufferCursor::new(page).into());
             }
 
-            Ok(FileRomSet {
-                pages: VecDeque::from(vec![
-                    load_rom_asset(rom0_path).with_context(|| "128K ROM0 load failed")?,
-                    load_rom_asset(&rom1_path).with_context(|| "128K ROM1 load failed")?,
-                ]),
-            })
+            Ok(FileRomSet { pages })
+        }
+    }
+}
+
+/// Loads ROM, split into multiple files with `.0`, `.1`, ... extensions
+fn load_multipart_rom(rom0_path: &Path, count: usize, name: &str) -> anyhow::Result<FileRomSet> {
+    let mut pages = VecDeque::with_capacity(count);
+    for part in 0..count {
+        let part_path = if is_container(rom0_path) {
+            let container_ext = rom0_path.extension().unwrap().to_string_lossy();
+            let mut new_path = rom0_path.to_owned();
+            new_path.set_extension(""); // removes just container extension
+            new_path.with_extension(format!("{}.{}", part, container_ext))
+        } else {
+            rom0_path.to_owned().with_extension(part.to_string())
+        };
+
+        if !part_path.exists() {
+            bail!("Provided {} ROM{} file does not exist", name, part);
         }
+
+        pages.push_back(
+            load_rom_asset(&part_path)
+                .with_context(|| format!("{} ROM{} load failed", name, part))?,
+        );
     }
+
+    Ok(FileRomSet { pages })
 }
 
+/// Detects file kind by its extension, file is not opened
 pub fn detect_file_type(path: &Path) -> anyhow::Result<DetectedFileKind> {
     if file_extension_matches_one_of(path, &SUPPORTED_TAPE_FORMATS) {
-        load_tape(path)?;
         Ok(DetectedFileKind::Tape)
     } else if file_extension_matches_one_of(path, &SUPPORTED_SNAPSHOT_FORMATS) {
-        load_snapshot(path)?;
         Ok(DetectedFileKind::Snapshot)
     } else if file_extension_matches_one_of(path, &SUPPORTED_SCREEN_FORMATS) {
         Ok(DetectedFileKind::Screen)
@@ -183,6 +261,13 @@ pub fn detect_file_type(path: &Path) -> anyhow::Result<DetectedFileKind> {
     }
 }
 
+/// Returns true if file can be opened via [`detect_file_type`]
+pub fn is_supported_file(path: &Path) -> bool {
+    file_extension_matches_one_of(path, &SUPPORTED_TAPE_FORMATS)
+        || file_extension_matches_one_of(path, &SUPPORTED_SNAPSHOT_FORMATS)
+        || file_extension_matches_one_of(path, &SUPPORTED_SCREEN_FORMATS)
+}
+
 fn is_container(path: &Path) -> bool {
     !matches!(detect_container(path), DetectedContainerKind::None)
 }
@@ -243,4 +328,40 @@ mod tests {
     fn file_extension_matches_returns_false() {
         assert!(!file_extension_matches(&Path::new("test.tap"), "sna"));
     }
+
+    #[test]
+    fn detect_file_type_by_extension() {
+        // Files are not opened, so they don't have to exist
+        assert!(matches!(
+            detect_file_type(Path::new("missing.tzx.gz")),
+            Ok(DetectedFileKind::Tape)
+        ));
+        assert!(matches!(
+            detect_file_type(Path::new("missing.z80")),
+            Ok(DetectedFileKind::Snapshot)
+        ));
+        assert!(matches!(
+            detect_file_type(Path::new("missing.scr")),
+            Ok(DetectedFileKind::Screen)
+        ));
+        assert!(detect_file_type(Path::new("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn load_tape_reports_file_errors() {
+        let dir = std::env::temp_dir().join(format!("rustzx-load-tape-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, data: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, data).unwrap();
+            path
+        };
+
+        assert!(load_tape(&dir.join("missing.tap")).is_err());
+        assert!(load_tape(&write("invalid.tzx", b"ZXTape?\x1A\x01\x14")).is_err());
+        assert!(load_tape(&write("invalid.tap.gz", b"not a gzip")).is_err());
+        assert!(load_tape(&write("valid.tzx", b"ZXTape!\x1A\x01\x14")).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -2,18 +2,32 @@ mod app;
 mod backends;
 mod host;
 
-use app::{RustzxApp, Settings};
-use structopt::StructOpt;
+use app::{Command, RustzxApp, Settings};
 
 fn main() {
     simple_logger::init_with_env().expect("Failed to initialize logger");
 
-    let settings = Settings::from_args();
-    let result = RustzxApp::from_config(settings)
-        .and_then(|mut emulator| emulator.start())
-        .map_err(|e| {
-            log::error!("ERROR: {:#}", e);
-        });
+    let result = Settings::load().and_then(|settings| {
+        if let Some(command) = &settings.command {
+            match command {
+                Command::Stats => app::print_stats(&settings),
+                Command::Diff { first, second } => app::print_state_diff(&settings, first, second),
+                Command::Sweep {
+                    dir,
+                    frames,
+                    threads,
+                    output,
+                } => app::run_sweep(&settings, dir, *frames, *threads, output),
+            }
+        } else if settings.list_sound_devices {
+            app::print_sound_devices(&settings)
+        } else {
+            RustzxApp::from_config(settings).and_then(|mut emulator| emulator.start())
+        }
+    });
+    let result = result.map_err(|e| {
+        log::error!("ERROR: {:#}", e);
+    });
 
     if result.is_err() {
         std::process::exit(1);